@@ -0,0 +1,43 @@
+//! Times flattening and rendering a 10,000 line log, answered by a fake jj:
+//! `cargo bench --bench log`
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use jjdag_core::{config::Config, profile, testing::FakeJj};
+use std::fmt::Write;
+
+const COMMITS: usize = 5000;
+const TOGGLES: usize = 50;
+
+/// A colored log of two lines per commit, like `builtin_log_compact` prints
+fn log() -> String {
+    let mut log = String::new();
+    for idx in 0..COMMITS {
+        let change_id: String = format!("{idx:08}")
+            .chars()
+            .map(|digit| (b'k' + digit as u8 - b'0') as char)
+            .collect();
+        let _ = writeln!(
+            log,
+            "\x1b[1m\x1b[34m○\x1b[0m  \x1b[1m\x1b[38;5;5m{change_id}\x1b[0m \
+             \x1b[38;5;3malice@example.com\x1b[39m \
+             \x1b[38;5;6m2024-01-01 09:00:00\x1b[39m \x1b[38;5;4m{idx:08x}\x1b[39m"
+        );
+        let _ = writeln!(log, "│  Commit number \x1b[1m{idx}\x1b[0m");
+    }
+    log.push_str("\x1b[1m\x1b[38;5;14m◆\x1b[0m  zzzzzzzz root() 00000000\n");
+    log
+}
+
+fn main() {
+    profile::enable();
+    let mut jjdag = FakeJj::new()
+        .start(&log(), &Config::default(), 200, 60)
+        .unwrap();
+    // Each fold and unfold flattens the whole log again and renders it
+    for _ in 0..TOGGLES {
+        jjdag
+            .send_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .unwrap();
+    }
+    print!("{}", profile::summary().unwrap());
+}
@@ -0,0 +1,49 @@
+use ansi_to_tui::IntoText;
+use anyhow::Result;
+use ratatui::text::Line;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// `text` without its ANSI escape sequences: the color codes jj prints, and
+/// any other control sequence starting with `ESC [`
+pub fn strip_ansi(text: &str) -> String {
+    if !text.contains('\x1b') {
+        return text.to_string();
+    }
+    let mut clean = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' || chars.peek() != Some(&'[') {
+            clean.push(c);
+            continue;
+        }
+        chars.next();
+        // Parameters and intermediates, up to and including the final byte
+        for c in chars.by_ref() {
+            if ('\x40'..='\x7e').contains(&c) {
+                break;
+            }
+        }
+    }
+    clean
+}
+
+/// `text` with only its color codes left, for showing the output of a command
+/// run in the terminal: cursor movement, titles and the like are dropped
+pub fn strip_non_style_ansi(text: &str) -> String {
+    static NON_STYLE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\x1b(\[[0-9;?]*[ -/]*([@-l]|[n-~])|\].*?(\x07|\x1b\\)|P.*?\x1b\\)").unwrap()
+    });
+    NON_STYLE.replace_all(text, "").to_string()
+}
+
+/// The styled spans of one line of jj's colored output, parsed once so that
+/// rendering it again is only a clone
+pub fn parse_line(text: &str) -> Result<Line<'static>> {
+    Ok(text
+        .into_text()?
+        .lines
+        .into_iter()
+        .next()
+        .unwrap_or_default())
+}
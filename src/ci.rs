@@ -0,0 +1,156 @@
+use crate::config::{CiProvider, fill_placeholders};
+use ratatui::style::{Color, Style};
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, TryRecvError},
+    },
+    thread,
+};
+
+/// Outcome of a commit's CI runs, as far as the provider's output tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiState {
+    Pass,
+    Fail,
+    Pending,
+    Unknown,
+}
+
+impl CiState {
+    /// Classify provider output by the first word that names a state, so
+    /// `gh`'s `success`/`failure`/`in_progress` work as well as plain words
+    fn parse(output: &str) -> Self {
+        for word in output
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .map(str::to_ascii_lowercase)
+        {
+            match word.as_str() {
+                "success" | "pass" | "passed" | "ok" => return Self::Pass,
+                "failure" | "fail" | "failed" | "error" | "cancelled" | "timed_out" => {
+                    return Self::Fail;
+                }
+                "pending" | "queued" | "in_progress" | "running" | "waiting" => {
+                    return Self::Pending;
+                }
+                _ => {}
+            }
+        }
+        Self::Unknown
+    }
+
+    pub fn glyph(self) -> (&'static str, Style) {
+        match self {
+            Self::Pass => ("✓", Style::default().fg(Color::Green)),
+            Self::Fail => ("✗", Style::default().fg(Color::Red)),
+            Self::Pending => ("●", Style::default().fg(Color::Yellow)),
+            Self::Unknown => ("?", Style::default().fg(Color::DarkGray)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CiStatus {
+    pub state: CiState,
+    /// Everything the provider printed, shown as the details
+    pub output: String,
+}
+
+/// A commit to ask the provider about
+pub struct CiTarget {
+    /// As the log shows it, which the status is keyed by
+    pub commit_id: String,
+    /// Filled in for `{commit_id}`, since providers want the whole id
+    pub full_commit_id: String,
+    pub change_id: String,
+    pub bookmark: Option<String>,
+}
+
+/// Providers run at once at most
+const WORKERS: usize = 4;
+
+/// CI statuses being checked in the background, arriving as they're done
+#[derive(Debug)]
+pub struct CiFetch(mpsc::Receiver<(String, CiStatus)>);
+
+impl CiFetch {
+    /// The statuses checked since last asked, waiting for all of them if
+    /// `wait`, and whether all of them are in
+    pub fn receive(&self, wait: bool) -> (Vec<(String, CiStatus)>, bool) {
+        let mut statuses = Vec::new();
+        loop {
+            let next = if wait {
+                self.0.recv().map_err(|_| TryRecvError::Disconnected)
+            } else {
+                self.0.try_recv()
+            };
+            match next {
+                Ok(status) => statuses.push(status),
+                Err(TryRecvError::Empty) => return (statuses, false),
+                Err(TryRecvError::Disconnected) => return (statuses, true),
+            }
+        }
+    }
+}
+
+/// Run the provider for every target on a few background threads, keyed by
+/// commit id. Targets the command has no value for, like commits without
+/// bookmarks when it uses `{bookmark}`, are skipped.
+pub fn fetch_statuses(provider: &CiProvider, repo_root: &str, targets: &[CiTarget]) -> CiFetch {
+    let (sender, receiver) = mpsc::channel();
+    // The provider runs on other threads, which don't know about the replay
+    if crate::session::is_replaying() {
+        return CiFetch(receiver);
+    }
+    let jobs: VecDeque<(String, String)> = targets
+        .iter()
+        .filter_map(|target| {
+            let values = [
+                ("commit_id", Some(target.full_commit_id.as_str())),
+                ("change_id", Some(target.change_id.as_str())),
+                ("bookmark", target.bookmark.as_deref()),
+            ];
+            let command_line = fill_placeholders(&provider.command, &values).ok()?;
+            Some((target.commit_id.clone(), command_line))
+        })
+        .collect();
+    let workers = WORKERS.min(jobs.len());
+    let jobs = Arc::new(Mutex::new(jobs));
+    for _ in 0..workers {
+        let jobs = Arc::clone(&jobs);
+        let sender = sender.clone();
+        let repo_root = repo_root.to_string();
+        thread::spawn(move || {
+            loop {
+                let job = jobs.lock().ok().and_then(|mut jobs| jobs.pop_front());
+                let Some((commit_id, command_line)) = job else {
+                    break;
+                };
+                let status = run_provider(&repo_root, &command_line);
+                // Nobody is waiting for statuses anymore
+                if sender.send((commit_id, status)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    CiFetch(receiver)
+}
+
+fn run_provider(repo_root: &str, command_line: &str) -> CiStatus {
+    match crate::shell_out::run_shell(repo_root, command_line) {
+        Ok((exit_status, output)) if exit_status.success() => CiStatus {
+            state: CiState::parse(&output),
+            output,
+        },
+        Ok((exit_status, output)) => CiStatus {
+            state: CiState::Unknown,
+            output: format!("{output}\n{command_line} exited with {exit_status}"),
+        },
+        Err(err) => CiStatus {
+            state: CiState::Unknown,
+            output: err.to_string(),
+        },
+    }
+}
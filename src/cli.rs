@@ -1,4 +1,7 @@
 use clap::Parser;
+use jjdag_core::{keymap::KeymapPreset, theme::Theme};
+use log::LevelFilter;
+use std::path::PathBuf;
 
 const DEFAULT_REVSET: &str = "root() | remote_bookmarks() | ancestors(immutable_heads().., 24)";
 
@@ -12,4 +15,34 @@ pub struct Args {
     /// Which revisions to show
     #[arg(short = 'r', long, value_name = "REVSETS", default_value = DEFAULT_REVSET)]
     pub revisions: String,
+
+    /// Navigation key preset, overriding the config file
+    #[arg(long, value_enum)]
+    pub keymap: Option<KeymapPreset>,
+
+    /// Color theme, overriding the config file
+    #[arg(long, value_enum)]
+    pub theme: Option<Theme>,
+
+    /// Most verbose level to log: off, error, warn, info, debug or trace
+    #[arg(long, value_name = "LEVEL", default_value = "debug")]
+    pub log_level: LevelFilter,
+
+    /// Record input and jj's output to a file, to replay for a bug report
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Play back a session recorded with --record, answering jj commands with
+    /// the recorded output instead of running jj
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// Time refreshing, flattening and rendering the log, showing the latest
+    /// times in a corner and a summary on exit
+    #[arg(long)]
+    pub profile: bool,
+
+    /// File to append the log to [default: $XDG_STATE_HOME/jjdag/logs/jjdag-<date>.log]
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
 }
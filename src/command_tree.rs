@@ -1,10 +1,13 @@
 use crate::update::{
     AbandonMode, AbsorbMode, BookmarkMoveMode, DescribeMode, DuplicateDestination,
-    DuplicateDestinationType, EditMode, GitFetchMode, GitPushMode, InterdiffMode, Message,
-    MetaeditAction, NewMode, NextPrevDirection, NextPrevMode, ParallelizeSource, RebaseDestination,
-    RebaseDestinationType, RebaseSourceType, RestoreMode, RevertDestination, RevertDestinationType,
-    RevertRevision, SignAction, SimplifyParentsMode, SquashMode, ViewMode,
+    DuplicateDestinationType, EditMode, GitFetchMode, GitPushMode, InterdiffMode, JumpTarget,
+    Message, MetaeditAction, NewMode, NextPrevDirection, NextPrevMode, ParallelizeSource,
+    RebaseDestination, RebaseDestinationType, RebaseSourceType, ResolveSide, RestoreMode,
+    RevertDestination, RevertDestinationType, RevertRevision, SignAction, SimplifyParentsMode,
+    SquashMode, ViewMode, ZoomMode,
 };
+use crate::{config::ExternalCommand, scripting::ScriptBinding};
+use anyhow::{Result, bail};
 use crossterm::event::KeyCode;
 use indexmap::IndexMap;
 use ratatui::{
@@ -12,13 +15,79 @@ use ratatui::{
     text::{Line, Span, Text},
 };
 use std::collections::HashMap;
+use unicode_width::UnicodeWidthStr;
 
 type HelpEntries = IndexMap<String, Vec<(String, String)>>;
 
+/// What the selected log node is, which decides the actions in its menu
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Commit,
+    File,
+    Hunk,
+}
+
+use NodeKind::{Commit, File, Hunk};
+
+/// Chords offered in the actions menu, the nodes they apply to, and whether
+/// they only apply to a commit with bookmarks (`Some(true)`) or without
+/// (`Some(false)`)
+const ACTION_MENU_CHORDS: [(&str, &[NodeKind], Option<bool>); 42] = [
+    ("dd", &[Commit], None),
+    ("nn", &[Commit], None),
+    ("nd", &[Commit], None),
+    ("nb", &[Commit], None),
+    ("ee", &[Commit], None),
+    ("ss", &[Commit, File, Hunk], None),
+    ("sb", &[Hunk], None),
+    ("Aa", &[Commit], None),
+    ("/", &[Commit], None),
+    ("\\", &[Commit], None),
+    ("Dd", &[Commit], None),
+    ("aa", &[Commit], None),
+    ("ab", &[Commit], Some(true)),
+    ("vv", &[Commit, File, Hunk], None),
+    ("vt", &[Commit, File], None),
+    ("vc", &[Commit], None),
+    ("vd", &[Commit], None),
+    ("zz", &[Commit], None),
+    ("Ee", &[Commit], None),
+    ("Rr", &[Commit, File, Hunk], None),
+    ("Rf", &[File], None),
+    ("Vv", &[Commit], None),
+    ("Ss", &[Commit], None),
+    ("yy", &[Commit], None),
+    ("rm", &[Commit], None),
+    ("X", &[Commit, File], None),
+    ("fb", &[Commit], None),
+    ("bc", &[Commit], None),
+    ("bs", &[Commit], None),
+    ("br", &[Commit], Some(true)),
+    ("bd", &[Commit], Some(true)),
+    ("gpr", &[Commit], Some(true)),
+    ("gpc", &[Commit], Some(false)),
+    ("fu", &[File], None),
+    ("fx", &[File], None),
+    ("fn", &[File], None),
+    ("fc", &[File], None),
+    ("f1", &[File], None),
+    ("f2", &[File], None),
+    ("fi", &[File], None),
+    ("x", &[File, Hunk], None),
+    ("t", &[Commit, File, Hunk], None),
+];
+
+/// A single bound command with its full key chord, as listed in the help overlay
+struct HelpCommand {
+    chord: String,
+    group: String,
+    description: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct CommandTreeNodeChildren {
     nodes: HashMap<KeyCode, CommandTreeNode>,
-    help: HelpEntries,
+    help: IndexMap<String, Vec<(KeyCode, String)>>,
 }
 
 impl CommandTreeNodeChildren {
@@ -38,15 +107,50 @@ impl CommandTreeNodeChildren {
     }
 
     fn get_help_entries(&self) -> HelpEntries {
-        let mut help = self.help.clone();
+        let mut help: HelpEntries = self
+            .help
+            .iter()
+            .map(|(group, entries)| {
+                let entries = entries
+                    .iter()
+                    .map(|(key_code, help_text)| (key_code.to_string(), help_text.clone()))
+                    .collect();
+                (group.clone(), entries)
+            })
+            .collect();
 
         for (_, entries) in help.iter_mut() {
-            entries.sort_by(|a, b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
+            entries.sort_by_key(|a| a.0.to_lowercase());
         }
 
         help
     }
 
+    /// Recursively collect every command below this node along with its full key chord
+    fn collect_help_commands(&self, prefix: &mut Vec<KeyCode>, commands: &mut Vec<HelpCommand>) {
+        for (group, entries) in &self.help {
+            for (key_code, help_text) in entries {
+                prefix.push(*key_code);
+                commands.push(HelpCommand {
+                    chord: prefix
+                        .iter()
+                        .map(|key_code| key_code.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    group: group.clone(),
+                    description: help_text.clone(),
+                });
+                if let Some(children) = self
+                    .get_node(key_code)
+                    .and_then(|node| node.children.as_ref())
+                {
+                    children.collect_help_commands(prefix, commands);
+                }
+                prefix.pop();
+            }
+        }
+    }
+
     pub fn get_help(&self) -> Text<'static> {
         let entries = self.get_help_entries();
         render_help_text(entries)
@@ -61,7 +165,7 @@ impl CommandTreeNodeChildren {
     ) {
         self.nodes.insert(key_code, node);
         let help_group = self.help.entry(help_group_text.to_string()).or_default();
-        help_group.push((key_code.to_string(), help_text.to_string()))
+        help_group.push((key_code, help_text.to_string()))
     }
 }
 
@@ -69,6 +173,8 @@ impl CommandTreeNodeChildren {
 pub struct CommandTreeNode {
     pub children: Option<CommandTreeNodeChildren>,
     pub action: Option<Message>,
+    /// Keep the parent chord pending after the action so it can be run again
+    pub repeatable: bool,
 }
 
 impl CommandTreeNode {
@@ -76,6 +182,7 @@ impl CommandTreeNode {
         Self {
             children: Some(CommandTreeNodeChildren::new()),
             action: None,
+            repeatable: false,
         }
     }
 
@@ -83,6 +190,14 @@ impl CommandTreeNode {
         Self {
             children: None,
             action: Some(action),
+            repeatable: false,
+        }
+    }
+
+    pub fn new_repeatable_action(action: Message) -> Self {
+        Self {
+            repeatable: true,
+            ..Self::new_action(action)
         }
     }
 
@@ -134,38 +249,92 @@ impl CommandTree {
         Some(node)
     }
 
-    pub fn get_help(&self) -> Text<'static> {
-        let nav_help = [
-            ("Tab ", "Toggle folding"),
-            ("PgDn", "Move down page"),
-            ("PgUp", "Move up page"),
-            ("j/↓ ", "Move down"),
-            ("k/↑ ", "Move up"),
-            ("l/→ ", "Next sibling"),
-            ("h/← ", "Prev sibling"),
-            ("K", "Select parent"),
-            ("@", "Select @ change"),
-        ]
-        .iter()
-        .map(|(key, help)| (key.to_string(), help.to_string()))
-        .collect();
+    /// Get one line per command for the searchable help overlay, each showing the
+    /// full key chord, description and help group. `bindings` are the keys bound
+    /// outside the command tree, as (keys, group, description).
+    pub fn get_help_commands(&self, bindings: Vec<(String, &str, &str)>) -> Vec<String> {
+        let mut commands: Vec<HelpCommand> = bindings
+            .into_iter()
+            .map(|(keys, group, description)| HelpCommand {
+                chord: keys,
+                group: group.to_string(),
+                description: description.to_string(),
+            })
+            .collect();
+        self.0
+            .children
+            .as_ref()
+            .unwrap()
+            .collect_help_commands(&mut Vec::new(), &mut commands);
 
-        let general_help = [
-            ("Spc/Ctrl-r", "Refresh log tree"),
-            ("Esc", "Clear app state"),
-            ("L", "Set log revset"),
-            ("I", "Toggle --ignore-immutable"),
-            ("?", "Show help"),
-            ("q", "Quit"),
-        ]
-        .iter()
-        .map(|(key, help)| (key.to_string(), help.to_string()))
-        .collect();
+        let chord_width = commands
+            .iter()
+            .map(|command| command.chord.width())
+            .max()
+            .unwrap_or(0);
+        commands
+            .into_iter()
+            .map(|command| {
+                let padding = " ".repeat(chord_width - command.chord.width());
+                format!(
+                    "{}{padding}  {}  ({})",
+                    command.chord, command.description, command.group
+                )
+            })
+            .collect()
+    }
 
-        let mut entries = self.0.children.as_ref().unwrap().get_help_entries();
-        entries.insert("Navigation".to_string(), nav_help);
-        entries.insert("General".to_string(), general_help);
-        render_help_text(entries)
+    /// The actions menu for a node: one line per command that applies to it,
+    /// laid out like the help overlay, with the message the command sends.
+    /// Bookmark commands name the commit's `bookmarks`.
+    pub fn action_menu(&self, kind: NodeKind, bookmarks: &[String]) -> Vec<(String, Message)> {
+        let has_bookmarks = !bookmarks.is_empty();
+        let actions: Vec<(String, String, Message)> = ACTION_MENU_CHORDS
+            .iter()
+            .filter(|(_, kinds, needs_bookmarks)| {
+                kinds.contains(&kind)
+                    && needs_bookmarks.is_none_or(|needed| needed == has_bookmarks)
+            })
+            .filter_map(|(chord, _, needs_bookmarks)| {
+                let key_codes: Vec<KeyCode> = chord.chars().map(KeyCode::Char).collect();
+                let message = self.get_node(&key_codes)?.action?;
+                let (group, description) = self.help_text(&key_codes)?;
+                let group = match needs_bookmarks {
+                    Some(true) => format!("{group} {}", bookmarks.join(", ")),
+                    _ => group.to_string(),
+                };
+                Some((
+                    chord.to_string(),
+                    format!("{description}  ({group})"),
+                    message,
+                ))
+            })
+            .collect();
+
+        let chord_width = actions
+            .iter()
+            .map(|(chord, _, _)| chord.width())
+            .max()
+            .unwrap_or(0);
+        actions
+            .into_iter()
+            .map(|(chord, text, message)| {
+                let padding = " ".repeat(chord_width - chord.width());
+                (format!("{chord}{padding}  {text}"), message)
+            })
+            .collect()
+    }
+
+    /// Help group and description of the command at a chord
+    fn help_text(&self, key_codes: &[KeyCode]) -> Option<(&str, &str)> {
+        let (last_key, rest_keys) = key_codes.split_last()?;
+        let children = self.get_node(rest_keys)?.children.as_ref()?;
+        children.help.iter().find_map(|(group, entries)| {
+            entries
+                .iter()
+                .find(|(key_code, _)| key_code == last_key)
+                .map(|(_, help_text)| (group.as_str(), help_text.as_str()))
+        })
     }
 
     pub fn new() -> Self {
@@ -182,6 +351,7 @@ impl CommandTree {
                 vec![KeyCode::Char('a'), KeyCode::Char('a')],
                 CommandTreeNode::new_action(Message::Abandon {
                     mode: AbandonMode::Default,
+                    range: false,
                 }),
             ),
             (
@@ -190,6 +360,7 @@ impl CommandTree {
                 vec![KeyCode::Char('a'), KeyCode::Char('b')],
                 CommandTreeNode::new_action(Message::Abandon {
                     mode: AbandonMode::RetainBookmarks,
+                    range: false,
                 }),
             ),
             (
@@ -198,6 +369,22 @@ impl CommandTree {
                 vec![KeyCode::Char('a'), KeyCode::Char('d')],
                 CommandTreeNode::new_action(Message::Abandon {
                     mode: AbandonMode::RestoreDescendants,
+                    range: false,
+                }),
+            ),
+            (
+                "Abandon",
+                "From selection to destination",
+                vec![KeyCode::Char('a'), KeyCode::Char('A')],
+                CommandTreeNode::new_action_with_children(Message::SaveSelection),
+            ),
+            (
+                "Abandon range",
+                "Select destination",
+                vec![KeyCode::Char('a'), KeyCode::Char('A'), KeyCode::Enter],
+                CommandTreeNode::new_action(Message::Abandon {
+                    mode: AbandonMode::Default,
+                    range: true,
                 }),
             ),
             (
@@ -214,6 +401,14 @@ impl CommandTree {
                     mode: AbsorbMode::Default,
                 }),
             ),
+            (
+                "Absorb",
+                "From selection, previewing first",
+                vec![KeyCode::Char('A'), KeyCode::Char('p')],
+                CommandTreeNode::new_action(Message::Absorb {
+                    mode: AbsorbMode::Preview,
+                }),
+            ),
             (
                 "Absorb",
                 "From selection into destination",
@@ -240,11 +435,23 @@ impl CommandTree {
                 vec![KeyCode::Char('b'), KeyCode::Char('c')],
                 CommandTreeNode::new_action(Message::BookmarkEditStart),
             ),
+            (
+                "Bookmark",
+                "Create at a revision (prompt)",
+                vec![KeyCode::Char('b'), KeyCode::Char('C')],
+                CommandTreeNode::new_action(Message::BookmarkCreateAtStart),
+            ),
             (
                 "Bookmark",
                 "New revision and tug bookmark",
                 vec![KeyCode::Char('b'), KeyCode::Char('n')],
-                CommandTreeNode::new_action(Message::NewOnBranch),
+                CommandTreeNode::new_action(Message::NewOnBranch { describe: false }),
+            ),
+            (
+                "Bookmark",
+                "New revision, tug bookmark and describe",
+                vec![KeyCode::Char('b'), KeyCode::Char('N')],
+                CommandTreeNode::new_action(Message::NewOnBranch { describe: true }),
             ),
             (
                 "Bookmark",
@@ -290,6 +497,25 @@ impl CommandTree {
                     mode: BookmarkMoveMode::AllowBackwards,
                 }),
             ),
+            (
+                "Bookmark move",
+                "All bookmarks on selection to destination",
+                vec![KeyCode::Char('b'), KeyCode::Char('m'), KeyCode::Char('a')],
+                CommandTreeNode::new_action_with_children(Message::SaveSelection),
+            ),
+            (
+                "Move all bookmarks to",
+                "Select destination",
+                vec![
+                    KeyCode::Char('b'),
+                    KeyCode::Char('m'),
+                    KeyCode::Char('a'),
+                    KeyCode::Enter,
+                ],
+                CommandTreeNode::new_action(Message::BookmarkMove {
+                    mode: BookmarkMoveMode::AllOnSaved,
+                }),
+            ),
             (
                 "Bookmark move",
                 "Tug to selection",
@@ -312,9 +538,9 @@ impl CommandTree {
             ),
             (
                 "Bookmark",
-                "Track",
+                "Track (in the remote bookmark browser)",
                 vec![KeyCode::Char('b'), KeyCode::Char('t')],
-                CommandTreeNode::new_action(Message::BookmarkTrack),
+                CommandTreeNode::new_action(Message::RemoteBookmarksStart),
             ),
             (
                 "Bookmark",
@@ -322,6 +548,12 @@ impl CommandTree {
                 vec![KeyCode::Char('b'), KeyCode::Char('u')],
                 CommandTreeNode::new_action(Message::BookmarkUntrack),
             ),
+            (
+                "Bookmark",
+                "Browse remote bookmarks",
+                vec![KeyCode::Char('b'), KeyCode::Char('R')],
+                CommandTreeNode::new_action(Message::RemoteBookmarksStart),
+            ),
             (
                 "Bookmark",
                 "Delete",
@@ -362,6 +594,32 @@ impl CommandTree {
                 vec![KeyCode::Char('c'), KeyCode::Char('c')],
                 CommandTreeNode::new_action(Message::Commit),
             ),
+            (
+                "Commit",
+                "Working copy from template",
+                vec![KeyCode::Char('c'), KeyCode::Char('p')],
+                CommandTreeNode::new_action(Message::DescriptionTemplate {
+                    mode: DescribeMode::Commit,
+                }),
+            ),
+            (
+                "Commands",
+                "CI",
+                vec![KeyCode::Char('C')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "CI",
+                "Check status of loaded commits",
+                vec![KeyCode::Char('C'), KeyCode::Char('C')],
+                CommandTreeNode::new_action(Message::CiRefresh),
+            ),
+            (
+                "CI",
+                "Show details for selection",
+                vec![KeyCode::Char('C'), KeyCode::Enter],
+                CommandTreeNode::new_action(Message::CiDetails),
+            ),
             (
                 "Commands",
                 "Describe",
@@ -374,6 +632,7 @@ impl CommandTree {
                 vec![KeyCode::Char('d'), KeyCode::Char('d')],
                 CommandTreeNode::new_action(Message::DescriptionEditStart {
                     mode: DescribeMode::Default,
+                    range: false,
                 }),
             ),
             (
@@ -382,6 +641,42 @@ impl CommandTree {
                 vec![KeyCode::Char('d'), KeyCode::Char('i')],
                 CommandTreeNode::new_action(Message::DescriptionEditStart {
                     mode: DescribeMode::IgnoreImmutable,
+                    range: false,
+                }),
+            ),
+            (
+                "Describe",
+                "Selection from template",
+                vec![KeyCode::Char('d'), KeyCode::Char('p')],
+                CommandTreeNode::new_action(Message::DescriptionTemplate {
+                    mode: DescribeMode::Default,
+                }),
+            ),
+            (
+                "Describe",
+                "Add trailer",
+                vec![KeyCode::Char('d'), KeyCode::Char('t')],
+                CommandTreeNode::new_action(Message::TrailerAdd),
+            ),
+            (
+                "Describe",
+                "Remove trailer",
+                vec![KeyCode::Char('d'), KeyCode::Char('T')],
+                CommandTreeNode::new_action(Message::TrailerRemove),
+            ),
+            (
+                "Describe",
+                "Each from selection to destination",
+                vec![KeyCode::Char('d'), KeyCode::Char('D')],
+                CommandTreeNode::new_action_with_children(Message::SaveSelection),
+            ),
+            (
+                "Describe range",
+                "Select destination",
+                vec![KeyCode::Char('d'), KeyCode::Char('D'), KeyCode::Enter],
+                CommandTreeNode::new_action(Message::DescriptionEditStart {
+                    mode: DescribeMode::Default,
+                    range: true,
                 }),
             ),
             (
@@ -484,6 +779,18 @@ impl CommandTree {
                 vec![KeyCode::Char('E'), KeyCode::Char('E')],
                 CommandTreeNode::new_action(Message::Evolog { patch: true }),
             ),
+            (
+                "Evolog",
+                "Toggle inline under selection",
+                vec![KeyCode::Char('E'), KeyCode::Char('i')],
+                CommandTreeNode::new_action(Message::ToggleInlineEvolog),
+            ),
+            (
+                "Evolog",
+                "Restore commit to selected version",
+                vec![KeyCode::Char('E'), KeyCode::Char('r')],
+                CommandTreeNode::new_action(Message::EvologRestore),
+            ),
             (
                 "Commands",
                 "File",
@@ -502,6 +809,70 @@ impl CommandTree {
                 vec![KeyCode::Char('f'), KeyCode::Char('u')],
                 CommandTreeNode::new_action(Message::FileUntrack),
             ),
+            (
+                "File",
+                "Untrack ignored files (Tab to mark several)",
+                vec![KeyCode::Char('f'), KeyCode::Char('U')],
+                CommandTreeNode::new_action(Message::FileUntrackIgnored),
+            ),
+            (
+                "File",
+                "Untrack files matching a glob",
+                vec![KeyCode::Char('f'), KeyCode::Char('g')],
+                CommandTreeNode::new_action(Message::FileUntrackGlobStart),
+            ),
+            (
+                "File",
+                "Browse files in selection",
+                vec![KeyCode::Char('f'), KeyCode::Char('b')],
+                CommandTreeNode::new_action(Message::FileBrowserStart),
+            ),
+            (
+                "File",
+                "Search files in selection (regex)",
+                vec![KeyCode::Char('f'), KeyCode::Char('s')],
+                CommandTreeNode::new_action(Message::GrepStart),
+            ),
+            (
+                "File",
+                "Make selection executable",
+                vec![KeyCode::Char('f'), KeyCode::Char('x')],
+                CommandTreeNode::new_action(Message::FileChmod { executable: true }),
+            ),
+            (
+                "File",
+                "Make selection non-executable",
+                vec![KeyCode::Char('f'), KeyCode::Char('n')],
+                CommandTreeNode::new_action(Message::FileChmod { executable: false }),
+            ),
+            (
+                "File",
+                "Show conflicts in selection",
+                vec![KeyCode::Char('f'), KeyCode::Char('c')],
+                CommandTreeNode::new_action(Message::FileConflictView),
+            ),
+            (
+                "File",
+                "Resolve selection taking side #1 (ours)",
+                vec![KeyCode::Char('f'), KeyCode::Char('1')],
+                CommandTreeNode::new_action(Message::ResolveTakeSide {
+                    side: ResolveSide::Ours,
+                }),
+            ),
+            (
+                "File",
+                "Resolve selection taking side #2 (theirs)",
+                vec![KeyCode::Char('f'), KeyCode::Char('2')],
+                CommandTreeNode::new_action(Message::ResolveTakeSide {
+                    side: ResolveSide::Theirs,
+                }),
+            ),
+            (
+                "File",
+                "Preview image before/after",
+                vec![KeyCode::Char('f'), KeyCode::Char('i')],
+                CommandTreeNode::new_action(Message::FileImagePreview),
+            ),
             (
                 "Commands",
                 "Git",
@@ -540,11 +911,9 @@ impl CommandTree {
             ),
             (
                 "Git fetch",
-                "Branch by name",
+                "Branch (in the remote bookmark browser)",
                 vec![KeyCode::Char('g'), KeyCode::Char('f'), KeyCode::Char('b')],
-                CommandTreeNode::new_action(Message::GitFetch {
-                    mode: GitFetchMode::Branch,
-                }),
+                CommandTreeNode::new_action(Message::RemoteBookmarksStart),
             ),
             (
                 "Git fetch",
@@ -600,6 +969,14 @@ impl CommandTree {
                     mode: GitPushMode::Deleted,
                 }),
             ),
+            (
+                "Git push",
+                "Pick deleted bookmarks",
+                vec![KeyCode::Char('g'), KeyCode::Char('p'), KeyCode::Char('D')],
+                CommandTreeNode::new_action(Message::GitPush {
+                    mode: GitPushMode::DeletedSelect,
+                }),
+            ),
             (
                 "Git push",
                 "New bookmark for selection",
@@ -624,6 +1001,12 @@ impl CommandTree {
                     mode: GitPushMode::Bookmark,
                 }),
             ),
+            (
+                "Git push",
+                "Choose remote, then push",
+                vec![KeyCode::Char('g'), KeyCode::Char('p'), KeyCode::Char('R')],
+                CommandTreeNode::new_action(Message::GitPushRemote),
+            ),
             (
                 "Git push",
                 "Tug and push bookmark",
@@ -734,6 +1117,14 @@ impl CommandTree {
                     mode: NewMode::Default,
                 }),
             ),
+            (
+                "New",
+                "After selection and describe",
+                vec![KeyCode::Char('n'), KeyCode::Char('d')],
+                CommandTreeNode::new_action(Message::New {
+                    mode: NewMode::Describe,
+                }),
+            ),
             (
                 "New",
                 "After selection (rebase children)",
@@ -966,6 +1357,14 @@ impl CommandTree {
                     mode: SquashMode::Default,
                 }),
             ),
+            (
+                "Squash",
+                "Hunk into commit that last changed its lines",
+                vec![KeyCode::Char('s'), KeyCode::Char('b')],
+                CommandTreeNode::new_action(Message::Squash {
+                    mode: SquashMode::HunkIntoBlamed,
+                }),
+            ),
             (
                 "Squash",
                 "Selection into destination",
@@ -986,12 +1385,148 @@ impl CommandTree {
                 vec![KeyCode::Char('t')],
                 CommandTreeNode::new_action(Message::Status),
             ),
+            (
+                "Commands",
+                "Go to",
+                vec![KeyCode::Char('G')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Go to",
+                "trunk()",
+                vec![KeyCode::Char('G'), KeyCode::Char('t')],
+                CommandTreeNode::new_action(Message::JumpTo {
+                    target: JumpTarget::Trunk,
+                }),
+            ),
+            (
+                "Go to",
+                "root()",
+                vec![KeyCode::Char('G'), KeyCode::Char('r')],
+                CommandTreeNode::new_action(Message::JumpTo {
+                    target: JumpTarget::Root,
+                }),
+            ),
+            (
+                "Go to",
+                "Nearest bookmarked ancestor",
+                vec![KeyCode::Char('G'), KeyCode::Char('b')],
+                CommandTreeNode::new_action(Message::JumpTo {
+                    target: JumpTarget::BookmarkedAncestor,
+                }),
+            ),
+            (
+                "Commands",
+                "Zoom",
+                vec![KeyCode::Char('z')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Zoom",
+                "Into selection and its descendants",
+                vec![KeyCode::Char('z'), KeyCode::Char('z')],
+                CommandTreeNode::new_action(Message::Zoom {
+                    mode: ZoomMode::Descendants,
+                }),
+            ),
+            (
+                "Zoom",
+                "Into selection and its ancestors",
+                vec![KeyCode::Char('z'), KeyCode::Char('a')],
+                CommandTreeNode::new_action(Message::Zoom {
+                    mode: ZoomMode::Ancestors,
+                }),
+            ),
+            (
+                "Zoom",
+                "Into one author's commits",
+                vec![KeyCode::Char('z'), KeyCode::Char('u')],
+                CommandTreeNode::new_action(Message::AuthorFilter),
+            ),
+            (
+                "Commands",
+                "Clear author filter",
+                vec![KeyCode::Char('U')],
+                CommandTreeNode::new_action(Message::ClearAuthorFilter),
+            ),
+            (
+                "Zoom",
+                "Into a saved revset",
+                vec![KeyCode::Char('z'), KeyCode::Char('r')],
+                CommandTreeNode::new_action(Message::SavedRevsets),
+            ),
+            (
+                "Zoom",
+                "Save the revset under a name",
+                vec![KeyCode::Char('z'), KeyCode::Char('s')],
+                CommandTreeNode::new_action(Message::SaveRevset),
+            ),
+            (
+                "Zoom",
+                "Out to the previous revset",
+                vec![KeyCode::Char('z'), KeyCode::Char('o')],
+                CommandTreeNode::new_action(Message::ZoomOut),
+            ),
+            (
+                "Commands",
+                "Actions for selection",
+                vec![KeyCode::Char('.')],
+                CommandTreeNode::new_action(Message::ActionMenu),
+            ),
+            (
+                "Commands",
+                "Fsmonitor",
+                vec![KeyCode::Char('O')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Fsmonitor",
+                "Watchman status",
+                vec![KeyCode::Char('O'), KeyCode::Char('s')],
+                CommandTreeNode::new_action(Message::WatchmanStatus),
+            ),
+            (
+                "Fsmonitor",
+                "Use watchman in this repo",
+                vec![KeyCode::Char('O'), KeyCode::Char('w')],
+                CommandTreeNode::new_action(Message::WatchmanEnable),
+            ),
+            (
+                "Commands",
+                "Shell",
+                vec![KeyCode::Char('!')],
+                CommandTreeNode::new_children(),
+            ),
+            (
+                "Shell",
+                "Run command",
+                vec![KeyCode::Char('!'), KeyCode::Char('!')],
+                CommandTreeNode::new_action(Message::ShellCommand),
+            ),
+            (
+                "Shell",
+                "Run command on each revision of range",
+                vec![KeyCode::Char('!'), KeyCode::Char('r')],
+                CommandTreeNode::new_action_with_children(Message::SaveSelection),
+            ),
+            (
+                "Run on range",
+                "Select destination",
+                vec![KeyCode::Char('!'), KeyCode::Char('r'), KeyCode::Enter],
+                CommandTreeNode::new_action(Message::RunOnRevisions),
+            ),
             (
                 "Commands",
                 "Split",
                 vec![KeyCode::Char('/')],
                 CommandTreeNode::new_action(Message::Split),
             ),
+            (
+                "Commands",
+                "Split with descriptions",
+                vec![KeyCode::Char('\\')],
+                CommandTreeNode::new_action(Message::SplitDescribed),
+            ),
             (
                 "Commands",
                 "Sign",
@@ -1007,6 +1542,12 @@ impl CommandTree {
                     range: false,
                 }),
             ),
+            (
+                "Sign",
+                "Selection with key",
+                vec![KeyCode::Char('S'), KeyCode::Char('k')],
+                CommandTreeNode::new_action(Message::SignWithKey),
+            ),
             (
                 "Sign",
                 "From selection to destination",
@@ -1227,6 +1768,17 @@ impl CommandTree {
                     destination: RebaseDestination::Selection,
                 }),
             ),
+            (
+                "Rebase branch onto",
+                "Add another destination",
+                vec![
+                    KeyCode::Char('r'),
+                    KeyCode::Char('b'),
+                    KeyCode::Char('o'),
+                    KeyCode::Char('+'),
+                ],
+                CommandTreeNode::new_repeatable_action(Message::AddRebaseDestination),
+            ),
             (
                 "Rebase branch onto",
                 "Trunk",
@@ -1380,6 +1932,17 @@ impl CommandTree {
                     destination: RebaseDestination::Selection,
                 }),
             ),
+            (
+                "Rebase source onto",
+                "Add another destination",
+                vec![
+                    KeyCode::Char('r'),
+                    KeyCode::Char('s'),
+                    KeyCode::Char('o'),
+                    KeyCode::Char('+'),
+                ],
+                CommandTreeNode::new_repeatable_action(Message::AddRebaseDestination),
+            ),
             (
                 "Rebase source onto",
                 "Trunk",
@@ -1533,6 +2096,17 @@ impl CommandTree {
                     destination: RebaseDestination::Selection,
                 }),
             ),
+            (
+                "Rebase revision onto",
+                "Add another destination",
+                vec![
+                    KeyCode::Char('r'),
+                    KeyCode::Char('r'),
+                    KeyCode::Char('o'),
+                    KeyCode::Char('+'),
+                ],
+                CommandTreeNode::new_repeatable_action(Message::AddRebaseDestination),
+            ),
             (
                 "Rebase revision onto",
                 "Trunk",
@@ -1653,6 +2227,66 @@ impl CommandTree {
                     mode: ViewMode::ToSelection,
                 }),
             ),
+            (
+                "View",
+                "Comparison of selection with trunk",
+                vec![KeyCode::Char('v'), KeyCode::Char('c')],
+                CommandTreeNode::new_action(Message::CompareWithTrunk),
+            ),
+            (
+                "View",
+                "Statistics of the log revset",
+                vec![KeyCode::Char('v'), KeyCode::Char('s')],
+                CommandTreeNode::new_action(Message::ShowStats),
+            ),
+            (
+                "View",
+                "Full description of selection",
+                vec![KeyCode::Char('v'), KeyCode::Char('d')],
+                CommandTreeNode::new_action(Message::ShowDescription),
+            ),
+            (
+                "View",
+                "Export the log to an ANSI or HTML file",
+                vec![KeyCode::Char('v'), KeyCode::Char('x')],
+                CommandTreeNode::new_action(Message::ExportLog),
+            ),
+            (
+                "View",
+                "Toggle dimming commits outside selection's lineage",
+                vec![KeyCode::Char('v'), KeyCode::Char('l')],
+                CommandTreeNode::new_action(Message::ToggleLineage),
+            ),
+            (
+                "View",
+                "Toggle the info pane between the bottom and the right",
+                vec![KeyCode::Char('v'), KeyCode::Char('p')],
+                CommandTreeNode::new_action(Message::ToggleInfoPanePosition),
+            ),
+            (
+                "View",
+                "Diff the selected merge against one parent",
+                vec![KeyCode::Char('v'), KeyCode::Char('o')],
+                CommandTreeNode::new_action(Message::DiffParent),
+            ),
+            (
+                "View",
+                "Toggle focus mode, hiding the header and key hints",
+                vec![KeyCode::Char('v'), KeyCode::Char('F')],
+                CommandTreeNode::new_action(Message::ToggleFocusMode),
+            ),
+            (
+                "View",
+                "Note on selection, kept outside the repo",
+                vec![KeyCode::Char('v'), KeyCode::Char('n')],
+                CommandTreeNode::new_action(Message::EditNote),
+            ),
+            (
+                "View",
+                "Toggle review mode, checking off viewed files",
+                vec![KeyCode::Char('v'), KeyCode::Char('r')],
+                CommandTreeNode::new_action(Message::ToggleReviewMode),
+            ),
             (
                 "View",
                 "From selection to destination",
@@ -1731,6 +2365,12 @@ impl CommandTree {
                     destination: RevertDestination::Selection,
                 }),
             ),
+            (
+                "Commands",
+                "Load full file diff",
+                vec![KeyCode::Char('x')],
+                CommandTreeNode::new_action(Message::LoadFullFileDiff),
+            ),
             (
                 "Commands",
                 "Resolve",
@@ -1749,12 +2389,24 @@ impl CommandTree {
                 vec![KeyCode::Char('u'), KeyCode::Char('u')],
                 CommandTreeNode::new_action(Message::Undo),
             ),
+            (
+                "Undo",
+                "Undo last jjdag action",
+                vec![KeyCode::Char('u'), KeyCode::Char('j')],
+                CommandTreeNode::new_action(Message::UndoAction),
+            ),
             (
                 "Undo",
                 "Redo last operation",
                 vec![KeyCode::Char('u'), KeyCode::Char('r')],
                 CommandTreeNode::new_action(Message::Redo),
             ),
+            (
+                "Undo",
+                "Abandon old operations and gc",
+                vec![KeyCode::Char('u'), KeyCode::Char('a')],
+                CommandTreeNode::new_action(Message::OpAbandon),
+            ),
             (
                 "Commands",
                 "Workspace",
@@ -1851,6 +2503,48 @@ impl CommandTree {
         tree.add_children(items);
         tree
     }
+
+    /// Bind the shell commands from the config under `!`
+    pub fn add_external_commands(&mut self, commands: &[ExternalCommand]) -> Result<()> {
+        for (idx, command) in commands.iter().enumerate() {
+            let key_codes = vec![KeyCode::Char('!'), KeyCode::Char(command.key)];
+            if self.get_node(&key_codes).is_some() {
+                bail!(
+                    "Key '{}' of command '{}' is already bound under '!'",
+                    command.key,
+                    command.name
+                );
+            }
+            self.add_children(vec![(
+                "Shell",
+                &command.name,
+                key_codes,
+                CommandTreeNode::new_action(Message::ExternalCommand { idx }),
+            )]);
+        }
+        Ok(())
+    }
+
+    /// Bind the functions the hooks script registered with `bind()` under `,`
+    pub fn add_script_bindings(&mut self, bindings: &[ScriptBinding]) -> Result<()> {
+        for (idx, binding) in bindings.iter().enumerate() {
+            let key_codes = vec![KeyCode::Char(','), KeyCode::Char(binding.key)];
+            if self.get_node(&key_codes).is_some() {
+                bail!(
+                    "Key '{}' of script binding '{}' is already bound under ','",
+                    binding.key,
+                    binding.description
+                );
+            }
+            self.add_children(vec![(
+                "Scripts",
+                &binding.description,
+                key_codes,
+                CommandTreeNode::new_action(Message::ScriptBinding { idx }),
+            )]);
+        }
+        Ok(())
+    }
 }
 
 fn render_help_text(entries: HelpEntries) -> Text<'static> {
@@ -1874,15 +2568,13 @@ fn render_help_text(entries: HelpEntries) -> Text<'static> {
                 } else {
                     String::new()
                 };
+                let header_padding = " ".repeat(COL_WIDTH.saturating_sub(header.width()));
                 col_lines.push(Line::from(vec![Span::styled(
-                    format!("{header:COL_WIDTH$}"),
+                    header + header_padding.as_str(),
                     Style::default().fg(Color::Blue),
                 )]));
                 col_lines.extend(chunk.into_iter().map(|(key, help)| {
-                    let mut num_cols = key.len() + 1 + help.len();
-                    if !key.is_ascii() {
-                        num_cols -= 2;
-                    }
+                    let num_cols = key.width() + 1 + help.width();
                     let padding = " ".repeat(COL_WIDTH.saturating_sub(num_cols));
                     Line::from(vec![
                         Span::styled(key, Style::default().fg(Color::Green)),
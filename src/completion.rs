@@ -0,0 +1,121 @@
+/// Characters that end a word in a revset, so `main | fo` completes `fo`
+const REVSET_DELIMITERS: [char; 8] = [' ', '|', '&', '~', '(', ')', ',', ':'];
+
+/// Where the word before the end of the input starts in a revset
+pub fn revset_word_start(input: &str) -> usize {
+    input.rfind(REVSET_DELIMITERS).map_or(0, |idx| idx + 1)
+}
+
+/// Where the last component of a path starts
+pub fn path_word_start(input: &str) -> usize {
+    input.rfind('/').map_or(0, |idx| idx + 1)
+}
+
+/// Entries of the directory a path is being typed in, directories first and
+/// ending in `/`. Hidden entries are left out unless the name typed so far
+/// starts with a dot.
+pub fn path_candidates(input: &str) -> Vec<String> {
+    let (dir, name) = input.split_at(path_word_start(input));
+    let dir = if dir.is_empty() {
+        ".".to_string()
+    } else {
+        crate::config::expand_home(dir)
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<(bool, String)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name.starts_with('.') && !name.starts_with('.') {
+                return None;
+            }
+            // Follows symlinks, so a link to a directory completes like one
+            let is_dir = entry.path().is_dir();
+            Some((!is_dir, file_name))
+        })
+        .collect();
+    entries.sort();
+    entries
+        .into_iter()
+        .map(|(is_file, file_name)| {
+            if is_file {
+                file_name
+            } else {
+                format!("{file_name}/")
+            }
+        })
+        .collect()
+}
+
+/// Matches for the word before the cursor in a text input, cycled with Tab
+#[derive(Debug)]
+pub struct Completion {
+    /// Byte index where the word being completed starts
+    word_start: usize,
+    pub matches: Vec<String>,
+    pub selected: usize,
+    /// The input and cursor as this completion left them, so that typing
+    /// anything else ends it
+    input: (String, usize),
+}
+
+impl Completion {
+    /// Fill in the first match (or the last, going in reverse) for the word
+    /// from `word_start` to the cursor, or return `None` if nothing starts
+    /// with it
+    pub fn start(
+        input: &mut String,
+        cursor: &mut usize,
+        word_start: usize,
+        candidates: impl IntoIterator<Item = String>,
+        reverse: bool,
+    ) -> Option<Self> {
+        let word = &input[word_start..*cursor];
+        let mut matches: Vec<String> = Vec::new();
+        for candidate in candidates {
+            if candidate.starts_with(word) && candidate != word && !matches.contains(&candidate) {
+                matches.push(candidate);
+            }
+        }
+        if matches.is_empty() {
+            return None;
+        }
+        let mut completion = Self {
+            word_start,
+            selected: if reverse { matches.len() - 1 } else { 0 },
+            matches,
+            input: (String::new(), 0),
+        };
+        completion.apply(input, cursor);
+        Some(completion)
+    }
+
+    /// Whether the input is still as this completion left it
+    pub fn is_current(&self, input: &str, cursor: usize) -> bool {
+        self.input.0 == input && self.input.1 == cursor
+    }
+
+    /// Replace the filled in match with the next or previous one
+    pub fn cycle(&mut self, input: &mut String, cursor: &mut usize, reverse: bool) {
+        let count = self.matches.len();
+        self.selected = if reverse {
+            (self.selected + count - 1) % count
+        } else {
+            (self.selected + 1) % count
+        };
+        self.apply(input, cursor);
+    }
+
+    pub fn word_start(&self) -> usize {
+        self.word_start
+    }
+
+    fn apply(&mut self, input: &mut String, cursor: &mut usize) {
+        let selected = &self.matches[self.selected];
+        input.replace_range(self.word_start..*cursor, selected);
+        *cursor = self.word_start + selected.len();
+        self.input = (input.clone(), *cursor);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{keymap::KeymapPreset, log_tree::UnfoldMode, theme::Theme, view::InfoPanePosition};
+use anyhow::{Context, Result, anyhow};
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::{collections::HashMap, path::PathBuf, str::FromStr};
+
+/// User configuration, read from `$XDG_CONFIG_HOME/jjdag/config.toml`
+/// (falling back to `~/.config/jjdag/config.toml`)
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Which set of navigation keys to start from
+    pub keymap: KeymapPreset,
+    /// Built-in color theme
+    pub theme: Theme,
+    /// Extra bindings layered on top of the preset, e.g. `"C-j" = "select-next"`
+    pub keys: HashMap<String, String>,
+    /// Whether unfolding a commit lists its changed files or only its diffstat
+    pub unfold: UnfoldMode,
+    /// Whether command output shows below the log or beside it
+    pub info_pane: InfoPanePosition,
+    /// Directory whose jj repositories are offered when launched outside a repo
+    pub projects_root: Option<String>,
+    /// Programs that open files instead of $EDITOR, tried in order
+    pub openers: Vec<Opener>,
+    /// Shell commands bound under `!`, filled in like the ones typed at `!!`
+    pub commands: Vec<ExternalCommand>,
+    /// Descriptions to start from when describing or committing
+    pub templates: Vec<DescriptionTemplate>,
+    /// Hints shown below the inline description editor
+    pub description_lint: DescriptionLintConfig,
+    /// Trailers offered when adding one to a description, with `{name}` and
+    /// `{email}` taken from jj's user settings
+    pub trailers: Vec<String>,
+    /// Command that reports the CI status of a commit
+    pub ci: Option<CiProvider>,
+    /// Revset whose bookmarks tugging moves, `DEFAULT_TUG_REVSET` if unset
+    pub tug_revset: Option<String>,
+    /// Tags computed with jj templates and shown on log lines
+    pub badges: Vec<Badge>,
+    /// Milliseconds between redraws while the screen changes without input,
+    /// as the log viewer does following the log file, `DEFAULT_TICK_MS` if
+    /// unset. Otherwise jjdag sleeps until there's input or a toast to clear.
+    pub tick_ms: Option<u64>,
+    /// How many command outputs `[` pages back through,
+    /// `DEFAULT_OUTPUT_HISTORY` if unset
+    pub output_history: Option<usize>,
+    /// Desktop notifications for long fetches and pushes
+    pub notifications: NotificationConfig,
+    /// Pin the header of the file being read above a scrolled diff, not
+    /// only its commit's
+    pub sticky_file_header: bool,
+}
+
+pub const DEFAULT_TICK_MS: u64 = 200;
+
+pub const DEFAULT_OUTPUT_HISTORY: usize = 20;
+
+/// The bookmarks closest below the working copy
+pub const DEFAULT_TUG_REVSET: &str = "heads(::@- & bookmarks())";
+
+/// A tag appended to the first line of a commit in the log. Without a
+/// `label`, whatever `template` prints is the tag, and commits it prints
+/// nothing for get none. With one, `template` is a condition and the label is
+/// shown when it's true.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Badge {
+    pub template: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// A color name like `"yellow"` or `"light-blue"`, or `"#rrggbb"`
+    #[serde(default = "default_badge_color")]
+    pub color: String,
+}
+
+fn default_badge_color() -> String {
+    "cyan".to_string()
+}
+
+impl Badge {
+    /// The template printing the tag's text for a commit
+    pub fn text_template(&self) -> String {
+        match &self.label {
+            Some(label) => format!("if({}, {label:?})", self.template),
+            None => format!("({})", self.template),
+        }
+    }
+
+    pub fn color(&self) -> Result<Color> {
+        Color::from_str(&self.color).map_err(|_| anyhow!("Invalid badge color {}", self.color))
+    }
+}
+
+/// A shell command printing a commit's CI status, with `{commit_id}`,
+/// `{change_id}` and `{bookmark}` filled in
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CiProvider {
+    pub command: String,
+    /// Also check every loaded commit when refreshing the log
+    #[serde(default)]
+    pub on_refresh: bool,
+}
+
+/// Text the inline description editor starts with. The cursor starts at the
+/// first `{placeholder}` and Tab moves to the next.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DescriptionTemplate {
+    pub name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DescriptionLintConfig {
+    /// Columns allowed in the first line
+    pub subject_length: usize,
+    /// Columns allowed in the other lines
+    pub body_length: usize,
+    pub blank_second_line: bool,
+    pub rules: Vec<DescriptionLintRule>,
+}
+
+impl Default for DescriptionLintConfig {
+    fn default() -> Self {
+        Self {
+            subject_length: 50,
+            body_length: 72,
+            blank_second_line: true,
+            rules: vec![
+                DescriptionLintRule {
+                    pattern: r"^(\w+(\(.*\))?!?: )?(Added|Adds|Fixed|Fixes|Changed|Changes|Removed|Removes|Updated|Updates)\b".to_string(),
+                    message: "Use the imperative mood: \"Add\", not \"Added\"".to_string(),
+                },
+                DescriptionLintRule {
+                    pattern: r"\.$".to_string(),
+                    message: "Leave the period off the subject".to_string(),
+                },
+            ],
+        }
+    }
+}
+
+/// When a fetch or push that took at least `min_seconds` finishes while the
+/// terminal isn't focused, a desktop notification says how it went
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    pub min_seconds: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_seconds: 10,
+        }
+    }
+}
+
+/// A regex that the subject shouldn't match, and the hint shown when it does
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DescriptionLintRule {
+    pub pattern: String,
+    pub message: String,
+}
+
+/// A named shell command run with `!` followed by its key
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalCommand {
+    pub key: char,
+    pub name: String,
+    pub command: String,
+}
+
+/// A shell command that opens the files matching a glob, with `{path}` and
+/// `{line}` replaced by the file and the selected line
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Opener {
+    /// Matched against the file name, or the whole path if it contains a `/`
+    pub glob: String,
+    pub command: String,
+}
+
+impl Opener {
+    pub fn matches(&self, path: &str) -> bool {
+        let target = if self.glob.contains('/') {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        glob_matches(self.glob.as_bytes(), target.as_bytes())
+    }
+
+    /// The command line with its placeholders filled in
+    pub fn command_line(&self, path: &str, line_num: Option<u32>) -> String {
+        self.command
+            .replace("{path}", &shell_quote(path))
+            .replace("{line}", &line_num.unwrap_or(1).to_string())
+    }
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Replace each `{name}` in a shell command with its quoted value, or return
+/// the name of the first placeholder used that has no value
+pub fn fill_placeholders<'a>(
+    command: &str,
+    values: &[(&'a str, Option<&str>)],
+) -> Result<String, &'a str> {
+    let mut command_line = command.to_string();
+    for (name, value) in values {
+        let placeholder = format!("{{{name}}}");
+        if !command_line.contains(&placeholder) {
+            continue;
+        }
+        let value = value.ok_or(*name)?;
+        command_line = command_line.replace(&placeholder, &shell_quote(value));
+    }
+    Ok(command_line)
+}
+
+/// Match `*` (within a path component), `**` (across components) and `?`
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|idx| glob_matches(rest, &text[idx..])),
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|idx| !text[..*idx].contains(&b'/'))
+            .any(|idx| glob_matches(rest, &text[idx..])),
+        [b'?', rest @ ..] => {
+            matches!(text, [c, text_rest @ ..] if *c != b'/' && glob_matches(rest, text_rest))
+        }
+        [c, rest @ ..] => {
+            matches!(text, [t, text_rest @ ..] if t == c && glob_matches(rest, text_rest))
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, or the defaults if there is none
+    pub fn load() -> Result<Self> {
+        let Some(path) = config_dir().map(|dir| dir.join("config.toml")) else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        log::info!("Loading config from {:?}", path);
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Cannot read config file {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+}
+
+/// The path with a leading `~/` replaced by the home directory
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}/{rest}"),
+        _ => path.to_string(),
+    }
+}
+
+/// Directory holding jjdag's config file
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("jjdag"))
+}
@@ -0,0 +1,155 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+/// jj makes markers longer than any run of the same character in the file,
+/// but never shorter than this
+const MIN_MARKER_LENGTH: usize = 7;
+/// Lines shown around each conflict
+const CONTEXT_LINES: usize = 3;
+const SIDE_COLORS: [Color; 2] = [Color::Cyan, Color::Magenta];
+
+/// What the lines under the last marker are
+#[derive(Debug, Clone, Copy)]
+enum Section {
+    Outside,
+    /// Between the conflict start and the first side, in jj's diff style
+    Start,
+    /// A diff from the base to the numbered side
+    Diff(usize),
+    /// The contents of the numbered side
+    Side(usize),
+    Base,
+}
+
+/// The file's conflicts with their markers, each side's label and lines in
+/// its own color, or `None` if the file has no conflict markers
+pub fn render(path: &str, content: &str) -> Option<Text<'static>> {
+    let mut lines: Vec<(Line<'static>, bool)> = Vec::new();
+    let mut section = Section::Outside;
+    let mut side = 0;
+    let mut conflict_count = 0;
+
+    for line in content.lines() {
+        let styled = match (marker(line), section) {
+            (Some(('<', label)), Section::Outside) => {
+                conflict_count += 1;
+                side = 0;
+                // Git-style conflicts start with the first side
+                section = if label.contains("side #") {
+                    side += 1;
+                    Section::Side(side)
+                } else {
+                    Section::Start
+                };
+                marker_line(line, conflict_style())
+            }
+            (_, Section::Outside) => {
+                lines.push((Line::raw(line.to_string()), false));
+                continue;
+            }
+            (Some(('>', _)), _) => {
+                section = Section::Outside;
+                marker_line(line, conflict_style())
+            }
+            (Some(('%', _)), _) => {
+                side += 1;
+                section = Section::Diff(side);
+                marker_line(line, side_style(side))
+            }
+            (Some(('+' | '=', _)), _) => {
+                side += 1;
+                section = Section::Side(side);
+                marker_line(line, side_style(side))
+            }
+            (Some(('-' | '|', _)), _) => {
+                section = Section::Base;
+                marker_line(line, base_style())
+            }
+            // The second line of a diff's label
+            (Some(('\\', _)), Section::Diff(side)) => marker_line(line, side_style(side)),
+            (_, Section::Diff(_)) => {
+                let style = match line.chars().next() {
+                    Some('+') => Style::default().fg(Color::Green),
+                    Some('-') => Style::default().fg(Color::Red),
+                    _ => Style::default(),
+                };
+                Line::styled(line.to_string(), style)
+            }
+            (_, Section::Side(side)) => Line::styled(line.to_string(), side_style(side)),
+            (_, Section::Base) => Line::styled(line.to_string(), base_style()),
+            (_, Section::Start) => Line::raw(line.to_string()),
+        };
+        lines.push((styled, true));
+    }
+
+    if conflict_count == 0 {
+        return None;
+    }
+
+    let noun = if conflict_count == 1 {
+        "conflict"
+    } else {
+        "conflicts"
+    };
+    let mut text = Text::from(Line::styled(
+        format!("{conflict_count} {noun} in {path}"),
+        Style::default().fg(Color::Cyan),
+    ));
+    // Only the conflicts and a few lines around them
+    let near_conflict = |idx: usize| {
+        let start = idx.saturating_sub(CONTEXT_LINES);
+        let end = (idx + CONTEXT_LINES + 1).min(lines.len());
+        lines[start..end]
+            .iter()
+            .any(|(_, in_conflict)| *in_conflict)
+    };
+    let mut skipped = false;
+    for (idx, (line, _)) in lines.iter().enumerate() {
+        if !near_conflict(idx) {
+            skipped = true;
+            continue;
+        }
+        if skipped {
+            text.push_line(Line::styled("⋮", Style::default().fg(Color::DarkGray)));
+            skipped = false;
+        }
+        text.push_line(line.clone());
+    }
+    if skipped {
+        text.push_line(Line::styled("⋮", Style::default().fg(Color::DarkGray)));
+    }
+    Some(text)
+}
+
+/// The character a conflict marker line is made of and the label after it
+fn marker(line: &str) -> Option<(char, &str)> {
+    let first = line.chars().next().filter(|c| "<>%+-|=\\".contains(*c))?;
+    let length = line.chars().take_while(|c| *c == first).count();
+    let label = &line[length..];
+    (length >= MIN_MARKER_LENGTH && (label.is_empty() || label.starts_with(' ')))
+        .then(|| (first, label.trim()))
+}
+
+/// A marker in the section's style with its label in bold
+fn marker_line(line: &str, style: Style) -> Line<'static> {
+    let label_start = line.find(' ').unwrap_or(line.len());
+    let (marker, label) = line.split_at(label_start);
+    Line::from(vec![
+        Span::styled(marker.to_string(), style),
+        Span::styled(label.to_string(), style.add_modifier(Modifier::BOLD)),
+    ])
+}
+
+fn conflict_style() -> Style {
+    Style::default().fg(Color::Red)
+}
+
+fn base_style() -> Style {
+    Style::default().fg(Color::Yellow)
+}
+
+fn side_style(side: usize) -> Style {
+    Style::default().fg(SIDE_COLORS[side.saturating_sub(1) % SIDE_COLORS.len()])
+}
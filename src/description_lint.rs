@@ -0,0 +1,81 @@
+use crate::config::DescriptionLintConfig;
+use anyhow::{Context, Result};
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+/// Checks a description as it's typed in the inline editor
+#[derive(Debug)]
+pub struct DescriptionLinter {
+    pub subject_length: usize,
+    pub body_length: usize,
+    blank_second_line: bool,
+    /// Subject patterns with the hint shown when they match
+    rules: Vec<(Regex, String)>,
+}
+
+impl DescriptionLinter {
+    pub fn new(config: &DescriptionLintConfig) -> Result<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| {
+                let regex = Regex::new(&rule.pattern).with_context(|| {
+                    format!("Invalid description lint pattern {}", rule.pattern)
+                })?;
+                Ok((regex, rule.message.clone()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            subject_length: config.subject_length,
+            body_length: config.body_length,
+            blank_second_line: config.blank_second_line,
+            rules,
+        })
+    }
+
+    /// Column past which a line of the description is too long
+    pub fn line_limit(&self, line_idx: usize) -> usize {
+        if line_idx == 0 {
+            self.subject_length
+        } else {
+            self.body_length
+        }
+    }
+
+    pub fn hints(&self, description: &str) -> Vec<String> {
+        let mut lines = description.split('\n');
+        let subject = lines.next().unwrap_or_default();
+        let mut hints = Vec::new();
+        if subject.width() > self.subject_length {
+            hints.push(format!(
+                "Subject is {} columns, over {}",
+                subject.width(),
+                self.subject_length
+            ));
+        }
+        if self.blank_second_line && lines.next().is_some_and(|line| !line.trim().is_empty()) {
+            hints.push("Leave the second line blank".to_string());
+        }
+        let long_lines: Vec<String> = description
+            .split('\n')
+            .enumerate()
+            .skip(1)
+            .filter(|(_, line)| line.width() > self.body_length)
+            .map(|(idx, _)| (idx + 1).to_string())
+            .collect();
+        if !long_lines.is_empty() {
+            hints.push(format!(
+                "Line {} over {} columns",
+                long_lines.join(", "),
+                self.body_length
+            ));
+        }
+        hints.extend(
+            self.rules
+                .iter()
+                .filter(|(regex, _)| regex.is_match(subject))
+                .map(|(_, message)| message.clone()),
+        );
+        hints
+    }
+}
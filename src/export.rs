@@ -0,0 +1,234 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Text,
+};
+use std::fmt::Write;
+
+/// Whether a path asks for HTML rather than ANSI text
+pub fn is_html_path(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".html") || path.ends_with(".htm")
+}
+
+/// The log as text with ANSI color codes, one line per rendered line
+pub fn to_ansi(items: &[Text]) -> String {
+    let mut out = String::new();
+    for spans in styled_lines(items) {
+        for (style, content) in spans {
+            let codes = sgr_codes(style);
+            if codes.is_empty() {
+                out.push_str(&content);
+            } else {
+                let _ = write!(out, "\x1b[{}m{content}\x1b[0m", codes.join(";"));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The log as a standalone HTML page, with the colors inlined
+pub fn to_html(items: &[Text], title: &str) -> String {
+    let mut body = String::new();
+    for spans in styled_lines(items) {
+        for (style, content) in spans {
+            let css = css(style);
+            if css.is_empty() {
+                body.push_str(&escape_html(&content));
+            } else {
+                let _ = write!(
+                    body,
+                    r#"<span style="{css}">{}</span>"#,
+                    escape_html(&content)
+                );
+            }
+        }
+        body.push('\n');
+    }
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>{}</title>\n<style>\n",
+            "body {{ background: #1e1e1e; color: #d4d4d4; }}\n",
+            "pre {{ font-family: ui-monospace, monospace; line-height: 1.2; }}\n",
+            "</style>\n</head>\n<body>\n<pre>\n{}</pre>\n</body>\n</html>\n",
+        ),
+        escape_html(title),
+        body
+    )
+}
+
+/// Each line's spans with the item and line styles folded in
+fn styled_lines(items: &[Text]) -> Vec<Vec<(Style, String)>> {
+    items
+        .iter()
+        .flat_map(|item| {
+            item.lines.iter().map(move |line| {
+                let line_style = item.style.patch(line.style);
+                let mut spans: Vec<(Style, String)> = line
+                    .spans
+                    .iter()
+                    .filter(|span| !span.content.is_empty())
+                    .map(|span| (line_style.patch(span.style), span.content.to_string()))
+                    .collect();
+                // Trailing spaces are padding, not content
+                while spans
+                    .last()
+                    .is_some_and(|(_, content)| content.trim_end().is_empty())
+                {
+                    spans.pop();
+                }
+                spans
+            })
+        })
+        .collect()
+}
+
+fn sgr_codes(style: Style) -> Vec<String> {
+    let mut codes = Vec::new();
+    let modifiers = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::CROSSED_OUT, "9"),
+    ];
+    for (modifier, code) in modifiers {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    if let Some(color) = style.fg.and_then(|color| sgr_color(color, 30)) {
+        codes.push(color);
+    }
+    if let Some(color) = style.bg.and_then(|color| sgr_color(color, 40)) {
+        codes.push(color);
+    }
+    codes
+}
+
+/// The SGR parameters for a color, with `base` 30 for foreground and 40 for
+/// background
+fn sgr_color(color: Color, base: u8) -> Option<String> {
+    Some(match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", base + 8),
+        Color::Indexed(idx) => format!("{};5;{idx}", base + 8),
+        color => {
+            let idx = ansi_index(color)?;
+            if idx < 8 {
+                (base + idx).to_string()
+            } else {
+                (base + 60 + idx - 8).to_string()
+            }
+        }
+    })
+}
+
+/// Position of a named color in the 16 color palette
+fn ansi_index(color: Color) -> Option<u8> {
+    Some(match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        Color::Reset | Color::Rgb(..) | Color::Indexed(_) => return None,
+    })
+}
+
+/// xterm's default 16 color palette
+const PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+fn hex_color(color: Color) -> Option<String> {
+    let (r, g, b) = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(idx) => indexed_rgb(idx),
+        color => PALETTE[ansi_index(color)? as usize],
+    };
+    Some(format!("#{r:02x}{g:02x}{b:02x}"))
+}
+
+/// The 256 color palette: the 16 named colors, a 6x6x6 cube and a gray ramp
+fn indexed_rgb(idx: u8) -> (u8, u8, u8) {
+    match idx {
+        0..16 => PALETTE[idx as usize],
+        16..232 => {
+            let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+            let idx = idx - 16;
+            (level(idx / 36), level(idx / 6 % 6), level(idx % 6))
+        }
+        _ => {
+            let gray = 8 + (idx - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn css(style: Style) -> String {
+    let (mut fg, mut bg) = (style.fg, style.bg);
+    if style.add_modifier.contains(Modifier::REVERSED) {
+        // Unset colors are the page's
+        (fg, bg) = (
+            bg.or(Some(Color::Rgb(30, 30, 30))),
+            fg.or(Some(Color::Rgb(212, 212, 212))),
+        );
+    }
+    let mut declarations = Vec::new();
+    if let Some(color) = fg.and_then(hex_color) {
+        declarations.push(format!("color: {color}"));
+    }
+    if let Some(color) = bg.and_then(hex_color) {
+        declarations.push(format!("background: {color}"));
+    }
+    let modifiers = [
+        (Modifier::BOLD, "font-weight: bold"),
+        (Modifier::DIM, "opacity: 0.6"),
+        (Modifier::ITALIC, "font-style: italic"),
+        (Modifier::UNDERLINED, "text-decoration: underline"),
+        (Modifier::CROSSED_OUT, "text-decoration: line-through"),
+    ];
+    for (modifier, declaration) in modifiers {
+        if style.add_modifier.contains(modifier) {
+            declarations.push(declaration.to_string());
+        }
+    }
+    declarations.join("; ")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,152 @@
+use ratatui::widgets::ListState;
+use std::collections::HashSet;
+
+/// The files of one revision, browsed as a directory tree
+#[derive(Debug)]
+pub struct FileBrowser {
+    pub change_id: String,
+    /// Files can be opened directly rather than through a temporary copy
+    pub is_working_copy: bool,
+    /// File paths, ordered so directories come before files at each level
+    files: Vec<String>,
+    expanded_dirs: HashSet<String>,
+    pub list_state: ListState,
+}
+
+/// A visible line of the tree
+#[derive(Debug, Clone)]
+pub struct FileBrowserRow {
+    pub path: String,
+    pub name: String,
+    pub depth: usize,
+    pub is_dir: bool,
+    pub expanded: bool,
+}
+
+impl FileBrowser {
+    pub fn new(change_id: String, is_working_copy: bool, mut files: Vec<String>) -> Self {
+        files.sort_by_cached_key(|path| {
+            let components: Vec<&str> = path.split('/').collect();
+            let last = components.len() - 1;
+            components
+                .into_iter()
+                .enumerate()
+                .map(|(idx, name)| (idx == last, name.to_string()))
+                .collect::<Vec<_>>()
+        });
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+        Self {
+            change_id,
+            is_working_copy,
+            files,
+            expanded_dirs: HashSet::new(),
+            list_state,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Directories and files inside expanded directories, in display order
+    pub fn rows(&self) -> Vec<FileBrowserRow> {
+        let mut rows = Vec::new();
+        let mut current_dirs: Vec<&str> = Vec::new();
+        for path in &self.files {
+            let components: Vec<&str> = path.split('/').collect();
+            let (name, dirs) = components.split_last().expect("split yields a component");
+
+            // Rows for the directories not shared with the previous file
+            let shared = current_dirs
+                .iter()
+                .zip(dirs)
+                .take_while(|(a, b)| a == b)
+                .count();
+            current_dirs = dirs.to_vec();
+            for depth in shared..dirs.len() {
+                if !self.ancestors_expanded(dirs, depth) {
+                    break;
+                }
+                let dir_path = dirs[..=depth].join("/");
+                rows.push(FileBrowserRow {
+                    expanded: self.expanded_dirs.contains(&dir_path),
+                    path: dir_path,
+                    name: dirs[depth].to_string(),
+                    depth,
+                    is_dir: true,
+                });
+            }
+
+            if self.ancestors_expanded(dirs, dirs.len()) {
+                rows.push(FileBrowserRow {
+                    path: path.clone(),
+                    name: name.to_string(),
+                    depth: dirs.len(),
+                    is_dir: false,
+                    expanded: false,
+                });
+            }
+        }
+        rows
+    }
+
+    /// Whether the first `depth` directories are all expanded
+    fn ancestors_expanded(&self, dirs: &[&str], depth: usize) -> bool {
+        (1..=depth).all(|len| self.expanded_dirs.contains(&dirs[..len].join("/")))
+    }
+
+    pub fn selected_row(&self) -> Option<FileBrowserRow> {
+        let rows = self.rows();
+        rows.get(self.list_state.selected().unwrap_or_default())
+            .cloned()
+    }
+
+    /// Move the selection, clamped to the visible rows
+    pub fn move_selection(&mut self, delta: i32) {
+        let len = self.rows().len();
+        let selected = self
+            .list_state
+            .selected()
+            .unwrap_or_default()
+            .saturating_add_signed(delta as isize)
+            .min(len.saturating_sub(1));
+        self.list_state.select(Some(selected));
+    }
+
+    /// Expand or collapse the selected directory
+    pub fn toggle_selected_dir(&mut self) {
+        let Some(row) = self.selected_row().filter(|row| row.is_dir) else {
+            return;
+        };
+        if !self.expanded_dirs.remove(&row.path) {
+            self.expanded_dirs.insert(row.path);
+        }
+    }
+
+    pub fn expand_selected_dir(&mut self) {
+        if let Some(row) = self
+            .selected_row()
+            .filter(|row| row.is_dir && !row.expanded)
+        {
+            self.expanded_dirs.insert(row.path);
+        }
+    }
+
+    /// Collapse the selected directory, or select the parent directory
+    pub fn collapse_selected(&mut self) {
+        let Some(row) = self.selected_row() else {
+            return;
+        };
+        if row.is_dir && row.expanded {
+            self.expanded_dirs.remove(&row.path);
+            return;
+        }
+        let Some((parent, _)) = row.path.rsplit_once('/') else {
+            return;
+        };
+        if let Some(idx) = self.rows().iter().position(|row| row.path == parent) {
+            self.list_state.select(Some(idx));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    config::Config,
+    model::Model,
+    session::{self, ReplayStep},
+    terminal::{self, Term},
+    update::{handle_input_event, handle_key_press},
+    view::view,
+};
+use anyhow::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{Terminal, backend::TestBackend, buffer::Buffer, layout::Rect};
+
+/// jjdag running without a terminal: keys are fed in and the screen is
+/// rendered into a buffer, for tools and tests that drive the UI
+pub struct Headless {
+    model: Model,
+    terminal: Terminal<TestBackend>,
+    /// Only used by commands that hand the terminal over to jj
+    detached_terminal: Term,
+}
+
+impl Headless {
+    pub fn new(
+        repository: String,
+        revset: String,
+        config: &Config,
+        width: u16,
+        height: u16,
+    ) -> Result<Self> {
+        let model = Model::new(repository, revset, config)?;
+        let mut headless = Self {
+            model,
+            terminal: Terminal::new(TestBackend::new(width, height))?,
+            detached_terminal: terminal::detached_terminal(Rect::new(0, 0, width, height))?,
+        };
+        headless.draw()?;
+        Ok(headless)
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn model_mut(&mut self) -> &mut Model {
+        &mut self.model
+    }
+
+    /// Handle a key press, run the jj commands it queued and any macro it
+    /// played, then render the result
+    pub fn send_key(&mut self, key: KeyEvent) -> Result<()> {
+        session::record_event(&Event::Key(key));
+        self.model.macros.record(key);
+        handle_key_press(self.detached_terminal.clone(), &mut self.model, key)?;
+        self.run_queued()?;
+        self.draw()
+    }
+
+    /// Handle an input event as if it had been read from the terminal, then
+    /// render the result
+    pub fn send_event(&mut self, event: Event) -> Result<()> {
+        session::record_event(&event);
+        handle_input_event(self.detached_terminal.clone(), &mut self.model, event)?;
+        self.run_queued()?;
+        self.draw()
+    }
+
+    /// Play back the events of a session started with
+    /// [`session::start_replay`], then render the result
+    pub fn replay(&mut self) -> Result<()> {
+        while let Some(step) = session::next_replay_step() {
+            match step {
+                ReplayStep::Event(event) => {
+                    handle_input_event(self.detached_terminal.clone(), &mut self.model, event)?;
+                    self.run_queued()?;
+                }
+                ReplayStep::Finished => self.model.show_toast("Replay finished"),
+            }
+        }
+        self.draw()
+    }
+
+    /// Run the jj commands queued and the keys of any macro played
+    fn run_queued(&mut self) -> Result<()> {
+        loop {
+            if self.model.has_queued_jj_commands() {
+                self.model.process_jj_command_queue()?;
+            } else if let Some(key) = self.model.next_macro_key() {
+                handle_key_press(self.detached_terminal.clone(), &mut self.model, key)?;
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for the CI statuses being checked in the background, then render
+    /// the result
+    pub fn wait_for_ci(&mut self) -> Result<()> {
+        self.model.receive_ci_statuses(true);
+        self.draw()
+    }
+
+    /// Type each character of `keys` as an unmodified key press, e.g. `"gpp"`
+    pub fn send_keys(&mut self, keys: &str) -> Result<()> {
+        for c in keys.chars() {
+            self.send_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))?;
+        }
+        Ok(())
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) -> Result<()> {
+        self.terminal.backend_mut().resize(width, height);
+        self.terminal.resize(Rect::new(0, 0, width, height))?;
+        self.model.dirty = true;
+        self.draw()
+    }
+
+    /// The screen as last rendered
+    pub fn buffer(&self) -> &Buffer {
+        self.terminal.backend().buffer()
+    }
+
+    /// The text on screen, one line per row, without styles or trailing spaces
+    pub fn screen(&self) -> String {
+        let buffer = self.buffer();
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| {
+                let row: String = (area.left()..area.right())
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect();
+                row.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Render the screen if anything changed since it was last rendered, as
+    /// the terminal frontend does
+    fn draw(&mut self) -> Result<()> {
+        self.model.expire_toasts();
+        if std::mem::take(&mut self.model.dirty) {
+            self.terminal.draw(|frame| view(&mut self.model, frame))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+use crate::terminal::Term;
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use crossterm::{
+    cursor::MoveTo,
+    event::{self, Event, KeyEventKind},
+    queue,
+};
+use image::{DynamicImage, ImageFormat, imageops::FilterType};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use std::io::{Write, stdout};
+
+const IMAGE_EXTENSIONS: [&str; 9] = [
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "tif", "tiff",
+];
+
+/// Kitty graphics payloads are sent in chunks of at most this many bytes
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Ways of drawing images in a terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Iterm,
+    Sixel,
+}
+
+impl GraphicsProtocol {
+    /// Guess the protocol from the variables the terminal sets, since asking the
+    /// terminal would race with the event loop for its reply
+    pub fn detect() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let term_program = var("TERM_PROGRAM");
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || matches!(term.as_str(), "xterm-kitty" | "xterm-ghostty")
+            || term_program == "ghostty"
+        {
+            Some(Self::Kitty)
+        } else if matches!(term_program.as_str(), "iTerm.app" | "WezTerm") {
+            Some(Self::Iterm)
+        } else if term.contains("sixel")
+            || matches!(term.as_str(), "foot" | "foot-extra" | "mlterm")
+            || term_program == "contour"
+        {
+            Some(Self::Sixel)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn is_image_path(path: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, extension)| {
+        IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+    })
+}
+
+/// Show a file's image before and after a change side by side until a key is
+/// pressed. `None` means the file doesn't exist on that side.
+pub fn show(
+    terminal: &Term,
+    protocol: GraphicsProtocol,
+    path: &str,
+    before: Option<Vec<u8>>,
+    after: Option<Vec<u8>>,
+) -> Result<()> {
+    let mut panes = Vec::new();
+    terminal.borrow_mut().draw(|frame| {
+        let area = popup_area(frame.area());
+        let [images_area, help_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        let [before_area, after_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(images_area);
+        frame.render_widget(Clear, area);
+
+        for (title, contents, pane_area) in [
+            ("Before", &before, before_area),
+            ("After", &after, after_area),
+        ] {
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {title}: {path} "));
+            let inner = block.inner(pane_area);
+            frame.render_widget(block, pane_area);
+            match contents {
+                Some(contents) => panes.push((inner, contents)),
+                None => frame.render_widget(
+                    Paragraph::new("(no file)").style(Style::default().fg(Color::DarkGray)),
+                    inner,
+                ),
+            }
+        }
+        frame.render_widget(
+            Paragraph::new(Line::from("Press any key to close").centered())
+                .style(Style::default().fg(Color::DarkGray)),
+            help_area,
+        );
+    })?;
+
+    let cell_size = cell_size();
+    let mut out = stdout();
+    for (area, contents) in panes {
+        let escape = match protocol {
+            GraphicsProtocol::Iterm => Some(iterm_escape(contents, area)),
+            GraphicsProtocol::Kitty => decode(contents)
+                .map(|image| kitty_escape(contents, &image, area, cell_size))
+                .transpose()?,
+            GraphicsProtocol::Sixel => {
+                decode(contents).map(|image| sixel_escape(&image, area, cell_size))
+            }
+        };
+        queue!(out, MoveTo(area.x, area.y))?;
+        match escape {
+            Some(escape) => out.write_all(escape.as_bytes())?,
+            None => out.write_all(b"(cannot decode image)")?,
+        }
+    }
+    out.flush()?;
+
+    loop {
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            break;
+        }
+    }
+
+    if protocol == GraphicsProtocol::Kitty {
+        out.write_all(b"\x1b_Ga=d,q=2\x1b\\")?;
+        out.flush()?;
+    }
+    // Images aren't cells ratatui knows about, so redraw everything
+    terminal.borrow_mut().clear()?;
+    Ok(())
+}
+
+fn popup_area(area: Rect) -> Rect {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Percentage(10),
+        Constraint::Percentage(80),
+        Constraint::Percentage(10),
+    ])
+    .areas(area);
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Percentage(5),
+        Constraint::Percentage(90),
+        Constraint::Percentage(5),
+    ])
+    .areas(area);
+    area
+}
+
+/// Width and height of a cell in pixels, guessed if the terminal won't say
+fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            u32::from(size.width / size.columns),
+            u32::from(size.height / size.rows),
+        ),
+        _ => (8, 16),
+    }
+}
+
+fn decode(contents: &[u8]) -> Option<DynamicImage> {
+    image::load_from_memory(contents).ok()
+}
+
+/// Pixel size of the image scaled to fill as much of the area as it can
+fn fit(image: &DynamicImage, area: Rect, (cell_width, cell_height): (u32, u32)) -> (u32, u32) {
+    let max_width = u32::from(area.width) * cell_width;
+    let max_height = u32::from(area.height) * cell_height;
+    let scale = f64::min(
+        f64::from(max_width) / f64::from(image.width().max(1)),
+        f64::from(max_height) / f64::from(image.height().max(1)),
+    );
+    (
+        ((f64::from(image.width()) * scale) as u32).clamp(1, max_width.max(1)),
+        ((f64::from(image.height()) * scale) as u32).clamp(1, max_height.max(1)),
+    )
+}
+
+/// iTerm2 decodes the file itself and keeps its aspect ratio within the area
+fn iterm_escape(contents: &[u8], area: Rect) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        contents.len(),
+        area.width,
+        area.height,
+        STANDARD.encode(contents)
+    )
+}
+
+/// Kitty takes PNG data and stretches it over the given number of cells
+fn kitty_escape(
+    contents: &[u8],
+    image: &DynamicImage,
+    area: Rect,
+    cell_size: (u32, u32),
+) -> Result<String> {
+    let png = if image::guess_format(contents).ok() == Some(ImageFormat::Png) {
+        contents.to_vec()
+    } else {
+        let mut png = Vec::new();
+        image.write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)?;
+        png
+    };
+    let (width, height) = fit(image, area, cell_size);
+    let columns = width.div_ceil(cell_size.0);
+    let rows = height.div_ceil(cell_size.1);
+
+    let encoded = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut escape = String::new();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(idx + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).expect("base64 is ASCII");
+        if idx == 0 {
+            escape.push_str(&format!(
+                "\x1b_Ga=T,f=100,q=2,C=1,c={columns},r={rows},m={more};{chunk}\x1b\\"
+            ));
+        } else {
+            escape.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+        }
+    }
+    Ok(escape)
+}
+
+/// Sixel needs the pixels themselves, quantized to a 6x6x6 color cube
+fn sixel_escape(image: &DynamicImage, area: Rect, cell_size: (u32, u32)) -> String {
+    let (width, height) = fit(image, area, cell_size);
+    let rgba = image
+        .resize_exact(width, height, FilterType::Triangle)
+        .to_rgba8();
+    let level = |value: u8| usize::from(value) * 5 / 255;
+    let colors: Vec<Option<usize>> = rgba
+        .pixels()
+        .map(|pixel| {
+            (pixel[3] >= 128).then(|| level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2]))
+        })
+        .collect();
+    let (width, height) = (width as usize, height as usize);
+
+    let mut escape = format!("\x1bPq\"1;1;{width};{height}");
+    for color in 0..216 {
+        let (red, green, blue) = (color / 36, color / 6 % 6, color % 6);
+        escape.push_str(&format!(
+            "#{color};2;{};{};{}",
+            red * 20,
+            green * 20,
+            blue * 20
+        ));
+    }
+
+    for band_top in (0..height).step_by(6) {
+        let band_height = 6.min(height - band_top);
+        let mut used = [false; 216];
+        for y in band_top..band_top + band_height {
+            for color in colors[y * width..(y + 1) * width].iter().flatten() {
+                used[*color] = true;
+            }
+        }
+
+        let mut first = true;
+        for color in (0..216).filter(|color| used[*color]) {
+            if !first {
+                // Back to the start of the band for the next color
+                escape.push('$');
+            }
+            first = false;
+            escape.push_str(&format!("#{color}"));
+
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = (0..band_height)
+                    .filter(|dy| colors[(band_top + dy) * width + x] == Some(color))
+                    .fold(0u8, |bits, dy| bits | (1 << dy));
+                let sixel = char::from(63 + bits);
+                run = match run {
+                    Some((run_sixel, len)) if run_sixel == sixel => Some((sixel, len + 1)),
+                    Some(previous) => {
+                        push_sixel_run(&mut escape, previous);
+                        Some((sixel, 1))
+                    }
+                    None => Some((sixel, 1)),
+                };
+            }
+            if let Some(run) = run {
+                push_sixel_run(&mut escape, run);
+            }
+        }
+        escape.push('-');
+    }
+    escape.push_str("\x1b\\");
+    escape
+}
+
+fn push_sixel_run(escape: &mut String, (sixel, len): (char, usize)) {
+    if len > 3 {
+        escape.push_str(&format!("!{len}{sixel}"));
+    } else {
+        escape.extend(std::iter::repeat_n(sixel, len));
+    }
+}
@@ -0,0 +1,486 @@
+use crate::update::{JumpTarget, Message};
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Built-in sets of navigation keys
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    /// hjkl navigation
+    #[default]
+    Vim,
+    /// C-n/C-p/C-f/C-b line and C-v/M-v page navigation
+    Emacs,
+}
+
+/// A key together with the modifiers that matter for matching (Ctrl and Alt)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    const fn plain(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+
+    const fn ctrl(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    const fn alt(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::ALT)
+    }
+
+    fn from_event(key: &KeyEvent) -> Self {
+        // Shift is already reflected in the character itself
+        Self::new(
+            key.code,
+            key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT),
+        )
+    }
+
+    /// Parse an emacs-style key description like `j`, `C-n`, `M-v` or `PageDown`
+    fn parse(spec: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec;
+        while rest.len() > 2 {
+            if let Some(stripped) = rest.strip_prefix("C-") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("M-") {
+                modifiers |= KeyModifiers::ALT;
+                rest = stripped;
+            } else {
+                break;
+            }
+        }
+
+        let code = match rest {
+            "Space" => KeyCode::Char(' '),
+            "Tab" => KeyCode::Tab,
+            "Esc" => KeyCode::Esc,
+            "Enter" => KeyCode::Enter,
+            "Backspace" => KeyCode::Backspace,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => bail!("Unknown key '{spec}'"),
+                }
+            }
+        };
+        Ok(Self::new(code, modifiers))
+    }
+
+    fn to_help_string(self) -> String {
+        let mut s = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            s.push_str("C-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            s.push_str("M-");
+        }
+        match self.code {
+            KeyCode::Char(' ') => s.push_str("Spc"),
+            KeyCode::Up => s.push('↑'),
+            KeyCode::Down => s.push('↓'),
+            KeyCode::Left => s.push('←'),
+            KeyCode::Right => s.push('→'),
+            KeyCode::PageUp => s.push_str("PgUp"),
+            KeyCode::PageDown => s.push_str("PgDn"),
+            code => s.push_str(&code.to_string()),
+        }
+        s
+    }
+}
+
+/// Every action that can be bound outside the command tree: config name, help
+/// group, help text and the message it sends
+const ACTIONS: [(&str, &str, &str, Message); 34] = [
+    (
+        "select-next",
+        "Navigation",
+        "Move down",
+        Message::SelectNextNode,
+    ),
+    (
+        "select-prev",
+        "Navigation",
+        "Move up",
+        Message::SelectPrevNode,
+    ),
+    (
+        "page-down",
+        "Navigation",
+        "Move down page",
+        Message::ScrollDownPage,
+    ),
+    (
+        "page-up",
+        "Navigation",
+        "Move up page",
+        Message::ScrollUpPage,
+    ),
+    (
+        "next-sibling",
+        "Navigation",
+        "Next sibling",
+        Message::SelectNextSiblingNode,
+    ),
+    (
+        "prev-sibling",
+        "Navigation",
+        "Prev sibling",
+        Message::SelectPrevSiblingNode,
+    ),
+    (
+        "select-parent",
+        "Navigation",
+        "Select parent",
+        Message::SelectParentNode,
+    ),
+    (
+        "select-next-commit",
+        "Navigation",
+        "Next commit, out of any unfolded diffs",
+        Message::SelectNextCommit,
+    ),
+    (
+        "select-prev-commit",
+        "Navigation",
+        "Previous commit, or the one the selection is unfolded under",
+        Message::SelectPrevCommit,
+    ),
+    (
+        "select-next-file",
+        "Navigation",
+        "Next file of the commit",
+        Message::SelectNextFile,
+    ),
+    (
+        "select-prev-file",
+        "Navigation",
+        "Previous file of the commit",
+        Message::SelectPrevFile,
+    ),
+    (
+        "select-next-hunk",
+        "Navigation",
+        "Next hunk of the commit's unfolded files",
+        Message::SelectNextHunk,
+    ),
+    (
+        "select-prev-hunk",
+        "Navigation",
+        "Previous hunk of the commit's unfolded files",
+        Message::SelectPrevHunk,
+    ),
+    (
+        "select-working-copy",
+        "Navigation",
+        "Select @ change",
+        Message::SelectCurrentWorkingCopy,
+    ),
+    (
+        "select-trunk",
+        "Navigation",
+        "Select trunk()",
+        Message::JumpTo {
+            target: JumpTarget::Trunk,
+        },
+    ),
+    (
+        "select-root",
+        "Navigation",
+        "Select root()",
+        Message::JumpTo {
+            target: JumpTarget::Root,
+        },
+    ),
+    (
+        "select-bookmarked-ancestor",
+        "Navigation",
+        "Select the nearest bookmarked ancestor",
+        Message::JumpTo {
+            target: JumpTarget::BookmarkedAncestor,
+        },
+    ),
+    (
+        "toggle-fold",
+        "Navigation",
+        "Toggle folding",
+        Message::ToggleLogListFold,
+    ),
+    ("refresh", "General", "Refresh log tree", Message::Refresh),
+    (
+        "clear",
+        "General",
+        "Back one key / clear state",
+        Message::Clear,
+    ),
+    (
+        "set-revset",
+        "General",
+        "Set log revset",
+        Message::SetRevset,
+    ),
+    (
+        "toggle-ignore-immutable",
+        "General",
+        "Toggle --ignore-immutable",
+        Message::ToggleIgnoreImmutable,
+    ),
+    (
+        "toggle-ignore-working-copy",
+        "General",
+        "Toggle snapshotting the working copy while browsing",
+        Message::ToggleIgnoreWorkingCopy,
+    ),
+    (
+        "toggle-info-pane",
+        "General",
+        "Move the info pane between the bottom and the right",
+        Message::ToggleInfoPanePosition,
+    ),
+    (
+        "toggle-focus-mode",
+        "General",
+        "Hide the header and key hints",
+        Message::ToggleFocusMode,
+    ),
+    (
+        "toggle-review-mode",
+        "General",
+        "Show which files have been viewed",
+        Message::ToggleReviewMode,
+    ),
+    (
+        "toggle-file-viewed",
+        "General",
+        "Mark the selected file viewed",
+        Message::ToggleFileViewed,
+    ),
+    (
+        "older-output",
+        "General",
+        "Show the output of an earlier command",
+        Message::ShowOlderOutput,
+    ),
+    (
+        "newer-output",
+        "General",
+        "Show the output of a later command",
+        Message::ShowNewerOutput,
+    ),
+    (
+        "record-macro",
+        "General",
+        "Record macro into register / stop",
+        Message::RecordMacro,
+    ),
+    (
+        "play-macro",
+        "General",
+        "Play macro from register (count first)",
+        Message::PlayMacro,
+    ),
+    ("help", "General", "Show help", Message::ShowHelp),
+    ("suspend", "General", "Suspend to shell", Message::Suspend),
+    ("quit", "General", "Quit", Message::Quit),
+];
+
+const COMMON_BINDINGS: [(KeyBinding, Message); 30] = [
+    (KeyBinding::plain(KeyCode::Down), Message::SelectNextNode),
+    (KeyBinding::plain(KeyCode::Up), Message::SelectPrevNode),
+    (
+        KeyBinding::plain(KeyCode::PageDown),
+        Message::ScrollDownPage,
+    ),
+    (KeyBinding::plain(KeyCode::PageUp), Message::ScrollUpPage),
+    (
+        KeyBinding::plain(KeyCode::Right),
+        Message::SelectNextSiblingNode,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Left),
+        Message::SelectPrevSiblingNode,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('H')),
+        Message::SelectParentNode,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('J')),
+        Message::SelectNextCommit,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('K')),
+        Message::SelectPrevCommit,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('}')),
+        Message::SelectNextFile,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('{')),
+        Message::SelectPrevFile,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char(')')),
+        Message::SelectNextHunk,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('(')),
+        Message::SelectPrevHunk,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('@')),
+        Message::SelectCurrentWorkingCopy,
+    ),
+    (KeyBinding::plain(KeyCode::Tab), Message::ToggleLogListFold),
+    (KeyBinding::plain(KeyCode::Char(' ')), Message::Refresh),
+    (KeyBinding::ctrl('r'), Message::Refresh),
+    (KeyBinding::plain(KeyCode::Esc), Message::Clear),
+    (KeyBinding::plain(KeyCode::Char('L')), Message::SetRevset),
+    (
+        KeyBinding::plain(KeyCode::Char('I')),
+        Message::ToggleIgnoreImmutable,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('F')),
+        Message::ToggleIgnoreWorkingCopy,
+    ),
+    (KeyBinding::plain(KeyCode::Char('Q')), Message::RecordMacro),
+    (KeyBinding::plain(KeyCode::Char('M')), Message::PlayMacro),
+    (KeyBinding::plain(KeyCode::Char('?')), Message::ShowHelp),
+    (KeyBinding::plain(KeyCode::F(11)), Message::ToggleFocusMode),
+    (
+        KeyBinding::plain(KeyCode::Char('\'')),
+        Message::ToggleFileViewed,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('[')),
+        Message::ShowOlderOutput,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char(']')),
+        Message::ShowNewerOutput,
+    ),
+    (KeyBinding::ctrl('z'), Message::Suspend),
+    (KeyBinding::plain(KeyCode::Char('q')), Message::Quit),
+];
+
+const VIM_BINDINGS: [(KeyBinding, Message); 6] = [
+    (
+        KeyBinding::plain(KeyCode::Char('j')),
+        Message::SelectNextNode,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('k')),
+        Message::SelectPrevNode,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('l')),
+        Message::SelectNextSiblingNode,
+    ),
+    (
+        KeyBinding::plain(KeyCode::Char('h')),
+        Message::SelectPrevSiblingNode,
+    ),
+    (KeyBinding::ctrl('n'), Message::SelectNextNode),
+    (KeyBinding::ctrl('p'), Message::SelectPrevNode),
+];
+
+const EMACS_BINDINGS: [(KeyBinding, Message); 7] = [
+    (KeyBinding::ctrl('n'), Message::SelectNextNode),
+    (KeyBinding::ctrl('p'), Message::SelectPrevNode),
+    (KeyBinding::ctrl('v'), Message::ScrollDownPage),
+    (KeyBinding::alt('v'), Message::ScrollUpPage),
+    (KeyBinding::ctrl('f'), Message::SelectNextSiblingNode),
+    (KeyBinding::ctrl('b'), Message::SelectPrevSiblingNode),
+    (KeyBinding::ctrl('g'), Message::Clear),
+];
+
+/// Bindings for everything outside the command tree: navigation and general keys
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: HashMap<KeyBinding, Message>,
+}
+
+impl Keymap {
+    /// Build a keymap from a preset plus user overrides mapping key descriptions
+    /// to action names (or `"none"` to unbind a key)
+    pub fn new(preset: KeymapPreset, overrides: &HashMap<String, String>) -> Result<Self> {
+        let preset_bindings: &[(KeyBinding, Message)] = match preset {
+            KeymapPreset::Vim => &VIM_BINDINGS,
+            KeymapPreset::Emacs => &EMACS_BINDINGS,
+        };
+        let mut bindings: HashMap<KeyBinding, Message> = COMMON_BINDINGS
+            .iter()
+            .chain(preset_bindings)
+            .copied()
+            .collect();
+
+        for (key, action) in overrides {
+            let binding = KeyBinding::parse(key)?;
+            if action == "none" {
+                bindings.remove(&binding);
+                continue;
+            }
+            let message = ACTIONS
+                .iter()
+                .find(|(name, ..)| name == action)
+                .map(|(.., message)| *message)
+                .ok_or_else(|| anyhow!("Unknown action '{action}' bound to '{key}'"))?;
+            bindings.insert(binding, message);
+        }
+
+        Ok(Self { bindings })
+    }
+
+    pub fn get(&self, key: &KeyEvent) -> Option<Message> {
+        self.bindings.get(&KeyBinding::from_event(key)).copied()
+    }
+
+    /// Help entries for the bound actions as (keys, group, description)
+    pub fn help_entries(&self) -> Vec<(String, &'static str, &'static str)> {
+        ACTIONS
+            .iter()
+            .filter_map(|(_, group, description, message)| {
+                Some((self.keys_for(*message)?, *group, *description))
+            })
+            .collect()
+    }
+
+    /// The keys bound to an action, like `j/Down`, if any are
+    pub fn keys_for(&self, message: Message) -> Option<String> {
+        let mut keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == message)
+            .map(|(binding, _)| binding.to_help_string())
+            .collect();
+        if keys.is_empty() {
+            return None;
+        }
+        keys.sort();
+        Some(keys.join("/"))
+    }
+}
@@ -0,0 +1,36 @@
+//! The core of jjdag: the model, the messages that update it and the view
+//! that renders it. The `jjdag` binary is a thin frontend over this crate;
+//! [`headless::Headless`] drives the same UI without a terminal.
+
+mod ansi;
+mod ci;
+mod command_tree;
+mod completion;
+pub mod config;
+mod conflicts;
+mod description_lint;
+mod export;
+mod file_browser;
+pub mod headless;
+mod image_preview;
+pub mod keymap;
+mod log_tree;
+pub mod logger;
+mod macros;
+pub mod model;
+mod notification;
+mod output_history;
+pub mod profile;
+mod remote_bookmarks;
+mod scripting;
+pub mod session;
+pub mod shell_out;
+pub mod state;
+mod stats;
+pub mod terminal;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod theme;
+mod trailers;
+pub mod update;
+pub mod view;
@@ -1,12 +1,15 @@
-use crate::model::GlobalArgs;
-use crate::shell_out::JjCommand;
-use ansi_to_tui::IntoText;
+use crate::ansi::{parse_line, strip_ansi};
+use crate::model::{GlobalArgs, format_size};
+use crate::profile;
+use crate::shell_out::{self, JjCommand, JjCommandError};
 use anyhow::{Error, Result, anyhow, bail};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
 };
 use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::OnceLock;
 
@@ -22,8 +25,48 @@ fn get_re_lines() -> &'static Regex {
     RE_LINES.get_or_init(|| Regex::new(r"^[ │]*\S+[ │]*(.*)\n[ │├┤┬┴╭╮╯╰─┼]*(.*)").unwrap())
 }
 
+fn get_re_commit_line() -> &'static Regex {
+    static RE_COMMIT_LINE: OnceLock<Regex> = OnceLock::new();
+    RE_COMMIT_LINE
+        .get_or_init(|| Regex::new(r"^.+([k-z]{8}(?:/\d+)?)\s+.*\s+([a-f0-9]{8}).*$").unwrap())
+}
+
+fn get_re_file_status() -> &'static Regex {
+    static RE_FILE_STATUS: OnceLock<Regex> = OnceLock::new();
+    RE_FILE_STATUS.get_or_init(|| Regex::new(r"^([MADRC])\s+(.+)$").unwrap())
+}
+
+fn get_re_rename() -> &'static Regex {
+    static RE_RENAME: OnceLock<Regex> = OnceLock::new();
+    RE_RENAME.get_or_init(|| Regex::new(r"^(.*)\{(.*?)\s*=>\s*(.*?)\}(.*)$").unwrap())
+}
+
+/// Old and new line numbers of a line of `jj diff --color-words`
+fn get_re_line_nums() -> &'static Regex {
+    static RE_LINE_NUMS: OnceLock<Regex> = OnceLock::new();
+    RE_LINE_NUMS.get_or_init(|| Regex::new(r"^\s*(\d+)?\s+(\d+)?:").unwrap())
+}
+
+fn get_re_hunk_separator() -> &'static Regex {
+    static RE_HUNK_SEPARATOR: OnceLock<Regex> = OnceLock::new();
+    RE_HUNK_SEPARATOR.get_or_init(|| Regex::new(r"^\s*\.\.\.\s*$").unwrap())
+}
+
 const INITIAL_LOAD_COUNT: usize = 200;
 const LOAD_BATCH_SIZE: usize = 200;
+/// Longer file diffs are summarized until loaded explicitly
+const MAX_DIFF_LINES: usize = 2000;
+
+/// What unfolding a commit shows under its diffstat
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnfoldMode {
+    /// The changed files, each of which unfolds to its diff
+    #[default]
+    Full,
+    /// Nothing until the diffstat itself is unfolded
+    Stat,
+}
 
 #[derive(Debug)]
 pub struct JjLog {
@@ -32,10 +75,47 @@ pub struct JjLog {
     last_change_id: Option<String>,
     revset: String,
     global_args: GlobalArgs,
+    unfold_mode: UnfoldMode,
+    diff_cache: DiffCache,
+    /// The one parent each merge's diff is against, by change id, instead of
+    /// all of them merged
+    diff_parents: HashMap<String, String>,
+}
+
+/// Output of the `jj diff` runs behind unfolding, by their arguments (so by
+/// change, file and diff format), so that unfolding a commit or file again,
+/// even after a refresh rebuilt the log, doesn't run jj again. Diffs of a
+/// change id only hold until the repo changes, so the cache is kept only
+/// while the latest operation stays the one it was filled at.
+#[derive(Debug, Default)]
+pub struct DiffCache {
+    op_id: Option<String>,
+    outputs: HashMap<Vec<String>, String>,
+}
+
+impl DiffCache {
+    /// Drop the cached diffs unless the repo is still at the operation they
+    /// were cached at; an unknown operation never matches
+    fn sync_op_id(&mut self, op_id: Option<String>) {
+        if op_id.is_none() || op_id != self.op_id {
+            self.outputs.clear();
+        }
+        self.op_id = op_id;
+    }
+
+    /// Run a `jj diff` command, or answer it from the cache
+    fn run(&mut self, cmd: JjCommand) -> Result<String, JjCommandError> {
+        if let Some(output) = self.outputs.get(cmd.args()) {
+            return Ok(output.clone());
+        }
+        let output = cmd.run()?;
+        self.outputs.insert(cmd.args().to_vec(), output.clone());
+        Ok(output)
+    }
 }
 
 impl JjLog {
-    pub fn new() -> Result<Self> {
+    pub fn new(unfold_mode: UnfoldMode) -> Result<Self> {
         Ok(JjLog {
             log_tree: Vec::new(),
             loaded_count: 0,
@@ -44,19 +124,73 @@ impl JjLog {
             global_args: GlobalArgs {
                 repository: String::new(),
                 ignore_immutable: false,
+                ignore_working_copy: false,
             },
+            unfold_mode,
+            diff_cache: DiffCache::default(),
+            diff_parents: HashMap::new(),
         })
     }
 
+    pub fn unfold_mode(&self) -> UnfoldMode {
+        self.unfold_mode
+    }
+
     pub fn load_log_tree(&mut self, global_args: &GlobalArgs, revset: &str) -> Result<()> {
         self.global_args = global_args.clone();
         self.revset = revset.to_string();
-        self.log_tree = CommitOrText::load_all(global_args, revset, INITIAL_LOAD_COUNT)?;
+        self.log_tree =
+            CommitOrText::load_all(global_args, revset, INITIAL_LOAD_COUNT, self.unfold_mode)?;
         self.loaded_count = self.log_tree.len();
         self.last_change_id = Self::get_last_change_id(&self.log_tree);
+        self.apply_diff_parents();
+        // After the log, whose snapshot of the working copy may be an
+        // operation of its own
+        let op_id = JjCommand::op_id(global_args.clone())
+            .run()
+            .ok()
+            .map(|op| strip_ansi(&op).trim().to_string())
+            .filter(|op| !op.is_empty());
+        self.diff_cache.sync_op_id(op_id);
         Ok(())
     }
 
+    fn apply_diff_parents(&mut self) {
+        for commit_or_text in &mut self.log_tree {
+            if let CommitOrText::Commit(commit) = commit_or_text {
+                commit.diff_from = self.diff_parents.get(&commit.change_id).cloned();
+            }
+        }
+    }
+
+    /// Diff the commit at the position against one of its parents, or
+    /// against all of them again with `None`, reloading its diffs if shown;
+    /// returns the commit's index in the flattened log
+    pub fn set_diff_parent(
+        &mut self,
+        global_args: &GlobalArgs,
+        tree_pos: &TreePosition,
+        parent: Option<String>,
+    ) -> Result<usize> {
+        let CommitOrText::Commit(commit) = &mut self.log_tree[tree_pos[COMMIT_OR_TEXT_IDX]] else {
+            bail!("Only commits have diffs");
+        };
+        match &parent {
+            Some(parent) => self
+                .diff_parents
+                .insert(commit.change_id.clone(), parent.clone()),
+            None => self.diff_parents.remove(&commit.change_id),
+        };
+        commit.diff_from = parent;
+        commit.loaded = false;
+        commit.diff_stat = None;
+        commit.file_diffs.clear();
+        if commit.unfolded {
+            commit.load(global_args, &mut self.diff_cache)?;
+        }
+        Ok(commit.flat_log_idx)
+    }
+
     fn get_last_change_id(log_tree: &[CommitOrText]) -> Option<String> {
         log_tree.iter().rev().find_map(|cot| {
             if let CommitOrText::Commit(commit) = cot {
@@ -75,18 +209,25 @@ impl JjLog {
 
         // Use revset to get commits older than last_change_id (ancestors of last_id's parents)
         let revset = format!("..{}-", last_id);
-        let new_commits = CommitOrText::load_all(&self.global_args, &revset, LOAD_BATCH_SIZE)?;
+        let new_commits = CommitOrText::load_all(
+            &self.global_args,
+            &revset,
+            LOAD_BATCH_SIZE,
+            self.unfold_mode,
+        )?;
 
         let has_more = !new_commits.is_empty();
         if has_more {
             self.log_tree.extend(new_commits);
             self.loaded_count = self.log_tree.len();
             self.last_change_id = Self::get_last_change_id(&self.log_tree);
+            self.apply_diff_parents();
         }
         Ok(has_more)
     }
 
     pub fn flatten_log(&mut self) -> Result<(Vec<Text<'static>>, Vec<TreePosition>)> {
+        let _timer = profile::timer("flatten_log");
         let mut log_list = Vec::new();
         let mut log_list_tree_positions = Vec::new();
 
@@ -111,12 +252,27 @@ impl JjLog {
             CommitOrText::Commit(commit) => commit,
         };
 
-        let file_diff_idx = if tree_pos.len() <= FILE_DIFF_IDX {
+        let child_idx = if tree_pos.len() <= FILE_DIFF_IDX {
             return Ok(commit);
         } else {
             tree_pos[FILE_DIFF_IDX]
         };
 
+        // Evolog entries come before the file diffs
+        let evolog_len = commit.evolog.len();
+        if child_idx < evolog_len {
+            return Ok(&mut commit.evolog[child_idx]);
+        }
+        let file_diff_offset = commit.file_diff_offset();
+        if child_idx < file_diff_offset {
+            return commit
+                .diff_stat
+                .as_mut()
+                .map(|diff_stat| diff_stat as &mut dyn LogTreeNode)
+                .ok_or_else(|| anyhow!("Trying to get unloaded diffstat for commit"));
+        }
+        let file_diff_idx = child_idx - file_diff_offset;
+
         // Traverse to file diff
         if !commit.loaded {
             bail!("Trying to get unloaded file diffs for commit");
@@ -157,7 +313,74 @@ impl JjLog {
             return None;
         }
         let commit = self.get_tree_commit(tree_pos)?;
-        Some(&commit.file_diffs[tree_pos[FILE_DIFF_IDX]])
+        let file_diff_idx = tree_pos[FILE_DIFF_IDX].checked_sub(commit.file_diff_offset())?;
+        commit.file_diffs.get(file_diff_idx)
+    }
+
+    /// First and last old-side line of the diff hunk at the position, or of
+    /// the hunk containing the line at it
+    pub fn get_tree_diff_hunk_old_range(&self, tree_pos: &TreePosition) -> Option<(u32, u32)> {
+        if tree_pos.len() <= DIFF_HUNK_IDX {
+            return None;
+        }
+        let file_diff = self.get_tree_file_diff(&tree_pos[..=FILE_DIFF_IDX].to_vec())?;
+        let diff_hunk = file_diff.diff_hunks.get(tree_pos[DIFF_HUNK_IDX])?;
+        Some((diff_hunk.red_start, diff_hunk.red_end))
+    }
+
+    /// Load the whole diff of a file that was cut off for being too long;
+    /// returns the file's index in the flattened log, or `None` if the
+    /// position isn't such a file
+    pub fn load_full_file_diff(
+        &mut self,
+        global_args: &GlobalArgs,
+        tree_pos: &TreePosition,
+    ) -> Result<Option<usize>> {
+        if tree_pos.len() <= FILE_DIFF_IDX {
+            return Ok(None);
+        }
+        let CommitOrText::Commit(commit) = &mut self.log_tree[tree_pos[COMMIT_OR_TEXT_IDX]] else {
+            return Ok(None);
+        };
+        let Some(file_diff) = tree_pos[FILE_DIFF_IDX]
+            .checked_sub(commit.file_diff_offset())
+            .and_then(|file_diff_idx| commit.file_diffs.get_mut(file_diff_idx))
+        else {
+            return Ok(None);
+        };
+        if !file_diff.truncated {
+            return Ok(None);
+        }
+        file_diff.load_diff_hunks(global_args, &mut self.diff_cache, true)?;
+        file_diff.unfolded = true;
+        Ok(Some(file_diff.flat_log_idx))
+    }
+
+    pub fn get_tree_evolog_entry(&self, tree_pos: &TreePosition) -> Option<&EvologEntry> {
+        if tree_pos.len() != FILE_DIFF_IDX + 1 {
+            return None;
+        }
+        let commit = self.get_tree_commit(tree_pos)?;
+        commit.evolog.get(tree_pos[FILE_DIFF_IDX])
+    }
+
+    /// Show or hide the evolution history of the commit at the position;
+    /// returns the commit's index in the flattened log
+    pub fn toggle_evolog(
+        &mut self,
+        global_args: &GlobalArgs,
+        tree_pos: &TreePosition,
+    ) -> Result<usize> {
+        let CommitOrText::Commit(commit) = &mut self.log_tree[tree_pos[COMMIT_OR_TEXT_IDX]] else {
+            bail!("Only commits have an evolution history");
+        };
+        if commit.evolog.is_empty() {
+            commit.evolog =
+                EvologEntry::load_all(global_args, &commit.change_id, &commit.graph_indent)?;
+        } else {
+            commit.evolog.clear();
+        }
+        Ok(commit.flat_log_idx)
     }
 
     pub fn get_current_commit(&self) -> Option<&Commit> {
@@ -175,9 +398,14 @@ impl JjLog {
     ) -> Result<usize> {
         let mut tree_pos = tree_pos.clone();
         tree_pos.truncate(DIFF_HUNK_IDX + 1);
-        let node = self.get_tree_node(&tree_pos)?;
-        node.toggle_fold(global_args)?;
-        Ok(node.flat_log_idx())
+        // Out of the log for the node to borrow while the log is borrowed
+        let mut diff_cache = std::mem::take(&mut self.diff_cache);
+        let flat_log_idx = self.get_tree_node(&tree_pos).and_then(|node| {
+            node.toggle_fold(global_args, &mut diff_cache)?;
+            Ok(node.flat_log_idx())
+        });
+        self.diff_cache = diff_cache;
+        flat_log_idx
     }
 }
 
@@ -191,7 +419,7 @@ pub trait LogTreeNode {
     ) -> Result<()>;
     fn flat_log_idx(&self) -> usize;
     fn children(&self) -> Vec<&dyn LogTreeNode>;
-    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()>;
+    fn toggle_fold(&mut self, global_args: &GlobalArgs, diff_cache: &mut DiffCache) -> Result<()>;
     /// Get the line number for this node (only implemented for DiffHunkLine)
     fn line_number(&self) -> Option<u32> {
         None
@@ -199,9 +427,9 @@ pub trait LogTreeNode {
 }
 
 pub type TreePosition = Vec<usize>;
-const COMMIT_OR_TEXT_IDX: usize = 0;
-const FILE_DIFF_IDX: usize = 1;
-const DIFF_HUNK_IDX: usize = 2;
+pub const COMMIT_OR_TEXT_IDX: usize = 0;
+pub const FILE_DIFF_IDX: usize = 1;
+pub const DIFF_HUNK_IDX: usize = 2;
 pub const DIFF_HUNK_LINE_IDX: usize = 3;
 
 pub fn get_parent_tree_position(tree_pos: &TreePosition) -> Option<TreePosition> {
@@ -222,10 +450,15 @@ pub enum CommitOrText {
 }
 
 impl CommitOrText {
-    fn load_all(global_args: &GlobalArgs, revset: &str, limit: usize) -> Result<Vec<Self>> {
+    fn load_all(
+        global_args: &GlobalArgs,
+        revset: &str,
+        limit: usize,
+        unfold_mode: UnfoldMode,
+    ) -> Result<Vec<Self>> {
         let output = JjCommand::log(revset, limit, global_args.clone()).run()?;
         let mut lines = output.trim().lines();
-        let re = Regex::new(r"^.+([k-z]{8}(?:/\d+)?)\s+.*\s+([a-f0-9]{8}).*$")?;
+        let re = get_re_commit_line();
 
         let mut commits_or_texts = Vec::new();
         loop {
@@ -235,17 +468,55 @@ impl CommitOrText {
             };
 
             if re.captures(&strip_ansi(line1)).is_none() {
-                commits_or_texts.push(Self::InfoText(InfoText::new(line1.to_string())));
+                commits_or_texts.push(Self::InfoText(InfoText::new(line1)?));
                 continue;
             };
 
             let line2 = lines.next().unwrap_or_default();
-            commits_or_texts.push(Self::Commit(Commit::new(format!("{line1}\n{line2}"))?));
+            commits_or_texts.push(Self::Commit(Commit::new(
+                format!("{line1}\n{line2}"),
+                unfold_mode,
+            )?));
         }
 
+        Self::load_bookmarks(global_args, &mut commits_or_texts)?;
         Ok(commits_or_texts)
     }
 
+    /// Fill in the local bookmarks of the commits, which the log line can't
+    /// tell apart from tags, remote bookmarks and their markers
+    fn load_bookmarks(global_args: &GlobalArgs, commits_or_texts: &mut [Self]) -> Result<()> {
+        let mut commits: Vec<&mut Commit> = commits_or_texts
+            .iter_mut()
+            .filter_map(|cot| match cot {
+                Self::Commit(commit) => Some(commit),
+                Self::InfoText(_) => None,
+            })
+            .collect();
+        if commits.is_empty() {
+            return Ok(());
+        }
+        let revset = commits
+            .iter()
+            .map(|commit| commit.commit_id.as_str())
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let output = strip_ansi(&JjCommand::local_bookmarks(&revset, global_args.clone()).run()?);
+        let mut bookmarks: HashMap<&str, Vec<String>> = HashMap::new();
+        for line in output.lines() {
+            if let Some((commit_id, names)) = line.split_once('\t') {
+                let names = names.split_whitespace().map(String::from).collect();
+                bookmarks.insert(commit_id, names);
+            }
+        }
+        for commit in &mut commits {
+            commit.bookmarks = bookmarks
+                .remove(commit.commit_id.as_str())
+                .unwrap_or_default();
+        }
+        Ok(())
+    }
+
     fn flatten(
         &mut self,
         tree_pos: TreePosition,
@@ -273,26 +544,34 @@ impl CommitOrText {
 #[derive(Debug)]
 pub struct Commit {
     pub change_id: String,
-    _commit_id: String,
+    pub commit_id: String,
     pub current_working_copy: bool,
     has_conflict: bool,
     _empty: bool,
     pub description_first_line: Option<String>,
+    pub bookmarks: Vec<String>,
     symbol: String,
     line1_graph_chars: String,
     line1_graph_chars_part2: String,
     line2_graph_chars: String,
-    pretty_line1: String,
-    pretty_line2: String,
+    pretty_line1: Line<'static>,
+    /// `None` for commits shown on one line
+    pretty_line2: Option<Line<'static>>,
     graph_indent: String,
     unfolded: bool,
+    unfold_mode: UnfoldMode,
     loaded: bool,
+    /// Earlier versions of the commit, shown above the file diffs when loaded
+    evolog: Vec<EvologEntry>,
+    diff_stat: Option<DiffStat>,
     file_diffs: Vec<FileDiff>,
+    /// The parent diffs are against, rather than all parents merged
+    pub diff_from: Option<String>,
     pub flat_log_idx: usize,
 }
 
 impl Commit {
-    fn new(pretty_string: String) -> Result<Self> {
+    fn new(pretty_string: String, unfold_mode: UnfoldMode) -> Result<Self> {
         let clean_string = strip_ansi(&pretty_string);
 
         let captures = get_re_fields()
@@ -360,24 +639,30 @@ impl Commit {
         let captures = get_re_lines()
             .captures(&pretty_string)
             .ok_or_else(|| anyhow!("Cannot parse commit lines: {:?}", pretty_string))?;
-        let pretty_line1 = captures
-            .get(1)
-            .ok_or_else(|| anyhow!("Cannot parse commit line1"))?
-            .as_str()
-            .into();
+        let pretty_line1 = parse_line(
+            captures
+                .get(1)
+                .ok_or_else(|| anyhow!("Cannot parse commit line1"))?
+                .as_str(),
+        )?;
         let pretty_line2 = captures
             .get(2)
             .ok_or_else(|| anyhow!("Cannot parse commit line2"))?
-            .as_str()
-            .into();
+            .as_str();
+        let pretty_line2 = if pretty_line2.is_empty() {
+            None
+        } else {
+            Some(parse_line(pretty_line2)?)
+        };
 
         Ok(Commit {
             change_id,
-            _commit_id: commit_id,
+            commit_id,
             current_working_copy,
             has_conflict,
             _empty: empty,
             description_first_line,
+            bookmarks: Vec::new(),
             symbol,
             line1_graph_chars,
             line1_graph_chars_part2,
@@ -386,11 +671,60 @@ impl Commit {
             pretty_line2,
             graph_indent,
             unfolded: false,
+            unfold_mode,
             loaded: false,
+            evolog: Vec::new(),
+            diff_stat: None,
             file_diffs: Vec::new(),
+            diff_from: None,
             flat_log_idx: 0,
         })
     }
+
+    /// Load the diffstat and changed files
+    fn load(&mut self, global_args: &GlobalArgs, diff_cache: &mut DiffCache) -> Result<()> {
+        let diff_from = self.diff_from.as_deref();
+        self.diff_stat = Some(DiffStat::load(
+            global_args,
+            diff_cache,
+            &self.change_id,
+            diff_from,
+            &self.graph_indent,
+            self.unfold_mode == UnfoldMode::Full,
+        )?);
+        self.file_diffs = FileDiff::load_all(
+            global_args,
+            diff_cache,
+            &self.change_id,
+            diff_from,
+            &self.graph_indent,
+        )?;
+        self.loaded = true;
+        Ok(())
+    }
+
+    /// The changed files, once loaded by unfolding the commit
+    pub fn file_diffs(&self) -> Option<&[FileDiff]> {
+        self.loaded.then_some(self.file_diffs.as_slice())
+    }
+
+    /// The changed files in the log, none while the commit or its diffstat
+    /// is folded
+    pub fn shown_file_diffs(&self) -> &[FileDiff] {
+        let diff_stat_folded = self
+            .diff_stat
+            .as_ref()
+            .is_some_and(|diff_stat| !diff_stat.unfolded);
+        if !self.unfolded || diff_stat_folded {
+            return &[];
+        }
+        &self.file_diffs
+    }
+
+    /// Child index of the first file diff
+    fn file_diff_offset(&self) -> usize {
+        self.evolog.len() + usize::from(self.diff_stat.is_some())
+    }
 }
 
 impl LogTreeNode for Commit {
@@ -414,14 +748,14 @@ impl LogTreeNode for Commit {
             fold_symbol(self.unfolded),
             Span::raw(" "),
         ]);
-        line1.extend(self.pretty_line1.into_text()?.lines[0].spans.clone());
+        line1.extend(self.pretty_line1.spans.clone());
         let mut lines = vec![line1];
-        if !self.pretty_line2.is_empty() {
+        if let Some(pretty_line2) = &self.pretty_line2 {
             let mut line2 = Line::from(vec![
                 Span::raw(self.line2_graph_chars.clone()),
                 Span::raw(" "),
             ]);
-            line2.extend(self.pretty_line2.into_text()?.lines[0].spans.clone());
+            line2.extend(pretty_line2.spans.clone());
             lines.push(line2);
         };
         Ok(Text::from(lines))
@@ -437,13 +771,29 @@ impl LogTreeNode for Commit {
         log_list.push(self.render()?);
         log_list_tree_positions.push(tree_pos.clone());
 
+        for (entry_idx, entry) in self.evolog.iter_mut().enumerate() {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(entry_idx);
+            entry.flatten(new_pos, log_list, log_list_tree_positions)?;
+        }
+
         if !self.unfolded {
             return Ok(());
         }
 
+        let file_diff_offset = self.file_diff_offset();
+        if let Some(diff_stat) = &mut self.diff_stat {
+            let mut new_pos = tree_pos.clone();
+            new_pos.push(self.evolog.len());
+            diff_stat.flatten(new_pos, log_list, log_list_tree_positions)?;
+            if !diff_stat.unfolded {
+                return Ok(());
+            }
+        }
+
         for (file_diff_idx, file_diff) in self.file_diffs.iter_mut().enumerate() {
             let mut new_pos = tree_pos.clone();
-            new_pos.push(file_diff_idx);
+            new_pos.push(file_diff_offset + file_diff_idx);
             file_diff.flatten(new_pos, log_list, log_list_tree_positions)?;
         }
 
@@ -455,22 +805,32 @@ impl LogTreeNode for Commit {
     }
 
     fn children(&self) -> Vec<&dyn LogTreeNode> {
-        self.file_diffs
+        let mut children: Vec<&dyn LogTreeNode> = self
+            .evolog
             .iter()
-            .map(|fd| fd as &dyn LogTreeNode)
-            .collect()
+            .map(|entry| entry as &dyn LogTreeNode)
+            .collect();
+        if !self.unfolded {
+            return children;
+        }
+        if let Some(diff_stat) = &self.diff_stat {
+            children.push(diff_stat);
+            if !diff_stat.unfolded {
+                return children;
+            }
+        }
+        children.extend(self.file_diffs.iter().map(|fd| fd as &dyn LogTreeNode));
+        children
     }
 
-    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
+    fn toggle_fold(&mut self, global_args: &GlobalArgs, diff_cache: &mut DiffCache) -> Result<()> {
         self.unfolded = !self.unfolded;
         if !self.unfolded {
             return Ok(());
         }
 
         if !self.loaded {
-            let file_diffs = FileDiff::load_all(global_args, &self.change_id, &self.graph_indent)?;
-            self.file_diffs = file_diffs;
-            self.loaded = true;
+            self.load(global_args, diff_cache)?;
         }
 
         Ok(())
@@ -479,22 +839,22 @@ impl LogTreeNode for Commit {
 
 #[derive(Debug)]
 pub struct InfoText {
-    pretty_string: String,
+    pretty_line: Line<'static>,
     flat_log_idx: usize,
 }
 
 impl InfoText {
-    fn new(pretty_string: String) -> Self {
-        Self {
-            pretty_string,
+    fn new(pretty_string: &str) -> Result<Self> {
+        Ok(Self {
+            pretty_line: parse_line(pretty_string)?,
             flat_log_idx: 0,
-        }
+        })
     }
 }
 
 impl LogTreeNode for InfoText {
     fn render(&self) -> Result<Text<'static>> {
-        Ok(self.pretty_string.into_text()?)
+        Ok(Text::from(self.pretty_line.clone()))
     }
 
     fn flatten(
@@ -517,7 +877,210 @@ impl LogTreeNode for InfoText {
         Vec::new()
     }
 
-    fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
+    fn toggle_fold(
+        &mut self,
+        _global_args: &GlobalArgs,
+        _diff_cache: &mut DiffCache,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// `jj diff --stat` of a commit, shown above its file diffs
+#[derive(Debug)]
+pub struct DiffStat {
+    /// One line per changed file
+    file_lines: Vec<Line<'static>>,
+    /// Files changed, insertions and deletions
+    summary: Line<'static>,
+    /// The parent the diff is against, if only one of a merge's
+    diff_from: Option<String>,
+    graph_indent: String,
+    /// Whether the file diffs below are shown
+    unfolded: bool,
+    flat_log_idx: usize,
+}
+
+impl DiffStat {
+    fn load(
+        global_args: &GlobalArgs,
+        diff_cache: &mut DiffCache,
+        change_id: &str,
+        diff_from: Option<&str>,
+        graph_indent: &str,
+        unfolded: bool,
+    ) -> Result<Self> {
+        let output = diff_cache.run(JjCommand::diff_stat(
+            change_id,
+            diff_from,
+            global_args.clone(),
+        ))?;
+        let mut file_lines = output
+            .trim_end()
+            .lines()
+            .map(parse_line)
+            .collect::<Result<Vec<_>>>()?;
+        let summary = file_lines.pop().unwrap_or_default();
+        Ok(Self {
+            file_lines,
+            summary,
+            diff_from: diff_from.map(String::from),
+            graph_indent: graph_indent.to_string(),
+            unfolded,
+            flat_log_idx: 0,
+        })
+    }
+}
+
+impl LogTreeNode for DiffStat {
+    fn render(&self) -> Result<Text<'static>> {
+        let mut header = Line::from(vec![
+            Span::raw(self.graph_indent.clone()),
+            fold_symbol(self.unfolded),
+            Span::raw(" "),
+        ]);
+        header.extend(self.summary.spans.clone());
+        if let Some(diff_from) = &self.diff_from {
+            header.push_span(Span::styled(
+                format!("  (from parent {diff_from})"),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        let mut lines = vec![header];
+        for file_line in &self.file_lines {
+            let mut line = Line::from(Span::raw(format!("{}  ", self.graph_indent)));
+            line.extend(file_line.spans.clone());
+            lines.push(line);
+        }
+        Ok(Text::from(lines))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos);
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        Vec::new()
+    }
+
+    fn toggle_fold(
+        &mut self,
+        _global_args: &GlobalArgs,
+        _diff_cache: &mut DiffCache,
+    ) -> Result<()> {
+        self.unfolded = !self.unfolded;
+        Ok(())
+    }
+}
+
+/// An earlier version of a commit from `jj evolog`
+#[derive(Debug)]
+pub struct EvologEntry {
+    pub commit_id: String,
+    age: String,
+    /// The operation that created this version (empty on jj versions that
+    /// don't report it)
+    operation: String,
+    description: String,
+    graph_indent: String,
+    flat_log_idx: usize,
+}
+
+impl EvologEntry {
+    fn load_all(
+        global_args: &GlobalArgs,
+        change_id: &str,
+        graph_indent: &str,
+    ) -> Result<Vec<Self>> {
+        let output = match JjCommand::evolog_entries(change_id, global_args.clone()).run() {
+            Ok(output) => output,
+            Err(JjCommandError::Failed { .. }) => {
+                JjCommand::evolog_entries_legacy(change_id, global_args.clone()).run()?
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Ok(output
+            .lines()
+            .map(strip_ansi)
+            // The first entry is the current version
+            .skip(1)
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, '\t');
+                Some(Self {
+                    commit_id: fields.next()?.to_string(),
+                    age: fields.next()?.to_string(),
+                    operation: fields.next()?.to_string(),
+                    description: fields.next()?.to_string(),
+                    graph_indent: graph_indent.to_string(),
+                    flat_log_idx: 0,
+                })
+            })
+            .collect())
+    }
+}
+
+impl LogTreeNode for EvologEntry {
+    fn render(&self) -> Result<Text<'static>> {
+        let description = if self.description.is_empty() {
+            "(no description set)"
+        } else {
+            &self.description
+        };
+        let mut spans = vec![
+            Span::raw(self.graph_indent.clone()),
+            Span::styled("◷ ", Style::default().fg(Color::DarkGray)),
+            Span::styled(self.commit_id.clone(), Style::default().fg(Color::Yellow)),
+            Span::raw(" "),
+            Span::styled(self.age.clone(), Style::default().fg(Color::DarkGray)),
+            Span::raw(" "),
+            Span::raw(description.to_string()),
+        ];
+        if !self.operation.is_empty() {
+            spans.push(Span::styled(
+                format!("  ({})", self.operation),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        Ok(Text::from(Line::from(spans)))
+    }
+
+    fn flatten(
+        &mut self,
+        tree_pos: TreePosition,
+        log_list: &mut Vec<Text<'static>>,
+        log_list_tree_positions: &mut Vec<TreePosition>,
+    ) -> Result<()> {
+        self.flat_log_idx = log_list.len();
+        log_list.push(self.render()?);
+        log_list_tree_positions.push(tree_pos);
+        Ok(())
+    }
+
+    fn flat_log_idx(&self) -> usize {
+        self.flat_log_idx
+    }
+
+    fn children(&self) -> Vec<&dyn LogTreeNode> {
+        Vec::new()
+    }
+
+    fn toggle_fold(
+        &mut self,
+        _global_args: &GlobalArgs,
+        _diff_cache: &mut DiffCache,
+    ) -> Result<()> {
         Ok(())
     }
 }
@@ -525,22 +1088,34 @@ impl LogTreeNode for InfoText {
 #[derive(Debug)]
 pub struct FileDiff {
     change_id: String,
+    /// The parent the diff is against, if only one of a merge's
+    diff_from: Option<String>,
     pub path: String,
-    description: String,
+    /// The path before a rename or copy
+    pub old_path: Option<String>,
+    /// What jj commands on this file operate on: both paths of a rename or copy
+    pub fileset: String,
     status: FileDiffStatus,
     graph_indent: String,
     unfolded: bool,
     loaded: bool,
     diff_hunks: Vec<DiffHunk>,
+    /// Shown instead of the diff hunks for binary files and overlong diffs
+    summary: Option<String>,
+    /// The diff was too long to load without asking
+    truncated: bool,
     flat_log_idx: usize,
 }
 
 impl FileDiff {
-    fn new(change_id: String, pretty_string: String, graph_indent: String) -> Result<Self> {
+    fn new(
+        change_id: String,
+        diff_from: Option<String>,
+        pretty_string: String,
+        graph_indent: String,
+    ) -> Result<Self> {
         let clean_string = strip_ansi(&pretty_string);
-        let re = Regex::new(r"^([MADRC])\s+(.+)$").unwrap();
-
-        let captures = re
+        let captures = get_re_file_status()
             .captures(&clean_string)
             .ok_or_else(|| anyhow!("Cannot parse file diff string: {clean_string}"))?;
         let status = captures
@@ -554,16 +1129,19 @@ impl FileDiff {
             .as_str()
             .into();
 
-        let path = match status {
+        let (path, old_path) = match status {
             FileDiffStatus::Renamed | FileDiffStatus::Copied => {
-                let rename_regex = Regex::new(r"^(.*)\{(.+?)\s*=>\s*(.+?)\}(.*)$").unwrap();
-                let captures = rename_regex.captures(&description).ok_or_else(|| {
+                let captures = get_re_rename().captures(&description).ok_or_else(|| {
                     anyhow!("Cannot parse file diff rename/copied paths: {description}")
                 })?;
                 let path_prefix = captures
                     .get(1)
                     .ok_or_else(|| anyhow!("Cannot parse file diff rename/copied path prefix"))?
                     .as_str();
+                let path_old_end = captures
+                    .get(2)
+                    .ok_or_else(|| anyhow!("Cannot parse file diff rename/copied path old end"))?
+                    .as_str();
                 let path_new_end = captures
                     .get(3)
                     .ok_or_else(|| anyhow!("Cannot parse file diff rename/copied path new end"))?
@@ -573,36 +1151,121 @@ impl FileDiff {
                     .ok_or_else(|| anyhow!("Cannot parse file diff rename/copied path suffix"))?
                     .as_str();
 
-                format!("{path_prefix}{path_new_end}{path_suffix}")
+                // An empty side like `{ => sub}/file` leaves a doubled separator
+                let join =
+                    |end: &str| format!("{path_prefix}{end}{path_suffix}").replace("//", "/");
+                (join(path_new_end), Some(join(path_old_end)))
             }
-            _ => description.clone(),
+            _ => (description, None),
+        };
+        let fileset = match &old_path {
+            Some(old_path) => format!("file:{old_path:?} | file:{path:?}"),
+            None => path.clone(),
         };
 
         Ok(Self {
             change_id,
+            diff_from,
             path,
-            description,
+            old_path,
+            fileset,
             status,
             graph_indent,
             unfolded: false,
             loaded: false,
             diff_hunks: Vec::new(),
+            summary: None,
+            truncated: false,
             flat_log_idx: 0,
         })
     }
 
+    /// Load the diff hunks, or a summary in their place for binary files and
+    /// (unless `full`) diffs over `MAX_DIFF_LINES`
+    fn load_diff_hunks(
+        &mut self,
+        global_args: &GlobalArgs,
+        diff_cache: &mut DiffCache,
+        full: bool,
+    ) -> Result<()> {
+        let output = diff_cache.run(JjCommand::diff_file(
+            &self.change_id,
+            self.diff_from.as_deref(),
+            &self.fileset,
+            global_args.clone(),
+        ))?;
+        let line_count = output.lines().count();
+        let is_binary = output.lines().map(strip_ansi).any(|line| {
+            let line = line.trim();
+            line == "(binary)" || line.starts_with("Binary files ")
+        });
+
+        self.diff_hunks = Vec::new();
+        self.summary = None;
+        self.truncated = false;
+        if is_binary {
+            self.summary = Some(self.binary_summary(global_args));
+        } else if !full && line_count > MAX_DIFF_LINES {
+            self.summary = Some(format!(
+                "diff truncated, {} lines — press x to load",
+                format_line_count(line_count)
+            ));
+            self.truncated = true;
+        } else {
+            self.diff_hunks = DiffHunk::parse_all(&output, &self.graph_indent)?;
+        }
+        self.loaded = true;
+        Ok(())
+    }
+
+    /// "binary, 1.2 MiB → 1.3 MiB", leaving out the side a file is added or
+    /// deleted on
+    fn binary_summary(&self, global_args: &GlobalArgs) -> String {
+        let size_at = |revision: &str, path: &str| {
+            shell_out::file_contents(&global_args.repository, revision, path)
+                .ok()
+                .map(|contents| format_size(contents.len() as u64))
+        };
+        let before = match self.status {
+            FileDiffStatus::Added => None,
+            _ => size_at(
+                &self
+                    .diff_from
+                    .clone()
+                    .unwrap_or_else(|| format!("{}-", self.change_id)),
+                self.old_path.as_deref().unwrap_or(&self.path),
+            ),
+        };
+        let after = match self.status {
+            FileDiffStatus::Deleted => None,
+            _ => size_at(&self.change_id, &self.path),
+        };
+        match (before, after) {
+            (Some(before), Some(after)) => format!("binary, {before} → {after}"),
+            (Some(size), None) | (None, Some(size)) => format!("binary, {size}"),
+            (None, None) => "binary".to_string(),
+        }
+    }
+
     fn load_all(
         global_args: &GlobalArgs,
+        diff_cache: &mut DiffCache,
         change_id: &str,
+        diff_from: Option<&str>,
         graph_indent: &str,
     ) -> Result<Vec<Self>> {
-        let output = JjCommand::diff_summary(change_id, global_args.clone()).run()?;
+        let output = diff_cache.run(JjCommand::diff_summary(
+            change_id,
+            diff_from,
+            global_args.clone(),
+        ))?;
         let lines: Vec<&str> = output.trim().lines().collect();
 
         let mut file_diffs = Vec::new();
         for line in lines {
             file_diffs.push(Self::new(
                 change_id.to_string(),
+                diff_from.map(String::from),
                 line.to_string(),
                 graph_indent.to_string(),
             )?);
@@ -619,11 +1282,23 @@ impl LogTreeNode for FileDiff {
             fold_symbol(self.unfolded),
             Span::raw(" "),
             Span::styled(
-                format!("{}  {}", self.status, self.description),
+                match &self.old_path {
+                    Some(old_path) => format!("{}  {old_path} → {}", self.status, self.path),
+                    None => format!("{}  {}", self.status, self.path),
+                },
                 Style::default().fg(Color::LightBlue),
             ),
         ]);
-        Ok(Text::from(line))
+        let mut lines = vec![line];
+        if self.unfolded
+            && let Some(summary) = &self.summary
+        {
+            lines.push(Line::from(vec![
+                Span::raw(format!("{}  ", self.graph_indent)),
+                Span::styled(summary.clone(), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+        Ok(Text::from(lines))
     }
 
     fn flatten(
@@ -660,14 +1335,11 @@ impl LogTreeNode for FileDiff {
             .collect()
     }
 
-    fn toggle_fold(&mut self, global_args: &GlobalArgs) -> Result<()> {
+    fn toggle_fold(&mut self, global_args: &GlobalArgs, diff_cache: &mut DiffCache) -> Result<()> {
         self.unfolded = !self.unfolded;
 
         if !self.loaded {
-            let diff_hunks =
-                DiffHunk::load_all(global_args, &self.change_id, &self.path, &self.graph_indent)?;
-            self.diff_hunks = diff_hunks;
-            self.loaded = true;
+            self.load_diff_hunks(global_args, diff_cache, false)?;
         }
 
         Ok(())
@@ -739,11 +1411,7 @@ impl DiffHunk {
         let line_num_chars_len = max_line_num.checked_ilog10().unwrap_or(0) as usize;
         let mut diff_hunk_lines = diff_hunk_lines;
         for line in diff_hunk_lines.iter_mut() {
-            line.pretty_string = line.pretty_string.replacen(
-                &" ".repeat(3_usize.saturating_sub(line_num_chars_len)),
-                "",
-                1,
-            );
+            line.dedent(3_usize.saturating_sub(line_num_chars_len));
         }
 
         Ok(Self {
@@ -762,7 +1430,6 @@ impl DiffHunk {
         diff_hunk_lines: &[DiffHunkLine],
         direction: SearchDirection,
     ) -> Result<(u32, u32)> {
-        let line_nums_regex = Regex::new(r"^\s*(\d+)?\s+(\d+)?:").unwrap();
         let mut red: Option<String> = None;
         let mut green: Option<String> = None;
 
@@ -770,12 +1437,12 @@ impl DiffHunk {
             SearchDirection::Down => diff_hunk_lines.iter().collect(),
             SearchDirection::Up => diff_hunk_lines.iter().rev().collect(),
         };
-        for line in hunk_lines.iter().map(|l| strip_ansi(&l.pretty_string)) {
+        for line in hunk_lines.iter().map(|l| &l.clean_string) {
             if matches!(line.trim(), "~" | "(binary)" | "(empty)") {
                 continue;
             }
-            let captures = line_nums_regex
-                .captures(&line)
+            let captures = get_re_line_nums()
+                .captures(line)
                 .ok_or_else(|| anyhow!("Cannot parse diff hunk line: {line:?}"))?;
             if red.is_none()
                 && let Some(num_match) = captures.get(1)
@@ -805,16 +1472,9 @@ impl DiffHunk {
         Ok((red.unwrap().parse()?, green.unwrap().parse()?))
     }
 
-    fn load_all(
-        global_args: &GlobalArgs,
-        change_id: &str,
-        file: &str,
-        graph_indent: &str,
-    ) -> Result<Vec<Self>> {
-        let output = JjCommand::diff_file(change_id, file, global_args.clone()).run()?;
+    fn parse_all(output: &str, graph_indent: &str) -> Result<Vec<Self>> {
         let output_lines: Vec<&str> = output.trim().lines().skip(1).collect();
 
-        let separator_regex = Regex::new(r"^\s*\.\.\.\s*$")?;
         let mut diff_hunks: Vec<DiffHunk> = Vec::new();
         let mut diff_hunk_lines = Vec::new();
 
@@ -829,8 +1489,7 @@ impl DiffHunk {
         fn parse_line_number(line: &str) -> Option<u32> {
             // Parse line number from format like "  OLD  NEW: content" or "    NEW: content" or "  OLD   :"
             // We want the NEW (green) line number
-            let regex = regex::Regex::new(r"^\s*(\d+)?\s+(\d+)?:").ok()?;
-            let captures = regex.captures(line)?;
+            let captures = get_re_line_nums().captures(line)?;
 
             // Try green line number first (group 2), fall back to red (group 1)
             captures
@@ -842,17 +1501,20 @@ impl DiffHunk {
         for line in output_lines {
             let clean_line = strip_ansi(line);
 
-            if separator_regex.is_match(&clean_line) {
+            if get_re_hunk_separator().is_match(&clean_line) {
                 push_diff_hunk(diff_hunk_lines)?;
                 diff_hunk_lines = Vec::new();
             } else {
                 // Parse line number from the diff line format (use clean_line without ANSI)
                 let line_num = parse_line_number(&clean_line);
+                let symbol = diff_symbol(&clean_line);
                 diff_hunk_lines.push(DiffHunkLine::new(
-                    line.to_string(),
+                    line,
+                    clean_line,
                     graph_indent.to_string(),
                     line_num,
-                ));
+                    symbol,
+                )?);
             }
         }
 
@@ -865,10 +1527,12 @@ impl DiffHunk {
                 .unwrap()
                 .diff_hunk_lines
                 .push(DiffHunkLine::new(
-                    "\x1b[35m~\x1b[0m".to_string(),
+                    "\x1b[35m~\x1b[0m",
+                    "~".to_string(),
                     graph_indent.to_string(),
                     None,
-                ));
+                    ' ',
+                )?);
         }
 
         Ok(diff_hunks)
@@ -937,7 +1601,11 @@ impl LogTreeNode for DiffHunk {
             .collect()
     }
 
-    fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
+    fn toggle_fold(
+        &mut self,
+        _global_args: &GlobalArgs,
+        _diff_cache: &mut DiffCache,
+    ) -> Result<()> {
         self.unfolded = !self.unfolded;
         Ok(())
     }
@@ -945,30 +1613,65 @@ impl LogTreeNode for DiffHunk {
 
 #[derive(Debug)]
 pub struct DiffHunkLine {
-    pretty_string: String,
+    pretty_line: Line<'static>,
+    clean_string: String,
     graph_indent: String,
     flat_log_idx: usize,
     line_number: Option<u32>,
+    /// `-` for a removed line, `+` for an added one, otherwise a space
+    symbol: char,
 }
 
 impl DiffHunkLine {
-    fn new(pretty_string: String, graph_indent: String, line_number: Option<u32>) -> Self {
-        Self {
-            pretty_string,
+    fn new(
+        pretty_string: &str,
+        clean_string: String,
+        graph_indent: String,
+        line_number: Option<u32>,
+        symbol: char,
+    ) -> Result<Self> {
+        Ok(Self {
+            pretty_line: parse_line(pretty_string)?,
+            clean_string,
             graph_indent,
             flat_log_idx: 0,
             line_number,
+            symbol,
+        })
+    }
+
+    /// Remove the first run of `count` spaces, which pads the line numbers.
+    /// Spaces can't run across spans, which only split where the colors
+    /// change.
+    fn dedent(&mut self, count: usize) {
+        let padding = " ".repeat(count);
+        self.clean_string = self.clean_string.replacen(&padding, "", 1);
+        if let Some(span) = self
+            .pretty_line
+            .spans
+            .iter_mut()
+            .find(|span| span.content.contains(&padding))
+        {
+            span.content = span.content.replacen(&padding, "", 1).into();
         }
     }
 }
 
 impl LogTreeNode for DiffHunkLine {
     fn render(&self) -> Result<Text<'static>> {
-        let clean_string = strip_ansi(&self.pretty_string);
-        let mut line = Line::from(vec![Span::raw(self.graph_indent.clone()), Span::raw("  ")]);
+        let marked = crate::theme::palette().diff_symbols && self.symbol != ' ';
+        let gutter = if marked {
+            format!("{} ", self.symbol)
+        } else {
+            "  ".to_string()
+        };
+        let mut line = Line::from(vec![
+            Span::raw(self.graph_indent.clone()),
+            Span::raw(gutter),
+        ]);
 
-        for span in self.pretty_string.into_text()?.lines[0].spans.clone() {
-            let span = if clean_string.starts_with("+") || clean_string.starts_with("-") {
+        for span in self.pretty_line.spans.clone() {
+            let span = if marked {
                 let style = span.style.bold();
                 span.style(style)
             } else {
@@ -1000,7 +1703,11 @@ impl LogTreeNode for DiffHunkLine {
         Vec::new()
     }
 
-    fn toggle_fold(&mut self, _global_args: &GlobalArgs) -> Result<()> {
+    fn toggle_fold(
+        &mut self,
+        _global_args: &GlobalArgs,
+        _diff_cache: &mut DiffCache,
+    ) -> Result<()> {
         Ok(())
     }
 
@@ -1009,12 +1716,29 @@ impl LogTreeNode for DiffHunkLine {
     }
 }
 
+/// Whether a line of `jj diff --color-words` was removed or added, from
+/// which sides it has a line number on
+fn diff_symbol(clean_line: &str) -> char {
+    let Some(captures) = get_re_line_nums().captures(clean_line) else {
+        return ' ';
+    };
+    match (captures.get(1), captures.get(2)) {
+        (Some(_), None) => '-',
+        (None, Some(_)) => '+',
+        _ => ' ',
+    }
+}
+
+/// "14k" for 14321
+fn format_line_count(count: usize) -> String {
+    if count >= 1000 {
+        format!("{}k", count / 1000)
+    } else {
+        count.to_string()
+    }
+}
+
 fn fold_symbol(unfolded: bool) -> Span<'static> {
     let symbol = if unfolded { "▾" } else { "▸" };
     Span::styled(symbol, Style::default().fg(Color::DarkGray))
 }
-
-pub fn strip_ansi(pretty_str: &str) -> String {
-    let ansi_regex = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
-    ansi_regex.replace_all(pretty_str, "").to_string()
-}
@@ -1,26 +1,30 @@
-use log::{Level, Log, Metadata, Record};
+use log::{LevelFilter, Log, Metadata, Record};
 use std::fs::{OpenOptions, create_dir_all};
-use std::io::Write;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// How much of the end of the log file the in-app log viewer reads
+const TAIL_BYTES: u64 = 256 * 1024;
+
+static LOG_FILE: OnceLock<PathBuf> = OnceLock::new();
 
 pub struct FileLogger {
     file: Mutex<std::fs::File>,
-    level: Level,
+    level: LevelFilter,
 }
 
 impl FileLogger {
-    pub fn init(level: Level) -> Result<(), Box<dyn std::error::Error>> {
-        let log_dir = PathBuf::from("/tmp/jjdag");
-        create_dir_all(&log_dir)?;
-
-        let date = chrono::Local::now().format("%Y-%m-%d");
-        let log_file = log_dir.join(format!("jjdag-{}.log", date));
+    pub fn init(level: LevelFilter, log_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(log_dir) = log_file.parent() {
+            create_dir_all(log_dir)?;
+        }
 
         let file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open(&log_file)?;
+            .open(log_file)?;
 
         let logger = Box::new(FileLogger {
             file: Mutex::new(file),
@@ -28,10 +32,62 @@ impl FileLogger {
         });
 
         log::set_boxed_logger(logger)?;
-        log::set_max_level(level.to_level_filter());
+        log::set_max_level(level);
+        let _ = LOG_FILE.set(log_file.to_path_buf());
 
         Ok(())
     }
+
+    /// Today's log file under the state directory, e.g.
+    /// `~/.local/state/jjdag/logs/jjdag-2024-01-31.log`
+    pub fn default_log_file() -> Option<PathBuf> {
+        let date = chrono::Local::now().format("%Y-%m-%d");
+        crate::state::state_dir().map(|dir| dir.join("logs").join(format!("jjdag-{}.log", date)))
+    }
+
+    /// The file being logged to, if logging is enabled
+    pub fn log_file() -> Option<&'static Path> {
+        LOG_FILE.get().map(PathBuf::as_path)
+    }
+}
+
+/// The last lines of the log file, read again only when the file changes
+#[derive(Debug, Default)]
+pub struct LogTail {
+    /// Length and modification time of the file when it was last read
+    read_at: Option<(u64, Option<SystemTime>)>,
+    lines: Vec<String>,
+}
+
+impl LogTail {
+    pub fn lines(&mut self) -> std::io::Result<&[String]> {
+        let Some(path) = FileLogger::log_file() else {
+            return Ok(&[]);
+        };
+        let metadata = std::fs::metadata(path)?;
+        let read_at = Some((metadata.len(), metadata.modified().ok()));
+        if self.read_at != read_at {
+            self.lines = read_tail(path, metadata.len())?;
+            self.read_at = read_at;
+        }
+        Ok(&self.lines)
+    }
+}
+
+fn read_tail(path: &Path, len: u64) -> std::io::Result<Vec<String>> {
+    let mut file = std::fs::File::open(path)?;
+    let start = len.saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = Vec::new();
+    file.take(len - start).read_to_end(&mut bytes)?;
+    let contents = String::from_utf8_lossy(&bytes);
+    let mut lines = contents.lines();
+    if start > 0 {
+        // The first line is most likely cut off
+        lines.next();
+    }
+    Ok(lines.map(String::from).collect())
 }
 
 impl Log for FileLogger {
@@ -0,0 +1,123 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use std::collections::{HashMap, VecDeque};
+
+/// Most times a macro can be played in one go
+const MAX_COUNT: u32 = 1000;
+/// Most macros playing each other, as in a macro that plays itself
+const MAX_DEPTH: usize = 10;
+/// Most keys waiting to be played back
+const MAX_PLAYBACK_KEYS: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroAction {
+    Record,
+    Play,
+}
+
+/// Key sequences recorded into registers and replayed as if typed
+#[derive(Debug, Default)]
+pub struct Macros {
+    registers: HashMap<char, Vec<KeyEvent>>,
+    /// Register being recorded into and the keys so far
+    recording: Option<(char, Vec<KeyEvent>)>,
+    /// Waiting for the register to record into or play, after an optional count
+    awaiting_register: Option<(MacroAction, u32)>,
+    /// Keys to play back, with how many macros deep they were played from
+    playback: VecDeque<(KeyEvent, usize)>,
+    /// How many macros deep the key being handled was played from, 0 when
+    /// it was typed
+    depth: usize,
+}
+
+impl Macros {
+    pub fn recording_register(&self) -> Option<char> {
+        self.recording.as_ref().map(|(register, _)| *register)
+    }
+
+    pub fn awaiting_register(&self) -> Option<(MacroAction, u32)> {
+        self.awaiting_register
+    }
+
+    /// Start recording or playing once a register is picked
+    pub fn prompt_register(&mut self, action: MacroAction) {
+        self.awaiting_register = Some((action, 0));
+    }
+
+    /// Handle a key typed while waiting for a register: digits build up a
+    /// playback count, a character picks the register, anything else cancels.
+    /// Returns a message to show when something went wrong.
+    pub fn register_key(&mut self, key_code: KeyCode) -> Option<String> {
+        let (action, count) = self.awaiting_register.take()?;
+        match key_code {
+            KeyCode::Char(digit @ '0'..='9') if action == MacroAction::Play => {
+                let digit = digit.to_digit(10).unwrap_or_default();
+                let count = count.saturating_mul(10).saturating_add(digit);
+                self.awaiting_register = Some((action, count.min(MAX_COUNT)));
+                None
+            }
+            KeyCode::Char(register) if action == MacroAction::Record => {
+                self.recording = Some((register, Vec::new()));
+                None
+            }
+            KeyCode::Char(register) => {
+                let Some(keys) = self.registers.get(&register) else {
+                    return Some(format!("Register {register} is empty"));
+                };
+                let depth = self.depth + 1;
+                let count = count.max(1) as usize;
+                if depth > MAX_DEPTH {
+                    self.stop_playback();
+                    return Some(format!(
+                        "Stopped: macros played each other more than {MAX_DEPTH} deep"
+                    ));
+                }
+                if self.playback.len() + keys.len() * count > MAX_PLAYBACK_KEYS {
+                    self.stop_playback();
+                    return Some(format!(
+                        "Stopped: macros would play more than {MAX_PLAYBACK_KEYS} keys"
+                    ));
+                }
+                // Ahead of the rest of a macro that plays this one
+                for _ in 0..count {
+                    for key in keys.iter().rev() {
+                        self.playback.push_front((*key, depth));
+                    }
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Remember a key typed by the user, if recording
+    pub fn record(&mut self, key: KeyEvent) {
+        self.depth = 0;
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(key);
+        }
+    }
+
+    /// Store the recording, minus the key that stopped it, and return its
+    /// register
+    pub fn stop_recording(&mut self) -> Option<char> {
+        let (register, mut keys) = self.recording.take()?;
+        keys.pop();
+        self.registers.insert(register, keys);
+        Some(register)
+    }
+
+    pub fn next_playback_key(&mut self) -> Option<KeyEvent> {
+        let (key, depth) = self.playback.pop_front()?;
+        self.depth = depth;
+        Some(key)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.playback.is_empty()
+    }
+
+    /// Drop the keys still to be played back
+    pub fn stop_playback(&mut self) {
+        self.playback.clear();
+    }
+}
@@ -1,29 +1,36 @@
 mod cli;
-mod command_tree;
-mod log_tree;
-mod logger;
-mod model;
-mod shell_out;
-mod terminal;
-mod update;
-mod view;
-
-use crate::model::{Model, State};
-use crate::update::update;
-use crate::view::view;
+mod repo_picker;
 
 use anyhow::Result;
 use clap::Parser;
 use cli::Args;
-use log::Level;
-use shell_out::JjCommand;
-use terminal::Term;
+use jjdag_core::{
+    config::Config,
+    logger,
+    model::{Model, State},
+    profile, session,
+    shell_out::JjCommand,
+    state,
+    terminal::{self, Term},
+    update::update,
+    view::view,
+};
+use log::LevelFilter;
 
 fn main() {
-    let _ = logger::FileLogger::init(Level::Debug);
+    let args = Args::parse();
+    if args.log_level != LevelFilter::Off
+        && let Some(log_file) = args
+            .log_file
+            .clone()
+            .or_else(logger::FileLogger::default_log_file)
+        && let Err(err) = logger::FileLogger::init(args.log_level, &log_file)
+    {
+        eprintln!("Cannot open log file {}: {err}", log_file.display());
+    }
     log::info!("jjdag starting up");
 
-    let result = run();
+    let result = run(args);
     if let Err(err) = result {
         log::error!("Fatal error: {}", err);
         // Avoids a redundant message "Error: Error:"
@@ -33,11 +40,36 @@ fn main() {
     log::info!("jjdag shutting down normally");
 }
 
-fn run() -> Result<()> {
-    let args = Args::parse();
+fn run(args: Args) -> Result<()> {
     log::info!("CLI args parsed, repository: {:?}", args.repository);
+    let mut config = Config::load()?;
+    if let Some(keymap) = args.keymap {
+        config.keymap = keymap;
+    }
+    args.theme.unwrap_or(config.theme).install();
+    if args.profile {
+        profile::enable();
+    }
+    if let Some(path) = &args.replay {
+        // The recorded repository needn't exist here, since jj isn't run
+        let (repository, revset) = session::start_replay(path)?;
+        let model = Model::new(repository, revset, &config)?;
+        return run_tui(model);
+    }
     let repository = match JjCommand::ensure_valid_repo(&args.repository) {
         Ok(repo) => repo,
+        Err(err)
+            if let Some(git_root) = repo_picker::find_git_only_repository(&args.repository) =>
+        {
+            // Plain git repository - offer to colocate jj with it
+            log::info!("Found git repository without jj at: {:?}", git_root);
+            if !repo_picker::confirm_init_colocated()? {
+                return Err(err.into());
+            }
+            let git_root = git_root.to_string_lossy();
+            JjCommand::git_init_colocate(&git_root)?;
+            JjCommand::ensure_valid_repo(&git_root)?
+        }
         Err(_) => {
             // Launch detection: check for subdirectory with .jj/ (power workspace post-scoop case)
             let cwd = std::env::current_dir()?;
@@ -65,32 +97,62 @@ fn run() -> Result<()> {
                     JjCommand::ensure_valid_repo(".")?
                 }
                 None => {
-                    // No recovery possible - propagate error by retrying
-                    JjCommand::ensure_valid_repo(&args.repository)?
+                    // No recovery possible - offer recent repositories, or propagate
+                    // the error by retrying if there are none
+                    let candidates = repo_picker::candidate_repositories(&config);
+                    if candidates.is_empty() {
+                        JjCommand::ensure_valid_repo(&args.repository)?
+                    } else {
+                        log::info!("Offering {} repositories to open", candidates.len());
+                        match repo_picker::pick_repository(candidates)? {
+                            Some(path) => {
+                                std::env::set_current_dir(&path)?;
+                                JjCommand::ensure_valid_repo(".")?
+                            }
+                            None => return Ok(()),
+                        }
+                    }
                 }
             }
         }
     };
     log::info!("Repository validated: {}", repository);
-    let model = Model::new(repository, args.revisions)?;
+    if let Err(err) = state::record_recent_repository(&repository) {
+        log::warn!("Could not record recent repository: {}", err);
+    }
+    if let Some(path) = &args.record {
+        session::start_recording(path, &repository, &args.revisions)?;
+    }
+    let model = Model::new(repository, args.revisions, &config)?;
     log::info!(
         "Model initialized with {} revisions",
         model.jj_log.log_tree.len()
     );
 
+    run_tui(model)
+}
+
+fn run_tui(model: Model) -> Result<()> {
     let terminal = terminal::init_terminal()?;
     log::info!("Starting TUI loop");
     let result = tui_loop(model, terminal);
     log::info!("TUI loop ended");
     terminal::relinquish_terminal()?;
 
+    if let Some(summary) = profile::summary() {
+        log::info!("Profile:\n{summary}");
+        eprint!("{summary}");
+    }
     result
 }
 
 fn tui_loop(mut model: Model, terminal: Term) -> Result<()> {
     log::debug!("Entering TUI loop");
     while model.state != State::Quit {
-        terminal.borrow_mut().draw(|f| view(&mut model, f))?;
+        // Redrawing an unchanged screen is visible over slow connections
+        if std::mem::take(&mut model.dirty) {
+            terminal.borrow_mut().draw(|f| view(&mut model, f))?;
+        }
         update(terminal.clone(), &mut model)?;
     }
     log::debug!("TUI loop exiting, state: {:?}", model.state);
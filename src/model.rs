@@ -1,24 +1,73 @@
 use crate::{
-    command_tree::{CommandTree, display_unbound_error_lines},
+    ansi::strip_ansi,
+    ci::{self, CiFetch, CiStatus, CiTarget},
+    command_tree::{CommandTree, NodeKind, display_unbound_error_lines},
+    completion::{self, Completion},
+    config::{
+        CiProvider, Config, DEFAULT_OUTPUT_HISTORY, DEFAULT_TICK_MS, DEFAULT_TUG_REVSET,
+        DescriptionTemplate, ExternalCommand, NotificationConfig, Opener, expand_home,
+        fill_placeholders,
+    },
+    description_lint::DescriptionLinter,
+    export,
+    file_browser::FileBrowser,
+    image_preview::{self, GraphicsProtocol},
+    keymap::Keymap,
     log_tree::{
-        DIFF_HUNK_LINE_IDX, JjLog, LogTreeNode, TreePosition, get_parent_tree_position, strip_ansi,
+        COMMIT_OR_TEXT_IDX, Commit, CommitOrText, DIFF_HUNK_IDX, DIFF_HUNK_LINE_IDX, FILE_DIFF_IDX,
+        JjLog, LogTreeNode, TreePosition, get_parent_tree_position,
     },
-    shell_out::{JjCommand, JjCommandError},
+    macros::{MacroAction, Macros},
+    notification::Notification,
+    output_history::OutputHistory,
+    profile,
+    remote_bookmarks::{RemoteBookmark, RemoteBookmarkBrowser},
+    scripting::{ScriptOutput, ScriptSelection, Scripts},
+    shell_out::{JjCommand, JjCommandError, JjFailureKind, OversizedFiles},
     terminal::Term,
+    trailers,
     update::{
-        AbandonMode, AbsorbMode, BookmarkMoveMode, DuplicateDestination, DuplicateDestinationType,
-        EditMode, GitFetchMode, GitPushMode, InterdiffMode, Message, MetaeditAction, NewMode,
-        NextPrevDirection, NextPrevMode, ParallelizeSource, RebaseDestination,
-        RebaseDestinationType, RebaseSourceType, RestoreMode, RevertDestination,
-        RevertDestinationType, RevertRevision, SignAction, SimplifyParentsMode, SquashMode,
-        TextPromptAction, ViewMode,
+        AbandonMode, AbsorbMode, BookmarkMoveMode, CompletionKind, DuplicateDestination,
+        DuplicateDestinationType, EditMode, GitFetchMode, GitPushMode, InterdiffMode, JumpTarget,
+        Message, MetaeditAction, NewMode, NextPrevDirection, NextPrevMode, ParallelizeSource,
+        RebaseDestination, RebaseDestinationType, RebaseSourceType, ResolveSide, RestoreMode,
+        RevertDestination, RevertDestinationType, RevertRevision, SignAction, SimplifyParentsMode,
+        SquashMode, TextPromptAction, ViewMode, ZoomMode,
     },
+    view::InfoPanePosition,
 };
 use ansi_to_tui::IntoText;
 use anyhow::Result;
 use arboard::Clipboard;
 use crossterm::event::KeyCode;
-use std::fmt;
+use indexmap::IndexMap;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+    path::Path,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+/// How often statuses checked in the background are picked up
+const CI_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Every operation before the current one
+const OP_ABANDON_DEFAULT_RANGE: &str = "..@-";
+const SPLIT_DEFAULT_DESCRIPTION: &str = "Split: part 1";
+/// Searches stop after this many matching lines
+const GREP_MAX_MATCHES: usize = 1000;
+/// Revisions other than the working copy are read a file at a time through
+/// jj, so searches of them stop after this many files
+const GREP_MAX_JJ_FILES: usize = 200;
+/// First choice in the split file popup, for picking changes in the diff editor
+const SPLIT_IN_DIFF_EDITOR: &str = "(choose changes in the diff editor)";
+/// Choice in the merge tool popup for jj's `ui.merge-editor`
+const DEFAULT_MERGE_TOOL: &str = "(default merge editor)";
+/// One `Name <email>` line per commit
+const AUTHORS_TEMPLATE: &str = r#"author.name() ++ " <" ++ author.email() ++ ">\n""#;
 
 /// Wrapper for Clipboard that implements Debug
 pub struct ClipboardWrapper(Option<Clipboard>);
@@ -74,6 +123,9 @@ pub enum State {
 pub struct GlobalArgs {
     pub repository: String,
     pub ignore_immutable: bool,
+    /// Browse without snapshotting the working copy first, so what's shown
+    /// may be out of date
+    pub ignore_working_copy: bool,
 }
 
 #[derive(Debug)]
@@ -81,13 +133,70 @@ pub struct Model {
     pub global_args: GlobalArgs,
     pub display_repository: String,
     pub revset: String,
+    /// Revsets zoomed in from, outermost first
+    pub zoom_stack: Vec<String>,
+    /// Loaded commits in the selection's lineage, while it's highlighted
+    lineage: Option<Lineage>,
+    author_filter: Option<AuthorFilter>,
     pub state: State,
     pub command_tree: CommandTree,
+    pub keymap: Keymap,
+    openers: Vec<Opener>,
+    external_commands: Vec<ExternalCommand>,
+    /// Hooks and bindings from the user's `hooks.rhai`
+    scripts: Option<Scripts>,
+    /// Whether the last command run on a range passed, by full change id
+    pub run_results: HashMap<String, bool>,
+    description_templates: Vec<DescriptionTemplate>,
+    pub description_linter: DescriptionLinter,
+    pub macros: Macros,
+    trailer_templates: Vec<String>,
+    ci_provider: Option<CiProvider>,
+    /// Revset whose bookmarks tugging moves
+    tug_revset: String,
+    /// Time between redraws while the screen changes without input
+    tick: Duration,
+    /// Where the info pane is, toggled at runtime
+    pub info_pane: InfoPanePosition,
+    /// Whether the header and key hints are hidden, leaving the screen to
+    /// the log
+    pub focus_mode: bool,
+    /// Whether file lines show if they've been viewed, and commits how many
+    /// of their files have
+    pub review_mode: bool,
+    /// Files marked viewed while reviewing, by change id
+    pub viewed_files: HashMap<String, BTreeSet<String>>,
+    /// Notes jotted about changes, by change id, kept outside the repo
+    pub notes: HashMap<String, String>,
+    /// Whether the file header is pinned above a scrolled diff along with
+    /// the commit's
+    sticky_file_header: bool,
+    /// Merge tool last picked for `jj resolve`, offered first next time
+    merge_tool: Option<String>,
+    /// jj's `fsmonitor.backend` as of startup, if snapshots don't scan the
+    /// whole working copy
+    pub fsmonitor: Option<String>,
+    /// What the CI provider last said, by commit id
+    pub ci_statuses: HashMap<String, CiStatus>,
+    /// Statuses still being checked in the background
+    ci_fetch: Option<CiFetch>,
+    /// Template printing each configured badge's text, and its color
+    badge_templates: Vec<(String, Color)>,
+    /// Badges of the loaded commits, by the commit id the log shows
+    pub badges: HashMap<String, Vec<(String, Color)>>,
     command_keys: Vec<KeyCode>,
     queued_jj_commands: Vec<JjCommand>,
     accumulated_command_output: Vec<Line<'static>>,
+    /// Outputs of the last commands run, for paging back through
+    output_history: OutputHistory,
+    notifications: NotificationConfig,
+    /// Whether the terminal has focus, as far as it reports
+    pub terminal_focused: bool,
+    /// A long command finished, to notify about if the terminal turns out to
+    /// be unfocused once the events read while it ran are handled
+    pending_notification: Option<Notification>,
     saved_change_id: Option<String>,
-    saved_file_path: Option<String>,
+    saved_fileset: Option<String>,
     saved_tree_position: Option<TreePosition>,
     pub jj_log: JjLog,
     pub log_list: Vec<Text<'static>>,
@@ -96,27 +205,130 @@ pub struct Model {
     pub log_list_layout: Rect,
     pub log_list_scroll_padding: usize,
     pub info_list: Option<Text<'static>>,
+    /// Transient confirmations, oldest first
+    pub toasts: Vec<Toast>,
+    /// Whether anything changed since the last frame was drawn
+    pub dirty: bool,
     /// Current fuzzy searchable popup for selection lists
     pub current_popup: Option<crate::update::Popup>,
     /// Where text input is currently active (source of truth)
     pub text_input_location: crate::update::TextInputLocation,
     /// Filter text for fuzzy searching in popups
     pub popup_filter: String,
-    /// Selected index in the current popup's filtered list
-    pub popup_selection: usize,
+    /// Selection and scroll position in the current popup's filtered list
+    pub popup_list: crate::update::PopupListState,
     /// Text input buffer for text prompt popups
     pub text_input: String,
     /// Cursor position in text input (byte index)
     pub text_cursor: usize,
     /// Track if user has been warned about first line exceeding 50 chars
     pub description_warning_shown: bool,
+    /// Whether the description being edited started from a template, so Tab
+    /// moves between its placeholders
+    pub description_from_template: bool,
     /// Track last click for double-click detection
     last_click_time: Option<std::time::Instant>,
     last_click_pos: Option<(u16, u16)>,
+    /// Log list index where the current mouse drag started
+    drag_anchor: Option<usize>,
+    /// `older::newer` revset when a range was selected with the mouse
+    mouse_range: Option<String>,
+    /// Destinations collected so far for a rebase onto several parents
+    rebase_destinations: Vec<String>,
+    /// Remote chosen for the next push, instead of jj's default
+    push_remote: Option<String>,
+    /// Rules the name being typed into the text input is checked against
+    name_check: Option<NameCheck>,
+    /// Revisions offered for the word being typed into a revision prompt
+    completion: Option<Completion>,
+    /// Commands shown in the info list that run once confirmed with Enter
+    commands_awaiting_confirmation: Vec<JjCommand>,
+    /// Operation the repo was at when the running queue started, and the
+    /// queue's first command
+    queue_start: Option<(String, String)>,
+    /// What jjdag ran this session, latest last
+    action_ops: Vec<ActionOps>,
+    /// Open the description editor on the working copy once the queue is done
+    describe_after_queue: bool,
+    /// A push to preview and confirm once the queue is done, for pushes that
+    /// depend on the commands before them
+    push_after_queue: Option<JjCommand>,
+    /// Untracked remote bookmarks that fetches in the queue brought in,
+    /// offered for tracking once it's done
+    fetched_bookmarks: Vec<String>,
+    /// A failed command and the ones queued after it, for the popup offering
+    /// ways to retry them
+    retry_commands: Vec<JjCommand>,
+    /// Commits still to be described when describing a range, last one next
+    describe_queue: Vec<String>,
+    /// Describe commands for the range commits already edited, run once all are done
+    pending_describe_cmds: Vec<JjCommand>,
+    /// Lines scrolled back from the end of jjdag's own log, when the log viewer is open
+    pub log_viewer_scroll: Option<usize>,
+    /// What the log viewer shows of jjdag's own log
+    pub log_tail: crate::logger::LogTail,
+    /// Browser over the files of a revision, shown in place of the log
+    pub file_browser: Option<FileBrowser>,
+    pub remote_bookmarks: Option<RemoteBookmarkBrowser>,
+    /// Temporary file a queued or confirmable command reads, such as an edited
+    /// copy of a file from an older revision; replaced by the next one
+    command_input_file: Option<tempfile::TempPath>,
+    /// Size of the repo store when a garbage collecting command was queued
+    repo_size_before_gc: Option<u64>,
     /// Clipboard for copy/paste operations
     clipboard: ClipboardWrapper,
 }
 
+/// A bookmark or workspace name being typed, which must be a valid ref name
+/// that isn't already taken
+#[derive(Debug)]
+struct NameCheck {
+    kind: &'static str,
+    existing: Vec<String>,
+}
+
+/// The loaded ancestors and descendants of a commit, and the commit itself
+#[derive(Debug, Default)]
+struct Lineage {
+    /// Short commit id of the selection they were loaded for
+    selected: Option<String>,
+    /// Short commit ids, as the log shows them
+    commit_ids: HashSet<String>,
+}
+
+/// A revset narrowed to one author's commits, and the revset it narrowed
+#[derive(Debug)]
+struct AuthorFilter {
+    author: String,
+    base_revset: String,
+    revset: String,
+}
+
+/// A short-lived confirmation shown in the top-right corner
+#[derive(Debug)]
+pub struct Toast {
+    pub message: String,
+    created_at: Instant,
+}
+
+/// The operations around something jjdag ran, so it can be undone without
+/// touching operations from elsewhere
+#[derive(Debug)]
+struct ActionOps {
+    command: String,
+    before: String,
+    after: String,
+}
+
+/// Where `ignore_paths` adds paths
+#[derive(Debug, Clone, Copy)]
+enum IgnoreFile {
+    /// The workspace's `.gitignore`, shared with everyone
+    GitIgnore,
+    /// The git repo's `info/exclude`, for this clone only
+    GitExclude,
+}
+
 #[derive(Debug)]
 enum ScrollDirection {
     Up,
@@ -124,39 +336,116 @@ enum ScrollDirection {
 }
 
 impl Model {
-    pub fn new(repository: String, revset: String) -> Result<Self> {
+    pub fn new(repository: String, revset: String, config: &Config) -> Result<Self> {
+        let display_repository = format_repository_for_display(&repository);
+        let global_args = GlobalArgs {
+            repository,
+            ignore_immutable: false,
+            ignore_working_copy: false,
+        };
+        let mut command_tree = CommandTree::new();
+        command_tree.add_external_commands(&config.commands)?;
+        let scripts = Scripts::load(global_args.clone())?;
+        if let Some(scripts) = &scripts {
+            command_tree.add_script_bindings(scripts.bindings())?;
+        }
         let mut model = Self {
             state: State::default(),
-            command_tree: CommandTree::new(),
+            command_tree,
+            keymap: Keymap::new(config.keymap, &config.keys)?,
+            openers: config.openers.clone(),
+            external_commands: config.commands.clone(),
+            scripts,
+            run_results: HashMap::new(),
+            description_templates: config.templates.clone(),
+            description_linter: DescriptionLinter::new(&config.description_lint)?,
+            macros: Macros::default(),
+            trailer_templates: config.trailers.clone(),
+            ci_provider: config.ci.clone(),
+            tug_revset: config
+                .tug_revset
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TUG_REVSET.to_string()),
+            tick: Duration::from_millis(config.tick_ms.unwrap_or(DEFAULT_TICK_MS)),
+            info_pane: config.info_pane,
+            focus_mode: false,
+            review_mode: false,
+            viewed_files: crate::state::load_viewed_files(),
+            notes: crate::state::load_notes(),
+            sticky_file_header: config.sticky_file_header,
+            fsmonitor: JjCommand::config_get("fsmonitor.backend", global_args.clone())
+                .run()
+                .map(|value| strip_ansi(&value).trim().to_string())
+                .ok()
+                .filter(|value| !value.is_empty() && value != "none"),
+            merge_tool: None,
+            ci_statuses: HashMap::new(),
+            ci_fetch: None,
+            badge_templates: config
+                .badges
+                .iter()
+                .map(|badge| Ok((badge.text_template(), badge.color()?)))
+                .collect::<Result<_>>()?,
+            badges: HashMap::new(),
             command_keys: Vec::new(),
             queued_jj_commands: Vec::new(),
             accumulated_command_output: Vec::new(),
+            output_history: OutputHistory::new(
+                config.output_history.unwrap_or(DEFAULT_OUTPUT_HISTORY),
+            ),
+            notifications: config.notifications.clone(),
+            terminal_focused: true,
+            pending_notification: None,
             saved_tree_position: None,
             saved_change_id: None,
-            saved_file_path: None,
-            jj_log: JjLog::new()?,
+            saved_fileset: None,
+            jj_log: JjLog::new(config.unfold)?,
             log_list: Vec::new(),
             log_list_state: ListState::default(),
             log_list_tree_positions: Vec::new(),
             log_list_layout: Rect::ZERO,
             log_list_scroll_padding: LOG_LIST_SCROLL_PADDING,
             info_list: None,
+            toasts: Vec::new(),
+            dirty: true,
             current_popup: None,
             text_input_location: crate::update::TextInputLocation::None,
             popup_filter: String::new(),
-            popup_selection: 0,
+            popup_list: crate::update::PopupListState::default(),
             text_input: String::new(),
             text_cursor: 0,
             description_warning_shown: false,
+            description_from_template: false,
             last_click_time: None,
             last_click_pos: None,
+            drag_anchor: None,
+            mouse_range: None,
+            rebase_destinations: Vec::new(),
+            push_remote: None,
+            name_check: None,
+            completion: None,
+            commands_awaiting_confirmation: Vec::new(),
+            queue_start: None,
+            action_ops: Vec::new(),
+            describe_after_queue: false,
+            push_after_queue: None,
+            fetched_bookmarks: Vec::new(),
+            retry_commands: Vec::new(),
+            describe_queue: Vec::new(),
+            pending_describe_cmds: Vec::new(),
+            log_viewer_scroll: None,
+            log_tail: crate::logger::LogTail::default(),
+            file_browser: None,
+            remote_bookmarks: None,
+            command_input_file: None,
+            repo_size_before_gc: None,
             clipboard: ClipboardWrapper::new(),
-            display_repository: format_repository_for_display(&repository),
-            global_args: GlobalArgs {
-                repository,
-                ignore_immutable: false,
-            },
+            display_repository,
+            global_args,
             revset,
+            zoom_stack: Vec::new(),
+            lineage: None,
+            author_filter: None,
         };
 
         model.sync()?;
@@ -178,9 +467,18 @@ impl Model {
     }
 
     pub fn sync(&mut self) -> Result<()> {
+        let _timer = profile::timer("refresh");
         self.jj_log.load_log_tree(&self.global_args, &self.revset)?;
         self.sync_log_list()?;
         self.reset_log_list_selection()?;
+        self.load_lineage()?;
+        self.load_badges();
+        if self.remote_bookmarks.is_some() {
+            let bookmarks = self.load_remote_bookmarks()?;
+            if let Some(browser) = &mut self.remote_bookmarks {
+                browser.reload(bookmarks);
+            }
+        }
         Ok(())
     }
 
@@ -190,23 +488,72 @@ impl Model {
     }
 
     pub fn refresh(&mut self) -> Result<()> {
-        // Add periods for visual feedback on repeated refreshes
-        let periods = self
-            .info_list
-            .as_ref()
-            .map(|t| t.to_string())
-            .filter(|s| s.starts_with("Refreshed"))
-            .map_or(0, |s| s.matches('.').count() + 3);
         self.clear();
         self.sync()?;
-        self.info_list = Some(format!("Refreshed{}", ".".repeat(periods)).into());
+        if self
+            .ci_provider
+            .as_ref()
+            .is_some_and(|provider| provider.on_refresh)
+        {
+            self.ci_refresh()?;
+        }
+        self.show_toast("Refreshed");
         Ok(())
     }
 
+    /// Show a transient confirmation that dismisses itself after a few seconds
+    pub fn show_toast(&mut self, message: impl Into<String>) {
+        self.toasts.push(Toast {
+            message: message.into(),
+            created_at: Instant::now(),
+        });
+        self.dirty = true;
+    }
+
+    pub fn expire_toasts(&mut self) {
+        let toast_count = self.toasts.len();
+        self.toasts
+            .retain(|toast| toast.created_at.elapsed() < TOAST_DURATION);
+        if self.toasts.len() != toast_count {
+            self.dirty = true;
+        }
+    }
+
+    /// How long the update loop can wait for input before there's something
+    /// to redraw anyway: not at all while jj commands are queued, a tick while
+    /// the log viewer follows the log file, and otherwise until the next toast
+    /// expires. `None` means nothing changes until there's input.
+    pub fn input_timeout(&self) -> Option<Duration> {
+        if self.has_queued_jj_commands() {
+            return Some(Duration::ZERO);
+        }
+        let next_toast_expiry = self
+            .toasts
+            .iter()
+            .map(|toast| TOAST_DURATION.saturating_sub(toast.created_at.elapsed()));
+        let log_viewer_tick = self.log_viewer_scroll.map(|_| self.tick);
+        let ci_tick = self.ci_fetch.as_ref().map(|_| CI_POLL_INTERVAL);
+        next_toast_expiry
+            .chain(log_viewer_tick)
+            .chain(ci_tick)
+            .min()
+    }
+
     pub fn toggle_ignore_immutable(&mut self) {
         self.global_args.ignore_immutable = !self.global_args.ignore_immutable;
     }
 
+    /// Stop or go back to snapshotting the working copy while browsing,
+    /// catching up right away when going back
+    pub fn toggle_ignore_working_copy(&mut self) -> Result<()> {
+        self.global_args.ignore_working_copy = !self.global_args.ignore_working_copy;
+        if self.global_args.ignore_working_copy {
+            Ok(())
+        } else {
+            self.sync()
+        }
+    }
+
     fn log_offset(&self) -> usize {
         self.log_list_state.offset()
     }
@@ -244,8 +591,17 @@ impl Model {
         self.get_file_path(tree_pos)
     }
 
-    fn get_saved_file_path(&self) -> Option<&str> {
-        self.saved_file_path.as_deref()
+    /// The selected file as jj commands should see it, covering both paths of
+    /// a renamed or copied file
+    fn get_selected_fileset(&self) -> Option<&str> {
+        let tree_pos = self.get_selected_tree_position();
+        self.jj_log
+            .get_tree_file_diff(&tree_pos)
+            .map(|file_diff| file_diff.fileset.as_str())
+    }
+
+    fn get_saved_fileset(&self) -> Option<&str> {
+        self.saved_fileset.as_deref()
     }
 
     fn get_file_path(&self, tree_pos: TreePosition) -> Option<&str> {
@@ -300,24 +656,268 @@ impl Model {
     fn maybe_load_more(&mut self) -> Result<()> {
         let selected = self.log_list_state.selected().unwrap();
         // If we're at the last item and there might be more to load
-        if selected >= self.log_list.len() - 1 {
-            let had_more = self.jj_log.load_more()?;
-            if had_more {
-                // Re-sync to include newly loaded items
-                self.sync_log_list()?;
-                // Move to the newly loaded first item
-                self.log_list_state.select_next();
+        if selected >= self.log_list.len() - 1 && self.load_more_log()? {
+            // Move to the newly loaded first item
+            self.log_list_state.select_next();
+        }
+        Ok(())
+    }
+
+    /// Load the next batch of the log, if there's more; returns whether there
+    /// was
+    fn load_more_log(&mut self) -> Result<bool> {
+        let had_more = self.jj_log.load_more()?;
+        if had_more {
+            // Re-sync to include newly loaded items
+            self.sync_log_list()?;
+            self.load_lineage()?;
+            self.load_badges();
+        }
+        Ok(had_more)
+    }
+
+    pub fn toggle_lineage(&mut self) -> Result<()> {
+        if self.lineage.take().is_some() {
+            self.show_toast("Showing all commits alike");
+        } else {
+            self.lineage = Some(Lineage::default());
+            self.load_lineage()?;
+            self.show_toast("Dimming commits outside the selection's lineage");
+        }
+        Ok(())
+    }
+
+    pub fn toggle_info_pane_position(&mut self) {
+        self.info_pane = match self.info_pane {
+            InfoPanePosition::Bottom => {
+                self.show_toast("Showing info on the right");
+                InfoPanePosition::Right
+            }
+            InfoPanePosition::Right => {
+                self.show_toast("Showing info at the bottom");
+                InfoPanePosition::Bottom
+            }
+        };
+    }
+
+    pub fn toggle_focus_mode(&mut self) {
+        self.focus_mode = !self.focus_mode;
+        if self.focus_mode {
+            self.show_toast("Focus mode: the header and key hints are hidden");
+        } else {
+            self.show_toast("Focus mode off");
+        }
+    }
+
+    pub fn toggle_review_mode(&mut self) {
+        self.review_mode = !self.review_mode;
+        if self.review_mode {
+            self.show_toast("Review mode: ' marks the selected file viewed");
+        } else {
+            self.show_toast("Review mode off");
+        }
+    }
+
+    /// Mark the selected file viewed, moving on to the next one, or unmark
+    /// it. Starts review mode so the mark shows.
+    pub fn toggle_file_viewed(&mut self) {
+        let tree_pos = self.get_selected_tree_position();
+        let (Some(commit), Some(file_diff)) = (
+            self.jj_log.get_tree_commit(&tree_pos),
+            tree_pos
+                .get(..=FILE_DIFF_IDX)
+                .and_then(|file_pos| self.jj_log.get_tree_file_diff(&file_pos.to_vec())),
+        ) else {
+            self.info_list = Some(Text::from("Select a file to mark it viewed"));
+            return;
+        };
+        let change_id = commit.change_id.clone();
+        let path = file_diff.path.clone();
+
+        let viewed = self.viewed_files.entry(change_id.clone()).or_default();
+        let now_viewed = viewed.insert(path.clone());
+        if !now_viewed {
+            viewed.remove(&path);
+            if viewed.is_empty() {
+                self.viewed_files.remove(&change_id);
             }
         }
+        if let Err(err) = crate::state::save_viewed_files(&self.viewed_files) {
+            self.display_error_lines(&err);
+        }
+        self.review_mode = true;
+        if now_viewed {
+            self.select_next_file();
+        }
+    }
+
+    /// How many of a commit's files are marked viewed
+    pub fn viewed_file_count(&self, change_id: &str) -> usize {
+        self.viewed_files.get(change_id).map_or(0, BTreeSet::len)
+    }
+
+    pub fn is_file_viewed(&self, change_id: &str, path: &str) -> bool {
+        self.viewed_files
+            .get(change_id)
+            .is_some_and(|paths| paths.contains(path))
+    }
+
+    /// Load which of the loaded commits are in the selection's lineage, if
+    /// it's highlighted
+    fn load_lineage(&mut self) -> Result<()> {
+        if self.lineage.is_none() {
+            return Ok(());
+        }
+        let selected = self
+            .get_selected_commit()
+            .map(|commit| commit.commit_id.clone());
+        let mut commit_ids = HashSet::new();
+        if let Some(selected) = &selected {
+            let loaded: Vec<&str> = self
+                .jj_log
+                .log_tree
+                .iter()
+                .filter_map(|cot| match cot {
+                    CommitOrText::Commit(commit) => Some(commit.commit_id.as_str()),
+                    CommitOrText::InfoText(_) => None,
+                })
+                .collect();
+            let output =
+                JjCommand::lineage(selected, &loaded.join(" | "), self.global_args.clone())
+                    .run()?;
+            commit_ids = strip_ansi(&output)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect();
+        }
+        self.lineage = Some(Lineage {
+            selected,
+            commit_ids,
+        });
+        Ok(())
+    }
+
+    /// Load the lineage again if the selection moved to another commit
+    pub fn sync_lineage(&mut self) -> Result<()> {
+        let Some(lineage) = &self.lineage else {
+            return Ok(());
+        };
+        let selected = self.get_selected_commit().map(|commit| &commit.commit_id);
+        if lineage.selected.as_ref() != selected {
+            self.load_lineage()?;
+        }
         Ok(())
     }
 
+    /// Evaluate the configured badges for the loaded commits. A template jj
+    /// rejects is reported without stopping the log from loading.
+    fn load_badges(&mut self) {
+        self.badges.clear();
+        if self.badge_templates.is_empty() {
+            return;
+        }
+        let commit_ids: Vec<String> = self
+            .jj_log
+            .log_tree
+            .iter()
+            .filter_map(|cot| match cot {
+                CommitOrText::Commit(commit) => Some(commit.commit_id.clone()),
+                CommitOrText::InfoText(_) => None,
+            })
+            .collect();
+        if commit_ids.is_empty() {
+            return;
+        }
+        // Keyed by the short commit id the log shows
+        let template = self.badge_templates.iter().fold(
+            "commit_id.shortest(8)".to_string(),
+            |template, (text_template, _)| format!(r#"{template} ++ "\t" ++ {text_template}"#),
+        ) + r#" ++ "\n""#;
+        let output = match JjCommand::log_with_template(
+            &commit_ids.join(" | "),
+            &template,
+            self.global_args.clone(),
+        )
+        .run()
+        {
+            Ok(output) => output,
+            Err(err) => {
+                self.display_error_lines(&err.into());
+                return;
+            }
+        };
+        let loaded: HashSet<&str> = commit_ids.iter().map(String::as_str).collect();
+        for line in strip_ansi(&output).lines() {
+            let mut fields = line.split('\t');
+            let Some(commit_id) = fields.next().filter(|id| loaded.contains(id)) else {
+                continue;
+            };
+            let badges: Vec<(String, Color)> = fields
+                .zip(&self.badge_templates)
+                .filter(|(text, _)| !text.trim().is_empty())
+                .map(|(text, (_, color))| (text.trim().to_string(), *color))
+                .collect();
+            if !badges.is_empty() {
+                self.badges.insert(commit_id.to_string(), badges);
+            }
+        }
+    }
+
+    /// Whether a commit shown in the log is outside the highlighted lineage
+    pub fn is_outside_lineage(&self, short_id: &str) -> bool {
+        self.lineage
+            .as_ref()
+            .is_some_and(|lineage| !lineage.commit_ids.contains(short_id))
+    }
+
     pub fn select_current_working_copy(&mut self) {
         if let Some(commit) = self.jj_log.get_current_commit() {
             self.log_select(commit.flat_log_idx);
         }
     }
 
+    /// Select the commit a jump goes to, loading more of the log until it's
+    /// there
+    pub fn jump_to(&mut self, target: JumpTarget) -> Result<()> {
+        let (revset, missing) = match target {
+            JumpTarget::Trunk => ("trunk()".to_string(), "trunk() is not in the log"),
+            JumpTarget::Root => ("root()".to_string(), "root() is not in the log"),
+            JumpTarget::BookmarkedAncestor => {
+                let Some(change_id) = self.get_selected_change_id() else {
+                    return self.invalid_selection();
+                };
+                (
+                    format!("heads(::{change_id}- & bookmarks())"),
+                    "No ancestor of the selection in the log has a bookmark",
+                )
+            }
+        };
+        let output = JjCommand::log_with_template(
+            &format!("({revset}) & ({})", self.revset),
+            r#"change_id ++ "\n""#,
+            self.global_args.clone(),
+        )
+        .run()?;
+        let Some(change_id) = strip_ansi(&output)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(String::from)
+        else {
+            self.info_list = Some(Text::from(missing));
+            return Ok(());
+        };
+        if self.find_commit_by_change_id(&change_id).is_none() {
+            while self.find_commit_by_change_id(&change_id).is_none() && self.jj_log.load_more()? {}
+            self.sync_log_list()?;
+            self.load_lineage()?;
+            self.load_badges();
+        }
+        self.select_change_id(&change_id);
+        Ok(())
+    }
+
     pub fn select_parent_node(&mut self) -> Result<()> {
         let tree_pos = self.get_selected_tree_position();
         if let Some(parent_pos) = get_parent_tree_position(&tree_pos) {
@@ -327,76 +927,231 @@ impl Model {
         Ok(())
     }
 
-    pub fn select_current_next_sibling_node(&mut self) -> Result<()> {
-        let tree_pos = self.get_selected_tree_position();
-        self.select_next_sibling_node(tree_pos)
+    /// Select the next node at the selection's depth, or the next node out
+    /// of its parent's once it's the last child. Diff lines move between
+    /// hunks.
+    pub fn select_current_next_sibling_node(&mut self) {
+        let selected = self.log_selected();
+        let depth = self.sibling_depth(selected);
+        if let Some(idx) = (selected + 1..self.log_list_tree_positions.len())
+            .find(|&idx| self.log_list_tree_positions[idx].len() <= depth)
+        {
+            self.log_select(idx);
+        }
     }
 
-    fn select_next_sibling_node(&mut self, tree_pos: TreePosition) -> Result<()> {
-        let mut tree_pos = tree_pos;
-        if tree_pos.len() == DIFF_HUNK_LINE_IDX + 1 {
-            tree_pos = get_parent_tree_position(&tree_pos).unwrap();
+    /// Select the previous node at the selection's depth, or its parent when
+    /// it's the first child
+    pub fn select_current_prev_sibling_node(&mut self) {
+        let selected = self.log_selected();
+        let depth = self.sibling_depth(selected);
+        if let Some(idx) = (0..selected)
+            .rev()
+            .find(|&idx| self.log_list_tree_positions[idx].len() <= depth)
+        {
+            self.log_select(idx);
         }
-        let idx = tree_pos[tree_pos.len() - 1];
+    }
 
-        match get_parent_tree_position(&tree_pos) {
-            Some(parent_pos) => {
-                let parent_node = self.jj_log.get_tree_node(&parent_pos)?;
-                let children = parent_node.children();
+    /// The depth sibling navigation moves at from a log list entry: a diff
+    /// line's hunk, otherwise the entry's own
+    fn sibling_depth(&self, idx: usize) -> usize {
+        self.log_list_tree_positions
+            .get(idx)
+            .map_or(1, |tree_pos| tree_pos.len().min(DIFF_HUNK_LINE_IDX))
+    }
 
-                if idx == children.len() - 1 {
-                    self.select_next_sibling_node(parent_pos)?;
-                } else {
-                    let sibling_idx = (idx + 1).min(children.len() - 1);
-                    self.log_list_state
-                        .select(Some(children[sibling_idx].flat_log_idx()));
-                }
+    /// Log list indices of the headers to pin above the log: the commit
+    /// whose unfolded nodes the log's top is scrolled into, and their file if
+    /// it's a diff and file headers are pinned too
+    pub fn sticky_header_indices(&self) -> Vec<usize> {
+        let offset = self.log_offset();
+        let Some(top) = self.log_list_tree_positions.get(offset) else {
+            return Vec::new();
+        };
+        let mut headers = Vec::new();
+        if top.len() > COMMIT_OR_TEXT_IDX + 1 {
+            headers.push(top[..=COMMIT_OR_TEXT_IDX].to_vec());
+        }
+        if self.sticky_file_header
+            && top.len() > FILE_DIFF_IDX + 1
+            && self
+                .jj_log
+                .get_tree_file_diff(&top[..=FILE_DIFF_IDX].to_vec())
+                .is_some()
+        {
+            headers.push(top[..=FILE_DIFF_IDX].to_vec());
+        }
+        // Headers come before everything unfolded under them
+        headers
+            .iter()
+            .filter_map(|header| {
+                (0..offset)
+                    .rev()
+                    .find(|&idx| self.log_list_tree_positions[idx] == *header)
+            })
+            .collect()
+    }
+
+    /// Whether a log list entry is a commit's line, rather than text between
+    /// commits or anything unfolded under one
+    fn is_commit_entry(&self, idx: usize) -> bool {
+        let tree_pos = &self.log_list_tree_positions[idx];
+        tree_pos.len() == COMMIT_OR_TEXT_IDX + 1 && self.jj_log.get_tree_commit(tree_pos).is_some()
+    }
+
+    /// Select the next commit, however deep in unfolded diffs the selection
+    /// is, loading more of the log at its end
+    pub fn select_next_commit(&mut self) -> Result<()> {
+        loop {
+            let selected = self.log_selected();
+            if let Some(idx) = (selected + 1..self.log_list_tree_positions.len())
+                .find(|&idx| self.is_commit_entry(idx))
+            {
+                self.log_select(idx);
+                return Ok(());
             }
-            None => {
-                let sibling_idx = (idx + 1).min(self.jj_log.log_tree.len() - 1);
-                self.log_list_state
-                    .select(Some(self.jj_log.log_tree[sibling_idx].flat_log_idx()));
+            if !self.load_more_log()? {
+                return Ok(());
             }
+        }
+    }
+
+    /// Select the next file unfolded under the selected commit
+    pub fn select_next_file(&mut self) {
+        self.select_in_commit(FILE_DIFF_IDX, true);
+    }
+
+    pub fn select_prev_file(&mut self) {
+        self.select_in_commit(FILE_DIFF_IDX, false);
+    }
+
+    /// Select the next hunk shown under the selected commit, from one
+    /// unfolded file to the next
+    pub fn select_next_hunk(&mut self) {
+        self.select_in_commit(DIFF_HUNK_IDX, true);
+    }
+
+    pub fn select_prev_hunk(&mut self) {
+        self.select_in_commit(DIFF_HUNK_IDX, false);
+    }
+
+    /// Select the next or previous node at a depth of the diffs shown under
+    /// the selected commit, staying at the selection if there's none
+    fn select_in_commit(&mut self, depth: usize, forward: bool) {
+        let selected = self.log_selected();
+        let commit_idx = self.log_list_tree_positions[selected][COMMIT_OR_TEXT_IDX];
+        let positions = &self.log_list_tree_positions;
+        let in_commit = |idx: &usize| positions[*idx][COMMIT_OR_TEXT_IDX] == commit_idx;
+        let is_match = |idx: &usize| {
+            let tree_pos = &positions[*idx];
+            // Evolog entries and the diffstat are at the depth of files too
+            tree_pos.len() == depth + 1
+                && (depth != FILE_DIFF_IDX || self.jj_log.get_tree_file_diff(tree_pos).is_some())
+        };
+        let found = if forward {
+            (selected + 1..positions.len())
+                .take_while(in_commit)
+                .find(is_match)
+        } else {
+            (0..selected).rev().take_while(in_commit).find(is_match)
         };
+        if let Some(idx) = found {
+            self.log_select(idx);
+        }
+    }
+
+    /// Select the closest commit above the selection: the one it's unfolded
+    /// under, or the previous one from a commit
+    pub fn select_prev_commit(&mut self) {
+        let selected = self.log_selected();
+        if let Some(idx) = (0..selected).rev().find(|&idx| self.is_commit_entry(idx)) {
+            self.log_select(idx);
+        }
+    }
 
+    /// Show or hide the earlier versions of the selected commit under it
+    pub fn toggle_inline_evolog(&mut self) -> Result<()> {
+        let mut tree_pos = self.get_selected_tree_position();
+        tree_pos.truncate(COMMIT_OR_TEXT_IDX + 1);
+        if self.jj_log.get_tree_commit(&tree_pos).is_none() {
+            return self.invalid_selection();
+        }
+        let log_list_selected_idx = self.jj_log.toggle_evolog(&self.global_args, &tree_pos)?;
+        self.sync_log_list()?;
+        self.log_select(log_list_selected_idx);
         Ok(())
     }
 
-    pub fn select_current_prev_sibling_node(&mut self) -> Result<()> {
+    /// Load the selected file's diff in full after it was cut off for length
+    pub fn load_full_file_diff(&mut self) -> Result<()> {
         let tree_pos = self.get_selected_tree_position();
-        self.select_prev_sibling_node(tree_pos)
+        let Some(log_list_selected_idx) = self
+            .jj_log
+            .load_full_file_diff(&self.global_args, &tree_pos)?
+        else {
+            return self.invalid_selection();
+        };
+        self.sync_log_list()?;
+        self.log_select(log_list_selected_idx);
+        Ok(())
     }
 
-    fn select_prev_sibling_node(&mut self, tree_pos: TreePosition) -> Result<()> {
-        if tree_pos.len() == DIFF_HUNK_LINE_IDX + 1 {
-            let parent_pos = get_parent_tree_position(&tree_pos).unwrap();
-            let parent_node_idx = self.jj_log.get_tree_node(&parent_pos)?.flat_log_idx();
-            self.log_select(parent_node_idx);
-            return Ok(());
-        }
-        let idx = tree_pos[tree_pos.len() - 1];
-
-        match get_parent_tree_position(&tree_pos) {
-            Some(parent_pos) => {
-                let parent_node = self.jj_log.get_tree_node(&parent_pos)?;
-                let children = parent_node.children();
+    /// Restore the selected commit's contents to the selected earlier version
+    pub fn jj_evolog_restore(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let Some(entry) = self.jj_log.get_tree_evolog_entry(&tree_pos) else {
+            return self.invalid_selection();
+        };
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let cmd = JjCommand::restore(
+            &["--from", &entry.commit_id, "--into", change_id],
+            None,
+            self.global_args.clone(),
+        );
+        self.queue_jj_command(cmd)
+    }
 
-                if idx == 0 {
-                    let parent_node_idx = parent_node.flat_log_idx();
-                    self.log_select(parent_node_idx);
-                } else {
-                    let sibling_idx = idx - 1;
-                    self.log_list_state
-                        .select(Some(children[sibling_idx].flat_log_idx()));
-                }
-            }
-            None => {
-                let sibling_idx = idx.saturating_sub(1);
-                self.log_list_state
-                    .select(Some(self.jj_log.log_tree[sibling_idx].flat_log_idx()));
-            }
+    /// Pick which parent of the selected merge its diffs are against
+    pub fn diff_parent_start(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id().map(String::from) else {
+            return self.invalid_selection();
         };
+        let output =
+            JjCommand::log_summaries(&format!("parents({change_id})"), self.global_args.clone())
+                .run()?;
+        let parents: Vec<(String, String)> = strip_ansi(&output)
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(parent, description)| (parent.to_string(), description.to_string()))
+            .collect();
+        if parents.len() < 2 {
+            self.info_list = Some(Text::from(
+                "Only merges can be diffed against one of their parents",
+            ));
+            return Ok(());
+        }
+        let mut choices = vec!["All parents merged, as jj diffs it".to_string()];
+        let mut diff_parents = vec![None];
+        for (parent, description) in parents {
+            choices.push(format!("{parent}  {description}"));
+            diff_parents.push(Some(parent));
+        }
+        self.open_popup(crate::update::Popup::DiffParent {
+            choices,
+            parents: diff_parents,
+        })
+    }
 
+    fn set_diff_parent(&mut self, parent: Option<String>) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let log_list_selected_idx =
+            self.jj_log
+                .set_diff_parent(&self.global_args, &tree_pos, parent)?;
+        self.sync_log_list()?;
+        self.log_select(log_list_selected_idx);
         Ok(())
     }
 
@@ -410,20 +1165,42 @@ impl Model {
 
     pub fn clear(&mut self) {
         self.info_list = None;
-        self.saved_tree_position = None;
-        self.saved_change_id = None;
-        self.saved_file_path = None;
+        self.push_remote = None;
+        self.describe_after_queue = false;
+        self.push_after_queue = None;
+        self.queue_start = None;
+        self.commands_awaiting_confirmation.clear();
+        self.clear_saved_selection();
         self.command_keys.clear();
         self.queued_jj_commands.clear();
         self.accumulated_command_output.clear();
+        self.output_history.stop_paging();
     }
 
-    /// User cancelled an action (e.g., closed editor without entering input).
-    /// The command key sequence is automatically cleared by `handle_command_key`
-    /// when the action is triggered, so we don't need to clear it here.
-    fn cancelled(&mut self) -> Result<()> {
-        self.info_list = Some(Text::from("Cancelled"));
-        Ok(())
+    /// Page back to the output of an earlier command
+    pub fn show_older_output(&mut self) {
+        self.info_list = Some(
+            self.output_history
+                .older()
+                .unwrap_or_else(|| Text::from("No command output yet")),
+        );
+    }
+
+    /// Page forward to the output of a later command
+    pub fn show_newer_output(&mut self) {
+        self.info_list = Some(
+            self.output_history
+                .newer()
+                .unwrap_or_else(|| Text::from("No command output yet")),
+        );
+    }
+
+    fn clear_saved_selection(&mut self) {
+        self.saved_tree_position = None;
+        self.saved_change_id = None;
+        self.saved_fileset = None;
+        self.mouse_range = None;
+        self.rebase_destinations.clear();
     }
 
     /// The selected or saved change is invalid for this operation (e.g., no
@@ -467,32 +1244,1238 @@ impl Model {
                 self.revset = old_revset;
             }
             Ok(()) => {
-                self.info_list = Some(Text::from(format!("Revset set to '{}'", self.revset)));
+                // A revset typed in replaces the zoomed one, so there's nothing
+                // to zoom back out to
+                self.zoom_stack.clear();
+                self.show_toast(format!("Revset set to '{}'", self.revset));
             }
         }
         Ok(())
     }
 
-    pub fn show_help(&mut self) {
-        self.info_list = Some(self.command_tree.get_help());
+    /// Narrow the revset to the selection's descendants or ancestors, keeping
+    /// the current one to zoom back out to
+    pub fn zoom(&mut self, mode: ZoomMode) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id().map(String::from) else {
+            return self.invalid_selection();
+        };
+        let zoomed = match mode {
+            ZoomMode::Descendants => format!("{change_id}::"),
+            ZoomMode::Ancestors => format!("::{change_id}"),
+        };
+        let outer = std::mem::replace(&mut self.revset, zoomed);
+        if let Err(err) = self.sync() {
+            self.display_error_lines(&err);
+            self.revset = outer;
+            return Ok(());
+        }
+        self.zoom_stack.push(outer);
+        self.select_change_id(&change_id);
+        Ok(())
     }
 
-    pub fn handle_command_key(&mut self, key_code: KeyCode) -> Option<Message> {
-        self.command_keys.push(key_code);
+    /// Offer the authors of the revset's commits, most commits first
+    pub fn author_filter_start(&mut self) -> Result<()> {
+        let output = JjCommand::log_with_template(
+            self.author_filter_base(),
+            AUTHORS_TEMPLATE,
+            self.global_args.clone(),
+        )
+        .run()?;
+        let mut counts: IndexMap<String, usize> = IndexMap::new();
+        for author in strip_ansi(&output).lines().filter(|line| !line.is_empty()) {
+            *counts.entry(author.to_string()).or_default() += 1;
+        }
+        counts.sort_by(|_, a, _, b| b.cmp(a));
+        let authors: Vec<String> = counts.into_keys().collect();
+        if authors.is_empty() {
+            self.info_list = Some(Text::from("No commits to filter"));
+            return Ok(());
+        }
+        self.open_popup(crate::update::Popup::AuthorFilter { authors })
+    }
 
-        let node = match self.command_tree.get_node(&self.command_keys) {
-            None => {
-                self.command_keys.pop();
-                display_unbound_error_lines(&mut self.info_list, &key_code);
-                return None;
-            }
-            Some(node) => node,
+    /// Narrow the revset to commits by an author picked as `Name <email>`,
+    /// replacing any author filter already applied
+    fn filter_by_author(&mut self, author: &str) -> Result<()> {
+        let (name, email) = match author.rsplit_once(" <") {
+            Some((name, email)) => (name, email.trim_end_matches('>')),
+            None => (author, ""),
         };
-        if let Some(children) = &node.children {
-            self.info_list = Some(children.get_help());
+        let pattern = if email.is_empty() { name } else { email };
+        let base_revset = self.author_filter_base().to_string();
+        let revset = format!("author(exact:{pattern:?}) & ({base_revset})");
+        let old_revset = std::mem::replace(&mut self.revset, revset.clone());
+        if let Err(err) = self.sync() {
+            self.display_error_lines(&err);
+            self.revset = old_revset;
+            return Ok(());
         }
-        if let Some(message) = node.action {
-            if node.children.is_none() {
+        self.author_filter = Some(AuthorFilter {
+            author: name.to_string(),
+            base_revset,
+            revset,
+        });
+        self.show_toast(format!("Showing commits by {name}"));
+        Ok(())
+    }
+
+    pub fn clear_author_filter(&mut self) -> Result<()> {
+        let is_active = self.active_author_filter().is_some();
+        let Some(filter) = self.author_filter.take().filter(|_| is_active) else {
+            self.info_list = Some(Text::from("No author filter to clear"));
+            return Ok(());
+        };
+        let change_id = self.get_selected_change_id().map(String::from);
+        self.revset = filter.base_revset;
+        self.sync()?;
+        if let Some(change_id) = change_id {
+            self.select_change_id(&change_id);
+        }
+        self.show_toast(format!("Showing commits by everyone in '{}'", self.revset));
+        Ok(())
+    }
+
+    /// Open the selection's note for editing, empty if it has none
+    pub fn edit_note_start(&mut self) {
+        let Some(change_id) = self.get_selected_change_id().map(String::from) else {
+            self.info_list = Some(Text::from("Select a commit to write a note on"));
+            return;
+        };
+        self.text_input = self.notes.get(&change_id).cloned().unwrap_or_default();
+        self.text_cursor = self.text_input.len();
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Note on Selection (empty to delete)",
+            placeholder: "review feedback, TODOs…",
+            action: TextPromptAction::EditNote { change_id },
+        };
+    }
+
+    fn save_note(&mut self, change_id: String, note: &str) -> Result<()> {
+        let note = note.split_whitespace().collect::<Vec<_>>().join(" ");
+        let message = if note.is_empty() {
+            self.notes.remove(&change_id);
+            format!("Deleted the note on {change_id}")
+        } else {
+            self.notes.insert(change_id.clone(), note);
+            format!("Saved the note on {change_id}")
+        };
+        if let Err(err) = crate::state::save_notes(&self.notes) {
+            self.display_error_lines(&err);
+            return Ok(());
+        }
+        self.show_toast(message);
+        Ok(())
+    }
+
+    pub fn export_log_start(&mut self) -> Result<()> {
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Export Log To (.html for HTML, anything else for ANSI)",
+            placeholder: "path",
+            action: TextPromptAction::ExportLogPath,
+        };
+        Ok(())
+    }
+
+    /// Write the log as it's drawn, folds and all, to a file, asking first
+    /// if there's one there already
+    fn export_log(&mut self, path: &str) -> Result<()> {
+        let path = expand_home(path.trim());
+        if path.is_empty() {
+            return Ok(());
+        }
+        if std::path::Path::new(&path).exists() {
+            let popup = crate::update::Popup::ExportOverwrite {
+                choices: vec![format!("Overwrite {path}"), "Cancel".to_string()],
+                path,
+            };
+            return self.open_popup(popup);
+        }
+        self.write_log_export(&path)
+    }
+
+    fn write_log_export(&mut self, path: &str) -> Result<()> {
+        let items = crate::view::log_items(self);
+        let contents = if export::is_html_path(path) {
+            export::to_html(&items, &format!("jj log -r '{}'", self.revset))
+        } else {
+            export::to_ansi(&items)
+        };
+        if let Err(err) = std::fs::write(path, contents) {
+            self.info_list = Some(Text::from(format!("Cannot write {path}: {err}")));
+            return Ok(());
+        }
+        self.show_toast(format!("Exported the log to {path}"));
+        Ok(())
+    }
+
+    pub fn save_revset_start(&mut self) -> Result<()> {
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Save Revset As",
+            placeholder: "name",
+            action: TextPromptAction::SaveRevsetName,
+        };
+        Ok(())
+    }
+
+    fn save_revset_as(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            self.info_list = Some(Text::from("Saved revsets need a name"));
+            return Ok(());
+        }
+        if let Err(err) = crate::state::save_revset(name, &self.revset) {
+            self.display_error_lines(&err);
+            return Ok(());
+        }
+        self.show_toast(format!("Saved '{}' as {name}", self.revset));
+        Ok(())
+    }
+
+    /// Offer the saved revsets, most recently saved first
+    pub fn saved_revsets_start(&mut self) -> Result<()> {
+        let (presets, revsets): (Vec<String>, Vec<String>) = crate::state::load_saved_revsets()
+            .into_iter()
+            .map(|(name, revset)| (format!("{name}  {revset}"), revset))
+            .unzip();
+        if presets.is_empty() {
+            self.info_list = Some(Text::from("No saved revsets, save one with z s"));
+            return Ok(());
+        }
+        self.open_popup(crate::update::Popup::SavedRevsets { presets, revsets })
+    }
+
+    fn show_saved_revset(&mut self, revset: String) -> Result<()> {
+        let old_revset = std::mem::replace(&mut self.revset, revset);
+        if let Err(err) = self.sync() {
+            self.display_error_lines(&err);
+            self.revset = old_revset;
+            return Ok(());
+        }
+        // Like a revset typed in, there's nothing to zoom back out to
+        self.zoom_stack.clear();
+        self.show_toast(format!("Revset set to '{}'", self.revset));
+        Ok(())
+    }
+
+    /// Author whose commits the log is narrowed to, unless the revset has
+    /// changed since
+    pub fn active_author_filter(&self) -> Option<&str> {
+        self.author_filter
+            .as_ref()
+            .filter(|filter| filter.revset == self.revset)
+            .map(|filter| filter.author.as_str())
+    }
+
+    /// The revset without the active author filter
+    fn author_filter_base(&self) -> &str {
+        match &self.author_filter {
+            Some(filter) if filter.revset == self.revset => &filter.base_revset,
+            _ => &self.revset,
+        }
+    }
+
+    pub fn zoom_out(&mut self) -> Result<()> {
+        let Some(outer) = self.zoom_stack.pop() else {
+            self.info_list = Some(Text::from("Not zoomed in"));
+            return Ok(());
+        };
+        // Keep the selected commit selected in the wider log
+        let change_id = self.get_selected_change_id().map(String::from);
+        let zoomed = std::mem::replace(&mut self.revset, outer);
+        if let Err(err) = self.sync() {
+            self.display_error_lines(&err);
+            let outer = std::mem::replace(&mut self.revset, zoomed);
+            self.zoom_stack.push(outer);
+            return Ok(());
+        }
+        if let Some(change_id) = change_id {
+            self.select_change_id(&change_id);
+        }
+        Ok(())
+    }
+
+    pub fn show_help(&mut self) -> Result<()> {
+        let commands = self
+            .command_tree
+            .get_help_commands(self.keymap.help_entries());
+        self.open_popup(crate::update::Popup::Help { commands })
+    }
+
+    pub fn open_action_menu(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let kind = match (
+            self.jj_log.get_tree_file_diff(&tree_pos),
+            tree_pos.len() - 1,
+        ) {
+            (Some(_), FILE_DIFF_IDX) => NodeKind::File,
+            (Some(_), _) => NodeKind::Hunk,
+            (None, COMMIT_OR_TEXT_IDX) if self.get_selected_commit().is_some() => NodeKind::Commit,
+            _ => return self.invalid_selection(),
+        };
+        let bookmarks = self
+            .get_selected_commit()
+            .map(|commit| commit.bookmarks.clone())
+            .unwrap_or_default();
+        let (actions, messages) = self
+            .command_tree
+            .action_menu(kind, &bookmarks)
+            .into_iter()
+            .unzip();
+        self.open_popup(crate::update::Popup::ActionMenu { actions, messages })
+    }
+
+    /// The message of the action picked from the actions menu, closing it, or
+    /// `None` if the actions menu isn't open
+    pub fn take_action_menu_selection(&mut self) -> Option<Message> {
+        let Some(crate::update::Popup::ActionMenu { actions, messages }) = &self.current_popup
+        else {
+            return None;
+        };
+        let selected = self.get_popup_selection();
+        let message = actions
+            .iter()
+            .position(|action| Some(action) == selected.as_ref())
+            .map(|idx| messages[idx]);
+        self.popup_cancel();
+        message
+    }
+
+    pub fn toggle_log_viewer(&mut self) {
+        self.log_viewer_scroll = match self.log_viewer_scroll {
+            Some(_) => None,
+            None => Some(0),
+        };
+    }
+
+    /// Scroll the log viewer; the view clamps it to the length of the log
+    pub fn log_viewer_scroll(&mut self, lines: i32) {
+        if let Some(scroll) = &mut self.log_viewer_scroll {
+            *scroll = scroll.saturating_add_signed(lines as isize);
+        }
+    }
+
+    /// Prompt for a regex to search the files of the selected revision for
+    pub fn grep_start(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let change_id = change_id.to_string();
+        let is_working_copy = self.is_selected_working_copy();
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Search Files in Revision (regex)",
+            placeholder: "pattern",
+            action: TextPromptAction::Grep {
+                change_id,
+                is_working_copy,
+            },
+        };
+        Ok(())
+    }
+
+    pub fn shell_command_start(&mut self) -> Result<()> {
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Shell Command ({change_id} {commit_id} {file} {repo_root})",
+            placeholder: "command",
+            action: TextPromptAction::ShellCommand,
+        };
+        Ok(())
+    }
+
+    pub fn run_external_command(&mut self, idx: usize) -> Result<()> {
+        let Some(command) = self.external_commands.get(idx) else {
+            return Ok(());
+        };
+        let command = command.command.clone();
+        self.run_shell_command(&command)
+    }
+
+    pub fn run_script_binding(&mut self, idx: usize) -> Result<()> {
+        let selection = self.script_selection();
+        let Some(scripts) = &mut self.scripts else {
+            return Ok(());
+        };
+        let output = scripts.run_binding(idx, selection);
+        let mut lines = Vec::new();
+        let cmds = self.apply_script_output(output, &mut lines)?;
+        if !cmds.is_empty() {
+            self.queue_jj_commands(cmds)?;
+            self.accumulated_command_output = lines;
+            self.update_info_list_for_queue();
+        } else if !lines.is_empty() {
+            self.info_list = Some(Text::from(lines));
+        }
+        Ok(())
+    }
+
+    /// The selected log entry as scripts see it
+    fn script_selection(&self) -> Option<ScriptSelection> {
+        let tree_pos = self.get_selected_tree_position();
+        let commit = self.jj_log.get_tree_commit(&tree_pos)?;
+        Some(ScriptSelection {
+            change_id: commit.change_id.clone(),
+            commit_id: commit.commit_id.clone(),
+            bookmarks: commit.bookmarks.clone(),
+            subject: commit.description_first_line.clone().unwrap_or_default(),
+            file: self.get_file_path(tree_pos).map(String::from),
+        })
+    }
+
+    /// Run the `before_`/`after_` script hook for a command taken off the
+    /// queue, adding what it printed to `lines`. Commands the hook queues run
+    /// next. Returns why the command shouldn't run, if a before hook blocked
+    /// it or failed.
+    fn run_script_hook(
+        &mut self,
+        phase: &str,
+        cmd: &JjCommand,
+        lines: &mut Vec<Line<'static>>,
+    ) -> Result<Option<String>> {
+        if !cmd.run_hooks() {
+            return Ok(None);
+        }
+        let selection = self.script_selection();
+        let Some(scripts) = &mut self.scripts else {
+            return Ok(None);
+        };
+        let mut output = scripts.run_hook(phase, cmd.args(), selection);
+        let blocked = output.blocked.take();
+        let failed = phase == "before" && output.error.is_some();
+        let cmds = self.apply_script_output(output, lines)?;
+        self.queued_jj_commands.splice(0..0, cmds);
+        Ok(blocked.or_else(|| failed.then(|| "Not run, since its hook failed".to_string())))
+    }
+
+    /// Show toasts and add the lines a script printed, returning the commands
+    /// it queued
+    fn apply_script_output(
+        &mut self,
+        output: ScriptOutput,
+        lines: &mut Vec<Line<'static>>,
+    ) -> Result<Vec<JjCommand>> {
+        for text in &output.info {
+            lines.extend(text.into_text()?.lines);
+        }
+        if let Some(err) = output.error {
+            lines.push(Line::styled(err, Style::default().fg(Color::Red)));
+        }
+        for message in output.toasts {
+            self.show_toast(message);
+        }
+        Ok(output
+            .queued
+            .into_iter()
+            .map(|args| JjCommand::from_script(args, false, self.global_args.clone()))
+            .collect())
+    }
+
+    /// Run a shell command from the workspace root with the selection filled
+    /// into its placeholders, and show what it printed
+    fn run_shell_command(&mut self, command: &str) -> Result<()> {
+        if command.trim().is_empty() {
+            return Ok(());
+        }
+        let repo_root = strip_ansi(&JjCommand::workspace_root(self.global_args.clone()).run()?)
+            .trim()
+            .to_string();
+        let tree_pos = self.get_selected_tree_position();
+        let commit = self.jj_log.get_tree_commit(&tree_pos);
+        let values = [
+            ("change_id", commit.map(|commit| commit.change_id.as_str())),
+            ("commit_id", commit.map(|commit| commit.commit_id.as_str())),
+            ("file", self.get_file_path(tree_pos.clone())),
+            ("repo_root", Some(repo_root.as_str())),
+        ];
+        let command_line = match fill_placeholders(command, &values) {
+            Ok(command_line) => command_line,
+            Err(name) => {
+                self.info_list =
+                    Some(format!("Nothing selected to fill in {{{name}}}").into_text()?);
+                return Ok(());
+            }
+        };
+
+        let (status, output) = match crate::shell_out::run_shell(&repo_root, &command_line) {
+            Ok(result) => result,
+            Err(err) => {
+                self.display_error_lines(&err);
+                return Ok(());
+            }
+        };
+        let mut text = Text::from(Line::styled(
+            format!("$ {command_line}"),
+            Style::default().fg(Color::DarkGray),
+        ));
+        text.extend(output.into_text()?);
+        if !status.success() {
+            text.push_line(Line::styled(
+                format!("Exited with {status}"),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        self.output_history.push(text.lines.clone());
+        self.info_list = Some(text);
+
+        // The command may have changed the repository
+        self.sync()
+    }
+
+    pub fn record_macro(&mut self) {
+        match self.macros.stop_recording() {
+            Some(register) => self.show_toast(format!("Recorded macro into {register}")),
+            None => self.macros.prompt_register(MacroAction::Record),
+        }
+    }
+
+    pub fn play_macro(&mut self) {
+        self.macros.prompt_register(MacroAction::Play);
+    }
+
+    pub fn macro_register(&mut self, key_code: KeyCode) {
+        if let Some(error) = self.macros.register_key(key_code) {
+            self.info_list = Some(Text::from(error));
+        }
+    }
+
+    /// Whether jj commands are queued to run on the next updates
+    pub fn has_queued_jj_commands(&self) -> bool {
+        !self.queued_jj_commands.is_empty()
+    }
+
+    pub fn is_playing_macro(&self) -> bool {
+        self.macros.is_playing()
+    }
+
+    /// Stop a playing macro, as when interrupted
+    pub fn stop_macro(&mut self) {
+        if self.macros.is_playing() {
+            self.macros.stop_playback();
+            self.show_toast("Stopped the macro");
+        }
+    }
+
+    /// The next key of a playing macro, once the commands queued by the
+    /// previous keys have run
+    pub fn next_macro_key(&mut self) -> Option<crossterm::event::KeyEvent> {
+        if self.has_queued_jj_commands() {
+            return None;
+        }
+        self.macros.next_playback_key()
+    }
+
+    pub fn show_stats(&mut self) -> Result<()> {
+        let log_output = JjCommand::log_with_template(
+            &self.revset,
+            crate::stats::LOG_TEMPLATE,
+            self.global_args.clone(),
+        )
+        .run()?;
+        let stat_output =
+            JjCommand::log_diff_stats(&self.revset, self.global_args.clone()).run()?;
+        let stats = crate::stats::Stats::parse(&strip_ansi(&log_output), &strip_ansi(&stat_output));
+        self.info_list = Some(stats.render(&self.revset));
+        Ok(())
+    }
+
+    /// Show the selected commit's description with its body, which the log
+    /// cuts to the first line
+    pub fn show_description(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id().map(String::from) else {
+            return self.invalid_selection();
+        };
+        let description =
+            strip_ansi(&JjCommand::get_description(&change_id, self.global_args.clone()).run()?);
+        let mut text = Text::from(Line::styled(
+            format!("Description of {change_id}"),
+            Style::default().fg(Color::Cyan),
+        ));
+        if description.trim().is_empty() {
+            text.push_line(Line::styled(
+                "(no description set)",
+                Style::default().fg(Color::DarkGray),
+            ));
+        } else {
+            text.extend(
+                description
+                    .trim_end()
+                    .lines()
+                    .map(|line| Line::raw(line.to_string())),
+            );
+        }
+        self.info_list = Some(text);
+        Ok(())
+    }
+
+    /// Show the commits only on the selection's side or only on trunk's side of
+    /// their fork point, and what the selection's side changes overall
+    pub fn compare_with_trunk(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id().map(String::from) else {
+            return self.invalid_selection();
+        };
+        let summaries = |revset: &str| -> Result<Vec<String>> {
+            Ok(JjCommand::log_summaries(revset, self.global_args.clone())
+                .run()?
+                .lines()
+                .map(strip_ansi)
+                .filter(|line| !line.trim().is_empty())
+                .collect())
+        };
+        let ahead = summaries(&format!("trunk()..({change_id})"))?;
+        let behind = summaries(&format!("({change_id})..trunk()"))?;
+        // Criss-crossing merges can leave several latest common ancestors
+        let fork_points: Vec<String> = JjCommand::log_with_template(
+            &format!("heads(::trunk() & ::({change_id}))"),
+            r#"commit_id.shortest(8) ++ "\n""#,
+            self.global_args.clone(),
+        )
+        .run()?
+        .lines()
+        .map(|line| strip_ansi(line).trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+        let diff_stat = match fork_points.as_slice() {
+            [fork_point] => {
+                JjCommand::diff_stat_from_to(fork_point, &change_id, self.global_args.clone())
+                    .run()?
+            }
+            _ => String::new(),
+        };
+
+        let heading = |text: String| Line::styled(text, Style::default().fg(Color::Cyan));
+        let commit_lines = |summaries: &[String]| -> Vec<Line<'static>> {
+            summaries
+                .iter()
+                .map(|summary| {
+                    let (id, description) = summary.split_once('\t').unwrap_or((summary, ""));
+                    let description = if description.is_empty() {
+                        "(no description set)"
+                    } else {
+                        description
+                    };
+                    Line::from(vec![
+                        Span::raw("  "),
+                        Span::styled(id.to_string(), Style::default().fg(Color::Magenta)),
+                        Span::raw(format!(" {description}")),
+                    ])
+                })
+                .collect()
+        };
+
+        let mut text = Text::from(heading(format!(
+            "{} ahead of trunk(), {} behind",
+            ahead.len(),
+            behind.len()
+        )));
+        if !ahead.is_empty() {
+            text.push_line(Line::raw(""));
+            text.push_line(heading("Only on the selection's side:".to_string()));
+            text.extend(commit_lines(&ahead));
+        }
+        if !behind.is_empty() {
+            text.push_line(Line::raw(""));
+            text.push_line(heading("Only on trunk():".to_string()));
+            text.extend(commit_lines(&behind));
+        }
+        if !diff_stat.trim().is_empty() {
+            text.push_line(Line::raw(""));
+            text.push_line(heading("Changes since the fork point:".to_string()));
+            text.extend(diff_stat.into_text()?);
+        }
+        if fork_points.len() > 1 {
+            text.push_line(Line::raw(""));
+            text.push_line(Line::styled(
+                format!(
+                    "No single fork point to show changes since: {} are all latest common ancestors",
+                    fork_points.join(", ")
+                ),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        self.info_list = Some(text);
+        Ok(())
+    }
+
+    pub fn trailer_add_start(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id().map(String::from) else {
+            return self.invalid_selection();
+        };
+        let config_value = |name: &str| {
+            JjCommand::config_get(name, self.global_args.clone())
+                .run()
+                .map(|value| strip_ansi(&value).trim().to_string())
+                .unwrap_or_default()
+        };
+        let (name, email) = (config_value("user.name"), config_value("user.email"));
+        let existing = trailers::trailers(&self.full_description(&change_id)?);
+        let trailers: Vec<String> = if self.trailer_templates.is_empty() {
+            trailers::DEFAULT_TRAILERS
+                .iter()
+                .map(|template| trailers::fill_template(template, &name, &email))
+                .collect()
+        } else {
+            self.trailer_templates
+                .iter()
+                .map(|template| trailers::fill_template(template, &name, &email))
+                .collect()
+        };
+        let trailers = trailers
+            .into_iter()
+            .filter(|trailer| !existing.contains(trailer))
+            .collect();
+        self.open_popup(crate::update::Popup::TrailerAdd {
+            change_id,
+            trailers,
+        })
+    }
+
+    pub fn trailer_remove_start(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id().map(String::from) else {
+            return self.invalid_selection();
+        };
+        let trailers = trailers::trailers(&self.full_description(&change_id)?);
+        if trailers.is_empty() {
+            self.info_list = Some(Text::from("The description has no trailers"));
+            return Ok(());
+        }
+        self.open_popup(crate::update::Popup::TrailerRemove {
+            change_id,
+            trailers,
+        })
+    }
+
+    fn full_description(&self, change_id: &str) -> Result<String> {
+        let description = JjCommand::get_description(change_id, self.global_args.clone()).run()?;
+        Ok(strip_ansi(&description))
+    }
+
+    /// Rewrite a description without opening an editor
+    fn describe_with(
+        &mut self,
+        change_id: &str,
+        rewrite: impl FnOnce(&str) -> String,
+    ) -> Result<()> {
+        let description = rewrite(&self.full_description(change_id)?);
+        let cmd = JjCommand::describe_with_message(
+            change_id,
+            &description,
+            false,
+            self.global_args.clone(),
+        );
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn ci_refresh(&mut self) -> Result<()> {
+        let Some(provider) = &self.ci_provider else {
+            self.info_list = Some(Text::from(
+                "No CI provider configured; set [ci] command in the config file",
+            ));
+            return Ok(());
+        };
+        let repo_root = strip_ansi(&JjCommand::workspace_root(self.global_args.clone()).run()?)
+            .trim()
+            .to_string();
+        let commits: Vec<&Commit> = self
+            .jj_log
+            .log_tree
+            .iter()
+            .filter_map(|commit_or_text| match commit_or_text {
+                CommitOrText::Commit(commit) => Some(commit),
+                CommitOrText::InfoText(_) => None,
+            })
+            .collect();
+        if commits.is_empty() {
+            return Ok(());
+        }
+        // The log only shows a prefix of each commit id
+        let revset = commits
+            .iter()
+            .map(|commit| commit.commit_id.as_str())
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let full_ids = strip_ansi(
+            &JjCommand::log_with_template(
+                &revset,
+                r#"commit_id.shortest(8) ++ "\t" ++ commit_id ++ "\n""#,
+                self.global_args.clone(),
+            )
+            .run()?,
+        );
+        let full_ids: HashMap<&str, &str> = full_ids
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .collect();
+        let targets: Vec<CiTarget> = commits
+            .iter()
+            .filter_map(|commit| {
+                Some(CiTarget {
+                    commit_id: commit.commit_id.clone(),
+                    full_commit_id: full_ids.get(commit.commit_id.as_str())?.to_string(),
+                    change_id: commit.change_id.clone(),
+                    bookmark: commit.bookmarks.first().cloned(),
+                })
+            })
+            .collect();
+        self.ci_fetch = Some(ci::fetch_statuses(provider, &repo_root, &targets));
+        Ok(())
+    }
+
+    /// Show the CI statuses checked in the background so far, or wait for all
+    /// of them if `wait`
+    pub fn receive_ci_statuses(&mut self, wait: bool) {
+        let Some(fetch) = &self.ci_fetch else {
+            return;
+        };
+        let (statuses, done) = fetch.receive(wait);
+        if !statuses.is_empty() {
+            self.ci_statuses.extend(statuses);
+            self.dirty = true;
+        }
+        if done {
+            self.ci_fetch = None;
+        }
+    }
+
+    pub fn ci_details(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let Some(commit) = self.jj_log.get_tree_commit(&tree_pos) else {
+            return self.invalid_selection();
+        };
+        self.info_list = Some(match self.ci_statuses.get(&commit.commit_id) {
+            Some(status) => {
+                let (glyph, style) = status.state.glyph();
+                let mut text = Text::from(Line::from(vec![
+                    Span::styled(glyph, style),
+                    Span::raw(format!(" CI status of {}", commit.commit_id)),
+                ]));
+                text.extend(status.output.into_text()?);
+                text
+            }
+            None => Text::from("No CI status for this commit; press C C to check"),
+        });
+        Ok(())
+    }
+
+    /// Prompt for a command to run on each revision from the saved selection
+    /// to the selected one
+    pub fn run_on_revisions_start(&mut self) -> Result<()> {
+        let (Some(from_change_id), Some(to_change_id)) =
+            (self.get_saved_change_id(), self.get_selected_change_id())
+        else {
+            return self.invalid_selection();
+        };
+        let revset = format!("{from_change_id}::{to_change_id}");
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Command to Run on Each Revision",
+            placeholder: "command",
+            action: TextPromptAction::RunOnRevisions { revset },
+        };
+        Ok(())
+    }
+
+    /// Run a shell command on each revision of a revset, oldest first, and
+    /// mark the revisions with whether it passed.
+    ///
+    /// `jj run` is still a stub, so the revisions are checked out one after
+    /// another in a scratch workspace instead, leaving the user's working
+    /// copy alone and letting build tools reuse their output between runs.
+    fn run_on_revisions(&mut self, revset: &str, command: &str) -> Result<()> {
+        if command.trim().is_empty() {
+            return Ok(());
+        }
+        let mut change_ids: Vec<String> = JjCommand::change_ids(revset, self.global_args.clone())
+            .run()?
+            .lines()
+            .map(|line| strip_ansi(line).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        change_ids.reverse();
+        if change_ids.is_empty() {
+            self.info_list = Some(Text::from("No revisions in range"));
+            return Ok(());
+        }
+
+        let results = match self.run_in_scratch_workspace(&change_ids, command) {
+            Ok(results) => results,
+            Err(err) => {
+                self.display_error_lines(&err);
+                return Ok(());
+            }
+        };
+
+        self.run_results.clear();
+        let mut text = Text::from(Line::styled(
+            format!("$ {command}"),
+            Style::default().fg(Color::DarkGray),
+        ));
+        let mut first_failure = None;
+        for (change_id, passed, output) in &results {
+            self.run_results.insert(change_id.clone(), *passed);
+            let (mark, color) = if *passed {
+                ("✓", Color::Green)
+            } else {
+                ("✗", Color::Red)
+            };
+            let description = self.loaded_description(change_id).unwrap_or_default();
+            text.push_line(Line::from(vec![
+                Span::styled(format!("{mark} "), Style::default().fg(color)),
+                Span::styled(
+                    change_id[..change_id.len().min(8)].to_string(),
+                    Style::default().fg(Color::Magenta),
+                ),
+                Span::raw(format!(" {description}")),
+            ]));
+            if !passed && first_failure.is_none() {
+                first_failure = Some((change_id, output));
+            }
+        }
+        if let Some((change_id, output)) = first_failure {
+            text.push_line(Line::raw(""));
+            text.push_line(Line::styled(
+                format!("Output for {}:", &change_id[..change_id.len().min(8)]),
+                Style::default().fg(Color::DarkGray),
+            ));
+            text.extend(output.into_text()?);
+        }
+        self.info_list = Some(text);
+        Ok(())
+    }
+
+    /// Check out each revision in a temporary workspace and run the command
+    /// there, returning each change id with whether it passed and its output
+    fn run_in_scratch_workspace(
+        &self,
+        change_ids: &[String],
+        command: &str,
+    ) -> Result<Vec<(String, bool, String)>> {
+        let scratch_dir = tempfile::tempdir()?;
+        let workspace_path = scratch_dir.path().join("workspace");
+        let workspace_path = workspace_path.to_string_lossy();
+        let workspace_name = format!("jjdag-run-{}", std::process::id());
+        JjCommand::workspace_add_at(
+            &workspace_path,
+            &workspace_name,
+            &change_ids[0],
+            self.global_args.clone(),
+        )
+        .run()?;
+        let scratch_args = GlobalArgs {
+            repository: workspace_path.to_string(),
+            ..self.global_args.clone()
+        };
+
+        let mut results = Vec::new();
+        let mut run = || -> Result<()> {
+            for (idx, change_id) in change_ids.iter().enumerate() {
+                if idx > 0 {
+                    JjCommand::new(change_id, &[], scratch_args.clone()).run()?;
+                }
+                let (status, output) = crate::shell_out::run_shell(&workspace_path, command)?;
+                results.push((change_id.clone(), status.success(), output));
+                // Drop anything the command wrote so the scratch commit stays
+                // empty and is abandoned when moving to the next revision
+                JjCommand::restore(&[], None, scratch_args.clone()).run()?;
+            }
+            Ok(())
+        };
+        let run_result = run();
+
+        let scratch_change_id = JjCommand::change_ids("@", scratch_args.clone()).run();
+        JjCommand::workspace_forget(&workspace_name, self.global_args.clone()).run()?;
+        if let Ok(scratch_change_id) = scratch_change_id {
+            JjCommand::abandon(
+                strip_ansi(&scratch_change_id).trim(),
+                None,
+                self.global_args.clone(),
+            )
+            .run()?;
+        }
+        run_result?;
+        Ok(results)
+    }
+
+    fn jj_grep(&mut self, change_id: String, is_working_copy: bool, pattern: String) -> Result<()> {
+        if pattern.is_empty() {
+            return Ok(());
+        }
+        let regex = match regex::Regex::new(&pattern) {
+            Ok(regex) => regex,
+            Err(err) => {
+                self.info_list = Some(format!("Invalid pattern: {err}").into_text()?);
+                return Ok(());
+            }
+        };
+
+        let mut files: Vec<String> = JjCommand::file_list(&change_id, self.global_args.clone())
+            .run()?
+            .lines()
+            .map(|line| strip_ansi(line).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let skipped = if is_working_copy {
+            0
+        } else {
+            let skipped = files.len().saturating_sub(GREP_MAX_JJ_FILES);
+            files.truncate(GREP_MAX_JJ_FILES);
+            skipped
+        };
+
+        let mut matches = Vec::new();
+        let mut locations = Vec::new();
+        'files: for file in files {
+            // The working copy is read from disk, other revisions through jj
+            let contents = if is_working_copy {
+                std::fs::read(std::path::Path::new(&self.global_args.repository).join(&file))
+                    .map_err(anyhow::Error::from)
+            } else {
+                crate::shell_out::file_contents(&self.global_args.repository, &change_id, &file)
+            };
+            let Ok(contents) = contents else {
+                continue;
+            };
+            if contents.contains(&0) {
+                // Binary file
+                continue;
+            }
+            for (idx, line) in String::from_utf8_lossy(&contents).lines().enumerate() {
+                if !regex.is_match(line) {
+                    continue;
+                }
+                matches.push(format!("{file}:{}: {}", idx + 1, line.trim()));
+                locations.push((file.clone(), idx as u32 + 1));
+                if matches.len() >= GREP_MAX_MATCHES {
+                    break 'files;
+                }
+            }
+        }
+
+        if matches.is_empty() {
+            let message = if skipped > 0 {
+                format!("No matches for {pattern} in the first {GREP_MAX_JJ_FILES} files")
+            } else {
+                format!("No matches for {pattern}")
+            };
+            self.info_list = Some(message.into_text()?);
+            return Ok(());
+        }
+        if skipped > 0 {
+            self.info_list = Some(
+                format!(
+                    "Searched the first {GREP_MAX_JJ_FILES} files, {skipped} more were left out"
+                )
+                .into_text()?,
+            );
+        }
+
+        let popup = crate::update::Popup::GrepResults {
+            change_id,
+            is_working_copy,
+            matches,
+            locations,
+        };
+        self.open_popup(popup)
+    }
+
+    /// Browse the files of the selected revision as a directory tree
+    pub fn file_browser_start(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let change_id = change_id.to_string();
+        let output = JjCommand::file_list(&change_id, self.global_args.clone()).run()?;
+        let files = output
+            .lines()
+            .map(|line| strip_ansi(line).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let browser = FileBrowser::new(change_id, self.is_selected_working_copy(), files);
+        if browser.is_empty() {
+            self.info_list = Some("No files in this revision".into_text()?);
+            return Ok(());
+        }
+        self.file_browser = Some(browser);
+        Ok(())
+    }
+
+    pub fn file_browser_close(&mut self) {
+        self.file_browser = None;
+    }
+
+    pub fn file_browser_move(&mut self, delta: i32) {
+        if let Some(browser) = &mut self.file_browser {
+            browser.move_selection(delta);
+        }
+    }
+
+    pub fn file_browser_expand(&mut self) {
+        if let Some(browser) = &mut self.file_browser {
+            browser.expand_selected_dir();
+        }
+    }
+
+    pub fn file_browser_collapse(&mut self) {
+        if let Some(browser) = &mut self.file_browser {
+            browser.collapse_selected();
+        }
+    }
+
+    /// Expand or collapse the selected directory, or open the selected file
+    pub fn file_browser_enter(&mut self, term: Term) -> Result<()> {
+        let Some(browser) = &mut self.file_browser else {
+            return Ok(());
+        };
+        let Some(row) = browser.selected_row() else {
+            return Ok(());
+        };
+        if row.is_dir {
+            browser.toggle_selected_dir();
+            return Ok(());
+        }
+        let change_id = browser.change_id.clone();
+        let is_working_copy = browser.is_working_copy;
+        self.open_file_in_editor(&change_id, &row.path, None, is_working_copy, term)
+    }
+
+    /// Restore the selected file in the working copy to its content in the
+    /// browsed revision
+    pub fn file_browser_restore(&mut self) -> Result<()> {
+        let Some(browser) = &self.file_browser else {
+            return Ok(());
+        };
+        let Some(row) = browser.selected_row().filter(|row| !row.is_dir) else {
+            return Ok(());
+        };
+        if browser.is_working_copy {
+            self.info_list = Some("The file is already in the working copy".into_text()?);
+            return Ok(());
+        }
+        let change_id = browser.change_id.clone();
+        self.file_browser = None;
+        let cmd = JjCommand::restore(
+            &["--from", &change_id, "--into", "@"],
+            Some(&row.path),
+            self.global_args.clone(),
+        );
+        self.queue_jj_command(cmd)
+    }
+
+    fn load_remote_bookmarks(&self) -> Result<Vec<RemoteBookmark>> {
+        let output = JjCommand::remote_bookmark_list(self.global_args.clone()).run()?;
+        Ok(RemoteBookmarkBrowser::parse(&output))
+    }
+
+    /// Browse the bookmarks of every remote, grouped by remote
+    pub fn remote_bookmarks_start(&mut self) -> Result<()> {
+        let browser = RemoteBookmarkBrowser::new(self.load_remote_bookmarks()?);
+        if browser.is_empty() {
+            self.info_list = Some("No remote bookmarks".into_text()?);
+            return Ok(());
+        }
+        self.remote_bookmarks = Some(browser);
+        Ok(())
+    }
+
+    pub fn remote_bookmarks_close(&mut self) {
+        self.remote_bookmarks = None;
+    }
+
+    pub fn remote_bookmarks_move(&mut self, delta: i32) {
+        if let Some(browser) = &mut self.remote_bookmarks {
+            browser.move_selection(delta);
+        }
+    }
+
+    /// The remote bookmark selected in the browser, or why there's none
+    fn selected_remote_bookmark(&mut self) -> Option<RemoteBookmark> {
+        let bookmark = self.remote_bookmarks.as_ref()?.selected_bookmark().cloned();
+        if bookmark.is_none() {
+            self.info_list = Some(Text::from("Select a bookmark under the remote"));
+        }
+        bookmark
+    }
+
+    pub fn remote_bookmarks_track(&mut self) -> Result<()> {
+        let Some(bookmark) = self.selected_remote_bookmark() else {
+            return Ok(());
+        };
+        let cmd = if bookmark.tracked {
+            JjCommand::bookmark_untrack(&bookmark.symbol(), self.global_args.clone())
+        } else {
+            JjCommand::bookmark_track(&[bookmark.symbol()], self.global_args.clone())
+        };
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn remote_bookmarks_fetch(&mut self) -> Result<()> {
+        let Some(browser) = &self.remote_bookmarks else {
+            return Ok(());
+        };
+        let Some(remote) = browser.selected_remote() else {
+            return Ok(());
+        };
+        let branch = browser.selected_bookmark().map(|bookmark| &bookmark.name);
+        let cmd = JjCommand::git_fetch_from_remote(
+            remote,
+            branch.map(String::as_str),
+            self.global_args.clone(),
+        );
+        self.queue_jj_command(cmd)
+    }
+
+    /// Ask for the name of a local bookmark to create where the selected
+    /// remote bookmark is, starting from its own name
+    pub fn remote_bookmarks_create_local(&mut self) -> Result<()> {
+        let Some(bookmark) = self.selected_remote_bookmark() else {
+            return Ok(());
+        };
+        if bookmark.commit_id.is_empty() {
+            self.info_list = Some(Text::from(format!(
+                "{} is {}, so there's no commit to create a bookmark at",
+                bookmark.symbol(),
+                bookmark.target
+            )));
+            return Ok(());
+        }
+        self.name_check = Some(self.bookmark_name_check()?);
+        self.text_cursor = bookmark.name.len();
+        self.text_input = bookmark.name;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Local Bookmark Name",
+            placeholder: "bookmark-name",
+            action: TextPromptAction::BookmarkCreateFromRemote {
+                commit_id: bookmark.commit_id,
+            },
+        };
+        Ok(())
+    }
+
+    pub fn handle_command_key(&mut self, key_code: KeyCode) -> Option<Message> {
+        self.command_keys.push(key_code);
+
+        let node = match self.command_tree.get_node(&self.command_keys) {
+            None => {
+                self.command_keys.pop();
+                display_unbound_error_lines(&mut self.info_list, &key_code);
+                return None;
+            }
+            Some(node) => node,
+        };
+        if let Some(children) = &node.children {
+            self.info_list = Some(children.get_help());
+        }
+        if let Some(message) = node.action {
+            if node.repeatable {
+                self.command_keys.pop();
+            } else if node.children.is_none() {
                 self.command_keys.clear();
             }
             return Some(message);
@@ -500,11 +2483,62 @@ impl Model {
         None
     }
 
+    /// Step back one level in the pending key chord, like backing out of a magit
+    /// transient. Backing out of a two-step command forgets its saved selection.
+    pub fn command_key_back(&mut self) {
+        if let Some(node) = self.command_tree.get_node(&self.command_keys)
+            && node.action.is_some()
+        {
+            self.clear_saved_selection();
+        }
+        self.command_keys.pop();
+
+        if self.command_keys.is_empty() {
+            self.clear();
+            return;
+        }
+        if let Some(children) = self
+            .command_tree
+            .get_node(&self.command_keys)
+            .and_then(|node| node.children.as_ref())
+        {
+            self.info_list = Some(children.get_help());
+        }
+    }
+
+    /// Whether the key continues the pending key chord, as opposed to being a
+    /// global binding like navigation
+    pub fn is_pending_command_key(&self, key_code: &KeyCode) -> bool {
+        self.has_pending_command_keys()
+            && self
+                .command_tree
+                .get_node(&self.command_keys)
+                .and_then(|node| node.children.as_ref())
+                .is_some_and(|children| children.get_node(key_code).is_some())
+    }
+
     /// Returns true if there are pending command keys in a multi-key sequence
     pub fn has_pending_command_keys(&self) -> bool {
         !self.command_keys.is_empty()
     }
 
+    pub fn pending_command_keys(&self) -> &[KeyCode] {
+        &self.command_keys
+    }
+
+    /// The jj command that will run next, if any are queued
+    pub fn running_jj_command(&self) -> Option<&JjCommand> {
+        self.queued_jj_commands.first()
+    }
+
+    /// The commit the current selection belongs to, if any
+    pub fn get_selected_commit(&self) -> Option<&Commit> {
+        let tree_pos = self
+            .log_list_tree_positions
+            .get(self.log_list_state.selected()?)?;
+        self.jj_log.get_tree_commit(tree_pos)
+    }
+
     pub fn scroll_down_once(&mut self) {
         if self.log_selected() <= self.log_offset() + self.log_list_scroll_padding {
             let _ = self.select_next_node();
@@ -558,9 +2592,8 @@ impl Model {
         *self.log_list_state.offset_mut() = target_offset;
     }
 
-    pub fn handle_mouse_click(&mut self, row: u16, column: u16) {
-        use std::time::{Duration, Instant};
-
+    /// Record a click, returning whether it completes a double-click
+    fn register_click(&mut self, row: u16, column: u16) -> bool {
         const DOUBLE_CLICK_THRESHOLD: Duration = Duration::from_millis(300);
 
         // Check for double-click
@@ -576,12 +2609,60 @@ impl Model {
         self.last_click_time = Some(Instant::now());
         self.last_click_pos = Some((row, column));
 
+        is_double_click
+    }
+
+    pub fn handle_mouse_click(&mut self, row: u16, column: u16, term: Term) {
         // Handle double-click - treat like Enter
-        if is_double_click {
-            let _ = self.enter_pressed();
+        if self.register_click(row, column) {
+            let _ = self.enter_pressed(term);
+            return;
+        }
+
+        let Some(target_node) = self.log_idx_at(row, column) else {
+            return;
+        };
+        self.drop_mouse_range();
+        self.log_select(target_node);
+        self.drag_anchor = Some(target_node);
+    }
+
+    /// Moving the selection drops a mouse range, unless it picks the
+    /// destination of a command that is using the range
+    pub fn drop_mouse_range(&mut self) {
+        if self.mouse_range.is_some() && !self.has_pending_command_keys() {
+            self.clear_saved_selection();
+        }
+    }
+
+    /// Select the clicked node and open its actions menu
+    pub fn handle_right_mouse_click(&mut self, row: u16, column: u16) -> Result<()> {
+        let Some(target_node) = self.log_idx_at(row, column) else {
+            return Ok(());
+        };
+        self.log_select(target_node);
+        self.open_action_menu()
+    }
+
+    /// Extend a range from the clicked node to the node under the mouse
+    pub fn handle_mouse_drag(&mut self, row: u16, column: u16) {
+        let Some(anchor) = self.drag_anchor else {
             return;
+        };
+        if let Some(target_node) = self.log_idx_at(row, column) {
+            self.select_range(anchor, target_node);
+        }
+    }
+
+    /// Select a range from the current selection to the clicked node
+    pub fn handle_shift_mouse_click(&mut self, row: u16, column: u16) {
+        if let Some(target_node) = self.log_idx_at(row, column) {
+            self.select_range(self.log_selected(), target_node);
         }
+    }
 
+    /// Log list index of the node at a screen position
+    fn log_idx_at(&self, row: u16, column: u16) -> Option<usize> {
         let Rect {
             x,
             y,
@@ -591,15 +2672,44 @@ impl Model {
 
         // Check if inside log list
         if row < y || row >= y + height || column < x || column >= x + width {
-            return;
+            return None;
         }
 
-        let target_node = self.line_dist_to_dest_node(
+        Some(self.line_dist_to_dest_node(
             row as usize - y as usize,
             self.log_offset(),
             &ScrollDirection::Down,
-        );
-        self.log_select(target_node);
+        ))
+    }
+
+    /// Select the commits at two log list indices as a range for range-based
+    /// commands: the older commit becomes the saved selection and the newer one
+    /// the current selection
+    fn select_range(&mut self, anchor_idx: usize, other_idx: usize) {
+        let newer_idx = anchor_idx.min(other_idx);
+        let older_idx = anchor_idx.max(other_idx);
+        let commit_at = |idx: usize| {
+            self.log_list_tree_positions
+                .get(idx)
+                .and_then(|tree_pos| self.jj_log.get_tree_commit(tree_pos))
+        };
+        let (Some(newer), Some(older)) = (commit_at(newer_idx), commit_at(older_idx)) else {
+            return;
+        };
+        if newer.change_id == older.change_id {
+            return;
+        }
+
+        let range = format!("{}::{}", older.change_id, newer.change_id);
+        let newer_flat_log_idx = newer.flat_log_idx;
+        self.saved_change_id = Some(older.change_id.clone());
+        self.saved_fileset = None;
+        self.saved_tree_position = Some(vec![
+            self.log_list_tree_positions[older_idx][COMMIT_OR_TEXT_IDX],
+        ]);
+        self.log_select(newer_flat_log_idx);
+        self.info_list = Some(Text::from(format!("Range selected: {range}")));
+        self.mouse_range = Some(range);
     }
 
     // Since some nodes contain multiple lines, we need a way to determine the destination node
@@ -635,28 +2745,43 @@ impl Model {
     }
 
     pub fn save_selection(&mut self) -> Result<()> {
+        // A range selected with the mouse already holds the saved selection
+        if self.mouse_range.is_some() && self.saved_change_id.is_some() {
+            return Ok(());
+        }
         let Some(change_id) = self.get_selected_change_id() else {
             self.clear();
             return self.invalid_selection();
         };
         self.saved_change_id = Some(change_id.to_string());
-        self.saved_file_path = self.get_selected_file_path().map(String::from);
+        self.saved_fileset = self.get_selected_fileset().map(String::from);
         self.saved_tree_position = Some(self.get_selected_tree_position());
 
         Ok(())
     }
 
-    pub fn jj_abandon(&mut self, mode: AbandonMode) -> Result<()> {
-        let Some(change_id) = self.get_selected_change_id() else {
-            return self.invalid_selection();
+    pub fn jj_abandon(&mut self, mode: AbandonMode, range: bool) -> Result<()> {
+        let revset = if range {
+            let Some(from_change_id) = self.get_saved_change_id() else {
+                return self.invalid_selection();
+            };
+            let Some(to_change_id) = self.get_selected_change_id() else {
+                return self.invalid_selection();
+            };
+            format!("{}::{}", from_change_id, to_change_id)
+        } else {
+            let Some(change_id) = self.get_selected_change_id() else {
+                return self.invalid_selection();
+            };
+            change_id.to_string()
         };
-        log::info!("Abandoning change: {}", change_id);
+        log::info!("Abandoning: {}", revset);
         let mode = match mode {
             AbandonMode::Default => None,
             AbandonMode::RetainBookmarks => Some("--retain-bookmarks"),
             AbandonMode::RestoreDescendants => Some("--restore-descendants"),
         };
-        let cmd = JjCommand::abandon(change_id, mode, self.global_args.clone());
+        let cmd = JjCommand::abandon(&revset, mode, self.global_args.clone());
         self.queue_jj_command(cmd)
     }
 
@@ -667,7 +2792,7 @@ impl Model {
                 let Some(from_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                (from_change_id, None, self.get_selected_file_path())
+                (from_change_id, None, self.get_selected_fileset())
             }
             AbsorbMode::Into => {
                 let Some(from_change_id) = self.get_saved_change_id() else {
@@ -679,9 +2804,10 @@ impl Model {
                 (
                     from_change_id,
                     Some(into_change_id),
-                    self.get_saved_file_path(),
+                    self.get_saved_fileset(),
                 )
             }
+            AbsorbMode::Preview => return self.jj_absorb_preview(),
         };
 
         let cmd = JjCommand::absorb(
@@ -693,12 +2819,150 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
+    /// Show where each hunk of the selected change would go and absorb once
+    /// confirmed
+    fn jj_absorb_preview(&mut self) -> Result<()> {
+        let Some(from_change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let from_change_id = from_change_id.to_string();
+        let maybe_fileset = self.get_selected_fileset().map(String::from);
+
+        let (mut lines, absorbs_any) =
+            self.absorb_preview_lines(&from_change_id, maybe_fileset.as_deref())?;
+        if !absorbs_any {
+            lines.push(Line::raw(""));
+            lines.push(Line::raw("Nothing would be absorbed"));
+            self.info_list = Some(Text::from(lines));
+            return Ok(());
+        }
+        let cmd = JjCommand::absorb(
+            &from_change_id,
+            None,
+            maybe_fileset.as_deref(),
+            self.global_args.clone(),
+        );
+        self.await_confirmation(lines, "Press Enter to absorb", vec![cmd]);
+        Ok(())
+    }
+
+    /// One line per hunk of the change naming the commit `jj absorb` would move
+    /// it into: the mutable ancestor that last changed every line the hunk
+    /// replaces. Also returns whether any hunk would move.
+    fn absorb_preview_lines(
+        &self,
+        from_change_id: &str,
+        maybe_fileset: Option<&str>,
+    ) -> Result<(Vec<Line<'static>>, bool)> {
+        let diff =
+            JjCommand::diff_git(from_change_id, maybe_fileset, self.global_args.clone()).run()?;
+        let parent = format!("({from_change_id})-");
+        let mutable_ancestors: HashSet<String> =
+            JjCommand::change_ids(&format!("mutable() & ::{parent}"), self.global_args.clone())
+                .run()?
+                .lines()
+                .map(|line| strip_ansi(line).trim().to_string())
+                .collect();
+
+        let hunk_regex = regex::Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+\d+(?:,\d+)? @@")?;
+        let mut annotations: HashMap<String, Vec<String>> = HashMap::new();
+        let mut lines = vec![Line::raw(format!("Absorbing {from_change_id}:"))];
+        let mut absorbs_any = false;
+        let mut in_file_header = false;
+        let mut old_path: Option<String> = None;
+        let mut new_path = String::new();
+
+        for line in diff.lines().map(strip_ansi) {
+            if line.starts_with("diff --git ") {
+                in_file_header = true;
+                continue;
+            }
+            if in_file_header {
+                if let Some(path) = line.strip_prefix("--- ") {
+                    // "/dev/null" for added files
+                    old_path = path.strip_prefix("a/").map(String::from);
+                } else if let Some(path) = line.strip_prefix("+++ ") {
+                    new_path = path.strip_prefix("b/").unwrap_or(path).to_string();
+                }
+            }
+            let Some(captures) = hunk_regex.captures(&line) else {
+                continue;
+            };
+            in_file_header = false;
+            let old_start: usize = captures[1].parse()?;
+            let old_len: usize = captures.get(2).map_or(Ok(1), |len| len.as_str().parse())?;
+
+            let destination = match &old_path {
+                None => Err("new file"),
+                Some(_) if old_len == 0 => Err("only adds lines"),
+                Some(old_path) => {
+                    let annotation = annotations.entry(old_path.clone()).or_insert_with(|| {
+                        JjCommand::file_annotate_change_ids(
+                            &parent,
+                            old_path,
+                            self.global_args.clone(),
+                        )
+                        .run()
+                        .map(|output| output.lines().map(strip_ansi).collect())
+                        .unwrap_or_default()
+                    });
+                    let commits: HashSet<&String> = annotation
+                        .get(old_start.saturating_sub(1)..old_start - 1 + old_len)
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                    match commits.into_iter().collect::<Vec<_>>()[..] {
+                        [commit] if mutable_ancestors.contains(commit) => Ok(commit.clone()),
+                        [_] => Err("last changed in an immutable commit"),
+                        [] => Err("cannot annotate the lines"),
+                        _ => Err("changes lines from several commits"),
+                    }
+                }
+            };
+
+            let mut spans = vec![Span::raw(format!("  {new_path} {}  ", &captures[0]))];
+            match destination {
+                Ok(change_id) => {
+                    absorbs_any = true;
+                    spans.push(Span::styled(
+                        format!("→ {}", &change_id[..change_id.len().min(8)]),
+                        Style::default().fg(Color::Magenta),
+                    ));
+                    if let Some(description) = self.loaded_description(&change_id) {
+                        spans.push(Span::raw(format!("  {description}")));
+                    }
+                }
+                Err(reason) => spans.push(Span::styled(
+                    format!("stays ({reason})"),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            }
+            lines.push(Line::from(spans));
+        }
+
+        Ok((lines, absorbs_any))
+    }
+
+    /// First description line of a change, if it is in the loaded log
+    fn loaded_description(&self, full_change_id: &str) -> Option<&str> {
+        self.jj_log
+            .log_tree
+            .iter()
+            .find_map(|commit_or_text| match commit_or_text {
+                CommitOrText::Commit(commit) if full_change_id.starts_with(&commit.change_id) => {
+                    commit.description_first_line.as_deref()
+                }
+                _ => None,
+            })
+    }
+
     /// Start inline bookmark editing for the selected commit
     pub fn bookmark_edit_start(&mut self) -> Result<()> {
         let Some(change_id) = self.get_selected_change_id() else {
             return self.invalid_selection();
         };
         let change_id = change_id.to_string();
+        self.name_check = Some(self.bookmark_name_check()?);
         self.text_input.clear();
         self.text_cursor = 0;
         self.text_input_location = crate::update::TextInputLocation::Bookmark { change_id };
@@ -708,6 +2972,7 @@ impl Model {
     /// Cancel bookmark editing
     pub fn bookmark_edit_cancel(&mut self) {
         self.text_input_location = crate::update::TextInputLocation::None;
+        self.name_check = None;
         self.text_input.clear();
         self.text_cursor = 0;
     }
@@ -725,6 +2990,44 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
+    /// Prompt for a bookmark name, then for the revision to create it at
+    /// (the selected change if left empty)
+    pub fn bookmark_create_at_start(&mut self) -> Result<()> {
+        let change_id = self.get_selected_change_id().unwrap_or("@").to_string();
+        self.name_check = Some(self.bookmark_name_check()?);
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Enter Bookmark Name",
+            placeholder: "new-bookmark-name",
+            action: TextPromptAction::BookmarkCreateName { change_id },
+        };
+        Ok(())
+    }
+
+    fn bookmark_create_revision_start(&mut self, name: String, change_id: String) -> Result<()> {
+        if name.trim().is_empty() {
+            self.info_list = Some("Bookmark name cannot be empty".into_text()?);
+            return Ok(());
+        }
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Revision to Create Bookmark At",
+            placeholder: "(selected change)",
+            action: TextPromptAction::BookmarkCreateRevision {
+                name: name.trim().to_string(),
+                change_id,
+            },
+        };
+        Ok(())
+    }
+
+    fn bookmark_create_at(&mut self, name: String, revision: String) -> Result<()> {
+        let cmd = JjCommand::bookmark_create(&name, &revision, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
     // ===== Description Editing Methods =====
 
     /// Start inline description editing for the selected commit
@@ -732,8 +3035,15 @@ impl Model {
         let Some(change_id) = self.get_selected_change_id() else {
             return self.invalid_selection();
         };
-        let change_id = change_id.to_string();
+        self.description_edit_change(change_id.to_string(), mode)
+    }
 
+    /// Start editing a change's description inline, on the selected line
+    fn description_edit_change(
+        &mut self,
+        change_id: String,
+        mode: crate::update::DescribeMode,
+    ) -> Result<()> {
         // Get the existing description to pre-fill (fetch full multi-line description)
         let existing_desc =
             match JjCommand::get_description(&change_id, self.global_args.clone()).run() {
@@ -747,9 +3057,7 @@ impl Model {
                 }
                 Err(_) => {
                     // Fall back to first line if command fails
-                    let tree_pos = self.get_selected_tree_position();
-                    self.jj_log
-                        .get_tree_commit(&tree_pos)
+                    self.find_commit_by_change_id(&change_id)
                         .and_then(|c| c.description_first_line.clone())
                         .unwrap_or_default()
                 }
@@ -758,6 +3066,7 @@ impl Model {
         self.text_input = existing_desc;
         self.text_cursor = self.text_input.len();
         self.description_warning_shown = false;
+        self.description_from_template = false;
         self.text_input_location =
             crate::update::TextInputLocation::Description { change_id, mode };
         Ok(())
@@ -772,44 +3081,166 @@ impl Model {
             _ => return Ok(()),
         };
 
-        // Check first line length for 50-column rule
+        // The hint below the editor already says the subject is too long, so
+        // only ask for Enter again
         let first_line = self
             .text_input
             .split('\n')
             .next()
             .unwrap_or(&self.text_input);
-        let first_line_len = first_line.chars().count();
+        if first_line.width() > self.description_linter.subject_length
+            && !self.description_warning_shown
+        {
+            self.description_warning_shown = true;
+            self.info_list = Some(Text::from(Line::from(Span::styled(
+                "Press Enter again to submit with the long subject",
+                Style::default().fg(Color::Yellow),
+            ))));
+            return Ok(());
+        }
+
+        let message = self.text_input.clone();
+        let mut describe_queue = std::mem::take(&mut self.describe_queue);
+        let mut cmds = std::mem::take(&mut self.pending_describe_cmds);
+        self.text_input_cancel(); // Clear editing state first
+
+        let ignore_immutable = mode == crate::update::DescribeMode::IgnoreImmutable;
+        cmds.push(if mode == crate::update::DescribeMode::Commit {
+            JjCommand::commit_with_message(&message, self.global_args.clone())
+        } else {
+            JjCommand::describe_with_message(
+                &change_id,
+                &message,
+                ignore_immutable,
+                self.global_args.clone(),
+            )
+        });
+
+        // Describing a range: move on to the next commit, running everything at the end
+        if let Some(next_change_id) = describe_queue.pop() {
+            self.select_change_id(&next_change_id);
+            self.description_edit_start(mode)?;
+            self.describe_queue = describe_queue;
+            self.pending_describe_cmds = cmds;
+            self.info_list = Some(Text::from(format!(
+                "Describing range: {} more after this one",
+                self.describe_queue.len()
+            )));
+            return Ok(());
+        }
+        self.queue_jj_commands(cmds)
+    }
+
+    pub fn description_template_start(&mut self, mode: crate::update::DescribeMode) -> Result<()> {
+        if self.description_templates.is_empty() {
+            self.info_list = Some(Text::from(
+                "No description templates configured; add [[templates]] to the config file",
+            ));
+            return Ok(());
+        }
+        let names = self
+            .description_templates
+            .iter()
+            .map(|template| template.name.clone())
+            .collect();
+        self.open_popup(crate::update::Popup::DescriptionTemplate { mode, names })
+    }
+
+    /// Start editing a description inline from a template, with the cursor at
+    /// its first placeholder
+    fn description_template_edit(
+        &mut self,
+        mode: crate::update::DescribeMode,
+        name: &str,
+    ) -> Result<()> {
+        let Some(template) = self
+            .description_templates
+            .iter()
+            .find(|template| template.name == name)
+        else {
+            return Ok(());
+        };
+        let text = template.text.clone();
+        if mode == crate::update::DescribeMode::Commit {
+            self.select_current_working_copy();
+        }
+        self.description_edit_start(mode)?;
+        self.text_input = text;
+        self.text_cursor = 0;
+        self.description_from_template = true;
+        if !self.text_input_next_placeholder() {
+            self.text_cursor = self.text_input.len();
+        }
+        Ok(())
+    }
+
+    /// Describe each commit from the saved selection to the selected one in turn,
+    /// oldest first
+    pub fn description_range_edit_start(
+        &mut self,
+        mode: crate::update::DescribeMode,
+    ) -> Result<()> {
+        let Some(from_change_id) = self.get_saved_change_id() else {
+            return self.invalid_selection();
+        };
+        let Some(to_change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let revset = format!("{}::{}", from_change_id, to_change_id);
+        self.clear_saved_selection();
+
+        let output = JjCommand::change_ids(&revset, self.global_args.clone()).run()?;
+        // Newest first, which makes popping off the end go oldest first
+        let change_ids: Vec<String> = output
+            .lines()
+            .map(|line| strip_ansi(line).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let total = change_ids.len();
+        // Only commits shown in the log can be edited inline
+        let mut describe_queue: Vec<String> = change_ids
+            .into_iter()
+            .filter_map(|change_id| {
+                self.find_commit_by_change_id(&change_id)
+                    .map(|commit| commit.change_id.clone())
+            })
+            .collect();
 
-        if first_line_len > 50 && !self.description_warning_shown {
-            // First line exceeds 50 chars and warning not shown yet
-            self.description_warning_shown = true;
-            self.info_list = Some(Text::from(vec![
-                Line::from(vec![Span::styled(
-                    "WARNING: First line exceeds 50 characters (",
-                    Style::default().fg(Color::Yellow),
-                )]),
-                Line::from(vec![Span::styled(
-                    format!(
-                        "found {}). Press Enter again to submit anyway.",
-                        first_line_len
-                    ),
-                    Style::default().fg(Color::Yellow),
-                )]),
-            ]));
-            return Ok(());
+        let Some(first_change_id) = describe_queue.pop() else {
+            return self.invalid_selection();
+        };
+        if describe_queue.len() + 1 < total {
+            self.show_toast(format!(
+                "Skipping {} commits not shown in the log",
+                total - describe_queue.len() - 1
+            ));
         }
+        self.select_change_id(&first_change_id);
+        self.description_edit_start(mode)?;
+        self.describe_queue = describe_queue;
+        Ok(())
+    }
 
-        let message = self.text_input.clone();
-        self.text_input_cancel(); // Clear editing state first
+    /// The loaded commit whose (possibly shortened) change id prefixes the full one
+    fn find_commit_by_change_id(&self, full_change_id: &str) -> Option<&Commit> {
+        self.jj_log
+            .log_tree
+            .iter()
+            .find_map(|commit_or_text| match commit_or_text {
+                CommitOrText::Commit(commit) if full_change_id.starts_with(&commit.change_id) => {
+                    Some(commit)
+                }
+                _ => None,
+            })
+    }
 
-        let ignore_immutable = mode == crate::update::DescribeMode::IgnoreImmutable;
-        let cmd = JjCommand::describe_with_message(
-            &change_id,
-            &message,
-            ignore_immutable,
-            self.global_args.clone(),
-        );
-        self.queue_jj_command(cmd)
+    fn select_change_id(&mut self, change_id: &str) {
+        if let Some(idx) = self
+            .find_commit_by_change_id(change_id)
+            .map(|commit| commit.flat_log_idx)
+        {
+            self.log_select(idx);
+        }
     }
 
     // ===== Popup Methods =====
@@ -818,75 +3249,116 @@ impl Model {
     pub fn open_popup(&mut self, popup: crate::update::Popup) -> Result<()> {
         self.current_popup = Some(popup);
         self.popup_filter = String::new();
-        self.popup_selection = 0;
+        self.popup_list.reset();
         Ok(())
     }
 
     /// Add a character to the popup filter
     pub fn popup_filter_char(&mut self, ch: char) {
         self.popup_filter.push(ch);
-        self.popup_selection = 0; // Reset selection when filter changes
+        self.popup_list.reset(); // Reset selection when filter changes
+    }
+
+    /// Paste clipboard contents into the popup filter
+    pub fn popup_filter_paste(&mut self) {
+        if let Ok(text) = self.clipboard.get_text() {
+            self.popup_filter.push_str(&single_line(&text));
+            self.popup_list.reset();
+        }
     }
 
     /// Remove last character from popup filter
     pub fn popup_filter_backspace(&mut self) {
         self.popup_filter.pop();
-        self.popup_selection = 0; // Reset selection when filter changes
+        self.popup_list.reset(); // Reset selection when filter changes
+    }
+
+    /// Number of popup items matching the current filter
+    fn popup_match_count(&self) -> usize {
+        self.current_popup
+            .as_ref()
+            .map_or(0, |popup| popup.matched_items(&self.popup_filter).len())
     }
 
     /// Move selection to next item in popup
     pub fn popup_next(&mut self) {
-        if let Some(ref popup) = self.current_popup {
-            let filtered_count = popup
-                .items()
-                .iter()
-                .filter(|item| {
-                    let filter_lower = self.popup_filter.to_lowercase();
-                    let item_lower = item.to_lowercase();
-                    filter_lower.is_empty() || item_lower.contains(&filter_lower)
-                })
-                .count();
-            if self.popup_selection + 1 < filtered_count {
-                self.popup_selection += 1;
-            }
-        }
+        let count = self.popup_match_count();
+        self.popup_list.select_next(count);
     }
 
     /// Move selection to previous item in popup
     pub fn popup_prev(&mut self) {
-        if self.popup_selection > 0 {
-            self.popup_selection -= 1;
+        let count = self.popup_match_count();
+        self.popup_list.select_prev(count);
+    }
+
+    /// Move selection down one page in popup
+    pub fn popup_page_down(&mut self) {
+        let count = self.popup_match_count();
+        self.popup_list.page_down(count);
+    }
+
+    /// Move selection up one page in popup
+    pub fn popup_page_up(&mut self) {
+        let count = self.popup_match_count();
+        self.popup_list.page_up(count);
+    }
+
+    /// Select the first popup item
+    pub fn popup_first(&mut self) {
+        self.popup_list.select_first();
+    }
+
+    /// Select the last popup item
+    pub fn popup_last(&mut self) {
+        let count = self.popup_match_count();
+        self.popup_list.select_last(count);
+    }
+
+    /// Click in a popup: select the clicked item, confirm on double-click and
+    /// cancel when clicking outside the popup
+    pub fn popup_click(&mut self, row: u16, column: u16) -> Option<Message> {
+        let is_double_click = self.register_click(row, column);
+        let position = ratatui::layout::Position::new(column, row);
+        if !self.popup_list.popup_area().contains(position) {
+            return Some(Message::PopupCancel);
         }
+
+        let count = self.popup_match_count();
+        let idx = self.popup_list.item_at(position, count)?;
+        self.popup_list.select(idx, count);
+        is_double_click.then_some(Message::PopupSelect)
     }
 
     /// Get the currently selected item from the popup
     fn get_popup_selection(&self) -> Option<String> {
         let popup = self.current_popup.as_ref()?;
-        let filter_lower = self.popup_filter.to_lowercase();
-        let filtered: Vec<&String> = popup
-            .items()
-            .iter()
-            .filter(|item| {
-                let item_lower = item.to_lowercase();
-                filter_lower.is_empty() || item_lower.contains(&filter_lower)
-            })
-            .collect();
-        filtered.get(self.popup_selection).map(|s| (*s).clone())
+        let matches = popup.matched_items(&self.popup_filter);
+        matches
+            .get(self.popup_list.selected())
+            .map(|popup_match| popup_match.item.clone())
     }
 
     /// Confirm popup selection and execute the command
-    pub fn popup_select(&mut self, _term: Term) -> Result<()> {
+    pub fn popup_select(&mut self, term: Term) -> Result<()> {
         let Some(selected) = self.get_popup_selection() else {
             self.popup_cancel();
             return Ok(());
         };
 
+        // Marked items are selected together, or just the selected one if none are
+        let marked = match self.popup_list.marked() {
+            [] => vec![selected.clone()],
+            marked => marked.to_vec(),
+        };
+
         // Take ownership of popup to avoid borrow issues
         let popup = self.current_popup.take().unwrap();
         self.popup_cancel(); // Clear state
 
         match popup {
             crate::update::Popup::BookmarkDelete { .. } => {
+                let selected = bookmark_entry_name(&selected);
                 let cmd = JjCommand::bookmark_delete(&selected, self.global_args.clone());
                 self.queue_jj_command(cmd)
             }
@@ -900,7 +3372,15 @@ impl Model {
                 );
                 self.queue_jj_command(cmd)
             }
-            crate::update::Popup::BookmarkRenameSelect { .. } => {
+            crate::update::Popup::BookmarkRenameSelect { bookmarks } => {
+                let selected = bookmark_entry_name(&selected);
+                self.name_check = Some(NameCheck {
+                    kind: "Bookmark",
+                    existing: bookmarks
+                        .iter()
+                        .map(|entry| bookmark_entry_name(entry))
+                        .collect(),
+                });
                 // Open text prompt for new bookmark name
                 self.text_input.clear();
                 self.text_cursor = 0;
@@ -914,6 +3394,7 @@ impl Model {
                 Ok(())
             }
             crate::update::Popup::BookmarkSet { .. } => {
+                let selected = bookmark_entry_name(&selected);
                 if let Some(change_id) = self.get_selected_change_id() {
                     let cmd =
                         JjCommand::bookmark_set(&selected, change_id, self.global_args.clone());
@@ -922,8 +3403,8 @@ impl Model {
                     self.invalid_selection()
                 }
             }
-            crate::update::Popup::BookmarkTrack { .. } => {
-                let cmd = JjCommand::bookmark_track(&selected, self.global_args.clone());
+            crate::update::Popup::TrackFetched { .. } => {
+                let cmd = JjCommand::bookmark_track(&marked, self.global_args.clone());
                 self.queue_jj_command(cmd)
             }
             crate::update::Popup::BookmarkUntrack { .. } => {
@@ -934,87 +3415,174 @@ impl Model {
                 let cmd = JjCommand::file_track(&selected, self.global_args.clone());
                 self.queue_jj_command(cmd)
             }
-
-            crate::update::Popup::GitFetchRemote {
-                select_for_branches,
-                ..
+            crate::update::Popup::GrepResults {
+                change_id,
+                is_working_copy,
+                matches,
+                locations,
             } => {
-                if select_for_branches {
-                    // Fetch bookmarks/branches from this remote and show branch selection popup
-                    let output = JjCommand::bookmark_list_with_args(
-                        &["bookmark", "list", "--remote", &selected],
-                        self.global_args.clone(),
-                    )
-                    .run()?;
-                    let branches: Vec<String> = output
-                        .lines()
-                        .map(|s| s.trim())
-                        .filter(|s| !s.is_empty())
-                        .map(|s| {
-                            let clean = strip_ansi(s);
-                            // Extract bookmark name: split by colon, then by whitespace
-                            // to handle "bookmark-name (deleted): ..."
-                            clean
-                                .split(':')
-                                .next()
-                                .unwrap_or(&clean)
-                                .trim()
-                                .split_whitespace()
-                                .next()
-                                .unwrap_or(&clean)
-                                .to_string()
-                        })
-                        .filter(|s| !s.is_empty())
-                        .collect();
-
-                    if branches.is_empty() {
-                        self.info_list = Some(
-                            format!("No branches found on remote '{}'", selected).into_text()?,
-                        );
-                        return Ok(());
-                    }
+                let Some((file, line)) = matches
+                    .iter()
+                    .position(|item| *item == selected)
+                    .and_then(|idx| locations.get(idx))
+                else {
+                    return Ok(());
+                };
+                self.open_file_in_editor(&change_id, file, Some(*line), is_working_copy, term)
+            }
+            crate::update::Popup::FileUntrack { .. } => {
+                let cmd = JjCommand::file_untrack_paths(&marked, self.global_args.clone());
+                self.queue_jj_command(cmd)
+            }
 
-                    let popup = crate::update::Popup::GitFetchRemoteBranches {
-                        remote: selected,
-                        branches,
-                    };
-                    self.open_popup(popup)
-                } else {
-                    // Fetch all from this remote
-                    let cmd =
-                        JjCommand::git_fetch_from_remote(&selected, None, self.global_args.clone());
-                    self.queue_jj_command(cmd)
-                }
+            crate::update::Popup::GitFetchRemote { .. } => {
+                let cmd =
+                    JjCommand::git_fetch_from_remote(&selected, None, self.global_args.clone());
+                self.queue_jj_command(cmd)
             }
-            crate::update::Popup::GitFetchRemoteBranches { remote, .. } => {
-                // Fetch specific branch from specific remote
-                let cmd = JjCommand::git_fetch_from_remote(
-                    &remote,
-                    Some(&selected),
+            crate::update::Popup::GitPushDeleted { remote, .. } => {
+                let cmd = JjCommand::git_push_bookmarks(
+                    &marked,
+                    remote.as_deref(),
                     self.global_args.clone(),
                 );
-                self.queue_jj_command(cmd)
+                self.confirm_git_push(cmd)
             }
             crate::update::Popup::GitPushBookmark {
                 change_id,
                 is_named_mode,
                 ..
             } => {
+                let selected = bookmark_entry_name(&selected);
                 if is_named_mode {
                     // Named mode: create bookmark at specific revision and push
                     let value = format!("{}={}", selected, change_id);
                     let cmd = JjCommand::git_push(
                         Some("--named"),
                         Some(&value),
+                        self.push_remote.take().as_deref(),
                         self.global_args.clone(),
                     );
-                    self.queue_jj_command(cmd)
+                    self.confirm_git_push(cmd)
                 } else {
                     // Bookmark mode: push existing bookmark
-                    let cmd =
-                        JjCommand::git_push(Some("-b"), Some(&selected), self.global_args.clone());
-                    self.queue_jj_command(cmd)
+                    let cmd = JjCommand::git_push(
+                        Some("-b"),
+                        Some(&selected),
+                        self.push_remote.take().as_deref(),
+                        self.global_args.clone(),
+                    );
+                    self.confirm_git_push(cmd)
+                }
+            }
+            crate::update::Popup::TugBookmarks {
+                to_change_id, push, ..
+            } => self.queue_tug(&marked, &to_change_id, push),
+            crate::update::Popup::AuthorFilter { .. } => self.filter_by_author(&selected),
+            crate::update::Popup::DiffParent { choices, parents } => {
+                match choices.iter().position(|choice| *choice == selected) {
+                    Some(idx) => self.set_diff_parent(parents[idx].clone()),
+                    None => Ok(()),
+                }
+            }
+            crate::update::Popup::SavedRevsets { presets, revsets } => {
+                match presets.iter().position(|preset| *preset == selected) {
+                    Some(idx) => self.show_saved_revset(revsets[idx].clone()),
+                    None => Ok(()),
+                }
+            }
+            crate::update::Popup::GitPushRemote { .. } => {
+                // Back to the push menu so a push mode can be picked for this remote
+                self.show_toast(format!("Next push goes to {selected}"));
+                self.push_remote = Some(selected);
+                self.command_keys = vec![KeyCode::Char('g'), KeyCode::Char('p')];
+                if let Some(children) = self
+                    .command_tree
+                    .get_node(&self.command_keys)
+                    .and_then(|node| node.children.as_ref())
+                {
+                    self.info_list = Some(children.get_help());
+                }
+                Ok(())
+            }
+            crate::update::Popup::Help { .. } => Ok(()),
+            crate::update::Popup::ActionMenu { .. } => Ok(()),
+            crate::update::Popup::OpenRepository { .. } => self.move_to_workspace(selected),
+            crate::update::Popup::InitColocated { .. } => Ok(()),
+            crate::update::Popup::ImmutableRetry { choices } => {
+                let mut cmds = std::mem::take(&mut self.retry_commands);
+                if choices.first() != Some(&selected) || cmds.is_empty() {
+                    return Ok(());
+                }
+                cmds[0] = cmds[0].ignoring_immutable();
+                self.queue_jj_commands(cmds)
+            }
+            crate::update::Popup::ExportOverwrite { path, choices } => {
+                if choices.first() != Some(&selected) {
+                    return Ok(());
+                }
+                self.write_log_export(&path)
+            }
+            crate::update::Popup::SnapshotTooLarge { files, choices } => {
+                let cmds = std::mem::take(&mut self.retry_commands);
+                let ignore_file = match choices.iter().position(|choice| *choice == selected) {
+                    Some(0) => IgnoreFile::GitIgnore,
+                    Some(1) => {
+                        // Every command snapshots the working copy again
+                        let setting = format!("snapshot.max-new-file-size={}", files.largest);
+                        let cmds = cmds.iter().map(|cmd| cmd.with_config(&setting)).collect();
+                        return self.queue_jj_commands(cmds);
+                    }
+                    Some(2) => IgnoreFile::GitExclude,
+                    _ => return Ok(()),
+                };
+                if let Err(err) = self.ignore_paths(&files.paths, ignore_file) {
+                    self.display_error_lines(&err);
+                    return Ok(());
                 }
+                self.queue_jj_commands(cmds)
+            }
+            crate::update::Popup::DescriptionTemplate { mode, .. } => {
+                self.description_template_edit(mode, &selected)
+            }
+            crate::update::Popup::TrailerAdd { change_id, .. } => self
+                .describe_with(&change_id, |description| {
+                    trailers::add_trailer(description, &selected)
+                }),
+            crate::update::Popup::TrailerRemove { change_id, .. } => self
+                .describe_with(&change_id, |description| {
+                    trailers::remove_trailer(description, &selected)
+                }),
+            crate::update::Popup::SignKey { revset, .. } => {
+                // Items are "<key>" or "<key id>  <user id>"
+                let key = selected.split("  ").next().unwrap_or(&selected);
+                let cmd = JjCommand::sign_with_key(&revset, key, self.global_args.clone());
+                self.queue_jj_command(cmd)
+            }
+            crate::update::Popup::MergeTool { change_id, .. } => {
+                let tool = (selected != DEFAULT_MERGE_TOOL).then_some(selected.as_str());
+                let cmd = JjCommand::resolve(&change_id, tool, self.global_args.clone(), term);
+                self.merge_tool = Some(selected.clone());
+                self.queue_jj_command(cmd)
+            }
+            crate::update::Popup::SplitFiles {
+                change_id,
+                first_description,
+                second_description,
+                ..
+            } => {
+                // Marking the diff editor choice along with files still means the files
+                let files: Vec<String> = marked
+                    .into_iter()
+                    .filter(|file| file != SPLIT_IN_DIFF_EDITOR)
+                    .collect();
+                self.jj_split_described(
+                    &change_id,
+                    &first_description,
+                    &second_description,
+                    &files,
+                    term,
+                )
             }
             crate::update::Popup::WorkspaceForget { .. } => {
                 let cmd = JjCommand::workspace_forget(&selected, self.global_args.clone());
@@ -1060,13 +3628,32 @@ impl Model {
     pub fn popup_cancel(&mut self) {
         self.current_popup = None;
         self.popup_filter = String::new();
-        self.popup_selection = 0;
+        self.popup_list.reset();
+        self.popup_list.clear_marks();
+    }
+
+    /// Mark the selected item and move on to the next one
+    pub fn popup_toggle_mark(&mut self) {
+        if !self
+            .current_popup
+            .as_ref()
+            .is_some_and(|popup| popup.allows_marking())
+        {
+            return;
+        }
+        let Some(selected) = self.get_popup_selection() else {
+            return;
+        };
+        self.popup_list.toggle_mark(&selected);
+        let count = self.popup_match_count();
+        self.popup_list.select_next(count);
     }
 
     // ===== Text Input Methods =====
 
     /// Insert a character at the current cursor position
-    /// For description editing: auto-wrap on space if line would exceed 72 chars
+    /// For description editing: auto-wrap on space once a body line reaches
+    /// the body length
     pub fn text_input_char(&mut self, ch: char) {
         if self.text_cursor > self.text_input.len() {
             self.text_cursor = self.text_input.len();
@@ -1081,9 +3668,9 @@ impl Model {
             let line_has_newline_before = self.text_input[..line_start].contains('\n');
 
             if !is_first_line || line_has_newline_before {
-                // We're on a subsequent line, check 72-column limit
-                let line_len = current_line.chars().count();
-                if line_len >= 72 {
+                // We're on a subsequent line, check the body length
+                let line_len = current_line.width();
+                if line_len >= self.description_linter.body_length {
                     // Replace space with newline for auto-wrap
                     self.text_input.insert(self.text_cursor, '\n');
                     self.text_cursor += 1;
@@ -1101,6 +3688,88 @@ impl Model {
         self.text_input_char('\n');
     }
 
+    /// Remove the next `{placeholder}` after the cursor, wrapping around, and
+    /// put the cursor where it was
+    pub fn text_input_next_placeholder(&mut self) -> bool {
+        static PLACEHOLDER: LazyLock<regex::Regex> =
+            LazyLock::new(|| regex::Regex::new(r"\{[^{}\n]*\}").unwrap());
+        let Some(range) = PLACEHOLDER
+            .find_at(&self.text_input, self.text_cursor)
+            .or_else(|| PLACEHOLDER.find(&self.text_input))
+            .map(|found| found.range())
+        else {
+            return false;
+        };
+        self.text_cursor = range.start;
+        self.text_input.replace_range(range, "");
+        true
+    }
+
+    /// Complete the path, or the revision (a change id, commit id, bookmark
+    /// or tag) before the cursor, or fill in the next (or previous) match if
+    /// Tab was just pressed
+    pub fn text_input_complete(&mut self, reverse: bool) {
+        if let Some(completion) = &mut self.completion
+            && completion.is_current(&self.text_input, self.text_cursor)
+        {
+            completion.cycle(&mut self.text_input, &mut self.text_cursor, reverse);
+            return;
+        }
+
+        let before_cursor = &self.text_input[..self.text_cursor];
+        let (word_start, candidates) = match self.text_input_location.completion_kind() {
+            Some(CompletionKind::Revisions) => (
+                completion::revset_word_start(before_cursor),
+                self.revision_candidates(),
+            ),
+            Some(CompletionKind::Paths) => (
+                completion::path_word_start(before_cursor),
+                completion::path_candidates(before_cursor),
+            ),
+            None => return,
+        };
+        self.completion = Completion::start(
+            &mut self.text_input,
+            &mut self.text_cursor,
+            word_start,
+            candidates,
+            reverse,
+        )
+        // A single match is simply filled in
+        .filter(|completion| completion.matches.len() > 1);
+    }
+
+    /// Bookmarks, tags, change ids and commit ids of the loaded log
+    fn revision_candidates(&self) -> Vec<String> {
+        let mut bookmarks = Vec::new();
+        let mut change_ids = Vec::new();
+        let mut commit_ids = Vec::new();
+        for commit in self.jj_log.log_tree.iter().filter_map(|cot| match cot {
+            CommitOrText::Commit(commit) => Some(commit),
+            CommitOrText::InfoText(_) => None,
+        }) {
+            bookmarks.extend(commit.bookmarks.iter().cloned());
+            change_ids.push(commit.change_id.clone());
+            commit_ids.push(commit.commit_id.clone());
+        }
+        let tags = JjCommand::tag_names(self.global_args.clone())
+            .run()
+            .unwrap_or_default();
+        bookmarks
+            .into_iter()
+            .chain(tags.lines().map(str::to_string))
+            .chain(change_ids)
+            .chain(commit_ids)
+            .collect()
+    }
+
+    /// The matches to show under the input, until something else is typed
+    pub fn text_input_completion(&self) -> Option<&Completion> {
+        self.completion
+            .as_ref()
+            .filter(|completion| completion.is_current(&self.text_input, self.text_cursor))
+    }
+
     /// Check if we're currently in description editing mode
     fn is_description_editing(&self) -> bool {
         matches!(
@@ -1126,48 +3795,27 @@ impl Model {
         (line_start, current_line.to_string())
     }
 
-    /// Delete character before cursor (backspace)
+    /// Delete grapheme before cursor (backspace)
     pub fn text_input_backspace(&mut self) {
-        if self.text_cursor > 0 {
-            let char_len = self.text_input[..self.text_cursor]
-                .chars()
-                .last()
-                .map(|c| c.len_utf8())
-                .unwrap_or(1);
-            self.text_cursor -= char_len;
-            self.text_input.remove(self.text_cursor);
-        }
+        let start = prev_grapheme_boundary(&self.text_input, self.text_cursor);
+        self.text_input.replace_range(start..self.text_cursor, "");
+        self.text_cursor = start;
     }
 
-    /// Delete character at cursor
+    /// Delete grapheme at cursor
     pub fn text_input_delete(&mut self) {
-        if self.text_cursor < self.text_input.len() {
-            self.text_input.remove(self.text_cursor);
-        }
+        let end = next_grapheme_boundary(&self.text_input, self.text_cursor);
+        self.text_input.replace_range(self.text_cursor..end, "");
     }
 
-    /// Move cursor left
+    /// Move cursor left one grapheme
     pub fn text_input_move_left(&mut self) {
-        if self.text_cursor > 0 {
-            let char_len = self.text_input[..self.text_cursor]
-                .chars()
-                .last()
-                .map(|c| c.len_utf8())
-                .unwrap_or(1);
-            self.text_cursor -= char_len;
-        }
+        self.text_cursor = prev_grapheme_boundary(&self.text_input, self.text_cursor);
     }
 
-    /// Move cursor right
+    /// Move cursor right one grapheme
     pub fn text_input_move_right(&mut self) {
-        if self.text_cursor < self.text_input.len() {
-            let char_len = self.text_input[self.text_cursor..]
-                .chars()
-                .next()
-                .map(|c| c.len_utf8())
-                .unwrap_or(1);
-            self.text_cursor += char_len;
-        }
+        self.text_cursor = next_grapheme_boundary(&self.text_input, self.text_cursor);
     }
 
     /// Move cursor to beginning
@@ -1195,20 +3843,18 @@ impl Model {
             return;
         }
 
-        // Calculate column position in current line
-        let col = self.text_cursor - line_start;
+        // Calculate column position (in graphemes) in current line
+        let col = self.text_input[line_start..self.text_cursor]
+            .graphemes(true)
+            .count();
 
         // Find the start of previous line
         let text_before_line = &self.text_input[..line_start - 1];
         let prev_line_start = text_before_line.rfind('\n').map(|pos| pos + 1).unwrap_or(0);
 
-        // Calculate end of previous line
-        let prev_line_end = line_start - 1;
-        let prev_line_len = prev_line_end - prev_line_start;
-
         // Move to same column in previous line, or end of line if shorter
-        let new_col = col.min(prev_line_len);
-        self.text_cursor = prev_line_start + new_col;
+        let prev_line = &self.text_input[prev_line_start..line_start - 1];
+        self.text_cursor = prev_line_start + grapheme_col_to_byte(prev_line, col);
     }
 
     /// Move cursor down one line (for multi-line text)
@@ -1230,7 +3876,9 @@ impl Model {
             .rfind('\n')
             .map(|pos| pos + 1)
             .unwrap_or(0);
-        let col = self.text_cursor - line_start;
+        let col = self.text_input[line_start..self.text_cursor]
+            .graphemes(true)
+            .count();
 
         // Find start and end of next line
         let next_line_start = line_end + 1;
@@ -1238,11 +3886,10 @@ impl Model {
             .find('\n')
             .map(|pos| next_line_start + pos)
             .unwrap_or(self.text_input.len());
-        let next_line_len = next_line_end - next_line_start;
 
         // Move to same column in next line, or end of line if shorter
-        let new_col = col.min(next_line_len);
-        self.text_cursor = next_line_start + new_col;
+        let next_line = &self.text_input[next_line_start..next_line_end];
+        self.text_cursor = next_line_start + grapheme_col_to_byte(next_line, col);
     }
 
     /// Cut from cursor to end of current line, placing text in clipboard
@@ -1293,8 +3940,30 @@ impl Model {
     /// Paste text from clipboard at cursor position
     pub fn text_input_paste(&mut self) {
         if let Ok(text) = self.clipboard.get_text() {
-            self.text_input.insert_str(self.text_cursor, &text);
-            self.text_cursor += text.len();
+            self.text_input_insert_str(&text);
+        }
+    }
+
+    /// Insert text at the cursor. Only descriptions are multi-line, so newlines
+    /// in pasted text become spaces everywhere else.
+    fn text_input_insert_str(&mut self, text: &str) {
+        let text = if self.is_description_editing() {
+            text.replace("\r\n", "\n")
+        } else {
+            single_line(text)
+        };
+        self.text_cursor = self.text_cursor.min(self.text_input.len());
+        self.text_input.insert_str(self.text_cursor, &text);
+        self.text_cursor += text.len();
+    }
+
+    /// Handle a bracketed paste from the terminal
+    pub fn paste_text(&mut self, text: &str) {
+        if self.text_input_location != crate::update::TextInputLocation::None {
+            self.text_input_insert_str(text);
+        } else if self.current_popup.is_some() {
+            self.popup_filter.push_str(&single_line(text));
+            self.popup_list.reset();
         }
     }
 
@@ -1331,10 +4000,37 @@ impl Model {
         self.text_input.clear();
         self.text_cursor = 0;
         self.description_warning_shown = false;
+        self.description_from_template = false;
+        self.describe_queue.clear();
+        self.pending_describe_cmds.clear();
+        self.name_check = None;
+        self.completion = None;
+    }
+
+    /// Why the name being typed can't be used, if it can't
+    pub fn text_input_name_error(&self) -> Option<String> {
+        let check = self.name_check.as_ref()?;
+        let name = self.text_input.as_str();
+        if name.is_empty() {
+            return None;
+        }
+        if let Some(reason) = invalid_ref_name_reason(name) {
+            return Some(format!("{} name {reason}", check.kind));
+        }
+        check
+            .existing
+            .iter()
+            .any(|existing| existing == name)
+            .then(|| format!("{} '{name}' already exists", check.kind))
     }
 
     /// Submit text input and execute the associated action based on location
     pub fn text_input_submit(&mut self, _term: Term) -> Result<()> {
+        // Keep the input open so the name can be fixed
+        if self.text_input_name_error().is_some() {
+            return Ok(());
+        }
+        self.name_check = None;
         match &self.text_input_location {
             crate::update::TextInputLocation::Popup { action, .. } => {
                 let action = action.clone();
@@ -1346,6 +4042,23 @@ impl Model {
                     TextPromptAction::BookmarkRenameSubmit { old_name } => {
                         self.bookmark_rename_submit(old_name, text)
                     }
+                    TextPromptAction::BookmarkCreateName { change_id } => {
+                        self.bookmark_create_revision_start(text, change_id)
+                    }
+                    TextPromptAction::BookmarkCreateRevision { name, change_id } => {
+                        let revision = match text.trim() {
+                            "" => change_id,
+                            revision => revision.to_string(),
+                        };
+                        self.bookmark_create_at(name, revision)
+                    }
+                    TextPromptAction::BookmarkCreateFromRemote { commit_id } => match text.trim() {
+                        "" => {
+                            self.info_list = Some("Bookmark name cannot be empty".into_text()?);
+                            Ok(())
+                        }
+                        name => self.bookmark_create_at(name.to_string(), commit_id),
+                    },
                     TextPromptAction::MetaeditSetAuthor { change_id } => {
                         self.metaedit_set_author(change_id, text)
                     }
@@ -1353,6 +4066,20 @@ impl Model {
                         self.metaedit_set_timestamp(change_id, text)
                     }
                     TextPromptAction::ParallelizeRevset => self.parallelize_with_revset(text),
+                    TextPromptAction::FileUntrackGlob => self.jj_file_untrack_glob(text),
+                    TextPromptAction::Grep {
+                        change_id,
+                        is_working_copy,
+                    } => self.jj_grep(change_id, is_working_copy, text),
+                    TextPromptAction::OpAbandon => self.jj_op_abandon(text),
+                    TextPromptAction::SplitFirstDescription { change_id } => {
+                        self.split_second_description_start(change_id, text);
+                        Ok(())
+                    }
+                    TextPromptAction::SplitSecondDescription {
+                        change_id,
+                        first_description,
+                    } => self.split_file_select(change_id, first_description, text),
                     TextPromptAction::NextPrev { direction, mode } => {
                         self.next_prev_with_offset(direction, mode, text)
                     }
@@ -1362,6 +4089,13 @@ impl Model {
                         self.jj_workspace_power_add(&text, _term)
                     }
                     TextPromptAction::PowerWorkspaceRename => self.jj_workspace_power_rename(&text),
+                    TextPromptAction::ShellCommand => self.run_shell_command(&text),
+                    TextPromptAction::RunOnRevisions { revset } => {
+                        self.run_on_revisions(&revset, &text)
+                    }
+                    TextPromptAction::SaveRevsetName => self.save_revset_as(&text),
+                    TextPromptAction::ExportLogPath => self.export_log(&text),
+                    TextPromptAction::EditNote { change_id } => self.save_note(change_id, &text),
                 }
             }
             crate::update::TextInputLocation::Revset { .. } => self.revset_edit_submit(),
@@ -1401,9 +4135,11 @@ impl Model {
     /// Calculate cursor position for revset editing in the header.
     /// Header format: "repository: {repo}  revset: {input}"
     fn calculate_revset_cursor_position(&self) -> Option<(u16, u16)> {
-        // Prefix: "repository: " (12) + repo + "  " (2) + "revset: " (8) = 22 + repo.len()
-        let prefix_len = 22 + self.display_repository.len();
-        let cursor_x = prefix_len + self.text_cursor;
+        // Prefix: "repository: " (12) + repo + "  " (2) + "revset: " (8) = 22 + repo width,
+        // then the zoom breadcrumb, each revset followed by " › " (3)
+        let breadcrumb_len: usize = self.zoom_stack.iter().map(|outer| outer.width() + 3).sum();
+        let prefix_len = 22 + self.display_repository.width() + breadcrumb_len;
+        let cursor_x = prefix_len + self.text_input[..self.text_cursor].width();
         Some((cursor_x as u16, 0))
     }
 
@@ -1446,9 +4182,9 @@ impl Model {
             .unwrap_or(0);
 
         let x = (self.log_list_layout.x
-            + first_line_visible.len() as u16
+            + first_line_visible.width() as u16
             + head_offset
-            + self.text_cursor as u16)
+            + self.text_input[..self.text_cursor].width() as u16)
             .saturating_sub(2);
 
         Some((x, y))
@@ -1533,7 +4269,9 @@ impl Model {
         // X position: prefix + cursor offset in line
         // Prefix: "  → " = 4 characters
         let prefix_len = 4;
-        let x = self.log_list_layout.x + prefix_len + cursor_offset_in_line as u16;
+        let line_start = self.text_cursor - cursor_offset_in_line;
+        let cursor_col = self.text_input[line_start..self.text_cursor].width();
+        let x = self.log_list_layout.x + prefix_len + cursor_col as u16;
 
         log::debug!(
             "CURSOR_DEBUG: FINAL cursor_line_idx={}, cursor_offset_in_line={}, x={}, y={}",
@@ -1551,7 +4289,7 @@ impl Model {
         let area = self.log_list_layout;
 
         // Popup dimensions (from render_text_prompt_popup)
-        let popup_width = (area.width * 2 / 3).min(60).max(40);
+        let popup_width = (area.width * 2 / 3).clamp(40, 60);
         let popup_height = 7u16;
         let popup_x = (area.width - popup_width) / 2;
         let popup_y = (area.height - popup_height) / 2;
@@ -1562,7 +4300,7 @@ impl Model {
         // X position: popup x + "> " prefix + cursor position
         let input_x = popup_x + 2; // border + padding
         let prefix_len = 2; // "> "
-        let x = input_x + prefix_len + self.text_cursor as u16;
+        let x = input_x + prefix_len + self.text_input[..self.text_cursor].width() as u16;
 
         Some((x, input_y))
     }
@@ -1622,27 +4360,7 @@ impl Model {
     pub fn jj_bookmark_delete(&mut self, _term: Term) -> Result<()> {
         log::info!("Opening bookmark delete popup");
         // Fetch bookmarks and open popup
-        let output = JjCommand::bookmark_list(self.global_args.clone()).run()?;
-        let bookmarks: Vec<String> = output
-            .lines()
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(|s| {
-                // Strip ANSI color codes from jj output
-                let clean = strip_ansi(s);
-                // Default format: "bookmark-name: commit-id description" or "bookmark-name (deleted): ..."
-                // Extract just the bookmark name (before colon, then before whitespace)
-                clean
-                    .split(':')
-                    .next()
-                    .unwrap_or(&clean)
-                    .trim()
-                    .split_whitespace()
-                    .next()
-                    .unwrap_or(&clean)
-                    .to_string()
-            })
-            .collect();
+        let bookmarks = self.bookmark_entries()?;
 
         if bookmarks.is_empty() {
             self.info_list = Some("No bookmarks to delete".into_text()?);
@@ -1670,7 +4388,6 @@ impl Model {
                     .split(':')
                     .next()
                     .unwrap_or(&clean)
-                    .trim()
                     .split_whitespace()
                     .next()
                     .unwrap_or(&clean)
@@ -1697,6 +4414,31 @@ impl Model {
 
     pub fn jj_bookmark_move(&mut self, mode: BookmarkMoveMode) -> Result<()> {
         let (from_change_id, to_change_id, allow_backwards) = match mode {
+            BookmarkMoveMode::AllOnSaved => {
+                let Some(from_change_id) = self.get_saved_change_id() else {
+                    return self.invalid_selection();
+                };
+                let Some(to_change_id) = self.get_selected_change_id() else {
+                    return self.invalid_selection();
+                };
+                let output =
+                    JjCommand::bookmark_names_at(from_change_id, self.global_args.clone()).run()?;
+                let bookmarks: Vec<String> = output
+                    .lines()
+                    .map(|s| strip_ansi(s).trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                if bookmarks.is_empty() {
+                    self.info_list = Some("No bookmarks on the saved change".into_text()?);
+                    return Ok(());
+                }
+                let cmd = JjCommand::bookmark_move_names(
+                    &bookmarks,
+                    to_change_id,
+                    self.global_args.clone(),
+                );
+                return self.queue_jj_command(cmd);
+            }
             BookmarkMoveMode::Default => {
                 let Some(from_change_id) = self.get_saved_change_id() else {
                     return self.invalid_selection();
@@ -1719,111 +4461,43 @@ impl Model {
                 let Some(to_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                ("heads(::@- & bookmarks())", to_change_id, false)
+                return self.tug_to(to_change_id.to_string(), false);
             }
-        };
-        let cmd = JjCommand::bookmark_move(
-            from_change_id,
-            to_change_id,
-            allow_backwards,
-            self.global_args.clone(),
-        );
-        self.queue_jj_command(cmd)
-    }
-
-    pub fn jj_bookmark_rename(&mut self, _term: Term) -> Result<()> {
-        // Fetch bookmarks and open popup for selection
-        let output = JjCommand::bookmark_list(self.global_args.clone()).run()?;
-        let bookmarks: Vec<String> = output
-            .lines()
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(|s| {
-                let clean = strip_ansi(s);
-                clean
-                    .split(':')
-                    .next()
-                    .unwrap_or(&clean)
-                    .trim()
-                    .split_whitespace()
-                    .next()
-                    .unwrap_or(&clean)
-                    .to_string()
-            })
-            .collect();
-
-        if bookmarks.is_empty() {
-            return Ok(());
-        }
-
-        let popup = crate::update::Popup::BookmarkRenameSelect { bookmarks };
-        self.open_popup(popup)
-    }
-
-    pub fn jj_bookmark_set(&mut self, _term: Term) -> Result<()> {
-        if self.get_selected_change_id().is_none() {
-            return self.invalid_selection();
-        }
-        // Fetch bookmarks and open popup
-        let output = JjCommand::bookmark_list(self.global_args.clone()).run()?;
-        let bookmarks: Vec<String> = output
-            .lines()
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(|s| {
-                let clean = strip_ansi(s);
-                clean
-                    .split(':')
-                    .next()
-                    .unwrap_or(&clean)
-                    .trim()
-                    .split_whitespace()
-                    .next()
-                    .unwrap_or(&clean)
-                    .to_string()
-            })
-            .collect();
+        };
+        let cmd = JjCommand::bookmark_move(
+            from_change_id,
+            to_change_id,
+            allow_backwards,
+            self.global_args.clone(),
+        );
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_bookmark_rename(&mut self, _term: Term) -> Result<()> {
+        // Fetch bookmarks and open popup for selection
+        let bookmarks = self.bookmark_entries()?;
 
         if bookmarks.is_empty() {
-            self.info_list = Some("No bookmarks to set".into_text()?);
             return Ok(());
         }
 
-        let popup = crate::update::Popup::BookmarkSet { bookmarks };
+        let popup = crate::update::Popup::BookmarkRenameSelect { bookmarks };
         self.open_popup(popup)
     }
 
-    pub fn jj_bookmark_track(&mut self, _term: Term) -> Result<()> {
-        // Fetch remote bookmarks and open popup
-        let output = JjCommand::bookmark_list_with_args(
-            &["bookmark", "list", "--all-remotes"],
-            self.global_args.clone(),
-        )
-        .run()?;
-        let remote_bookmarks: Vec<String> = output
-            .lines()
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(|s| {
-                let clean = strip_ansi(s);
-                clean
-                    .split(':')
-                    .next()
-                    .unwrap_or(&clean)
-                    .trim()
-                    .split_whitespace()
-                    .next()
-                    .unwrap_or(&clean)
-                    .to_string()
-            })
-            .collect();
+    pub fn jj_bookmark_set(&mut self, _term: Term) -> Result<()> {
+        if self.get_selected_change_id().is_none() {
+            return self.invalid_selection();
+        }
+        // Fetch bookmarks and open popup
+        let bookmarks = self.bookmark_entries()?;
 
-        if remote_bookmarks.is_empty() {
-            self.info_list = Some("No remote bookmarks to track".into_text()?);
+        if bookmarks.is_empty() {
+            self.info_list = Some("No bookmarks to set".into_text()?);
             return Ok(());
         }
 
-        let popup = crate::update::Popup::BookmarkTrack { remote_bookmarks };
+        let popup = crate::update::Popup::BookmarkSet { bookmarks };
         self.open_popup(popup)
     }
 
@@ -1844,7 +4518,6 @@ impl Model {
                     .split(':')
                     .next()
                     .unwrap_or(&clean)
-                    .trim()
                     .split_whitespace()
                     .next()
                     .unwrap_or(&clean)
@@ -1864,7 +4537,7 @@ impl Model {
 
     pub fn jj_commit(&mut self, term: Term) -> Result<()> {
         log::info!("Committing changes");
-        let maybe_file_path = self.get_selected_file_path();
+        let maybe_file_path = self.get_selected_fileset();
         let cmd = JjCommand::commit(maybe_file_path, self.global_args.clone(), term);
         self.queue_jj_command(cmd)
     }
@@ -1922,10 +4595,24 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
-    pub fn enter_pressed(&mut self) -> Result<()> {
+    pub fn enter_pressed(&mut self, term: Term) -> Result<()> {
         let tree_pos = self.get_selected_tree_position();
         log::debug!("enter_pressed called, tree_pos.len() = {}", tree_pos.len());
 
+        // If on an evolog entry, show what changed since that version
+        if let Some(entry) = self.jj_log.get_tree_evolog_entry(&tree_pos) {
+            let Some(change_id) = self.get_selected_change_id() else {
+                return self.invalid_selection();
+            };
+            let cmd = JjCommand::diff_from_to(
+                &entry.commit_id,
+                change_id,
+                self.global_args.clone(),
+                term,
+            );
+            return self.queue_jj_command(cmd);
+        }
+
         // If on a commit (revision title), edit that revision
         if tree_pos.len() == 1 {
             log::debug!("On commit, calling jj_edit");
@@ -1963,76 +4650,96 @@ impl Model {
             return self.invalid_selection();
         };
 
-        // Open the file using jj cat piped to $EDITOR
-        // For the working copy (@), we can open directly; otherwise use jj cat
-        let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
-
-        // Parse editor command - handle cases like "code --wait" or "vim -u NONE"
-        let mut editor_parts = editor.split_whitespace();
-        let editor_bin = editor_parts.next().unwrap_or("vim");
-        let editor_args: Vec<&str> = editor_parts.collect();
-
-        // Build the file argument - include line number if available
-        let file_arg = if let Some(num) = line_num {
-            format!("{}:{}", file_path, num)
-        } else {
-            file_path.to_string()
-        };
-
-        if change_id == "@" || self.is_selected_working_copy() {
-            log::debug!("Opening working copy file: {}", file_arg);
-            // Open working copy file directly - spawn and forget (non-blocking)
-            let full_path = std::path::Path::new(&self.global_args.repository).join(&file_arg);
-            std::process::Command::new(editor_bin)
-                .args(&editor_args)
-                .arg(&full_path)
-                .spawn()?;
-        } else {
-            // For historical revisions, use jj cat and pipe to editor
-            // Since many editors don't support piping directly, we'll use a tempfile approach
-            let temp_file = tempfile::NamedTempFile::with_suffix(
-                std::path::Path::new(&file_path)
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or(""),
-            )?;
-            let temp_path = temp_file.path().to_path_buf();
-
-            // Get file content at this revision
-            let output = std::process::Command::new("jj")
-                .args([
-                    "file",
-                    "show",
-                    "--color=never",
-                    "--repository",
-                    &self.global_args.repository,
-                    "-r",
-                    change_id,
-                    "--",
-                    &file_path,
-                ])
-                .output()?;
+        let is_working_copy = change_id == "@" || self.is_selected_working_copy();
+        let change_id = change_id.to_string();
+        self.open_file_in_editor(&change_id, &file_path, line_num, is_working_copy, term)
+    }
 
-            if !output.status.success() {
-                return Err(anyhow::anyhow!(
-                    "Failed to get file content: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
+    /// Open a file as it is in a revision in $EDITOR and wait for it to exit:
+    /// the file itself in the working copy, otherwise a temporary copy of its
+    /// contents whose edits can then be applied to the revision
+    fn open_file_in_editor(
+        &mut self,
+        change_id: &str,
+        file_path: &str,
+        line_num: Option<u32>,
+        is_working_copy: bool,
+        term: Term,
+    ) -> Result<()> {
+        if is_working_copy {
+            let full_path = std::path::Path::new(&self.global_args.repository).join(file_path);
+            log::debug!("Opening working copy file: {}", full_path.display());
+            if let Err(err) = self.run_file_opener(&term, file_path, &full_path, line_num) {
+                self.display_error_lines(&err);
+                return Ok(());
             }
+            // Pick up the edits in the log
+            return self.sync();
+        }
 
-            std::fs::write(&temp_path, &output.stdout)?;
-
-            // Open the temp file in editor
-            log::debug!("Opening temp file: {}", temp_path.display());
-            std::process::Command::new(editor_bin)
-                .args(&editor_args)
-                .arg(&temp_path)
-                .spawn()?;
+        let contents = match crate::shell_out::file_contents(
+            &self.global_args.repository,
+            change_id,
+            file_path,
+        ) {
+            Ok(contents) => contents,
+            Err(err) => {
+                self.display_error_lines(&err);
+                return Ok(());
+            }
+        };
+        let temp_file = tempfile::Builder::new()
+            .suffix(
+                &std::path::Path::new(file_path)
+                    .file_name()
+                    .map(|name| format!("-{}", name.to_string_lossy()))
+                    .unwrap_or_default(),
+            )
+            .tempfile()?;
+        std::fs::write(temp_file.path(), &contents)?;
+        let temp_path = temp_file.into_temp_path();
+
+        log::debug!("Opening temp file: {}", temp_path.display());
+        if let Err(err) = self.run_file_opener(&term, file_path, &temp_path, line_num) {
+            self.display_error_lines(&err);
+            return Ok(());
+        }
+        if std::fs::read(&temp_path)? == contents {
+            self.command_input_file = None;
+            return Ok(());
         }
 
+        let cmd =
+            JjCommand::diffedit_file(change_id, file_path, &temp_path, self.global_args.clone());
+        self.command_input_file = Some(temp_path);
+        self.await_confirmation(
+            vec![Line::raw(format!(
+                "You edited {file_path} as it is in {change_id}"
+            ))],
+            &format!("Press Enter to apply the edit to {change_id} (descendants are rebased)"),
+            vec![cmd],
+        );
         Ok(())
     }
 
+    /// Open a file with the first configured opener matching its path in the
+    /// repo, or $EDITOR, and wait for it to exit
+    fn run_file_opener(
+        &self,
+        term: &Term,
+        file_path: &str,
+        open_path: &std::path::Path,
+        line_num: Option<u32>,
+    ) -> Result<()> {
+        match self.openers.iter().find(|opener| opener.matches(file_path)) {
+            Some(opener) => crate::shell_out::run_shell_interactive(
+                term,
+                &opener.command_line(&open_path.to_string_lossy(), line_num),
+            ),
+            None => crate::shell_out::run_editor(term, open_path, line_num),
+        }
+    }
+
     /// Get the line number from a diff hunk line at the given tree position.
     /// Uses the LogTreeNode::line_number trait method.
     fn get_diff_line_number(&mut self, tree_pos: &TreePosition) -> Option<u32> {
@@ -2083,6 +4790,110 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
+    pub fn jj_file_chmod(&mut self, executable: bool) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let Some(file_path) = self.get_selected_file_path() else {
+            return self.invalid_selection();
+        };
+        let cmd = JjCommand::file_chmod(change_id, file_path, executable, self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    /// Show the selected image file as it was before and after its revision
+    pub fn file_image_preview(&mut self, term: Term) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let Some(file_diff) = self.jj_log.get_tree_file_diff(&tree_pos) else {
+            return self.invalid_selection();
+        };
+        if !image_preview::is_image_path(&file_diff.path) {
+            self.info_list = Some(Text::from(format!("{} is not an image", file_diff.path)));
+            return Ok(());
+        }
+        let Some(protocol) = GraphicsProtocol::detect() else {
+            self.info_list = Some(Text::from(
+                "Image previews need a terminal with Kitty, iTerm2 or sixel graphics",
+            ));
+            return Ok(());
+        };
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+
+        // Either side is missing when the file is added or deleted
+        let repository = &self.global_args.repository;
+        let before_path = file_diff.old_path.as_deref().unwrap_or(&file_diff.path);
+        let before =
+            crate::shell_out::file_contents(repository, &format!("{change_id}-"), before_path).ok();
+        let after = crate::shell_out::file_contents(repository, change_id, &file_diff.path).ok();
+        image_preview::show(&term, protocol, &file_diff.path, before, after)
+    }
+
+    /// Show the conflicts in the selected file, with each side in its own color
+    pub fn file_conflict_view(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let Some(file_diff) = self.jj_log.get_tree_file_diff(&tree_pos) else {
+            return self.invalid_selection();
+        };
+        let path = file_diff.path.clone();
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let content = JjCommand::file_show(change_id, &path, self.global_args.clone()).run()?;
+        self.info_list = Some(
+            crate::conflicts::render(&path, &strip_ansi(&content))
+                .unwrap_or_else(|| Text::from(format!("{path} has no conflict markers"))),
+        );
+        Ok(())
+    }
+
+    /// Offer the tracked files that the ignore rules now match, which are the
+    /// ones jj allows untracking
+    pub fn jj_file_untrack_ignored(&mut self) -> Result<()> {
+        let tracked_files: Vec<String> = JjCommand::file_list("@", self.global_args.clone())
+            .run()?
+            .lines()
+            .map(|s| strip_ansi(s).trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        let git_dir = JjCommand::git_root(self.global_args.clone()).run()?;
+        let work_tree = JjCommand::workspace_root(self.global_args.clone()).run()?;
+        let ignored_files = crate::shell_out::git_check_ignored(
+            strip_ansi(&git_dir).trim(),
+            strip_ansi(&work_tree).trim(),
+            &tracked_files,
+        )?;
+
+        if ignored_files.is_empty() {
+            self.info_list = Some("No ignored files are tracked".into_text()?);
+            return Ok(());
+        }
+
+        let popup = crate::update::Popup::FileUntrack { ignored_files };
+        self.open_popup(popup)
+    }
+
+    pub fn file_untrack_glob_start(&mut self) {
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Untrack Files Matching Glob (must be ignored)",
+            placeholder: "build/**",
+            action: TextPromptAction::FileUntrackGlob,
+        };
+    }
+
+    fn jj_file_untrack_glob(&mut self, glob: String) -> Result<()> {
+        let glob = glob.trim();
+        if glob.is_empty() {
+            return Ok(());
+        }
+        let fileset = format!("glob:{glob:?}");
+        let cmd = JjCommand::file_untrack_paths(&[fileset], self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
     pub fn jj_git_fetch(&mut self, mode: GitFetchMode, _term: Term) -> Result<()> {
         log::info!("Git fetch, mode: {:?}", mode);
         match mode {
@@ -2099,66 +4910,16 @@ impl Model {
                 let cmd = JjCommand::git_fetch(Some("--tracked"), None, self.global_args.clone());
                 self.queue_jj_command(cmd)
             }
-            GitFetchMode::Branch => {
-                // Show remotes first, then we'll fetch branches from selected remote
-                let output = JjCommand::git_remote_list(self.global_args.clone()).run()?;
-                let remotes: Vec<String> = output
-                    .lines()
-                    .map(|s| s.trim())
-                    .filter(|s| !s.is_empty())
-                    .map(|s| {
-                        // jj git remote list outputs "origin git@github.com:..."
-                        // We only want the remote name (first word)
-                        strip_ansi(s)
-                            .split_whitespace()
-                            .next()
-                            .unwrap_or(s)
-                            .trim()
-                            .to_string()
-                    })
-                    .filter(|s| !s.is_empty())
-                    .collect();
-
-                if remotes.is_empty() {
-                    self.info_list = Some("No remotes configured".into_text()?);
-                    return Ok(());
-                }
-
-                let popup = crate::update::Popup::GitFetchRemote {
-                    remotes,
-                    select_for_branches: true,
-                };
-                self.open_popup(popup)
-            }
             GitFetchMode::Remote => {
                 // Fetch remotes and show popup
-                let output = JjCommand::git_remote_list(self.global_args.clone()).run()?;
-                let remotes: Vec<String> = output
-                    .lines()
-                    .map(|s| s.trim())
-                    .filter(|s| !s.is_empty())
-                    .map(|s| {
-                        // jj git remote list outputs "origin git@github.com:..."
-                        // We only want the remote name (first word)
-                        strip_ansi(s)
-                            .split_whitespace()
-                            .next()
-                            .unwrap_or(s)
-                            .trim()
-                            .to_string()
-                    })
-                    .filter(|s| !s.is_empty())
-                    .collect();
+                let remotes = self.git_remotes()?;
 
                 if remotes.is_empty() {
                     self.info_list = Some("No remotes configured".into_text()?);
                     return Ok(());
                 }
 
-                let popup = crate::update::Popup::GitFetchRemote {
-                    remotes,
-                    select_for_branches: false,
-                };
+                let popup = crate::update::Popup::GitFetchRemote { remotes };
                 self.open_popup(popup)
             }
         }
@@ -2171,6 +4932,7 @@ impl Model {
             GitPushMode::All => (Some("--all"), None),
             GitPushMode::Tracked => (Some("--tracked"), None),
             GitPushMode::Deleted => (Some("--deleted"), None),
+            GitPushMode::DeletedSelect => return self.git_push_deleted_select(),
             GitPushMode::Revision => {
                 let Some(change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
@@ -2188,24 +4950,7 @@ impl Model {
                     return self.invalid_selection();
                 };
                 // Fetch bookmarks and open popup
-                let output = JjCommand::bookmark_list(self.global_args.clone()).run()?;
-                let bookmarks: Vec<String> = output
-                    .lines()
-                    .map(|s| s.trim())
-                    .filter(|s| !s.is_empty())
-                    .map(|s| {
-                        let clean = strip_ansi(s);
-                        clean
-                            .split(':')
-                            .next()
-                            .unwrap_or(&clean)
-                            .trim()
-                            .split_whitespace()
-                            .next()
-                            .unwrap_or(&clean)
-                            .to_string()
-                    })
-                    .collect();
+                let bookmarks = self.bookmark_entries()?;
 
                 if bookmarks.is_empty() {
                     self.info_list = Some("No bookmarks to push".into_text()?);
@@ -2221,24 +4966,7 @@ impl Model {
             }
             GitPushMode::Bookmark => {
                 // Fetch bookmarks and open popup
-                let output = JjCommand::bookmark_list(self.global_args.clone()).run()?;
-                let bookmarks: Vec<String> = output
-                    .lines()
-                    .map(|s| s.trim())
-                    .filter(|s| !s.is_empty())
-                    .map(|s| {
-                        let clean = strip_ansi(s);
-                        clean
-                            .split(':')
-                            .next()
-                            .unwrap_or(&clean)
-                            .trim()
-                            .split_whitespace()
-                            .next()
-                            .unwrap_or(&clean)
-                            .to_string()
-                    })
-                    .collect();
+                let bookmarks = self.bookmark_entries()?;
 
                 if bookmarks.is_empty() {
                     self.info_list = Some("No bookmarks to push".into_text()?);
@@ -2253,8 +4981,140 @@ impl Model {
                 return self.open_popup(popup);
             }
         };
-        let cmd = JjCommand::git_push(flag, value.as_deref(), self.global_args.clone());
-        self.queue_jj_command(cmd)
+        let cmd = JjCommand::git_push(
+            flag,
+            value.as_deref(),
+            self.push_remote.take().as_deref(),
+            self.global_args.clone(),
+        );
+        self.confirm_git_push(cmd)
+    }
+
+    /// Offer the bookmark deletions `jj git push --deleted` would push, to
+    /// push only the ones marked
+    fn git_push_deleted_select(&mut self) -> Result<()> {
+        let remote = self.push_remote.take();
+        let cmd = JjCommand::git_push(
+            Some("--deleted"),
+            None,
+            remote.as_deref(),
+            self.global_args.clone(),
+        );
+        let summary = match cmd.with_arg("--dry-run").run() {
+            Ok(summary) => strip_ansi(&summary),
+            // Let the real push report the error
+            Err(JjCommandError::Failed { .. }) => return self.queue_jj_command(cmd),
+            Err(JjCommandError::Other { err }) => return Err(err),
+        };
+        let deletion = regex::Regex::new(r"Delete bookmark (\S+) from").unwrap();
+        let bookmarks: Vec<String> = summary
+            .lines()
+            .filter_map(|line| Some(deletion.captures(line)?[1].to_string()))
+            .collect();
+        if bookmarks.is_empty() {
+            self.info_list = Some(Text::from("No deleted bookmarks to push"));
+            return Ok(());
+        }
+        self.open_popup(crate::update::Popup::GitPushDeleted { bookmarks, remote })
+    }
+
+    /// Pick the remote the next push goes to instead of the default one
+    pub fn git_push_remote_select(&mut self) -> Result<()> {
+        let remotes = self.git_remotes()?;
+        if remotes.is_empty() {
+            self.info_list = Some("No remotes configured".into_text()?);
+            return Ok(());
+        }
+        self.open_popup(crate::update::Popup::GitPushRemote { remotes })
+    }
+
+    fn bookmark_name_check(&self) -> Result<NameCheck> {
+        Ok(NameCheck {
+            kind: "Bookmark",
+            existing: self
+                .bookmark_entries()?
+                .iter()
+                .map(|entry| bookmark_entry_name(entry))
+                .collect(),
+        })
+    }
+
+    fn workspace_name_check(&self) -> Result<NameCheck> {
+        let output = JjCommand::workspace_list(self.global_args.clone()).run()?;
+        Ok(NameCheck {
+            kind: "Workspace",
+            existing: output
+                .lines()
+                .map(|line| {
+                    strip_ansi(line)
+                        .split(':')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string()
+                })
+                .filter(|name| !name.is_empty())
+                .collect(),
+        })
+    }
+
+    /// Bookmarks described by their target change and how the remote bookmarks
+    /// tracking them compare, e.g. "main  kxqpwrst  @origin (behind by 2)"
+    fn bookmark_entries(&self) -> Result<Vec<String>> {
+        let output = JjCommand::bookmark_list_detailed(self.global_args.clone()).run()?;
+        let mut entries: Vec<String> = Vec::new();
+        for line in output.lines() {
+            let clean = strip_ansi(line);
+            let fields: Vec<&str> = clean.split('\t').collect();
+            let [name, remote, target, counts @ ..] = fields.as_slice() else {
+                continue;
+            };
+            if remote.is_empty() {
+                entries.push(format!("{name}  {target}"));
+                continue;
+            }
+            // Remote bookmarks follow their local bookmark
+            let Some(entry) = entries
+                .last_mut()
+                .filter(|entry| bookmark_entry_name(entry) == *name)
+            else {
+                continue;
+            };
+            if *remote == "git" {
+                continue;
+            }
+            let mut status = Vec::new();
+            if let [ahead, behind] = counts {
+                if *ahead != "0" {
+                    status.push(format!("ahead by {ahead}"));
+                }
+                if *behind != "0" {
+                    status.push(format!("behind by {behind}"));
+                }
+            }
+            entry.push_str(&format!("  @{remote}"));
+            if !status.is_empty() {
+                entry.push_str(&format!(" ({})", status.join(", ")));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn git_remotes(&self) -> Result<Vec<String>> {
+        let output = JjCommand::git_remote_list(self.global_args.clone()).run()?;
+        Ok(output
+            .lines()
+            .map(|s| {
+                // jj git remote list outputs "origin git@github.com:..."
+                // We only want the remote name (first word)
+                strip_ansi(s)
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .filter(|s| !s.is_empty())
+            .collect())
     }
 
     pub fn jj_interdiff(&mut self, mode: InterdiffMode, term: Term) -> Result<()> {
@@ -2263,7 +5123,7 @@ impl Model {
                 let Some(from_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                (from_change_id, "@", self.get_selected_file_path())
+                (from_change_id, "@", self.get_selected_fileset())
             }
             InterdiffMode::FromSelectionToDestination => {
                 let Some(from_change_id) = self.get_saved_change_id() else {
@@ -2272,13 +5132,13 @@ impl Model {
                 let Some(to_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                (from_change_id, to_change_id, self.get_saved_file_path())
+                (from_change_id, to_change_id, self.get_saved_fileset())
             }
             InterdiffMode::ToSelection => {
                 let Some(to_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                ("@", to_change_id, self.get_selected_file_path())
+                ("@", to_change_id, self.get_selected_fileset())
             }
         };
 
@@ -2357,7 +5217,7 @@ impl Model {
     pub fn jj_new(&mut self, mode: NewMode) -> Result<()> {
         log::info!("Creating new change, mode: {:?}", mode);
         let cmd = match mode {
-            NewMode::Default => {
+            NewMode::Default | NewMode::Describe => {
                 let Some(change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
@@ -2381,7 +5241,9 @@ impl Model {
                 JjCommand::new(change_id, &["--insert-after"], self.global_args.clone())
             }
         };
-        self.queue_jj_command(cmd)
+        self.queue_jj_command(cmd)?;
+        self.describe_after_queue = mode == NewMode::Describe;
+        Ok(())
     }
 
     pub fn jj_new_after_trunk_sync(&mut self) -> Result<()> {
@@ -2390,13 +5252,17 @@ impl Model {
         self.queue_jj_commands(vec![fetch_cmd, new_cmd])
     }
 
-    pub fn jj_new_on_branch(&mut self) -> Result<()> {
+    pub fn jj_new_on_branch(&mut self, describe: bool) -> Result<()> {
         let Some(change_id) = self.get_selected_change_id() else {
             return self.invalid_selection();
         };
         let new_cmd = JjCommand::new(change_id, &[], self.global_args.clone());
-        let tug_cmd = JjCommand::tug(self.global_args.clone());
-        self.queue_jj_commands(vec![new_cmd, tug_cmd])
+        // Which bookmarks qualify isn't known until the new change exists, so
+        // this moves all of them rather than asking
+        let tug_cmd = JjCommand::tug(&self.tug_revset, self.global_args.clone());
+        self.queue_jj_commands(vec![new_cmd, tug_cmd])?;
+        self.describe_after_queue = describe;
+        Ok(())
     }
 
     pub fn jj_next_prev(
@@ -2478,9 +5344,14 @@ impl Model {
             source_type,
             destination_type
         );
-        let Some(source_change_id) = self.get_saved_change_id() else {
+        let Some(saved_change_id) = self.get_saved_change_id() else {
             return self.invalid_selection();
         };
+        // Rebasing revisions moves the whole mouse-selected range, if there is one
+        let source_change_id = match &self.mouse_range {
+            Some(range) if source_type == RebaseSourceType::Revisions => range,
+            _ => saved_change_id,
+        };
         let source_type = match source_type {
             RebaseSourceType::Branch => "--branch",
             RebaseSourceType::Source => "--source",
@@ -2501,17 +5372,45 @@ impl Model {
             RebaseDestination::Trunk => "trunk()",
             RebaseDestination::Current => "@",
         };
+        // Destinations added earlier come first, in the order they were added
+        let mut destinations: Vec<&str> = self
+            .rebase_destinations
+            .iter()
+            .map(String::as_str)
+            .collect();
+        if !destinations.contains(&destination) {
+            destinations.push(destination);
+        }
 
         let cmd = JjCommand::rebase(
             source_type,
             source_change_id,
             destination_type,
-            destination,
+            &destinations,
             self.global_args.clone(),
         );
         self.queue_jj_command(cmd)
     }
 
+    /// Remember the selected commit as one of several rebase destinations
+    pub fn add_rebase_destination(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        if !self
+            .rebase_destinations
+            .iter()
+            .any(|dest| dest == change_id)
+        {
+            self.rebase_destinations.push(change_id.to_string());
+        }
+        self.show_toast(format!(
+            "Destinations: {}",
+            self.rebase_destinations.join(", ")
+        ));
+        Ok(())
+    }
+
     pub fn jj_rebase_selected_branch_onto_trunk(&mut self) -> Result<()> {
         log::info!("Rebasing selected branch onto trunk");
         let Some(source_change_id) = self.get_selected_change_id() else {
@@ -2522,7 +5421,7 @@ impl Model {
             "--branch",
             source_change_id,
             "--onto",
-            "trunk()",
+            &["trunk()"],
             self.global_args.clone(),
         );
         self.queue_jj_command(cmd)
@@ -2539,16 +5438,81 @@ impl Model {
             "--branch",
             source_change_id,
             "--onto",
-            "trunk()",
+            &["trunk()"],
             self.global_args.clone(),
         );
         self.queue_jj_commands(vec![fetch_cmd, rebase_cmd])
     }
 
+    pub fn op_abandon_start(&mut self) {
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Abandon Operations (empty: all but the current one)",
+            placeholder: OP_ABANDON_DEFAULT_RANGE,
+            action: TextPromptAction::OpAbandon,
+        };
+    }
+
+    /// Abandon the operations and garbage collect, reporting how much the repo shrank
+    fn jj_op_abandon(&mut self, operations: String) -> Result<()> {
+        let operations = match operations.trim() {
+            "" => OP_ABANDON_DEFAULT_RANGE,
+            operations => operations,
+        };
+        log::info!("Abandoning operations: {}", operations);
+        self.repo_size_before_gc = Some(repo_store_size(&self.global_args.repository));
+        let abandon_cmd = JjCommand::op_abandon(operations, self.global_args.clone());
+        let gc_cmd = JjCommand::util_gc(self.global_args.clone());
+        self.queue_jj_commands(vec![abandon_cmd, gc_cmd])
+    }
+
+    /// Revert the operations of the last thing jjdag ran, rather than undoing
+    /// whatever operation happens to be the latest. Operations since then,
+    /// including ones run elsewhere, are kept.
+    pub fn jj_undo_action(&mut self) -> Result<()> {
+        let Some(action) = self.action_ops.last() else {
+            self.info_list = Some("Nothing jjdag ran this session to undo".into_text()?);
+            return Ok(());
+        };
+        let output = JjCommand::op_ids_at(&action.after, self.global_args.clone()).run()?;
+        let operations: Vec<String> = strip_ansi(&output)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let Some(count) = operations.iter().position(|op| *op == action.before) else {
+            self.info_list =
+                Some(format!("Cannot find the operations `{}` ran", action.command).into_text()?);
+            return Ok(());
+        };
+        // Latest first, so each revert applies on top of the ones after it
+        let cmds: Vec<JjCommand> = operations[..count]
+            .iter()
+            .map(|op| JjCommand::op_revert(op, self.global_args.clone()))
+            .collect();
+        let mut lines: Vec<Line> = cmds.iter().flat_map(JjCommand::to_lines).collect();
+        lines.push(Line::raw(format!("Undoes `{}`", action.command)));
+        self.await_confirmation(lines, "Press Enter to revert", cmds);
+        Ok(())
+    }
+
+    /// Id of the latest operation, if jj reports one
+    fn current_op_id(&self) -> Result<Option<String>> {
+        match JjCommand::op_id(self.global_args.clone()).run() {
+            Ok(output) => {
+                let op = strip_ansi(&output).trim().to_string();
+                Ok((!op.is_empty()).then_some(op))
+            }
+            Err(JjCommandError::Failed { .. }) => Ok(None),
+            Err(JjCommandError::Other { err }) => Err(err),
+        }
+    }
+
     pub fn jj_redo(&mut self) -> Result<()> {
         log::info!("Redoing operation");
         let cmd = JjCommand::redo(self.global_args.clone());
-        self.queue_jj_command(cmd)
+        self.confirm_op_revert(cmd, None, "Press Enter to redo what this undid")
     }
 
     pub fn jj_restore(&mut self, mode: RestoreMode) -> Result<()> {
@@ -2557,10 +5521,7 @@ impl Model {
                 let Some(change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                (
-                    vec!["--changes-in", change_id],
-                    self.get_selected_file_path(),
-                )
+                (vec!["--changes-in", change_id], self.get_selected_fileset())
             }
             RestoreMode::ChangesInRestoreDescendants => {
                 let Some(change_id) = self.get_selected_change_id() else {
@@ -2568,20 +5529,20 @@ impl Model {
                 };
                 (
                     vec!["--changes-in", change_id, "--restore-descendants"],
-                    self.get_selected_file_path(),
+                    self.get_selected_fileset(),
                 )
             }
             RestoreMode::From => {
                 let Some(change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                (vec!["--from", change_id], self.get_selected_file_path())
+                (vec!["--from", change_id], self.get_selected_fileset())
             }
             RestoreMode::Into => {
                 let Some(change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                (vec!["--into", change_id], self.get_selected_file_path())
+                (vec!["--into", change_id], self.get_selected_fileset())
             }
             RestoreMode::FromInto => {
                 let Some(from_change_id) = self.get_saved_change_id() else {
@@ -2592,7 +5553,7 @@ impl Model {
                 };
                 (
                     vec!["--from", from_change_id, "--into", into_change_id],
-                    self.get_saved_file_path(),
+                    self.get_saved_fileset(),
                 )
             }
         };
@@ -2645,11 +5606,44 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
-    pub fn jj_resolve(&mut self, term: Term) -> Result<()> {
+    /// Resolve with a merge tool picked from the ones jj is configured with
+    pub fn jj_resolve(&mut self, term: Term) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let change_id = change_id.to_string();
+        let output = JjCommand::config_list_merge_tools(self.global_args.clone()).run()?;
+        let configured = merge_tool_names(&strip_ansi(&output));
+        if configured.is_empty() {
+            let cmd = JjCommand::resolve(&change_id, None, self.global_args.clone(), term);
+            return self.queue_jj_command(cmd);
+        }
+
+        let mut tools: Vec<String> = self.merge_tool.iter().cloned().collect();
+        for tool in [DEFAULT_MERGE_TOOL.to_string(), ":builtin".to_string()]
+            .into_iter()
+            .chain(configured)
+        {
+            if !tools.contains(&tool) {
+                tools.push(tool);
+            }
+        }
+        self.open_popup(crate::update::Popup::MergeTool { change_id, tools })
+    }
+
+    pub fn jj_resolve_take_side(&mut self, side: ResolveSide) -> Result<()> {
         let Some(change_id) = self.get_selected_change_id() else {
             return self.invalid_selection();
         };
-        let cmd = JjCommand::resolve(&change_id, self.global_args.clone(), term);
+        let Some(file_path) = self.get_selected_fileset() else {
+            return self.invalid_selection();
+        };
+        let tool = match side {
+            ResolveSide::Ours => ":ours",
+            ResolveSide::Theirs => ":theirs",
+        };
+        let cmd =
+            JjCommand::resolve_with_tool(change_id, tool, file_path, self.global_args.clone());
         self.queue_jj_command(cmd)
     }
 
@@ -2677,6 +5671,49 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
+    /// Show which backend jj signs with and offer the keys available to it
+    pub fn sign_key_select_start(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let revset = change_id.to_string();
+
+        let config_value = |name: &str, global_args: GlobalArgs| {
+            JjCommand::config_get(name, global_args)
+                .run()
+                .map(|value| strip_ansi(&value).trim().to_string())
+                .ok()
+                .filter(|value| !value.is_empty())
+        };
+        let backend = config_value("signing.backend", self.global_args.clone())
+            .unwrap_or_else(|| "none".to_string());
+        let configured_key = config_value("signing.key", self.global_args.clone());
+
+        let mut keys = match backend.as_str() {
+            "gpg" => crate::shell_out::list_gpg_secret_keys(),
+            "ssh" => crate::shell_out::list_ssh_public_keys(),
+            "none" => {
+                self.info_list = Some(
+                    "Signing is not configured (set signing.backend in your jj config)"
+                        .into_text()?,
+                );
+                return Ok(());
+            }
+            _ => Vec::new(),
+        };
+        if let Some(configured_key) = configured_key {
+            keys.retain(|key| !key.starts_with(&configured_key));
+            keys.insert(0, format!("{configured_key}  (signing.key)"));
+        }
+        if keys.is_empty() {
+            self.info_list = Some(format!("No {backend} signing keys found").into_text()?);
+            return Ok(());
+        }
+
+        self.info_list = Some(format!("Signing backend: {backend}").into_text()?);
+        self.open_popup(crate::update::Popup::SignKey { revset, keys })
+    }
+
     pub fn jj_simplify_parents(&mut self, mode: SimplifyParentsMode) -> Result<()> {
         let Some(change_id) = self.get_selected_change_id() else {
             return self.invalid_selection();
@@ -2694,54 +5731,151 @@ impl Model {
             return self.invalid_selection();
         };
         log::info!("Splitting change: {}", change_id);
-        let cmd = JjCommand::split(change_id, "Split: part 1", self.global_args.clone(), term);
+        let cmd = JjCommand::split(
+            change_id,
+            SPLIT_DEFAULT_DESCRIPTION,
+            self.global_args.clone(),
+            term,
+        );
         self.queue_jj_command(cmd)
     }
 
+    pub fn split_described_start(&mut self) -> Result<()> {
+        let Some(change_id) = self.get_selected_change_id() else {
+            return self.invalid_selection();
+        };
+        let change_id = change_id.to_string();
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Description of First Part",
+            placeholder: SPLIT_DEFAULT_DESCRIPTION,
+            action: TextPromptAction::SplitFirstDescription { change_id },
+        };
+        Ok(())
+    }
+
+    fn split_second_description_start(&mut self, change_id: String, first_description: String) {
+        self.text_input.clear();
+        self.text_cursor = 0;
+        self.text_input_location = crate::update::TextInputLocation::Popup {
+            prompt: "Description of Second Part",
+            placeholder: "(keep current description)",
+            action: TextPromptAction::SplitSecondDescription {
+                change_id,
+                first_description,
+            },
+        };
+    }
+
+    /// Offer the changed files of the commit, any of which can be marked to move
+    /// them into the first part without opening the diff editor
+    fn split_file_select(
+        &mut self,
+        change_id: String,
+        first_description: String,
+        second_description: String,
+    ) -> Result<()> {
+        let output = JjCommand::diff_name_only(&change_id, self.global_args.clone()).run()?;
+        let mut files = vec![SPLIT_IN_DIFF_EDITOR.to_string()];
+        files.extend(
+            output
+                .lines()
+                .map(|line| strip_ansi(line).trim().to_string())
+                .filter(|line| !line.is_empty()),
+        );
+
+        let first_description = match first_description.trim() {
+            "" => SPLIT_DEFAULT_DESCRIPTION.to_string(),
+            _ => first_description,
+        };
+        let popup = crate::update::Popup::SplitFiles {
+            change_id,
+            first_description,
+            second_description,
+            files,
+        };
+        self.open_popup(popup)
+    }
+
+    fn jj_split_described(
+        &mut self,
+        change_id: &str,
+        first_description: &str,
+        second_description: &str,
+        files: &[String],
+        term: Term,
+    ) -> Result<()> {
+        log::info!("Splitting change {} with descriptions", change_id);
+        let mut cmds = Vec::new();
+        // The second part keeps the original commit's description, so set that first
+        if !second_description.trim().is_empty() {
+            cmds.push(JjCommand::describe_with_message(
+                change_id,
+                second_description,
+                false,
+                self.global_args.clone(),
+            ));
+        }
+        cmds.push(match files {
+            [] => JjCommand::split(change_id, first_description, self.global_args.clone(), term),
+            files => JjCommand::split_files(
+                change_id,
+                first_description,
+                files,
+                self.global_args.clone(),
+            ),
+        });
+        self.queue_jj_commands(cmds)
+    }
+
     pub fn jj_tug(&mut self) -> Result<()> {
-        let cmd = JjCommand::tug(self.global_args.clone());
-        self.queue_jj_command(cmd)
+        self.tug_to("@".to_string(), false)
     }
 
     pub fn jj_tug_and_git_push(&mut self) -> Result<()> {
-        // Find bookmarks at the parent commit that will be tugged
-        let output = JjCommand::bookmark_list_with_args(
-            &[
-                "bookmark",
-                "list",
-                "-r",
-                "heads(::@- & bookmarks())",
-                "-T",
-                "name",
-            ],
-            self.global_args.clone(),
-        )
-        .run()?;
+        self.tug_to("@".to_string(), true)
+    }
 
+    /// Move the bookmarks on the tug revset, asking which ones when there are
+    /// several
+    fn tug_to(&mut self, to_change_id: String, push: bool) -> Result<()> {
+        let output =
+            JjCommand::bookmark_names_at(&self.tug_revset, self.global_args.clone()).run()?;
         let bookmarks: Vec<String> = output
             .lines()
-            .map(|s| s.trim())
+            .map(|s| strip_ansi(s).trim().to_string())
             .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
             .collect();
 
-        if bookmarks.is_empty() {
-            self.info_list = Some("No bookmarks to tug and push".into_text()?);
-            return Ok(());
+        match bookmarks.len() {
+            0 => {
+                self.info_list = Some("No bookmarks to tug".into_text()?);
+                Ok(())
+            }
+            1 => self.queue_tug(&bookmarks, &to_change_id, push),
+            _ => self.open_popup(crate::update::Popup::TugBookmarks {
+                bookmarks,
+                to_change_id,
+                push,
+            }),
         }
+    }
 
-        // Queue tug command first
-        let tug_cmd = JjCommand::tug(self.global_args.clone());
-
-        // Then queue git push for each bookmark
-        let mut cmds = vec![tug_cmd];
-        for bookmark in &bookmarks {
-            let push_cmd =
-                JjCommand::git_push(Some("-b"), Some(bookmark), self.global_args.clone());
-            cmds.push(push_cmd);
+    fn queue_tug(&mut self, bookmarks: &[String], to_change_id: &str, push: bool) -> Result<()> {
+        let move_cmd =
+            JjCommand::bookmark_move_names(bookmarks, to_change_id, self.global_args.clone());
+        let push_remote = self.push_remote.take();
+        self.queue_jj_command(move_cmd)?;
+        if push {
+            // The push can only be previewed once the bookmarks have moved
+            self.push_after_queue = Some(JjCommand::git_push_bookmarks(
+                bookmarks,
+                push_remote.as_deref(),
+                self.global_args.clone(),
+            ));
         }
-
-        self.queue_jj_commands(cmds)
+        Ok(())
     }
 
     pub fn jj_squash(&mut self, mode: SquashMode, term: Term) -> Result<()> {
@@ -2752,7 +5886,7 @@ impl Model {
                 let Some(commit) = self.jj_log.get_tree_commit(&tree_pos) else {
                     return self.invalid_selection();
                 };
-                let maybe_file_path = self.get_selected_file_path();
+                let maybe_file_path = self.get_selected_fileset();
 
                 if commit.description_first_line.is_none() {
                     JjCommand::squash_noninteractive(
@@ -2773,7 +5907,7 @@ impl Model {
                 let Some(from_change_id) = self.get_saved_change_id() else {
                     return self.invalid_selection();
                 };
-                let maybe_file_path = self.get_saved_file_path();
+                let maybe_file_path = self.get_saved_fileset();
                 let Some(into_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
@@ -2785,8 +5919,96 @@ impl Model {
                     term,
                 )
             }
+            SquashMode::HunkIntoBlamed => return self.jj_squash_hunk_into_blamed(),
+        };
+
+        self.queue_jj_command(cmd)
+    }
+
+    /// Squash the selected diff hunk into the mutable commit that last changed
+    /// the lines it replaces, found with `jj file annotate`
+    fn jj_squash_hunk_into_blamed(&mut self) -> Result<()> {
+        let tree_pos = self.get_selected_tree_position();
+        let (Some(file_diff), Some((old_start, old_end))) = (
+            self.jj_log.get_tree_file_diff(&tree_pos),
+            self.jj_log.get_tree_diff_hunk_old_range(&tree_pos),
+        ) else {
+            return self.invalid_selection();
+        };
+        if file_diff.old_path.is_some() {
+            self.info_list = Some(Text::from("Cannot squash hunks of renamed or copied files"));
+            return Ok(());
+        }
+        let file_path = file_diff.path.clone();
+        let Some(change_id) = self.get_selected_change_id().map(String::from) else {
+            return self.invalid_selection();
+        };
+        let parent = format!("({change_id})-");
+
+        // Find the git hunk the selected hunk was rendered from
+        let diff =
+            JjCommand::diff_git(&change_id, Some(&file_path), self.global_args.clone()).run()?;
+        let Some(hunk) = GitHunk::parse_all(&diff)
+            .into_iter()
+            .filter(|hunk| hunk.old_start <= old_end && old_start <= hunk.old_end())
+            .max_by_key(|hunk| hunk.old_end().min(old_end) - hunk.old_start.max(old_start))
+        else {
+            return self.invalid_selection();
         };
+        let removed_lines = hunk.removed_line_numbers();
+        if removed_lines.is_empty() {
+            self.info_list = Some(Text::from(
+                "The hunk only adds lines, so no commit last changed them",
+            ));
+            return Ok(());
+        }
+
+        let annotation: Vec<String> =
+            JjCommand::file_annotate_change_ids(&parent, &file_path, self.global_args.clone())
+                .run()?
+                .lines()
+                .map(strip_ansi)
+                .collect();
+        let blamed: HashSet<&String> = removed_lines
+            .iter()
+            .filter_map(|line| annotation.get(*line as usize - 1))
+            .collect();
+        let [blamed] = blamed.into_iter().collect::<Vec<_>>()[..] else {
+            self.info_list = Some(Text::from(
+                "The lines the hunk changes were last changed by several commits",
+            ));
+            return Ok(());
+        };
+        let is_mutable =
+            !JjCommand::change_ids(&format!("{blamed} & mutable()"), self.global_args.clone())
+                .run()?
+                .trim()
+                .is_empty();
+        if !is_mutable {
+            self.info_list = Some(Text::from(format!(
+                "The lines the hunk changes were last changed by immutable commit {}",
+                &blamed[..blamed.len().min(8)]
+            )));
+            return Ok(());
+        }
 
+        // The file as it will be in the squash selection: the parent's version
+        // with just this hunk applied
+        let parent_contents =
+            crate::shell_out::file_contents(&self.global_args.repository, &parent, &file_path)?;
+        let selected_contents = hunk.apply(&String::from_utf8_lossy(&parent_contents));
+        let temp_file = tempfile::NamedTempFile::new()?;
+        std::fs::write(temp_file.path(), selected_contents)?;
+        let temp_path = temp_file.into_temp_path();
+
+        let cmd = JjCommand::squash_file_contents(
+            &change_id,
+            blamed,
+            &file_path,
+            &temp_path,
+            self.global_args.clone(),
+        );
+        self.command_input_file = Some(temp_path);
         self.queue_jj_command(cmd)
     }
 
@@ -2796,10 +6018,38 @@ impl Model {
         self.queue_jj_command(cmd)
     }
 
+    pub fn jj_watchman_status(&mut self) -> Result<()> {
+        let cmd = JjCommand::watchman_status(self.global_args.clone());
+        self.queue_jj_command(cmd)
+    }
+
+    pub fn jj_watchman_enable(&mut self) -> Result<()> {
+        let enable_cmd = JjCommand::watchman_enable(self.global_args.clone());
+        let status_cmd = JjCommand::watchman_status(self.global_args.clone());
+        // The backend is only read at startup
+        self.fsmonitor = Some("watchman".to_string());
+        self.queue_jj_commands(vec![enable_cmd, status_cmd])
+    }
+
     pub fn jj_undo(&mut self) -> Result<()> {
         log::info!("Undoing last operation");
         let cmd = JjCommand::undo(self.global_args.clone());
-        self.queue_jj_command(cmd)
+        // Undoing an undo goes further back, reverting the operation it
+        // restored to
+        let operation = match JjCommand::op_description(self.global_args.clone()).run() {
+            Ok(description) => strip_ansi(&description)
+                .trim()
+                .strip_prefix("undo: restore to operation ")
+                .and_then(|rest| rest.split_whitespace().next())
+                .map(String::from),
+            Err(JjCommandError::Failed { .. }) => return self.queue_jj_command(cmd),
+            Err(JjCommandError::Other { err }) => return Err(err),
+        };
+        self.confirm_op_revert(
+            cmd,
+            operation.as_deref(),
+            "Press Enter to undo this operation",
+        )
     }
 
     pub fn jj_view(&mut self, mode: ViewMode, term: Term) -> Result<()> {
@@ -2808,7 +6058,7 @@ impl Model {
                 let Some(change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                match self.get_selected_file_path() {
+                match self.get_selected_fileset() {
                     Some(file_path) => JjCommand::diff_file_interactive(
                         change_id,
                         file_path,
@@ -2822,7 +6072,7 @@ impl Model {
                 let Some(from_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                let file = self.get_selected_file_path();
+                let file = self.get_selected_fileset();
                 JjCommand::diff_from_to_interactive(
                     from_change_id,
                     "@",
@@ -2838,7 +6088,7 @@ impl Model {
                 let Some(to_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                let file = self.get_selected_file_path();
+                let file = self.get_selected_fileset();
                 JjCommand::diff_from_to_interactive(
                     from_change_id,
                     to_change_id,
@@ -2851,7 +6101,7 @@ impl Model {
                 let Some(to_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                let file = self.get_selected_file_path();
+                let file = self.get_selected_fileset();
                 JjCommand::diff_from_to_interactive(
                     "trunk()",
                     to_change_id,
@@ -2864,7 +6114,7 @@ impl Model {
                 let Some(to_change_id) = self.get_selected_change_id() else {
                     return self.invalid_selection();
                 };
-                let file = self.get_selected_file_path();
+                let file = self.get_selected_fileset();
                 JjCommand::diff_from_to_interactive(
                     "@",
                     to_change_id,
@@ -2909,7 +6159,7 @@ impl Model {
 
     pub fn jj_workspace_add(&mut self, path: &str, term: Term) -> Result<()> {
         log::info!("Adding workspace at path: {}", path);
-        let cmd = JjCommand::workspace_add(path, self.global_args.clone(), term);
+        let cmd = JjCommand::workspace_add(&expand_home(path), self.global_args.clone(), term);
         self.queue_jj_command(cmd)
     }
 
@@ -3047,6 +6297,7 @@ impl Model {
     }
 
     pub fn workspace_rename_current_start(&mut self) -> Result<()> {
+        self.name_check = Some(self.workspace_name_check()?);
         self.text_input.clear();
         self.text_cursor = 0;
         self.text_input_location = crate::update::TextInputLocation::Popup {
@@ -3128,7 +6379,7 @@ impl Model {
 
             if let Some(parent_path) = current_path.parent() {
                 // Move all contents from current directory up to parent
-                let entries: Vec<_> = std::fs::read_dir(&current_path)?
+                let entries: Vec<_> = std::fs::read_dir(current_path)?
                     .filter_map(|e| e.ok())
                     .collect();
 
@@ -3143,7 +6394,7 @@ impl Model {
                 std::env::set_current_dir(parent_path)?;
 
                 // Remove the now-empty default directory
-                let _ = std::fs::remove_dir(&current_path);
+                let _ = std::fs::remove_dir(current_path);
 
                 // Update the model to reflect new location
                 self.move_to_workspace(parent_path.to_string_lossy().to_string())?;
@@ -3175,6 +6426,7 @@ impl Model {
 
     /// Set up text prompt for power workspace rename with selected workspace
     fn power_workspace_rename_start_with_name(&mut self, workspace_name: &str) -> Result<()> {
+        self.name_check = Some(self.workspace_name_check()?);
         self.saved_change_id = Some(workspace_name.to_string());
         self.text_input = workspace_name.to_string();
         self.text_cursor = self.text_input.len();
@@ -3375,9 +6627,12 @@ impl Model {
 
         // Change the process working directory to the new workspace
         std::env::set_current_dir(&new_workspace_path)?;
+        if let Err(err) = crate::state::record_recent_repository(&new_workspace_path) {
+            log::warn!("Could not record recent repository: {}", err);
+        }
 
         // Reinitialize JjLog
-        self.jj_log = JjLog::new()?;
+        self.jj_log = JjLog::new(self.jj_log.unfold_mode())?;
 
         // Clear cached view state
         self.log_list.clear();
@@ -3386,7 +6641,7 @@ impl Model {
 
         // Clear saved selections (they won't transfer between workspaces)
         self.saved_change_id = None;
-        self.saved_file_path = None;
+        self.saved_fileset = None;
         self.saved_tree_position = None;
 
         // Clear pending commands and output
@@ -3400,7 +6655,7 @@ impl Model {
         self.text_input.clear();
         self.text_cursor = 0;
         self.popup_filter.clear();
-        self.popup_selection = 0;
+        self.popup_list.reset();
 
         // Sync to reload from new location
         self.sync()?;
@@ -3414,7 +6669,11 @@ impl Model {
 
     fn queue_jj_commands(&mut self, cmds: Vec<JjCommand>) -> Result<()> {
         self.accumulated_command_output.clear();
+        // A mouse range is used up by the commands it was selected for
+        self.mouse_range = None;
         self.queued_jj_commands = cmds;
+        self.describe_after_queue = false;
+        self.push_after_queue = None;
         self.update_info_list_for_queue();
         Ok(())
     }
@@ -3428,13 +6687,220 @@ impl Model {
         self.info_list = Some(Text::from(lines));
     }
 
+    pub fn has_commands_awaiting_confirmation(&self) -> bool {
+        !self.commands_awaiting_confirmation.is_empty()
+    }
+
+    pub fn confirm_commands(&mut self) -> Result<()> {
+        let cmds = std::mem::take(&mut self.commands_awaiting_confirmation);
+        self.queue_jj_commands(cmds)
+    }
+
+    /// Drop the commands awaiting confirmation, keeping what was shown about
+    /// them but not the prompt to run them
+    pub fn cancel_confirmation(&mut self) {
+        self.commands_awaiting_confirmation.clear();
+        if let Some(info_list) = &mut self.info_list {
+            // The blank line and prompt `await_confirmation` added
+            let len = info_list.lines.len().saturating_sub(2);
+            info_list.lines.truncate(len);
+        }
+    }
+
+    /// Show the info list with a prompt and run the commands once confirmed
+    fn await_confirmation(
+        &mut self,
+        mut lines: Vec<Line<'static>>,
+        prompt: &str,
+        cmds: Vec<JjCommand>,
+    ) {
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(
+            format!("{prompt}, Esc to cancel"),
+            Style::default().fg(Color::Yellow),
+        ));
+        self.clear();
+        self.info_list = Some(Text::from(lines));
+        self.commands_awaiting_confirmation = cmds;
+    }
+
+    /// Show what the push would change on the remote and only push once confirmed
+    fn confirm_git_push(&mut self, cmd: JjCommand) -> Result<()> {
+        let summary = match cmd.with_arg("--dry-run").run() {
+            Ok(summary) => summary,
+            // Let the real push report the error (and offer any retry)
+            Err(JjCommandError::Failed { .. }) => return self.queue_jj_command(cmd),
+            Err(JjCommandError::Other { err }) => return Err(err),
+        };
+
+        let mut lines = cmd.to_lines();
+        lines.extend(summary.into_text()?.lines);
+        if strip_ansi(&summary).contains("Nothing changed") {
+            self.clear();
+            self.info_list = Some(Text::from(lines));
+            return Ok(());
+        }
+        self.await_confirmation(lines, "Press Enter to push", vec![cmd]);
+        Ok(())
+    }
+
+    /// Show the operation an undo or redo reverts, the latest one if `None`,
+    /// and what it changed, and only run the command once confirmed
+    fn confirm_op_revert(
+        &mut self,
+        cmd: JjCommand,
+        operation: Option<&str>,
+        prompt: &str,
+    ) -> Result<()> {
+        let mut lines = cmd.to_lines();
+        for preview in [
+            JjCommand::op_log_entry(operation, self.global_args.clone()),
+            JjCommand::op_diff(operation, self.global_args.clone()),
+        ] {
+            match preview.run() {
+                Ok(output) => lines.extend(output.into_text()?.lines),
+                // Let the real command report the error
+                Err(JjCommandError::Failed { .. }) => return self.queue_jj_command(cmd),
+                Err(JjCommandError::Other { err }) => return Err(err),
+            }
+        }
+        self.await_confirmation(lines, prompt, vec![cmd]);
+        Ok(())
+    }
+
+    /// Show why a command failed along with a popup of ways to retry it and
+    /// the commands queued after it
+    fn offer_retry(
+        &mut self,
+        cmd: JjCommand,
+        output: Vec<Line<'static>>,
+        popup: crate::update::Popup,
+    ) -> Result<()> {
+        let remaining = std::mem::take(&mut self.queued_jj_commands);
+        self.clear();
+        self.info_list = Some(Text::from(output));
+        self.retry_commands = std::iter::once(cmd).chain(remaining).collect();
+        self.open_popup(popup)
+    }
+
+    /// Add paths jj printed to the workspace's `.gitignore` or the repo's
+    /// local exclude file, anchored at the workspace root
+    fn ignore_paths(&self, paths: &[String], ignore_file: IgnoreFile) -> Result<()> {
+        let root = strip_ansi(&JjCommand::workspace_root(self.global_args.clone()).run()?)
+            .trim()
+            .to_string();
+        let path = match ignore_file {
+            IgnoreFile::GitIgnore => Path::new(&root).join(".gitignore"),
+            IgnoreFile::GitExclude => {
+                let git_dir = strip_ansi(&JjCommand::git_root(self.global_args.clone()).run()?)
+                    .trim()
+                    .to_string();
+                Path::new(&git_dir).join("info").join("exclude")
+            }
+        };
+
+        // jj prints paths relative to the directory it runs in, which is ours
+        let cwd = std::env::current_dir()?;
+        let mut contents = std::fs::read_to_string(&path).unwrap_or_default();
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        for file in paths {
+            let file = cwd.join(file);
+            match file.strip_prefix(&root) {
+                Ok(relative) => {
+                    contents.push('/');
+                    contents.push_str(&relative.to_string_lossy().replace('\\', "/"));
+                }
+                Err(_) => contents.push_str(&file.to_string_lossy()),
+            }
+            contents.push('\n');
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, contents)
+            .map_err(|err| anyhow::anyhow!("Cannot write {}: {err}", path.display()))
+    }
+
+    /// What to do about a failed command, given why it failed
+    fn failure_hint(&self, kind: JjFailureKind) -> Option<String> {
+        let key = |message: Message, fallback: &str| {
+            self.keymap
+                .keys_for(message)
+                .unwrap_or_else(|| fallback.to_string())
+        };
+        match kind {
+            JjFailureKind::Immutable if !self.global_args.ignore_immutable => Some(format!(
+                "Press {} to allow rewriting immutable commits (--ignore-immutable) from now on",
+                key(Message::ToggleIgnoreImmutable, "the toggle-ignore-immutable key")
+            )),
+            JjFailureKind::Immutable => None,
+            JjFailureKind::Conflict => Some(
+                "Resolve the conflicts first, with `jj resolve` or by editing the conflicted commit"
+                    .to_string(),
+            ),
+            JjFailureKind::Auth => Some(
+                "The remote rejected the credentials; check your SSH agent or git credential helper"
+                    .to_string(),
+            ),
+            JjFailureKind::NoSuchRevision => Some(format!(
+                "The log may be out of date; press {} to refresh it",
+                key(Message::Refresh, "the refresh key")
+            )),
+            JjFailureKind::Divergence => Some(
+                "This would leave the change with several visible commits; abandon the other versions first"
+                    .to_string(),
+            ),
+            JjFailureKind::FileTooLarge => Some(
+                "Ignore the file or raise snapshot.max-new-file-size, then run the command again"
+                    .to_string(),
+            ),
+            JjFailureKind::Other => None,
+        }
+    }
+
+    /// Get ready to notify that a fetch or push finished, if it took long
+    /// enough for the user to have switched to something else
+    fn notify_when_long(&mut self, cmd: &JjCommand, elapsed: Duration, succeeded: bool) {
+        if self.notifications.enabled
+            && (cmd.is_git_fetch() || cmd.is_git_push())
+            && elapsed >= Duration::from_secs(self.notifications.min_seconds)
+        {
+            self.pending_notification = Some(Notification::new(
+                &cmd.to_subcommand_string(),
+                succeeded,
+                &self.display_repository,
+            ));
+        }
+    }
+
+    pub fn has_pending_notification(&self) -> bool {
+        self.pending_notification.is_some()
+    }
+
+    /// Send the pending notification if the terminal isn't focused, and drop
+    /// it either way
+    pub fn resolve_notification(&mut self) {
+        if let Some(notification) = self.pending_notification.take()
+            && !self.terminal_focused
+        {
+            notification.send();
+        }
+    }
+
     pub fn process_jj_command_queue(&mut self) -> Result<()> {
         if self.queued_jj_commands.is_empty() {
             return Ok(());
         }
+        self.dirty = true;
 
         let cmd = self.queued_jj_commands.remove(0);
-        let result = cmd.run();
+        if self.queue_start.is_none() {
+            self.queue_start = self
+                .current_op_id()?
+                .map(|op| (op, cmd.to_subcommand_string()));
+        }
 
         // Accumulate output from this command (with blank line separator)
         if !self.accumulated_command_output.is_empty() {
@@ -3442,18 +6908,114 @@ impl Model {
         }
         self.accumulated_command_output.extend(cmd.to_lines());
 
+        let mut hook_lines = Vec::new();
+        if let Some(reason) = self.run_script_hook("before", &cmd, &mut hook_lines)? {
+            self.queued_jj_commands.clear();
+            self.repo_size_before_gc = None;
+            self.accumulated_command_output.extend(hook_lines);
+            self.accumulated_command_output
+                .push(Line::styled(reason, Style::default().fg(Color::Yellow)));
+            let final_output = self.accumulated_command_output.clone();
+            self.output_history.push(final_output.clone());
+            self.clear();
+            self.info_list = Some(Text::from(final_output));
+            return Ok(());
+        }
+        self.accumulated_command_output.extend(hook_lines);
+        let started_at = Instant::now();
+        let result = cmd.run();
+        let elapsed = started_at.elapsed();
+
         match result {
             Ok(output) => {
+                if cmd.is_git_fetch() {
+                    self.fetched_bookmarks
+                        .extend(new_untracked_bookmarks(&strip_ansi(&output)));
+                }
                 self.accumulated_command_output
                     .extend(output.into_text()?.lines);
+                let mut hook_lines = Vec::new();
+                self.run_script_hook("after", &cmd, &mut hook_lines)?;
+                self.accumulated_command_output.extend(hook_lines);
 
                 if self.queued_jj_commands.is_empty() {
                     // All commands done, show final output and sync
+                    let mut toast = format!("✓ {}", cmd.to_subcommand_string());
+                    if let Some(size_before) = self.repo_size_before_gc.take() {
+                        let size_after = repo_store_size(&self.global_args.repository);
+                        let freed = format_size(size_before.saturating_sub(size_after));
+                        self.accumulated_command_output.push(Line::raw(""));
+                        self.accumulated_command_output.push(Line::raw(format!(
+                            "Repo size: {} → {} (freed {freed})",
+                            format_size(size_before),
+                            format_size(size_after),
+                        )));
+                        toast = format!("{toast}, freed {freed}");
+                    }
                     let final_output = self.accumulated_command_output.clone();
+                    self.output_history.push(final_output.clone());
+                    let describe = self.describe_after_queue;
+                    let push = self.push_after_queue.take();
+                    let queue_start = self.queue_start.take();
+                    let selected_change_id = self.get_selected_change_id().map(String::from);
                     self.clear();
                     self.info_list = Some(Text::from(final_output));
-                    if cmd.sync() {
+                    let op_after = match queue_start {
+                        Some(_) => self.current_op_id()?,
+                        None => None,
+                    };
+                    let repo_changed = match (&queue_start, &op_after) {
+                        (Some((before, _)), Some(after)) => before != after,
+                        _ => true,
+                    };
+                    // Closing an editor or merge tool without changes leaves the
+                    // log as it was
+                    if cmd.sync() && (repo_changed || !cmd.is_interactive()) {
                         self.sync()?;
+                        // Back where the command was started from, rather than @
+                        if cmd.is_interactive()
+                            && let Some(change_id) = selected_change_id
+                        {
+                            self.select_change_id(&change_id);
+                        }
+                    }
+                    if cmd.is_op_revert() {
+                        self.action_ops.pop();
+                    } else if let Some((before, command)) = queue_start
+                        && let Some(after) = op_after
+                        && repo_changed
+                    {
+                        self.action_ops.push(ActionOps {
+                            command,
+                            before,
+                            after,
+                        });
+                    }
+                    self.show_toast(toast);
+                    self.notify_when_long(&cmd, elapsed, true);
+                    if describe {
+                        // The new change is the working copy, whatever is selected
+                        let output = JjCommand::change_ids("@", self.global_args.clone()).run()?;
+                        if let Some(change_id) = strip_ansi(&output)
+                            .lines()
+                            .map(str::trim)
+                            .find(|line| !line.is_empty())
+                        {
+                            self.select_change_id(change_id);
+                            self.description_edit_change(
+                                change_id.to_string(),
+                                crate::update::DescribeMode::Default,
+                            )?;
+                        }
+                    }
+                    if let Some(push) = push {
+                        self.confirm_git_push(push)?;
+                    }
+                    let fetched_bookmarks = std::mem::take(&mut self.fetched_bookmarks);
+                    if !fetched_bookmarks.is_empty() {
+                        self.open_popup(crate::update::Popup::TrackFetched {
+                            remote_bookmarks: fetched_bookmarks,
+                        })?;
                     }
                 } else {
                     // More commands to run, update info_list to show next command
@@ -3462,13 +7024,58 @@ impl Model {
             }
             Err(err) => match err {
                 JjCommandError::Other { err } => return Err(err),
-                JjCommandError::Failed { stderr } => {
+                JjCommandError::Failed { stderr, kind } => {
                     // Command failed, show error with accumulated output
+                    self.repo_size_before_gc = None;
+                    self.fetched_bookmarks.clear();
+                    self.describe_after_queue = false;
+                    self.push_after_queue = None;
+                    self.queue_start = None;
                     self.accumulated_command_output
                         .extend(stderr.into_text()?.lines);
+                    if let Some(hint) = self.failure_hint(kind) {
+                        self.accumulated_command_output.push(Line::raw(""));
+                        self.accumulated_command_output.push(Line::styled(
+                            format!("⚠ {hint}"),
+                            Style::default().fg(Color::Yellow),
+                        ));
+                    }
                     let final_output = self.accumulated_command_output.clone();
-                    self.clear();
-                    self.info_list = Some(Text::from(final_output));
+                    self.output_history.push(final_output.clone());
+                    self.notify_when_long(&cmd, elapsed, false);
+                    if cmd.is_git_push() && stderr.contains("--allow-new") {
+                        self.await_confirmation(
+                            final_output,
+                            "Press Enter to retry with --allow-new",
+                            vec![cmd.with_arg("--allow-new")],
+                        );
+                    } else if kind == JjFailureKind::Immutable && !cmd.ignores_immutable() {
+                        let retry = format!(
+                            "Retry `{}` with --ignore-immutable",
+                            cmd.to_command_string()
+                        );
+                        let popup = crate::update::Popup::ImmutableRetry {
+                            choices: vec![retry, "Cancel".to_string()],
+                        };
+                        self.offer_retry(cmd, final_output, popup)?;
+                    } else if kind == JjFailureKind::FileTooLarge
+                        && let Some(files) = OversizedFiles::parse(&stderr)
+                    {
+                        let names = files.paths.join(", ");
+                        let choices = vec![
+                            format!("Add {names} to .gitignore and retry"),
+                            format!("Retry with snapshot.max-new-file-size={}", files.largest),
+                            format!(
+                                "Ignore {names} in this clone only (.git/info/exclude) and retry"
+                            ),
+                            "Cancel".to_string(),
+                        ];
+                        let popup = crate::update::Popup::SnapshotTooLarge { files, choices };
+                        self.offer_retry(cmd, final_output, popup)?;
+                    } else {
+                        self.clear();
+                        self.info_list = Some(Text::from(final_output));
+                    }
                 }
             },
         }
@@ -3477,6 +7084,240 @@ impl Model {
     }
 }
 
+/// Tools in `jj config list merge-tools` output that can merge, rather than
+/// only diff
+fn merge_tool_names(config_list: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for line in config_list.lines() {
+        let Some(rest) = line.strip_prefix("merge-tools.") else {
+            continue;
+        };
+        let (name, key) = match rest.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once("\".") {
+                Some((name, key)) => (name, key),
+                None => continue,
+            },
+            None => match rest.split_once('.') {
+                Some((name, key)) => (name, key),
+                None => continue,
+            },
+        };
+        if key.starts_with("merge-args") && !names.iter().any(|existing| existing == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Remote bookmarks `jj git fetch` reports as new and untracked, as
+/// `name@remote`
+fn new_untracked_bookmarks(fetch_output: &str) -> Vec<String> {
+    fetch_output
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("bookmark: ")?;
+            let (name, status) = rest.split_once(char::is_whitespace)?;
+            (status.contains("[new]") && status.contains("untracked")).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Why a name isn't a valid git ref name (which bookmarks are pushed as), if it
+/// isn't
+fn invalid_ref_name_reason(name: &str) -> Option<String> {
+    if name.chars().any(char::is_whitespace) {
+        return Some("cannot contain spaces".to_string());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| c.is_control() || "~^:?*[\\".contains(*c))
+    {
+        return Some(format!("cannot contain '{}'", c.escape_default()));
+    }
+    for sequence in ["..", "@{", "//"] {
+        if name.contains(sequence) {
+            return Some(format!("cannot contain '{sequence}'"));
+        }
+    }
+    if name == "@" {
+        return Some("cannot be '@'".to_string());
+    }
+    if let Some(c) = name.chars().next().filter(|c| matches!(c, '-' | '.' | '/')) {
+        return Some(format!("cannot start with '{c}'"));
+    }
+    if let Some(suffix) = [".lock", ".", "/"]
+        .into_iter()
+        .find(|suffix| name.ends_with(suffix))
+    {
+        return Some(format!("cannot end with '{suffix}'"));
+    }
+    None
+}
+
+/// The bookmark name at the start of an entry from `Model::bookmark_entries`
+fn bookmark_entry_name(entry: &str) -> String {
+    entry.split_whitespace().next().unwrap_or(entry).to_string()
+}
+
+/// Total size of the files in the repo store shared by the workspace's operations
+fn repo_store_size(workspace_root: &str) -> u64 {
+    let repo_path = Path::new(workspace_root).join(".jj").join("repo");
+    // Secondary workspaces store the path to the main repo instead
+    let repo_path = match std::fs::read_to_string(&repo_path) {
+        Ok(target) => Path::new(workspace_root).join(".jj").join(target.trim()),
+        Err(_) => repo_path,
+    };
+    dir_size(&repo_path)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// A hunk of a `jj diff --git` diff
+struct GitHunk {
+    old_start: u32,
+    old_len: u32,
+    /// Lines starting with ' ', '-', '+' or '\\'
+    lines: Vec<String>,
+}
+
+impl GitHunk {
+    fn parse_all(diff: &str) -> Vec<Self> {
+        let hunk_regex = regex::Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+\d+(?:,\d+)? @@").unwrap();
+        let mut hunks: Vec<Self> = Vec::new();
+        for line in diff.lines().map(strip_ansi) {
+            if let Some(captures) = hunk_regex.captures(&line) {
+                hunks.push(Self {
+                    old_start: captures[1].parse().unwrap_or(0),
+                    old_len: captures
+                        .get(2)
+                        .map_or(1, |len| len.as_str().parse().unwrap_or(0)),
+                    lines: Vec::new(),
+                });
+            } else if line.starts_with("diff --git ") {
+                // Lines up to the next hunk are file headers
+                hunks.push(Self {
+                    old_start: 0,
+                    old_len: 0,
+                    lines: Vec::new(),
+                });
+            } else if let Some(hunk) = hunks.last_mut()
+                && hunk.old_start > 0
+            {
+                hunk.lines.push(line);
+            }
+        }
+        hunks.retain(|hunk| hunk.old_start > 0);
+        hunks
+    }
+
+    fn old_end(&self) -> u32 {
+        (self.old_start + self.old_len).saturating_sub(1)
+    }
+
+    /// Old-side numbers of the lines the hunk removes or replaces
+    fn removed_line_numbers(&self) -> Vec<u32> {
+        let mut old_line = self.old_start;
+        let mut removed = Vec::new();
+        for line in &self.lines {
+            match line.chars().next() {
+                Some(' ') => old_line += 1,
+                Some('-') => {
+                    removed.push(old_line);
+                    old_line += 1;
+                }
+                _ => {}
+            }
+        }
+        removed
+    }
+
+    /// The old file with only this hunk applied
+    fn apply(&self, old_contents: &str) -> String {
+        let old_lines: Vec<&str> = old_contents.split_inclusive('\n').collect();
+        let skip = (self.old_start as usize)
+            .saturating_sub(1)
+            .min(old_lines.len());
+        let mut new_contents: String = old_lines[..skip].concat();
+
+        for (idx, line) in self.lines.iter().enumerate() {
+            let no_newline = self
+                .lines
+                .get(idx + 1)
+                .is_some_and(|next| next.starts_with('\\'));
+            let (kind, text) = line.split_at(line.len().min(1));
+            if kind == " " || kind == "+" {
+                new_contents.push_str(text);
+                if !no_newline {
+                    new_contents.push('\n');
+                }
+            }
+        }
+
+        let rest = (skip + self.old_len as usize).min(old_lines.len());
+        new_contents.push_str(&old_lines[rest..].concat());
+        new_contents
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Byte index of the grapheme cluster boundary before `idx`
+fn prev_grapheme_boundary(text: &str, idx: usize) -> usize {
+    text[..idx]
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(0, |(pos, _)| pos)
+}
+
+/// Byte index of the grapheme cluster boundary after `idx`
+fn next_grapheme_boundary(text: &str, idx: usize) -> usize {
+    text[idx..]
+        .graphemes(true)
+        .next()
+        .map_or(idx, |grapheme| idx + grapheme.len())
+}
+
+/// Byte index of the `col`th grapheme in `line`, or the end of the line if shorter
+fn grapheme_col_to_byte(line: &str, col: usize) -> usize {
+    line.grapheme_indices(true)
+        .nth(col)
+        .map_or(line.len(), |(pos, _)| pos)
+}
+
+/// Collapse pasted text onto one line for single-line inputs
+fn single_line(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn format_repository_for_display(repository: &str) -> String {
     let Ok(home_dir) = std::env::var("HOME") else {
         return repository.to_string();
@@ -0,0 +1,43 @@
+/// A desktop notification that a long command finished
+#[derive(Debug)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+}
+
+impl Notification {
+    pub fn new(command: &str, succeeded: bool, repository: &str) -> Self {
+        let summary = if succeeded {
+            format!("✓ {command} finished")
+        } else {
+            format!("✗ {command} failed")
+        };
+        Self {
+            summary,
+            body: format!("jjdag in {repository}"),
+        }
+    }
+
+    /// Show the notification, logging why if it can't be
+    #[cfg(feature = "notifications")]
+    pub fn send(&self) {
+        log::info!("Notifying: {}", self.summary);
+        if let Err(err) = notify_rust::Notification::new()
+            .appname("jjdag")
+            .summary(&self.summary)
+            .body(&self.body)
+            .show()
+        {
+            log::warn!("Cannot send a desktop notification: {err}");
+        }
+    }
+
+    #[cfg(not(feature = "notifications"))]
+    pub fn send(&self) {
+        log::info!(
+            "Not notifying, built without the notifications feature: {} ({})",
+            self.summary,
+            self.body
+        );
+    }
+}
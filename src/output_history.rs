@@ -0,0 +1,67 @@
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Text},
+};
+use std::collections::VecDeque;
+
+/// The outputs of the last commands run, newest first, to page back through
+/// after the info pane has moved on
+#[derive(Debug)]
+pub struct OutputHistory {
+    outputs: VecDeque<Vec<Line<'static>>>,
+    capacity: usize,
+    /// Which output is shown while paging, 0 being the newest
+    shown: Option<usize>,
+}
+
+impl OutputHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            outputs: VecDeque::with_capacity(capacity),
+            capacity,
+            shown: None,
+        }
+    }
+
+    /// Remember a command's output, forgetting the oldest one if full
+    pub fn push(&mut self, lines: Vec<Line<'static>>) {
+        self.shown = None;
+        if self.capacity == 0 || lines.is_empty() {
+            return;
+        }
+        self.outputs.push_front(lines);
+        self.outputs.truncate(self.capacity);
+    }
+
+    /// Start paging from the newest output again
+    pub fn stop_paging(&mut self) {
+        self.shown = None;
+    }
+
+    /// The output before the one shown, or the newest if not paging yet
+    pub fn older(&mut self) -> Option<Text<'static>> {
+        let idx = self.shown.map_or(0, |idx| idx + 1);
+        self.show(idx.min(self.outputs.len().checked_sub(1)?))
+    }
+
+    /// The output after the one shown, or the newest if not paging yet
+    pub fn newer(&mut self) -> Option<Text<'static>> {
+        let idx = self.shown.map_or(0, |idx| idx.saturating_sub(1));
+        self.show(idx)
+    }
+
+    fn show(&mut self, idx: usize) -> Option<Text<'static>> {
+        let lines = self.outputs.get(idx)?;
+        self.shown = Some(idx);
+        let mut text = Text::from(Line::styled(
+            format!(
+                "Output {} of {}, [ for older and ] for newer",
+                idx + 1,
+                self.outputs.len()
+            ),
+            Style::default().fg(Color::DarkGray),
+        ));
+        text.extend(lines.iter().cloned());
+        Some(text)
+    }
+}
@@ -0,0 +1,106 @@
+use indexmap::IndexMap;
+use std::{
+    cell::RefCell,
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+/// How long a stage took, over every time it ran
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timing {
+    pub count: u32,
+    pub total: Duration,
+    pub max: Duration,
+    pub last: Duration,
+}
+
+thread_local! {
+    /// Timings by stage in the order the stages first ran, while profiling
+    static TIMINGS: RefCell<Option<IndexMap<&'static str, Timing>>> = const { RefCell::new(None) };
+}
+
+/// Start timing the stages instrumented with [`timer`]
+pub fn enable() {
+    TIMINGS.set(Some(IndexMap::new()));
+}
+
+pub fn is_enabled() -> bool {
+    TIMINGS.with_borrow(Option::is_some)
+}
+
+/// Times a stage until dropped
+pub struct Timer {
+    stage: &'static str,
+    started_at: Instant,
+}
+
+/// Time a stage until the returned timer is dropped, if profiling
+pub fn timer(stage: &'static str) -> Option<Timer> {
+    is_enabled().then(|| Timer {
+        stage,
+        started_at: Instant::now(),
+    })
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let elapsed = self.started_at.elapsed();
+        TIMINGS.with_borrow_mut(|timings| {
+            let Some(timings) = timings else {
+                return;
+            };
+            let timing = timings.entry(self.stage).or_default();
+            timing.count += 1;
+            timing.total += elapsed;
+            timing.max = timing.max.max(elapsed);
+            timing.last = elapsed;
+        });
+    }
+}
+
+/// Each stage's timing so far, in the order they first ran
+pub fn timings() -> Vec<(&'static str, Timing)> {
+    TIMINGS.with_borrow(|timings| {
+        timings
+            .iter()
+            .flatten()
+            .map(|(stage, timing)| (*stage, *timing))
+            .collect()
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// The latest time of each stage, e.g. `render 2.1ms  refresh 40.3ms`
+pub fn overlay_text() -> String {
+    timings()
+        .iter()
+        .map(|(stage, timing)| format!("{stage} {:.1}ms", millis(timing.last)))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// A table of every stage's timings, or `None` if not profiling
+pub fn summary() -> Option<String> {
+    if !is_enabled() {
+        return None;
+    }
+    let mut summary = format!(
+        "{:<12} {:>7} {:>11} {:>10} {:>10}\n",
+        "stage", "count", "total ms", "mean ms", "max ms"
+    );
+    for (stage, timing) in timings() {
+        let mean = timing.total / timing.count.max(1);
+        let _ = writeln!(
+            summary,
+            "{stage:<12} {:>7} {:>11.1} {:>10.2} {:>10.2}",
+            timing.count,
+            millis(timing.total),
+            millis(mean),
+            millis(timing.max)
+        );
+    }
+    Some(summary)
+}
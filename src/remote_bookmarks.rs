@@ -0,0 +1,152 @@
+use crate::ansi::strip_ansi;
+use ratatui::widgets::ListState;
+
+/// A bookmark as a remote has it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteBookmark {
+    pub name: String,
+    pub remote: String,
+    pub tracked: bool,
+    /// Shortest change id of the target, or whether it's conflicted or deleted
+    pub target: String,
+    /// Full commit id of the target, empty unless it has exactly one
+    pub commit_id: String,
+    pub description: String,
+}
+
+impl RemoteBookmark {
+    /// `name@remote`, as jj commands take it
+    pub fn symbol(&self) -> String {
+        format!("{}@{}", self.name, self.remote)
+    }
+}
+
+/// A line of the remote bookmark browser
+#[derive(Debug, Clone, Copy)]
+pub enum RemoteBookmarkRow<'a> {
+    /// A remote, followed by its bookmarks
+    Remote {
+        name: &'a str,
+        count: usize,
+    },
+    Bookmark(&'a RemoteBookmark),
+}
+
+/// The bookmarks of every remote, browsed grouped by remote
+#[derive(Debug)]
+pub struct RemoteBookmarkBrowser {
+    /// Ordered by remote, then name
+    bookmarks: Vec<RemoteBookmark>,
+    pub list_state: ListState,
+}
+
+impl RemoteBookmarkBrowser {
+    pub fn new(mut bookmarks: Vec<RemoteBookmark>) -> Self {
+        bookmarks.sort_by(|a, b| (&a.remote, &a.name).cmp(&(&b.remote, &b.name)));
+        let mut list_state = ListState::default();
+        // The first bookmark, under its remote
+        list_state.select(Some(1));
+        Self {
+            bookmarks,
+            list_state,
+        }
+    }
+
+    /// Parse the output of `JjCommand::remote_bookmark_list`, leaving out the
+    /// git backend's own refs
+    pub fn parse(output: &str) -> Vec<RemoteBookmark> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let clean = strip_ansi(line);
+                let mut fields = clean.splitn(6, '\t');
+                let bookmark = RemoteBookmark {
+                    name: fields.next()?.to_string(),
+                    remote: fields.next()?.to_string(),
+                    tracked: fields.next()? == "tracked",
+                    target: fields.next()?.to_string(),
+                    commit_id: fields.next().unwrap_or_default().to_string(),
+                    description: fields.next().unwrap_or_default().to_string(),
+                };
+                (bookmark.remote != "git").then_some(bookmark)
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+
+    /// Each remote followed by its bookmarks
+    pub fn rows(&self) -> Vec<RemoteBookmarkRow<'_>> {
+        let mut rows = Vec::new();
+        for (idx, bookmark) in self.bookmarks.iter().enumerate() {
+            if idx == 0 || self.bookmarks[idx - 1].remote != bookmark.remote {
+                rows.push(RemoteBookmarkRow::Remote {
+                    name: &bookmark.remote,
+                    count: self
+                        .bookmarks
+                        .iter()
+                        .filter(|other| other.remote == bookmark.remote)
+                        .count(),
+                });
+            }
+            rows.push(RemoteBookmarkRow::Bookmark(bookmark));
+        }
+        rows
+    }
+
+    pub fn selected_row(&self) -> Option<RemoteBookmarkRow<'_>> {
+        self.rows()
+            .get(self.list_state.selected().unwrap_or_default())
+            .copied()
+    }
+
+    /// The selected bookmark, `None` on a remote's line
+    pub fn selected_bookmark(&self) -> Option<&RemoteBookmark> {
+        match self.selected_row()? {
+            RemoteBookmarkRow::Bookmark(bookmark) => Some(bookmark),
+            RemoteBookmarkRow::Remote { .. } => None,
+        }
+    }
+
+    /// The remote of the selected line
+    pub fn selected_remote(&self) -> Option<&str> {
+        match self.selected_row()? {
+            RemoteBookmarkRow::Bookmark(bookmark) => Some(&bookmark.remote),
+            RemoteBookmarkRow::Remote { name, .. } => Some(name),
+        }
+    }
+
+    /// Move the selection, clamped to the rows
+    pub fn move_selection(&mut self, delta: i32) {
+        let len = self.rows().len();
+        let selected = self
+            .list_state
+            .selected()
+            .unwrap_or_default()
+            .saturating_add_signed(delta as isize)
+            .min(len.saturating_sub(1));
+        self.list_state.select(Some(selected));
+    }
+
+    /// Take the bookmarks listed again, keeping the same one selected if it's
+    /// still there, otherwise the same line
+    pub fn reload(&mut self, bookmarks: Vec<RemoteBookmark>) {
+        let selected = self.selected_bookmark().map(RemoteBookmark::symbol);
+        let selected_idx = self.list_state.selected().unwrap_or_default();
+        let offset = self.list_state.offset();
+        let mut reloaded = Self::new(bookmarks);
+        let rows = reloaded.rows();
+        let idx = selected
+            .and_then(|symbol| {
+                rows.iter().position(|row| {
+                    matches!(row, RemoteBookmarkRow::Bookmark(bookmark) if bookmark.symbol() == symbol)
+                })
+            })
+            .unwrap_or(selected_idx.min(rows.len().saturating_sub(1)));
+        reloaded.list_state.select(Some(idx));
+        *reloaded.list_state.offset_mut() = offset;
+        *self = reloaded;
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use jjdag_core::{
+    config::{Config, expand_home},
+    state, terminal,
+    update::{Popup, PopupListState},
+    view::render_selection_popup,
+};
+use std::path::{Path, PathBuf};
+
+const INIT_COLOCATED_CHOICE: &str = "Run `jj git init --colocate` and open it";
+const QUIT_CHOICE: &str = "Quit";
+
+/// Repositories to offer when jjdag is launched outside of one: recently opened
+/// repositories that still exist, then jj repositories directly under the
+/// configured projects root
+pub fn candidate_repositories(config: &Config) -> Vec<String> {
+    let mut candidates: Vec<String> = state::load_recent_repositories()
+        .into_iter()
+        .filter(|repository| Path::new(repository).join(".jj").is_dir())
+        .collect();
+
+    if let Some(projects_root) = &config.projects_root
+        && let Ok(entries) = std::fs::read_dir(expand_home(projects_root))
+    {
+        let mut projects: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.join(".jj").is_dir())
+            .map(|path| path.to_string_lossy().into_owned())
+            .filter(|path| !candidates.contains(path))
+            .collect();
+        projects.sort();
+        candidates.extend(projects);
+    }
+
+    candidates
+}
+
+/// The root of the plain git repository (one with `.git` but no `.jj`)
+/// containing the path, if any
+pub fn find_git_only_repository(path: &str) -> Option<PathBuf> {
+    let path = std::fs::canonicalize(path).ok()?;
+    let root = path.ancestors().find(|dir| dir.join(".git").exists())?;
+    (!root.join(".jj").exists()).then(|| root.to_path_buf())
+}
+
+/// Ask whether to initialize jj colocated with the git repository
+pub fn confirm_init_colocated() -> Result<bool> {
+    let terminal = terminal::init_terminal()?;
+    let result = run_picker(
+        &terminal,
+        Popup::InitColocated {
+            choices: vec![INIT_COLOCATED_CHOICE.to_string(), QUIT_CHOICE.to_string()],
+        },
+    );
+    terminal::relinquish_terminal()?;
+    Ok(result?.as_deref() == Some(INIT_COLOCATED_CHOICE))
+}
+
+/// Show a fuzzy searchable list of repositories and return the chosen one, or
+/// `None` if the user cancelled
+pub fn pick_repository(candidates: Vec<String>) -> Result<Option<String>> {
+    let terminal = terminal::init_terminal()?;
+    let result = run_picker(
+        &terminal,
+        Popup::OpenRepository {
+            repositories: candidates,
+        },
+    );
+    terminal::relinquish_terminal()?;
+    result
+}
+
+fn run_picker(terminal: &terminal::Term, popup: Popup) -> Result<Option<String>> {
+    let mut filter = String::new();
+    let mut list_state = PopupListState::default();
+
+    loop {
+        terminal.borrow_mut().draw(|frame| {
+            render_selection_popup(frame, &popup, &filter, &list_state, frame.area())
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let count = popup.matched_items(&filter).len();
+        match key.code {
+            KeyCode::Enter => {
+                let matches = popup.matched_items(&filter);
+                return Ok(matches
+                    .get(list_state.selected())
+                    .map(|popup_match| popup_match.item.clone()));
+            }
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(None);
+            }
+            KeyCode::Down => list_state.select_next(count),
+            KeyCode::Up => list_state.select_prev(count),
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                list_state.select_next(count)
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                list_state.select_prev(count)
+            }
+            KeyCode::PageDown => list_state.page_down(count),
+            KeyCode::PageUp => list_state.page_up(count),
+            KeyCode::Home => list_state.select_first(),
+            KeyCode::End => list_state.select_last(count),
+            KeyCode::Backspace => {
+                filter.pop();
+                list_state.reset();
+            }
+            KeyCode::Char(c) => {
+                filter.push(c);
+                list_state.reset();
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{config::config_dir, model::GlobalArgs, shell_out::JjCommand};
+use anyhow::{Context, Result, bail};
+use rhai::{
+    AST, Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope,
+    module_resolvers::DummyModuleResolver,
+};
+use std::{cell::RefCell, fmt, rc::Rc};
+
+/// Keeps a runaway script from freezing the UI
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 32;
+
+/// jj subcommands a script may run with `jj()`, since they only read the repo
+const READ_ONLY_COMMANDS: [&[&str]; 14] = [
+    &["log"],
+    &["show"],
+    &["diff"],
+    &["status"],
+    &["evolog"],
+    &["root"],
+    &["file", "list"],
+    &["file", "show"],
+    &["file", "annotate"],
+    &["bookmark", "list"],
+    &["config", "get"],
+    &["op", "log"],
+    &["workspace", "root"],
+    &["workspace", "list"],
+];
+
+/// What a script sees of the selected log entry
+#[derive(Debug, Default)]
+pub struct ScriptSelection {
+    pub change_id: String,
+    pub commit_id: String,
+    pub bookmarks: Vec<String>,
+    pub subject: String,
+    pub file: Option<String>,
+}
+
+/// A key under `,` that calls a script function
+#[derive(Debug, Clone)]
+pub struct ScriptBinding {
+    pub key: char,
+    pub description: String,
+    function: String,
+}
+
+/// What a hook or bound function asked for while it ran
+#[derive(Debug, Default)]
+pub struct ScriptOutput {
+    /// Why the hooked command shouldn't run, when a before hook says so
+    pub blocked: Option<String>,
+    pub error: Option<String>,
+    /// jj commands to run afterwards, without hooks
+    pub queued: Vec<Vec<String>>,
+    pub toasts: Vec<String>,
+    pub info: Vec<String>,
+}
+
+/// Shared between the script API functions and the model side of a call
+#[derive(Default)]
+struct ScriptState {
+    command: Vec<String>,
+    selection: Option<ScriptSelection>,
+    bindings: Vec<ScriptBinding>,
+    output: ScriptOutput,
+}
+
+/// User hooks and bindings from `hooks.rhai` next to the config file
+pub struct Scripts {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: Rc<RefCell<ScriptState>>,
+    bindings: Vec<ScriptBinding>,
+}
+
+impl fmt::Debug for Scripts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scripts")
+            .field("bindings", &self.bindings)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scripts {
+    /// Compile the hooks file and run its top level, which registers bindings,
+    /// or return `None` if there is no hooks file
+    pub fn load(global_args: GlobalArgs) -> Result<Option<Self>> {
+        let Some(path) = config_dir().map(|dir| dir.join("hooks.rhai")) else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        log::info!("Loading hooks from {:?}", path);
+        let state = Rc::new(RefCell::new(ScriptState::default()));
+        let engine = new_engine(&state, global_args);
+        let ast = engine
+            .compile_file(path.clone())
+            .map_err(|err| anyhow::anyhow!("{err}"))
+            .with_context(|| format!("Invalid hooks file {}", path.display()))?;
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|err| anyhow::anyhow!("{err}"))
+            .with_context(|| format!("Error running hooks file {}", path.display()))?;
+
+        let bindings = std::mem::take(&mut state.borrow_mut().bindings);
+        for binding in &bindings {
+            if !has_function(&ast, &binding.function) {
+                bail!(
+                    "Key '{}' in {} is bound to '{}', which isn't a function without parameters",
+                    binding.key,
+                    path.display(),
+                    binding.function
+                );
+            }
+        }
+        // Anything the top level queued or printed isn't tied to a command
+        state.borrow_mut().output = ScriptOutput::default();
+
+        Ok(Some(Self {
+            engine,
+            ast,
+            scope,
+            state,
+            bindings,
+        }))
+    }
+
+    pub fn bindings(&self) -> &[ScriptBinding] {
+        &self.bindings
+    }
+
+    /// Run the `before_`/`after_` hook for a command, if the script has one.
+    /// The most specific hook wins, so `git push` runs `before_git_push`
+    /// rather than `before_git`.
+    pub fn run_hook(
+        &mut self,
+        phase: &str,
+        args: &[String],
+        selection: Option<ScriptSelection>,
+    ) -> ScriptOutput {
+        let subcommand: Vec<String> = args
+            .iter()
+            .take_while(|arg| !arg.starts_with('-'))
+            .take(2)
+            .map(|arg| arg.replace('-', "_"))
+            .collect();
+        let Some(function) = (1..=subcommand.len())
+            .rev()
+            .map(|len| format!("{phase}_{}", subcommand[..len].join("_")))
+            .find(|function| has_function(&self.ast, function))
+        else {
+            return ScriptOutput::default();
+        };
+
+        let result = self.call(&function, args, selection);
+        let mut output = std::mem::take(&mut self.state.borrow_mut().output);
+        match result {
+            Ok(value) if phase == "before" => {
+                if value.as_bool() == Ok(false) {
+                    output.blocked = Some(format!("Blocked by {function}"));
+                } else if let Ok(reason) = value.into_string() {
+                    output.blocked = Some(reason);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => output.error = Some(format!("{function}: {err}")),
+        }
+        output
+    }
+
+    /// Call the function bound to a key
+    pub fn run_binding(&mut self, idx: usize, selection: Option<ScriptSelection>) -> ScriptOutput {
+        let Some(binding) = self.bindings.get(idx) else {
+            return ScriptOutput::default();
+        };
+        let function = binding.function.clone();
+        let result = self.call(&function, &[], selection);
+        let mut output = std::mem::take(&mut self.state.borrow_mut().output);
+        if let Err(err) = result {
+            output.error = Some(format!("{function}: {err}"));
+        }
+        output
+    }
+
+    fn call(
+        &mut self,
+        function: &str,
+        args: &[String],
+        selection: Option<ScriptSelection>,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        {
+            let mut state = self.state.borrow_mut();
+            state.command = args.to_vec();
+            state.selection = selection;
+        }
+        // The top level already ran when loading, so don't bind keys again
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options(options, &mut self.scope, &self.ast, function, ())
+    }
+}
+
+fn has_function(ast: &AST, name: &str) -> bool {
+    ast.iter_functions()
+        .any(|function| function.name == name && function.params.is_empty())
+}
+
+/// An engine that can't load modules or touch anything but the functions
+/// registered here
+fn new_engine(state: &Rc<RefCell<ScriptState>>, global_args: GlobalArgs) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS);
+
+    // The terminal belongs to the UI, so printing goes to the info list
+    let print_state = state.clone();
+    engine.on_print(move |text| print_state.borrow_mut().output.info.push(text.to_string()));
+    engine.on_debug(|text, source, pos| log::debug!("hooks.rhai {source:?} {pos}: {text}"));
+
+    let command_state = state.clone();
+    engine.register_fn("command", move || -> Array {
+        command_state
+            .borrow()
+            .command
+            .iter()
+            .cloned()
+            .map(Dynamic::from)
+            .collect()
+    });
+
+    let selection_state = state.clone();
+    engine.register_fn("selection", move || -> Dynamic {
+        let state = selection_state.borrow();
+        let Some(selection) = &state.selection else {
+            return Dynamic::UNIT;
+        };
+        let mut map = Map::new();
+        map.insert("change_id".into(), selection.change_id.clone().into());
+        map.insert("commit_id".into(), selection.commit_id.clone().into());
+        let bookmarks: Array = selection
+            .bookmarks
+            .iter()
+            .cloned()
+            .map(Dynamic::from)
+            .collect();
+        map.insert("bookmarks".into(), bookmarks.into());
+        map.insert("subject".into(), selection.subject.clone().into());
+        map.insert(
+            "file".into(),
+            selection.file.clone().map_or(Dynamic::UNIT, Dynamic::from),
+        );
+        map.into()
+    });
+
+    engine.register_fn(
+        "jj",
+        move |args: Array| -> Result<String, Box<EvalAltResult>> {
+            let args = string_args(args)?;
+            if !READ_ONLY_COMMANDS.iter().any(|command| {
+                args.iter()
+                    .map(String::as_str)
+                    .take(command.len())
+                    .eq(command.iter().copied())
+            }) {
+                return Err(format!(
+                    "jj {} may change the repo, use queue() instead",
+                    args.join(" ")
+                )
+                .into());
+            }
+            JjCommand::from_script(args, true, global_args.clone())
+                .run()
+                .map(|output| crate::ansi::strip_ansi(&output))
+                .map_err(|err| err.to_string().into())
+        },
+    );
+
+    let queue_state = state.clone();
+    engine.register_fn(
+        "queue",
+        move |args: Array| -> Result<(), Box<EvalAltResult>> {
+            let args = string_args(args)?;
+            queue_state.borrow_mut().output.queued.push(args);
+            Ok(())
+        },
+    );
+
+    let toast_state = state.clone();
+    engine.register_fn("toast", move |message: &str| {
+        toast_state
+            .borrow_mut()
+            .output
+            .toasts
+            .push(message.to_string());
+    });
+
+    let bind_state = state.clone();
+    engine.register_fn(
+        "bind",
+        move |key: &str, description: &str, function: &str| -> Result<(), Box<EvalAltResult>> {
+            let mut chars = key.chars();
+            let (Some(key), None) = (chars.next(), chars.next()) else {
+                return Err(format!("bind() takes a single character key, not {key:?}").into());
+            };
+            bind_state.borrow_mut().bindings.push(ScriptBinding {
+                key,
+                description: description.to_string(),
+                function: function.to_string(),
+            });
+            Ok(())
+        },
+    );
+
+    engine
+}
+
+fn string_args(args: Array) -> Result<Vec<String>, Box<EvalAltResult>> {
+    args.into_iter()
+        .map(|arg| {
+            arg.into_string().map_err(|type_name| {
+                format!("jj arguments must be strings, not {type_name}").into()
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,263 @@
+use crate::shell_out::{self, JjCommandError, JjCommandOutput, JjRunner};
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use crossterm::event::Event;
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::VecDeque, fs::File, io::Write, path::Path, rc::Rc};
+
+/// A recorded session: the input events read and the jj commands run, in the
+/// order they happened. Written as TOML, one `[[entries]]` table at a time, so
+/// that a crash leaves everything up to it on disk.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Recording {
+    /// What jjdag was started with, so the replay starts the same way
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    repository: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    revset: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum Entry {
+    Event { event: Event },
+    Jj(JjRun),
+    File(FileRun),
+}
+
+/// A jj command as its runner saw it and what jj answered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JjRun {
+    args: Vec<String>,
+    stdout: String,
+    stderr: String,
+    /// Whether jj failed, with the error message in `stderr`
+    #[serde(default)]
+    failed: bool,
+}
+
+/// A file as `jj file show` printed it in a revision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileRun {
+    revision: String,
+    path: String,
+    /// Base64, as the file may be binary
+    #[serde(default)]
+    contents: String,
+    /// Why jj couldn't show the file, if it couldn't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// What playing back a recording does next
+pub enum ReplayStep {
+    Event(Event),
+    /// The last event was played, reported once
+    Finished,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<File>> = const { RefCell::new(None) };
+    static REPLAY_EVENTS: RefCell<Option<VecDeque<Event>>> = const { RefCell::new(None) };
+    /// Recorded files still to be read, while a recording is played back
+    static REPLAY_FILES: RefCell<Option<VecDeque<FileRun>>> = const { RefCell::new(None) };
+}
+
+/// Record the input events and jj commands of this session to a file
+pub fn start_recording(path: &Path, repository: &str, revset: &str) -> Result<()> {
+    let mut file =
+        File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+    let header = toml::to_string(&Recording {
+        repository: repository.to_string(),
+        revset: revset.to_string(),
+        entries: Vec::new(),
+    })?;
+    writeln!(
+        file,
+        "# jjdag session, play it back with `jjdag --replay <file>`\n{header}"
+    )
+    .with_context(|| format!("Cannot write {}", path.display()))?;
+    log::info!("Recording session to {}", path.display());
+    RECORDER.set(Some(file));
+    Ok(())
+}
+
+/// Stop recording, closing the file
+pub fn stop_recording() {
+    RECORDER.set(None);
+}
+
+fn record(entry: Entry) {
+    RECORDER.with_borrow_mut(|recorder| {
+        let Some(file) = recorder else {
+            return;
+        };
+        let result = toml::to_string(&Recording {
+            entries: vec![entry],
+            ..Recording::default()
+        })
+        .map_err(anyhow::Error::from)
+        .and_then(|table| Ok(writeln!(file, "{table}")?));
+        if let Err(err) = result {
+            log::error!("Cannot record session, stopping: {err}");
+            *recorder = None;
+        }
+    });
+}
+
+/// Record an input event read from the terminal, if recording
+pub fn record_event(event: &Event) {
+    record(Entry::Event {
+        event: event.clone(),
+    });
+}
+
+/// Record a jj command and its outcome, if recording
+pub fn record_jj(args: &[String], output: &Result<JjCommandOutput, JjCommandError>) {
+    if RECORDER.with_borrow(Option::is_none) {
+        return;
+    }
+    let (stdout, stderr, failed) = match output {
+        Ok(output) => (output.stdout.clone(), output.stderr.clone(), false),
+        Err(err) => (String::new(), err.to_string(), true),
+    };
+    record(Entry::Jj(JjRun {
+        args: args.to_vec(),
+        stdout,
+        stderr,
+        failed,
+    }));
+}
+
+/// Record a file read with `jj file show`, if recording
+pub fn record_file(revision: &str, path: &str, contents: &Result<Vec<u8>>) {
+    if RECORDER.with_borrow(Option::is_none) {
+        return;
+    }
+    let (contents, error) = match contents {
+        Ok(contents) => (BASE64.encode(contents), None),
+        Err(err) => (String::new(), Some(err.to_string())),
+    };
+    record(Entry::File(FileRun {
+        revision: revision.to_string(),
+        path: path.to_string(),
+        contents,
+        error,
+    }));
+}
+
+/// The recorded contents of a file in a revision, if a recording is being
+/// played back
+pub fn replay_file(revision: &str, path: &str) -> Option<Result<Vec<u8>>> {
+    REPLAY_FILES.with_borrow_mut(|files| {
+        let files = files.as_mut()?;
+        let Some(position) = files
+            .iter()
+            .position(|file| file.revision == revision && file.path == path)
+        else {
+            return Some(Err(anyhow!(
+                "`jj file show -r {revision} {path}` is not in the recording"
+            )));
+        };
+        let file = files.drain(..=position).next_back()?;
+        Some(match file.error {
+            Some(error) => Err(anyhow!(error)),
+            None => BASE64.decode(file.contents).map_err(anyhow::Error::from),
+        })
+    })
+}
+
+/// Whether a recording is being played back on this thread
+pub fn is_replaying() -> bool {
+    REPLAY_FILES.with_borrow(Option::is_some)
+}
+
+/// Refuse to run a program other than jj while a recording is played back:
+/// its output isn't in the recording, and it would act on the real files
+pub fn refuse_while_replaying(command_line: &str) -> Result<()> {
+    if is_replaying() {
+        bail!("Not running `{command_line}` while replaying a session");
+    }
+    Ok(())
+}
+
+/// Play back a recorded session: its events are read instead of the
+/// terminal's until they run out, and jj commands get the recorded answers
+/// instead of running jj. Other programs, like shell commands and editors,
+/// aren't run. Returns the repository and revset the session was recorded
+/// with.
+pub fn start_replay(path: &Path) -> Result<(String, String)> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let recording: Recording = toml::from_str(&contents)
+        .with_context(|| format!("Invalid session recording {}", path.display()))?;
+    let mut events = VecDeque::new();
+    let mut jj_runs = VecDeque::new();
+    let mut files = VecDeque::new();
+    for entry in recording.entries {
+        match entry {
+            Entry::Event { event } => events.push_back(event),
+            Entry::Jj(run) => jj_runs.push_back(run),
+            Entry::File(file) => files.push_back(file),
+        }
+    }
+    log::info!(
+        "Replaying {} events and {} jj commands from {}",
+        events.len(),
+        jj_runs.len(),
+        path.display()
+    );
+    REPLAY_EVENTS.set(Some(events));
+    REPLAY_FILES.set(Some(files));
+    shell_out::set_runner(Some(Rc::new(ReplayRunner(RefCell::new(jj_runs)))));
+    Ok((recording.repository, recording.revset))
+}
+
+/// The next recorded event to handle, if a recording is being played back
+pub fn next_replay_step() -> Option<ReplayStep> {
+    REPLAY_EVENTS.with_borrow_mut(|events| {
+        let step = match events.as_mut()?.pop_front() {
+            Some(event) => ReplayStep::Event(event),
+            None => ReplayStep::Finished,
+        };
+        if matches!(step, ReplayStep::Finished) {
+            *events = None;
+        }
+        Some(step)
+    })
+}
+
+/// Answers jj commands with the recorded answers, in order
+struct ReplayRunner(RefCell<VecDeque<JjRun>>);
+
+impl JjRunner for ReplayRunner {
+    fn run(&self, args: &[String]) -> Result<JjCommandOutput, JjCommandError> {
+        let mut runs = self.0.borrow_mut();
+        let Some(position) = runs.iter().position(|run| run.args == args) else {
+            return Err(JjCommandError::new_failed(format!(
+                "Error: `jj {}` is not in the recording",
+                args.join(" ")
+            )));
+        };
+        // The replay has gone differently from the recording, so the next
+        // recorded run of the same command is the best guess
+        if position > 0 {
+            log::warn!(
+                "Replay diverged: skipping {position} recorded jj commands to reach `jj {}`",
+                args.join(" ")
+            );
+            runs.drain(..position);
+        }
+        let run = runs.pop_front().expect("the run found is still there");
+        if run.failed {
+            Err(JjCommandError::new_failed(run.stderr))
+        } else {
+            Ok(JjCommandOutput {
+                stdout: run.stdout,
+                stderr: run.stderr,
+            })
+        }
+    }
+}
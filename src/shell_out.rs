@@ -1,22 +1,42 @@
 use crate::model::GlobalArgs;
+use crate::session;
 use crate::terminal::{self, Term};
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use ratatui::style::{Color, Style};
 use ratatui::text::{Line, Span};
 use regex::Regex;
 use std::{
-    env,
+    cell::RefCell,
     io::{Read, Write},
-    process::Command,
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    rc::Rc,
+    sync::LazyLock,
 };
 
-#[derive(Debug)]
+/// Read-only commands for browsing the repo, which can skip snapshotting the
+/// working copy
+const BROWSING_COMMANDS: [&[&str]; 7] = [
+    &["log"],
+    &["show"],
+    &["diff"],
+    &["evolog"],
+    &["file", "list"],
+    &["file", "show"],
+    &["file", "annotate"],
+];
+
+#[derive(Debug, Clone)]
 pub struct JjCommand {
     args: Vec<String>,
     global_args: GlobalArgs,
+    /// Settings passed with `--config` to this command only
+    config: Vec<String>,
     interactive_term: Option<Term>,
     return_output: ReturnOutput,
     sync: bool,
+    /// Whether script hooks run around this command when it's queued
+    run_hooks: bool,
 }
 
 impl JjCommand {
@@ -29,9 +49,11 @@ impl JjCommand {
         Self {
             args: args.iter().map(|a| a.to_string()).collect(),
             global_args,
+            config: Vec::new(),
             interactive_term,
             return_output,
             sync: true,
+            run_hooks: true,
         }
     }
 
@@ -44,16 +66,104 @@ impl JjCommand {
         Self {
             args: args.iter().map(|a| a.to_string()).collect(),
             global_args,
+            config: Vec::new(),
             interactive_term,
             return_output,
             sync: false,
+            run_hooks: true,
         }
     }
 
+    /// A command put together by a user script. Queries return what jj
+    /// printed and leave the log alone. Hooks don't run around either, so a
+    /// hook can't trigger itself.
+    pub fn from_script(args: Vec<String>, query: bool, global_args: GlobalArgs) -> Self {
+        let return_output = if query {
+            ReturnOutput::Stdout
+        } else {
+            ReturnOutput::Stderr
+        };
+        Self {
+            args,
+            global_args,
+            config: Vec::new(),
+            interactive_term: None,
+            return_output,
+            sync: !query,
+            run_hooks: false,
+        }
+    }
+
+    /// The same command run with `--ignore-immutable`
+    pub fn ignoring_immutable(&self) -> Self {
+        let mut cmd = self.clone();
+        cmd.global_args.ignore_immutable = true;
+        cmd
+    }
+
+    /// The same command with a `--config` setting, like `ui.color=never`
+    pub fn with_config(&self, setting: &str) -> Self {
+        let mut cmd = self.clone();
+        cmd.config.push(setting.to_string());
+        cmd
+    }
+
+    pub fn ignores_immutable(&self) -> bool {
+        self.global_args.ignore_immutable
+    }
+
+    /// Whether to run without snapshotting the working copy, which only the
+    /// commands for browsing the repo do
+    fn skips_snapshot(&self) -> bool {
+        self.global_args.ignore_working_copy
+            && BROWSING_COMMANDS.iter().any(|command| {
+                self.args
+                    .iter()
+                    .map(String::as_str)
+                    .take(command.len())
+                    .eq(command.iter().copied())
+            })
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn run_hooks(&self) -> bool {
+        self.run_hooks
+    }
+
     pub fn sync(&self) -> bool {
         self.sync
     }
 
+    pub fn is_git_push(&self) -> bool {
+        self.args
+            .starts_with(&["git".to_string(), "push".to_string()])
+    }
+
+    pub fn is_git_fetch(&self) -> bool {
+        self.args
+            .starts_with(&["git".to_string(), "fetch".to_string()])
+    }
+
+    /// Whether jjdag hands the terminal to this command while it runs
+    pub fn is_interactive(&self) -> bool {
+        self.interactive_term.is_some()
+    }
+
+    pub fn is_op_revert(&self) -> bool {
+        self.args
+            .starts_with(&["op".to_string(), "revert".to_string()])
+    }
+
+    /// The same command with an extra argument appended
+    pub fn with_arg(&self, arg: &str) -> Self {
+        let mut cmd = self.clone();
+        cmd.args.push(arg.to_string());
+        cmd
+    }
+
     pub fn to_lines(&self) -> Vec<Line<'static>> {
         let line = Line::from(vec![
             Span::styled("❯", Style::default().fg(Color::Yellow)),
@@ -64,17 +174,52 @@ impl JjCommand {
         vec![line, blank_line]
     }
 
+    /// Short name of the jj subcommand, e.g. "jj git push"
+    pub fn to_subcommand_string(&self) -> String {
+        let subcommand: Vec<&str> = self
+            .args
+            .iter()
+            .take_while(|arg| !arg.starts_with('-'))
+            .take(2)
+            .map(|arg| arg.as_str())
+            .collect();
+        format!("jj {}", subcommand.join(" "))
+    }
+
+    pub fn to_command_string(&self) -> String {
+        format!("jj {}", self.args.join(" "))
+    }
+
     pub fn run(&self) -> Result<String, JjCommandError> {
-        let output = match &self.interactive_term {
-            None => self.run_noninteractive(),
-            Some(term) => self.run_interactive(term),
-        }?;
+        let runner = RUNNER.with_borrow(Clone::clone);
+        let output = match (runner, &self.interactive_term) {
+            (Some(runner), _) => runner.run(&self.runner_args()),
+            (None, None) => self.run_noninteractive(),
+            (None, Some(term)) => self.run_interactive(term),
+        };
+        session::record_jj(&self.runner_args(), &output);
+        let output = output?;
         match self.return_output {
             ReturnOutput::Stdout => Ok(output.stdout),
             ReturnOutput::Stderr => Ok(output.stderr),
         }
     }
 
+    /// The arguments a [`JjRunner`] is given
+    fn runner_args(&self) -> Vec<String> {
+        let mut args = self.args.clone();
+        for setting in &self.config {
+            args.extend(["--config".to_string(), setting.clone()]);
+        }
+        if self.global_args.ignore_immutable {
+            args.push("--ignore-immutable".to_string());
+        }
+        if self.skips_snapshot() {
+            args.push("--ignore-working-copy".to_string());
+        }
+        args
+    }
+
     fn run_noninteractive(&self) -> Result<JjCommandOutput, JjCommandError> {
         log::info!("Running jj command: {}", self.args.join(" "));
         let mut command = self.base_command();
@@ -109,7 +254,7 @@ impl JjCommand {
         stderr_handle
             .read_to_end(&mut buf)
             .map_err(JjCommandError::new_other)?;
-        let stderr = strip_non_style_ansi(&String::from_utf8_lossy(&buf));
+        let stderr = crate::ansi::strip_non_style_ansi(&String::from_utf8_lossy(&buf));
         let status = child.wait().map_err(JjCommandError::new_other)?;
 
         terminal::takeover_terminal(term).map_err(JjCommandError::new_other)?;
@@ -163,11 +308,22 @@ impl JjCommand {
             &self.global_args.repository,
         ];
         command.args(args);
+        for config in crate::theme::palette().jj_config {
+            command.args(["--config", config]);
+        }
+
+        for setting in &self.config {
+            command.args(["--config", setting]);
+        }
 
         if self.global_args.ignore_immutable {
             command.arg("--ignore-immutable");
         }
 
+        if self.skips_snapshot() {
+            command.arg("--ignore-working-copy");
+        }
+
         command
     }
 
@@ -184,13 +340,83 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
     }
 
-    pub fn diff_summary(change_id: &str, global_args: GlobalArgs) -> Self {
-        let args = ["diff", "--summary", "--revisions", change_id];
+    /// The arguments picking what a diff of a change is against: its
+    /// parents, or only `from` if given
+    fn diff_range_args<'a>(change_id: &'a str, from: Option<&'a str>) -> Vec<&'a str> {
+        match from {
+            Some(from) => vec!["--from", from, "--to", change_id],
+            None => vec!["--revisions", change_id],
+        }
+    }
+
+    pub fn diff_summary(change_id: &str, from: Option<&str>, global_args: GlobalArgs) -> Self {
+        let mut args = vec!["diff", "--summary"];
+        args.extend(Self::diff_range_args(change_id, from));
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
     }
 
-    pub fn diff_file(change_id: &str, file: &str, global_args: GlobalArgs) -> Self {
-        let args = ["diff", "--color-words", "--revisions", change_id, file];
+    pub fn diff_stat(change_id: &str, from: Option<&str>, global_args: GlobalArgs) -> Self {
+        let mut args = vec!["diff", "--stat"];
+        args.extend(Self::diff_range_args(change_id, from));
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    pub fn diff_stat_from_to(from: &str, to: &str, global_args: GlobalArgs) -> Self {
+        let args = ["diff", "--stat", "--from", from, "--to", to];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    pub fn log_with_template(revset: &str, template: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "-r",
+            revset,
+            "--no-graph",
+            "--no-pager",
+            "-T",
+            template,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// The diffstat of each revision, without anything else about it
+    pub fn log_diff_stats(revset: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "-r",
+            revset,
+            "--no-graph",
+            "--no-pager",
+            "-T",
+            "''",
+            "--stat",
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// "change id<TAB>description" for each revision, newest first
+    pub fn log_summaries(revset: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "-r",
+            revset,
+            "--no-graph",
+            "--no-pager",
+            "-T",
+            r#"change_id.shortest(8) ++ "\t" ++ description.first_line() ++ "\n""#,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    pub fn diff_file(
+        change_id: &str,
+        from: Option<&str>,
+        file: &str,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let mut args = vec!["diff", "--color-words"];
+        args.extend(Self::diff_range_args(change_id, from));
+        args.push(file);
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
     }
 
@@ -218,19 +444,6 @@ impl JjCommand {
         Self::_new_skip_sync(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
-    pub fn describe(
-        change_id: &str,
-        ignore_immutable: bool,
-        global_args: GlobalArgs,
-        term: Term,
-    ) -> Self {
-        let mut args = vec!["describe", change_id];
-        if ignore_immutable {
-            args.push("--ignore-immutable");
-        }
-        Self::_new(&args, global_args, Some(term), ReturnOutput::Stderr)
-    }
-
     pub fn describe_with_message(
         change_id: &str,
         message: &str,
@@ -258,6 +471,35 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
     }
 
+    /// The short commit id of each revision and the names of its local
+    /// bookmarks, tab separated
+    pub fn local_bookmarks(revset: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "log",
+            "-r",
+            revset,
+            "-T",
+            r#"commit_id.shortest(8) ++ "\t" ++ local_bookmarks.map(|b| b.name()).join(" ") ++ "\n""#,
+            "--no-graph",
+            "--no-pager",
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Full change ids of the revisions, newest first
+    pub fn change_ids(revset: &str, global_args: GlobalArgs) -> Self {
+        let args = vec![
+            "log",
+            "-r",
+            revset,
+            "-T",
+            r#"change_id ++ "\n""#,
+            "--no-graph",
+            "--no-pager",
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
     pub fn duplicate(
         change_id: &str,
         destination_type: Option<&str>,
@@ -300,12 +542,12 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
-    pub fn abandon(change_id: &str, mode: Option<&str>, global_args: GlobalArgs) -> Self {
+    pub fn abandon(revset: &str, mode: Option<&str>, global_args: GlobalArgs) -> Self {
         let mut args = vec!["abandon"];
         if let Some(mode) = mode {
             args.push(mode);
         }
-        args.push(change_id);
+        args.push(revset);
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
@@ -326,6 +568,33 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    pub fn diff_git(change_id: &str, maybe_fileset: Option<&str>, global_args: GlobalArgs) -> Self {
+        let mut args = vec!["diff", "--git", "--revisions", change_id];
+        if let Some(fileset) = maybe_fileset {
+            args.push(fileset);
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    pub fn file_show(change_id: &str, file_path: &str, global_args: GlobalArgs) -> Self {
+        let args = ["file", "show", "-r", change_id, "--", file_path];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Full change id of the commit that last changed each line of a file
+    pub fn file_annotate_change_ids(revision: &str, file: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "file",
+            "annotate",
+            "-r",
+            revision,
+            "-T",
+            r#"commit.change_id() ++ "\n""#,
+            file,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
     pub fn revert(
         revision: &str,
         destination_type: &str,
@@ -341,6 +610,28 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    pub fn sign_with_key(revset: &str, key: &str, global_args: GlobalArgs) -> Self {
+        let args = ["sign", "-r", revset, "--key", key];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn config_get(name: &str, global_args: GlobalArgs) -> Self {
+        let args = ["config", "get", name];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Have snapshots of this repo ask watchman what changed instead of
+    /// scanning the working copy
+    pub fn watchman_enable(global_args: GlobalArgs) -> Self {
+        let args = ["config", "set", "--repo", "fsmonitor.backend", "watchman"];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn watchman_status(global_args: GlobalArgs) -> Self {
+        let args = ["debug", "watchman", "status"];
+        Self::_new_skip_sync(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
     pub fn show(change_id: &str, global_args: GlobalArgs, term: Term) -> Self {
         let args = ["show", change_id];
         Self::_new_skip_sync(&args, global_args, Some(term), ReturnOutput::Stderr)
@@ -361,11 +652,89 @@ impl JjCommand {
         Self::_new(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
+    /// Split without the diff editor, moving the file into the first commit
+    pub fn split_files(
+        change_id: &str,
+        message: &str,
+        files: &[String],
+        global_args: GlobalArgs,
+    ) -> Self {
+        let mut args = vec!["split", "-r", change_id, "-m", message];
+        args.extend(files.iter().map(String::as_str));
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn diff_name_only(change_id: &str, global_args: GlobalArgs) -> Self {
+        let args = ["diff", "--name-only", "--revisions", change_id];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
     pub fn undo(global_args: GlobalArgs) -> Self {
         let args = ["undo"];
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    /// An operation as `jj op log` shows it, the latest one if `None`
+    pub fn op_log_entry(operation: Option<&str>, global_args: GlobalArgs) -> Self {
+        let mut args = vec!["op", "log", "-n1"];
+        if let Some(operation) = operation {
+            args.extend(["--at-op", operation]);
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// The commits and bookmarks an operation changed, the latest one if `None`
+    pub fn op_diff(operation: Option<&str>, global_args: GlobalArgs) -> Self {
+        let mut args = vec!["op", "diff"];
+        if let Some(operation) = operation {
+            args.extend(["--operation", operation]);
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Description of the latest operation
+    pub fn op_description(global_args: GlobalArgs) -> Self {
+        let args = ["op", "log", "-n1", "--no-graph", "-T", "description"];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Id of the latest operation
+    pub fn op_id(global_args: GlobalArgs) -> Self {
+        let args = ["op", "log", "-n1", "--no-graph", "-T", "id"];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Ids of an operation and the ones before it, latest first
+    pub fn op_ids_at(operation: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "op",
+            "log",
+            "--at-op",
+            operation,
+            "--no-graph",
+            "-T",
+            r#"id ++ "\n""#,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Undo what an operation changed, keeping the operations after it
+    pub fn op_revert(operation: &str, global_args: GlobalArgs) -> Self {
+        let args = ["op", "revert", operation];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn op_abandon(operations: &str, global_args: GlobalArgs) -> Self {
+        let args = ["op", "abandon", operations];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    /// Delete unreachable objects right away rather than after the default grace period
+    pub fn util_gc(global_args: GlobalArgs) -> Self {
+        let args = ["util", "gc", "--expire=now"];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     pub fn redo(global_args: GlobalArgs) -> Self {
         let args = ["redo"];
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
@@ -379,14 +748,22 @@ impl JjCommand {
         Self::_new(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
+    pub fn commit_with_message(message: &str, global_args: GlobalArgs) -> Self {
+        let args = ["commit", "--message", message];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     pub fn rebase(
         source_type: &str,
         source: &str,
         destination_type: &str,
-        destination: &str,
+        destinations: &[&str],
         global_args: GlobalArgs,
     ) -> Self {
-        let args = vec!["rebase", source_type, source, destination_type, destination];
+        let mut args = vec!["rebase", source_type, source];
+        for destination in destinations {
+            args.extend([destination_type, destination]);
+        }
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
@@ -438,15 +815,8 @@ impl JjCommand {
         Self::_new(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
-    pub fn tug(global_args: GlobalArgs) -> Self {
-        let args = [
-            "bookmark",
-            "move",
-            "--from",
-            "heads(::@- & bookmarks())",
-            "--to",
-            "@",
-        ];
+    pub fn tug(tug_revset: &str, global_args: GlobalArgs) -> Self {
+        let args = ["bookmark", "move", "--from", tug_revset, "--to", "@"];
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
@@ -458,11 +828,37 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
-    pub fn resolve(change_id: &str, global_args: GlobalArgs, term: Term) -> Self {
-        let args = ["resolve", "-r", change_id];
+    pub fn resolve(
+        change_id: &str,
+        maybe_tool: Option<&str>,
+        global_args: GlobalArgs,
+        term: Term,
+    ) -> Self {
+        let mut args = vec!["resolve", "-r", change_id];
+        if let Some(tool) = maybe_tool {
+            args.extend(["--tool", tool]);
+        }
         Self::_new(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
+    /// The merge tool settings, including the ones jj knows by default
+    pub fn config_list_merge_tools(global_args: GlobalArgs) -> Self {
+        let args = ["config", "list", "--include-defaults", "merge-tools"];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Resolve a file's conflicts with one of jj's built-in `:ours` and
+    /// `:theirs` tools
+    pub fn resolve_with_tool(
+        change_id: &str,
+        tool: &str,
+        file_path: &str,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let args = ["resolve", "-r", change_id, "--tool", tool, file_path];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     pub fn evolog(change_id: &str, patch: bool, global_args: GlobalArgs, term: Term) -> Self {
         let mut args = vec!["evolog", "-r", change_id];
         if patch {
@@ -471,6 +867,45 @@ impl JjCommand {
         Self::_new_skip_sync(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
+    /// Versions of a change, newest first, one per line as
+    /// "commit id<TAB>age<TAB>operation<TAB>description"
+    pub fn evolog_entries(change_id: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "evolog",
+            "-r",
+            change_id,
+            "--no-graph",
+            "-T",
+            concat!(
+                r#"commit.commit_id().short(12) ++ "\t" ++ commit.committer().timestamp().ago()"#,
+                r#" ++ "\t" ++ operation.description() ++ "\t""#,
+                r#" ++ commit.description().first_line() ++ "\n""#,
+            ),
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// `evolog_entries` for jj versions whose evolog template is a plain commit
+    pub fn evolog_entries_legacy(change_id: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "evolog",
+            "-r",
+            change_id,
+            "--no-graph",
+            "-T",
+            concat!(
+                r#"commit_id.short(12) ++ "\t" ++ committer.timestamp().ago()"#,
+                r#" ++ "\t\t" ++ description.first_line() ++ "\n""#,
+            ),
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    pub fn diff_from_to(from: &str, to: &str, global_args: GlobalArgs, term: Term) -> Self {
+        let args = ["diff", "--from", from, "--to", to];
+        Self::_new_skip_sync(&args, global_args, Some(term), ReturnOutput::Stderr)
+    }
+
     pub fn interdiff(
         from: &str,
         to: &str,
@@ -495,6 +930,104 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    /// Untrack several paths, or a fileset such as `glob:build/**`
+    pub fn file_untrack_paths(paths: &[String], global_args: GlobalArgs) -> Self {
+        let mut args = vec!["file", "untrack"];
+        args.extend(paths.iter().map(String::as_str));
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn file_chmod(
+        change_id: &str,
+        file_path: &str,
+        executable: bool,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let mode = if executable { "x" } else { "n" };
+        let args = ["file", "chmod", mode, "-r", change_id, file_path];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    /// Replace a file in a revision with the contents of another file, using
+    /// `jj diffedit` with a copy command as the diff editor
+    pub fn diffedit_file(
+        change_id: &str,
+        file_path: &str,
+        contents_path: &Path,
+        global_args: GlobalArgs,
+    ) -> Self {
+        // Only the changed files are materialized, so the directory may be missing
+        let script = r#"mkdir -p "$(dirname "$2")" && cp "$1" "$2""#;
+        let edit_args = format!(
+            "merge-tools.jjdag-apply.edit-args=[\"-c\", {:?}, {:?}, {:?}, {:?}]",
+            script,
+            "sh",
+            contents_path.to_string_lossy(),
+            format!("$right/{file_path}"),
+        );
+        let args = [
+            "diffedit",
+            "-r",
+            change_id,
+            "--tool",
+            "jjdag-apply",
+            "--config",
+            "merge-tools.jjdag-apply.program=\"sh\"",
+            "--config",
+            &edit_args,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    /// Squash part of a change into another, selecting exactly the given
+    /// contents for one file and nothing from the others
+    pub fn squash_file_contents(
+        from_change_id: &str,
+        into_change_id: &str,
+        file_path: &str,
+        contents_path: &Path,
+        global_args: GlobalArgs,
+    ) -> Self {
+        // The selection starts out as the whole change, so reset it to the
+        // parent's files before putting the file in place
+        let script = concat!(
+            r#"find "$3" -mindepth 1 -maxdepth 1 -exec rm -rf {} + && cp -R "$2"/. "$3"/"#,
+            r#" && mkdir -p "$(dirname "$3/$4")" && cp "$1" "$3/$4""#,
+        );
+        let edit_args = format!(
+            "merge-tools.jjdag-select.edit-args=[\"-c\", {:?}, {:?}, {:?}, {:?}, {:?}, {:?}]",
+            script,
+            "sh",
+            contents_path.to_string_lossy(),
+            "$left",
+            "$right",
+            file_path,
+        );
+        let args = [
+            "squash",
+            "--from",
+            from_change_id,
+            "--into",
+            into_change_id,
+            "--use-destination-message",
+            "--interactive",
+            "--tool",
+            "jjdag-select",
+            "--config",
+            "ui.diff-instructions=false",
+            "--config",
+            "merge-tools.jjdag-select.program=\"sh\"",
+            "--config",
+            &edit_args,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    pub fn file_list(revision: &str, global_args: GlobalArgs) -> Self {
+        let args = ["file", "list", "-r", revision];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
     pub fn file_list_untracked(global_args: GlobalArgs) -> Self {
         let args = ["file", "list", "--untracked"];
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
@@ -530,17 +1063,16 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
     }
 
-    pub fn git_branch_list(remote: Option<&str>, global_args: GlobalArgs) -> Self {
-        let mut args = vec!["git", "branch", "list"];
+    pub fn git_push(
+        flag: Option<&str>,
+        value: Option<&str>,
+        remote: Option<&str>,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let mut args = vec!["git", "push"];
         if let Some(remote) = remote {
-            args.push("-r");
-            args.push(remote);
+            args.extend(["--remote", remote]);
         }
-        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
-    }
-
-    pub fn git_push(flag: Option<&str>, value: Option<&str>, global_args: GlobalArgs) -> Self {
-        let mut args = vec!["git", "push"];
         if let Some(flag) = flag {
             args.push(flag);
         }
@@ -550,6 +1082,28 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    /// Push only the named bookmarks, deleting the ones deleted locally from
+    /// the remote
+    pub fn git_push_bookmarks(
+        bookmarks: &[String],
+        remote: Option<&str>,
+        global_args: GlobalArgs,
+    ) -> Self {
+        // `-b` takes string patterns, so a name with `*` in it would match others
+        let patterns: Vec<String> = bookmarks
+            .iter()
+            .map(|bookmark| format!("exact:{bookmark}"))
+            .collect();
+        let mut args = vec!["git", "push"];
+        if let Some(remote) = remote {
+            args.extend(["--remote", remote]);
+        }
+        for pattern in &patterns {
+            args.extend(["-b", pattern]);
+        }
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     /// Fetch from a specific remote, optionally filtering by branch
     pub fn git_fetch_from_remote(
         remote: &str,
@@ -575,8 +1129,43 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
-    pub fn bookmark_list(global_args: GlobalArgs) -> Self {
-        let args = ["bookmark", "list"];
+    /// Bookmarks and the remote bookmarks tracking them, one per line as
+    /// "name<TAB>remote<TAB>target<TAB>ahead<TAB>behind" (remote empty for local
+    /// bookmarks, counts only for tracked remote bookmarks)
+    pub fn bookmark_list_detailed(global_args: GlobalArgs) -> Self {
+        let args = [
+            "bookmark",
+            "list",
+            "-T",
+            concat!(
+                r#"name ++ "\t" ++ if(remote, remote) ++ "\t""#,
+                r#" ++ if(normal_target, normal_target.change_id().shortest(8),"#,
+                r#" if(conflict, "conflicted", "deleted"))"#,
+                r#" ++ if(tracked, "\t" ++ tracking_ahead_count.lower()"#,
+                r#" ++ "\t" ++ tracking_behind_count.lower()) ++ "\n""#,
+            ),
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Every remote bookmark, one per line as
+    /// "name<TAB>remote<TAB>tracked<TAB>target<TAB>commit id<TAB>description"
+    /// (tracked empty if untracked, target "conflicted" or "deleted" with no
+    /// commit id or description if it has no single commit)
+    pub fn remote_bookmark_list(global_args: GlobalArgs) -> Self {
+        let args = [
+            "bookmark",
+            "list",
+            "--all-remotes",
+            "-T",
+            concat!(
+                r#"if(remote, name ++ "\t" ++ remote ++ "\t" ++ if(tracked, "tracked") ++ "\t""#,
+                r#" ++ if(normal_target, normal_target.change_id().shortest(8)"#,
+                r#" ++ "\t" ++ normal_target.commit_id()"#,
+                r#" ++ "\t" ++ normal_target.description().first_line(),"#,
+                r#" if(conflict, "conflicted", "deleted")) ++ "\n")"#,
+            ),
+        ];
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
     }
 
@@ -622,6 +1211,52 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
+    /// Move the named bookmarks to a revision in one go
+    pub fn bookmark_move_names(
+        bookmark_names: &[String],
+        to_change_id: &str,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let mut args = vec!["bookmark", "move"];
+        args.extend(bookmark_names.iter().map(String::as_str));
+        args.extend(["--to", to_change_id]);
+        Self::_new(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
+    /// The ancestors and descendants of a commit among the given revisions,
+    /// and the commit itself, one short commit id per line
+    pub fn lineage(commit_id: &str, revset: &str, global_args: GlobalArgs) -> Self {
+        let revset = format!("(::{commit_id} | {commit_id}::) & ({revset})");
+        let args = [
+            "log",
+            "--revisions",
+            &revset,
+            "--no-graph",
+            "-T",
+            r#"commit_id.shortest(8) ++ "\n""#,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Names of all tags, one per line
+    pub fn tag_names(global_args: GlobalArgs) -> Self {
+        let args = ["tag", "list", "-T", r#"name ++ "\n""#];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
+    /// Names of the local bookmarks pointing at a revision, one per line
+    pub fn bookmark_names_at(change_id: &str, global_args: GlobalArgs) -> Self {
+        let args = [
+            "bookmark",
+            "list",
+            "-r",
+            change_id,
+            "-T",
+            r#"if(remote, "", name ++ "\n")"#,
+        ];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
     pub fn bookmark_rename(
         old_bookmark_name: &str,
         new_bookmark_name: &str,
@@ -636,8 +1271,9 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
-    pub fn bookmark_track(bookmark_at_remote: &str, global_args: GlobalArgs) -> Self {
-        let args = ["bookmark", "track", bookmark_at_remote];
+    pub fn bookmark_track(bookmarks_at_remote: &[String], global_args: GlobalArgs) -> Self {
+        let mut args = vec!["bookmark", "track"];
+        args.extend(bookmarks_at_remote.iter().map(String::as_str));
         Self::_new(&args, global_args, None, ReturnOutput::Stderr)
     }
 
@@ -651,6 +1287,12 @@ impl JjCommand {
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
     }
 
+    /// Path of the git repository backing the jj repository
+    pub fn git_root(global_args: GlobalArgs) -> Self {
+        let args = ["git", "root"];
+        Self::_new(&args, global_args, None, ReturnOutput::Stdout)
+    }
+
     pub fn workspace_root(global_args: GlobalArgs) -> Self {
         let args = ["workspace", "root"];
         Self::_new(&args, global_args, None, ReturnOutput::Stdout)
@@ -676,6 +1318,16 @@ impl JjCommand {
         Self::_new_skip_sync(&args, global_args, Some(term), ReturnOutput::Stderr)
     }
 
+    pub fn workspace_add_at(
+        path: &str,
+        name: &str,
+        revision: &str,
+        global_args: GlobalArgs,
+    ) -> Self {
+        let args = ["workspace", "add", "--name", name, "-r", revision, path];
+        Self::_new_skip_sync(&args, global_args, None, ReturnOutput::Stderr)
+    }
+
     pub fn ensure_valid_repo(repository: &str) -> Result<String, JjCommandError> {
         log::debug!("Validating repository: {}", repository);
         let args = [
@@ -708,9 +1360,26 @@ impl JjCommand {
             Err(JjCommandError::new_failed(stderr))
         }
     }
+
+    /// Turn an existing git repository into a colocated jj repository
+    pub fn git_init_colocate(path: &str) -> Result<(), JjCommandError> {
+        log::info!("Initializing colocated jj repository in: {}", path);
+        let output = Command::new("jj")
+            .args(["git", "init", "--colocate", path])
+            .output()
+            .map_err(JjCommandError::new_other)?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(JjCommandError::new_failed(
+                String::from_utf8_lossy(&output.stderr).into(),
+            ))
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum ReturnOutput {
     Stdout,
     Stderr,
@@ -718,13 +1387,114 @@ enum ReturnOutput {
 
 #[derive(Debug)]
 pub enum JjCommandError {
-    Failed { stderr: String },
-    Other { err: anyhow::Error },
+    /// jj ran and reported an error
+    Failed {
+        stderr: String,
+        kind: JjFailureKind,
+    },
+    Other {
+        err: anyhow::Error,
+    },
+}
+
+/// Why jj failed, as far as its error message tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JjFailureKind {
+    /// The command would rewrite an immutable commit
+    Immutable,
+    /// The command refuses to work on conflicted commits
+    Conflict,
+    /// The git remote didn't accept our credentials
+    Auth,
+    /// A revision in the command doesn't exist (any more)
+    NoSuchRevision,
+    /// The command would leave a change with several visible commits
+    Divergence,
+    /// New files in the working copy are over `snapshot.max-new-file-size`
+    FileTooLarge,
+    Other,
+}
+
+impl JjFailureKind {
+    fn parse(stderr: &str) -> Self {
+        let stderr = crate::ansi::strip_ansi(stderr).to_lowercase();
+        let mentions = |patterns: &[&str]| patterns.iter().any(|pattern| stderr.contains(pattern));
+        if mentions(&["too large to be snapshotted", "refused to snapshot"]) {
+            Self::FileTooLarge
+        } else if mentions(&["is immutable", "immutable commit"]) {
+            Self::Immutable
+        } else if mentions(&["doesn't exist", "does not exist", "no such revision"]) {
+            Self::NoSuchRevision
+        } else if mentions(&[
+            "permission denied",
+            "authentication",
+            "could not read username",
+            "publickey",
+            "credentials",
+        ]) {
+            Self::Auth
+        } else if mentions(&["divergen"]) {
+            Self::Divergence
+        } else if mentions(&["conflict"]) {
+            Self::Conflict
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// New files jj refused to snapshot for being over `snapshot.max-new-file-size`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OversizedFiles {
+    /// As jj printed them, absolute or relative to the current directory
+    pub paths: Vec<String>,
+    /// Size in bytes of the largest, which a raised limit has to allow
+    pub largest: u64,
+}
+
+impl OversizedFiles {
+    /// Read the files from jj's error, which lists them as either
+    /// `The file '<path>' is too large to be snapshotted: ...` or, in newer
+    /// versions, `  <path>: 15.0MiB (15730640 bytes); the maximum size ...`
+    pub fn parse(stderr: &str) -> Option<Self> {
+        static OLD_FORMAT: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"file '(.+?)' is too large to be snapshotted").unwrap());
+        static NEW_FORMAT: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"(?m)^\s+(.+?): [\d.]+ ?\w+ \((\d+) bytes\); the maximum size").unwrap()
+        });
+        static SUGGESTED_LIMIT: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"snapshot\.max-new-file-size[= ](\d+)").unwrap());
+
+        let stderr = crate::ansi::strip_ansi(stderr);
+        let mut paths = Vec::new();
+        let mut sizes = Vec::new();
+        for captures in OLD_FORMAT.captures_iter(&stderr) {
+            paths.push(captures[1].to_string());
+        }
+        for captures in NEW_FORMAT.captures_iter(&stderr) {
+            paths.push(captures[1].to_string());
+            sizes.extend(captures[2].parse::<u64>().ok());
+        }
+        sizes.extend(
+            SUGGESTED_LIMIT
+                .captures_iter(&stderr)
+                .filter_map(|captures| captures[1].parse::<u64>().ok()),
+        );
+        if paths.is_empty() {
+            return None;
+        }
+        paths.dedup();
+        Some(Self {
+            paths,
+            largest: sizes.into_iter().max()?,
+        })
+    }
 }
 
 impl JjCommandError {
-    fn new_failed(stderr: String) -> Self {
+    pub fn new_failed(stderr: String) -> Self {
         Self::Failed {
+            kind: JjFailureKind::parse(&stderr),
             stderr: stderr.trim().to_string(),
         }
     }
@@ -737,7 +1507,7 @@ impl JjCommandError {
 impl std::fmt::Display for JjCommandError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Failed { stderr } => {
+            Self::Failed { stderr, .. } => {
                 write!(f, "{stderr}")
             }
             Self::Other { err } => err.fmt(f),
@@ -747,6 +1517,173 @@ impl std::fmt::Display for JjCommandError {
 
 impl std::error::Error for JjCommandError {}
 
+/// Contents of a file as it is in a revision
+pub fn file_contents(repository: &str, change_id: &str, file_path: &str) -> Result<Vec<u8>> {
+    if let Some(contents) = session::replay_file(change_id, file_path) {
+        return contents;
+    }
+    let contents = jj_file_show(repository, change_id, file_path);
+    session::record_file(change_id, file_path, &contents);
+    contents
+}
+
+fn jj_file_show(repository: &str, change_id: &str, file_path: &str) -> Result<Vec<u8>> {
+    let output = Command::new("jj")
+        .args([
+            "file",
+            "show",
+            "--color=never",
+            "--repository",
+            repository,
+            "-r",
+            change_id,
+            "--",
+            file_path,
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to get file content: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Open a file in $EDITOR, at a line if given, and wait for the editor to exit
+pub fn run_editor(terminal: &Term, file: &Path, line_num: Option<u32>) -> Result<()> {
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
+    session::refuse_while_replaying(&format!("{editor} {}", file.display()))?;
+    // Handle editor commands with arguments like "code --wait" or "vim -u NONE"
+    let mut editor_parts = editor.split_whitespace();
+    let editor_bin = editor_parts.next().unwrap_or("vim");
+    let mut command = Command::new(editor_bin);
+    command.args(editor_parts);
+
+    let editor_name = Path::new(editor_bin)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(editor_bin);
+    match (line_num, editor_name) {
+        (Some(line), "hx" | "helix" | "subl" | "zed") => {
+            command.arg(format!("{}:{line}", file.display()))
+        }
+        (Some(line), "code" | "codium") => command
+            .arg("--goto")
+            .arg(format!("{}:{line}", file.display())),
+        (Some(line), _) => command.arg(format!("+{line}")).arg(file),
+        (None, _) => command.arg(file),
+    };
+
+    terminal::relinquish_terminal()?;
+    let status = command.status();
+    terminal::takeover_terminal(terminal)?;
+    let status = status.map_err(|err| anyhow!("Cannot run {editor_bin}: {err}"))?;
+    if !status.success() {
+        bail!("{editor_bin} exited with {status}");
+    }
+    Ok(())
+}
+
+/// Run a shell command line in the terminal and wait for it to exit
+pub fn run_shell_interactive(terminal: &Term, command_line: &str) -> Result<()> {
+    session::refuse_while_replaying(command_line)?;
+    log::info!("Running shell command: {command_line}");
+    terminal::relinquish_terminal()?;
+    let status = Command::new("sh").args(["-c", command_line]).status();
+    terminal::takeover_terminal(terminal)?;
+    let status = status.map_err(|err| anyhow!("Cannot run {command_line}: {err}"))?;
+    if !status.success() {
+        bail!("{command_line} exited with {status}");
+    }
+    Ok(())
+}
+
+/// Run a shell command in a directory and capture its stdout and stderr together
+pub fn run_shell(dir: &str, command_line: &str) -> Result<(ExitStatus, String)> {
+    session::refuse_while_replaying(command_line)?;
+    log::info!("Running shell command: {command_line}");
+    let output = Command::new("sh")
+        .args(["-c", &format!("exec 2>&1; {command_line}")])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| anyhow!("Cannot run {command_line}: {err}"))?;
+    Ok((
+        output.status,
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    ))
+}
+
+/// The files matched by the ignore rules of the git repository, whether or
+/// not they are tracked
+pub fn git_check_ignored(git_dir: &str, work_tree: &str, files: &[String]) -> Result<Vec<String>> {
+    session::refuse_while_replaying("git check-ignore")?;
+    let mut child = Command::new("git")
+        .args(["--git-dir", git_dir, "--work-tree", work_tree])
+        .args(["check-ignore", "--no-index", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(files.join("\n").as_bytes())?;
+    }
+    // Exits with 1 when nothing is ignored
+    let output = child.wait_with_output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
+/// Secret keys gpg can sign with, as "<key id>  <first user id>"
+pub fn list_gpg_secret_keys() -> Vec<String> {
+    let Ok(output) = Command::new("gpg")
+        .args(["--list-secret-keys", "--with-colons"])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    let mut keys = Vec::new();
+    let mut key_id: Option<String> = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        match fields.first() {
+            Some(&"sec") => key_id = fields.get(4).map(|id| id.to_string()),
+            Some(&"uid") => {
+                if let (Some(id), Some(uid)) = (key_id.take(), fields.get(9)) {
+                    keys.push(format!("{id}  {uid}"));
+                }
+            }
+            _ => {}
+        }
+    }
+    keys
+}
+
+/// Public keys in `~/.ssh`, which jj's ssh backend accepts as signing keys
+pub fn list_ssh_public_keys() -> Vec<String> {
+    let Some(ssh_dir) =
+        std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".ssh"))
+    else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(ssh_dir) else {
+        return Vec::new();
+    };
+    let mut keys: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pub"))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    keys.sort();
+    keys
+}
+
 /// Parse the workspace_store/index file to find a workspace's path.
 /// The file uses a simple protobuf-like format where each entry is:
 ///   0a <total_len> 0a <name_len> <name> 12 <path_len> <path>
@@ -881,7 +1818,6 @@ pub fn update_workspace_path(
         let path_len = *contents.get(i).ok_or("Unexpected end of file")? as usize;
         i += 1;
         let old_path_start = i;
-        i += path_len; // Skip old path bytes
         let old_path =
             String::from_utf8_lossy(&contents[old_path_start..old_path_start + path_len]);
         log::debug!("old_path='{}'", old_path);
@@ -919,62 +1855,28 @@ pub fn update_workspace_path(
     }
 }
 
-struct JjCommandOutput {
-    stdout: String,
-    stderr: String,
+pub struct JjCommandOutput {
+    pub stdout: String,
+    pub stderr: String,
 }
 
-pub fn get_input_from_editor(
-    interactive_term: Term,
-    starting_text: Option<&str>,
-    help_text: Option<&str>,
-) -> Result<Option<String>> {
-    // Create temp file
-    let mut temp_file = tempfile::Builder::new()
-        .suffix(".jjdescription")
-        .tempfile()?;
-    if let Some(text) = starting_text {
-        writeln!(temp_file, "{text}")?;
-        temp_file.flush()?;
-    }
-    if let Some(text) = help_text {
-        writeln!(temp_file, "\n\nJJ: {text}")?;
-        writeln!(
-            temp_file,
-            "JJ: Lines starting with \"JJ:\" (like this one) will be removed."
-        )?;
-
-        temp_file.flush()?;
-    }
-    let temp_path = temp_file.path().to_path_buf();
-
-    // Open editor in temp file
-    let editor = env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
-    terminal::relinquish_terminal()?;
-    let status = Command::new(&editor).arg(&temp_path).status()?;
-    terminal::takeover_terminal(&interactive_term)?;
-    if !status.success() {
-        anyhow::bail!("Editor exited with non-zero status");
-    }
+/// Runs the commands `JjCommand` builds. jjdag spawns the jj binary unless
+/// another runner is installed with [`set_runner`], as tests do to answer
+/// with canned output.
+pub trait JjRunner {
+    /// Run jj with a command's arguments, followed by the settings it was
+    /// given with `with_config`, `--ignore-immutable` if it ignores
+    /// immutability and `--ignore-working-copy` if it skips the snapshot, but
+    /// not the `--color` and `--config` flags always added when spawning jj
+    fn run(&self, args: &[String]) -> Result<JjCommandOutput, JjCommandError>;
+}
 
-    // Remove all lines starting with "JJ: "
-    let contents = std::fs::read_to_string(&temp_path)?;
-    let contents: String = contents
-        .lines()
-        .filter(|line| !line.starts_with("JJ:"))
-        .collect::<Vec<&str>>()
-        .join("\n")
-        .trim()
-        .to_string();
-    if contents.is_empty() {
-        Ok(None)
-    } else {
-        Ok(Some(contents))
-    }
+thread_local! {
+    static RUNNER: RefCell<Option<Rc<dyn JjRunner>>> = const { RefCell::new(None) };
 }
 
-fn strip_non_style_ansi(str: &str) -> String {
-    let non_style_ansi_regex =
-        Regex::new(r"\x1b(\[[0-9;?]*[ -/]*([@-l]|[n-~])|\].*?(\x07|\x1b\\)|P.*?\x1b\\)").unwrap();
-    non_style_ansi_regex.replace_all(str, "").to_string()
+/// Run the commands on this thread with `runner` instead of the jj binary,
+/// or with the binary again when `None`
+pub fn set_runner(runner: Option<Rc<dyn JjRunner>>) {
+    RUNNER.set(runner);
 }
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result, anyhow};
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+};
+
+const RECENT_REPOSITORIES_FILE: &str = "recent_repositories";
+const MAX_RECENT_REPOSITORIES: usize = 20;
+const SAVED_REVSETS_FILE: &str = "saved_revsets";
+const VIEWED_FILES_FILE: &str = "viewed_files";
+const NOTES_FILE: &str = "notes";
+
+/// Directory for state jjdag keeps between runs: `$XDG_STATE_HOME/jjdag`
+/// (falling back to `~/.local/state/jjdag`)
+pub fn state_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(base.join("jjdag"))
+}
+
+/// Recently opened repositories, most recent first
+pub fn load_recent_repositories() -> Vec<String> {
+    let Some(path) = state_dir().map(|dir| dir.join(RECENT_REPOSITORIES_FILE)) else {
+        return Vec::new();
+    };
+    std::fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Move the repository to the front of the recent repositories list
+pub fn record_recent_repository(repository: &str) -> Result<()> {
+    let dir = state_dir().ok_or_else(|| anyhow!("Cannot determine state directory"))?;
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Cannot create state directory {}", dir.display()))?;
+
+    let mut repositories = load_recent_repositories();
+    repositories.retain(|recent| recent != repository);
+    repositories.insert(0, repository.to_string());
+    repositories.truncate(MAX_RECENT_REPOSITORIES);
+
+    let path = dir.join(RECENT_REPOSITORIES_FILE);
+    std::fs::write(&path, repositories.join("\n") + "\n")
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Revsets saved under a name, most recently saved first
+pub fn load_saved_revsets() -> Vec<(String, String)> {
+    let Some(path) = state_dir().map(|dir| dir.join(SAVED_REVSETS_FILE)) else {
+        return Vec::new();
+    };
+    std::fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| line.split_once('\t'))
+                .map(|(name, revset)| (name.to_string(), revset.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Save a revset under a name, replacing any revset saved under it before
+pub fn save_revset(name: &str, revset: &str) -> Result<()> {
+    let dir = state_dir().ok_or_else(|| anyhow!("Cannot determine state directory"))?;
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Cannot create state directory {}", dir.display()))?;
+
+    let mut saved = load_saved_revsets();
+    saved.retain(|(saved_name, _)| saved_name != name);
+    saved.insert(0, (name.to_string(), revset.to_string()));
+
+    let contents: String = saved
+        .iter()
+        .map(|(name, revset)| format!("{name}\t{revset}\n"))
+        .collect();
+    let path = dir.join(SAVED_REVSETS_FILE);
+    std::fs::write(&path, contents).with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Files marked viewed while reviewing, by change id
+pub fn load_viewed_files() -> HashMap<String, BTreeSet<String>> {
+    let Some(path) = state_dir().map(|dir| dir.join(VIEWED_FILES_FILE)) else {
+        return HashMap::new();
+    };
+    let mut viewed_files: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for line in std::fs::read_to_string(path).unwrap_or_default().lines() {
+        if let Some((change_id, path)) = line.split_once('\t') {
+            viewed_files
+                .entry(change_id.to_string())
+                .or_default()
+                .insert(path.to_string());
+        }
+    }
+    viewed_files
+}
+
+/// Replace the files marked viewed
+pub fn save_viewed_files(viewed_files: &HashMap<String, BTreeSet<String>>) -> Result<()> {
+    let dir = state_dir().ok_or_else(|| anyhow!("Cannot determine state directory"))?;
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Cannot create state directory {}", dir.display()))?;
+
+    let contents: String = viewed_files
+        .iter()
+        .flat_map(|(change_id, paths)| {
+            paths
+                .iter()
+                .map(move |path| format!("{change_id}\t{path}\n"))
+        })
+        .collect();
+    let path = dir.join(VIEWED_FILES_FILE);
+    std::fs::write(&path, contents).with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Notes jotted about changes, by change id
+pub fn load_notes() -> HashMap<String, String> {
+    let Some(path) = state_dir().map(|dir| dir.join(NOTES_FILE)) else {
+        return HashMap::new();
+    };
+    std::fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| line.split_once('\t'))
+                .map(|(change_id, note)| (change_id.to_string(), note.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Replace the notes, each of which is one line
+pub fn save_notes(notes: &HashMap<String, String>) -> Result<()> {
+    let dir = state_dir().ok_or_else(|| anyhow!("Cannot determine state directory"))?;
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Cannot create state directory {}", dir.display()))?;
+
+    let contents: String = notes
+        .iter()
+        .map(|(change_id, note)| format!("{change_id}\t{note}\n"))
+        .collect();
+    let path = dir.join(NOTES_FILE);
+    std::fs::write(&path, contents).with_context(|| format!("Cannot write {}", path.display()))
+}
@@ -0,0 +1,194 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use indexmap::IndexMap;
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span, Text},
+};
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthStr;
+
+/// Separates commits in the log output, since descriptions span lines
+pub const RECORD_SEPARATOR: char = '\0';
+/// "author<TAB>date<TAB>description", each commit starting with a NUL
+pub const LOG_TEMPLATE: &str = concat!(
+    r#""\0" ++ author.name() ++ "\t" ++ author.timestamp().format("%Y-%m-%d")"#,
+    r#" ++ "\t" ++ description"#,
+);
+
+const SPARKLINE_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Longer histories are bucketed by week
+const MAX_DAILY_BUCKETS: i64 = 90;
+const TOP_ENTRIES: usize = 10;
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    commit_count: usize,
+    /// Authors with their commit counts, most commits first
+    authors: Vec<(String, usize)>,
+    dates: Vec<NaiveDate>,
+    /// Files with the number of lines changed in them, most first
+    churned_files: Vec<(String, usize)>,
+    description_lengths: Vec<usize>,
+}
+
+impl Stats {
+    /// Gather the stats from `jj log` output using `LOG_TEMPLATE`, and the
+    /// `--stat` output of the same commits
+    pub fn parse(log_output: &str, stat_output: &str) -> Self {
+        let mut authors: IndexMap<String, usize> = IndexMap::new();
+        let mut dates = Vec::new();
+        let mut description_lengths = Vec::new();
+        for record in log_output
+            .split(RECORD_SEPARATOR)
+            .filter(|record| !record.is_empty())
+        {
+            let mut fields = record.splitn(3, '\t');
+            let author = fields.next().unwrap_or_default();
+            let date = fields.next().unwrap_or_default();
+            let description = fields.next().unwrap_or_default();
+            *authors.entry(author.to_string()).or_default() += 1;
+            if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                dates.push(date);
+            }
+            description_lengths.push(description.trim().chars().count());
+        }
+
+        let mut churn: HashMap<String, usize> = HashMap::new();
+        for line in stat_output.lines() {
+            let Some((path, change)) = line.rsplit_once(" | ") else {
+                continue;
+            };
+            let Some(lines_changed) = change
+                .split_whitespace()
+                .next()
+                .and_then(|count| count.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            *churn.entry(path.trim().to_string()).or_default() += lines_changed;
+        }
+
+        let mut authors: Vec<(String, usize)> = authors.into_iter().collect();
+        authors.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let mut churned_files: Vec<(String, usize)> = churn.into_iter().collect();
+        churned_files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        Self {
+            commit_count: description_lengths.len(),
+            authors,
+            dates,
+            churned_files,
+            description_lengths,
+        }
+    }
+
+    pub fn render(&self, revset: &str) -> Text<'static> {
+        let heading = |text: String| Line::styled(text, Style::default().fg(Color::Cyan));
+        let mut text = Text::from(heading(format!(
+            "{} commits in {revset}",
+            self.commit_count
+        )));
+        if self.commit_count == 0 {
+            return text;
+        }
+
+        text.push_line(Line::raw(""));
+        text.push_line(heading("Commits per author:".to_string()));
+        text.extend(bar_lines(&self.authors));
+
+        if let Some((label, sparkline)) = self.sparkline() {
+            text.push_line(Line::raw(""));
+            text.push_line(heading(label));
+            text.push_line(Line::styled(
+                format!("  {sparkline}"),
+                Style::default().fg(Color::Green),
+            ));
+        }
+
+        if !self.churned_files.is_empty() {
+            text.push_line(Line::raw(""));
+            text.push_line(heading("Most changed files (lines):".to_string()));
+            text.extend(bar_lines(&self.churned_files));
+        }
+
+        let total_length: usize = self.description_lengths.iter().sum();
+        let undescribed = self
+            .description_lengths
+            .iter()
+            .filter(|length| **length == 0)
+            .count();
+        text.push_line(Line::raw(""));
+        text.push_line(heading(format!(
+            "Average description length: {} characters ({undescribed} without one)",
+            total_length / self.commit_count
+        )));
+        text
+    }
+
+    /// Commits per day from the first commit to the last, or per week when
+    /// that would be too wide
+    fn sparkline(&self) -> Option<(String, String)> {
+        let first = *self.dates.iter().min()?;
+        let last = *self.dates.iter().max()?;
+        let by_week = (last - first).num_days() >= MAX_DAILY_BUCKETS;
+        let bucket_start = |date: NaiveDate| {
+            if by_week {
+                date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+            } else {
+                date
+            }
+        };
+        let step = if by_week { 7 } else { 1 };
+        let first_bucket = bucket_start(first);
+        let bucket_count = ((bucket_start(last) - first_bucket).num_days() / step + 1) as usize;
+        let mut buckets = vec![0usize; bucket_count];
+        for date in &self.dates {
+            buckets[((bucket_start(*date) - first_bucket).num_days() / step) as usize] += 1;
+        }
+
+        let max = buckets.iter().copied().max().unwrap_or(1).max(1);
+        let sparkline = buckets
+            .iter()
+            .map(|count| {
+                if *count == 0 {
+                    ' '
+                } else {
+                    SPARKLINE_CHARS[(count * (SPARKLINE_CHARS.len() - 1)).div_ceil(max)]
+                }
+            })
+            .collect();
+        let unit = if by_week { "week" } else { "day" };
+        Some((
+            format!("Commits per {unit}, {first} to {last} (most {max}):"),
+            sparkline,
+        ))
+    }
+}
+
+/// The top entries with their counts and a bar scaled to the largest
+fn bar_lines(entries: &[(String, usize)]) -> Vec<Line<'static>> {
+    let top = &entries[..entries.len().min(TOP_ENTRIES)];
+    let name_width = top.iter().map(|(name, _)| name.width()).max().unwrap_or(0);
+    let max = top.first().map_or(1, |(_, count)| (*count).max(1));
+    let mut lines: Vec<Line<'static>> = top
+        .iter()
+        .map(|(name, count)| {
+            let padding = " ".repeat(name_width - name.width());
+            Line::from(vec![
+                Span::raw(format!("  {name}{padding}  {count:>5} ")),
+                Span::styled(
+                    "█".repeat((count * BAR_WIDTH).div_ceil(max)),
+                    Style::default().fg(Color::Green),
+                ),
+            ])
+        })
+        .collect();
+    if entries.len() > top.len() {
+        lines.push(Line::styled(
+            format!("  … and {} more", entries.len() - top.len()),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    lines
+}
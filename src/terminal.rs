@@ -1,10 +1,13 @@
 use anyhow::Result;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
+use ratatui::{Terminal, TerminalOptions, Viewport, backend::CrosstermBackend, layout::Rect};
 use std::{
     cell::RefCell,
     io::{Stdout, stdout},
@@ -17,26 +20,73 @@ pub type Term = Rc<RefCell<Terminal<CrosstermBackend<Stdout>>>>;
 pub fn init_terminal() -> Result<Term> {
     install_panic_hook();
     enable_raw_mode()?;
-    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste,
+        EnableFocusChange
+    )?;
     let terminal = Rc::new(RefCell::new(Terminal::new(
         CrosstermBackend::new(stdout()),
     )?));
     Ok(terminal)
 }
 
+/// A terminal that is never set up or drawn to, for driving the UI without
+/// one. Only commands that hand the terminal to jj, like an editor, use it.
+pub fn detached_terminal(area: Rect) -> Result<Term> {
+    let terminal = Terminal::with_options(
+        CrosstermBackend::new(stdout()),
+        TerminalOptions {
+            viewport: Viewport::Fixed(area),
+        },
+    )?;
+    Ok(Rc::new(RefCell::new(terminal)))
+}
+
 pub fn takeover_terminal(terminal: &Term) -> Result<()> {
     enable_raw_mode()?;
-    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste,
+        EnableFocusChange
+    )?;
     terminal.borrow_mut().clear()?;
     Ok(())
 }
 
 pub fn relinquish_terminal() -> Result<()> {
-    execute!(stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    execute!(
+        stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        DisableFocusChange
+    )?;
     disable_raw_mode()?;
     Ok(())
 }
 
+/// Hand the terminal back to the shell and stop the process as if Ctrl-Z had been
+/// pressed outside raw mode, then take the terminal over again once resumed
+#[cfg(unix)]
+pub fn suspend(terminal: &Term) -> Result<()> {
+    relinquish_terminal()?;
+    // Returns once the shell sends SIGCONT (e.g. `fg`)
+    if unsafe { libc::raise(libc::SIGTSTP) } != 0 {
+        log::warn!("Could not suspend: {}", std::io::Error::last_os_error());
+    }
+    takeover_terminal(terminal)
+}
+
+#[cfg(not(unix))]
+pub fn suspend(_terminal: &Term) -> Result<()> {
+    Ok(())
+}
+
 pub fn install_panic_hook() {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
@@ -0,0 +1,157 @@
+use crate::{
+    config::Config,
+    headless::Headless,
+    shell_out::{self, JjCommandError, JjCommandOutput, JjRunner},
+};
+use anyhow::Result;
+use std::{cell::RefCell, rc::Rc};
+
+/// A scripted stand-in for jj, so the update loop can run without a jj binary.
+/// Commands get the answer of the newest response whose arguments they start
+/// with, or empty output if there is none, and every command is recorded.
+#[derive(Clone, Default)]
+pub struct FakeJj(Rc<RefCell<FakeJjState>>);
+
+#[derive(Default)]
+struct FakeJjState {
+    /// Argument prefixes and what jj answers them with, oldest first
+    responses: Vec<(Vec<String>, Response)>,
+    calls: Vec<Vec<String>>,
+}
+
+#[derive(Clone)]
+enum Response {
+    Success {
+        stdout: String,
+        stderr: String,
+    },
+    Failure {
+        stderr: String,
+    },
+    /// Each stdout in turn, the last one from then on
+    Sequence {
+        stdouts: Vec<String>,
+    },
+}
+
+impl FakeJj {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer commands starting with `args` with `stdout`, as queries like
+    /// `jj log` do
+    pub fn on(&self, args: &[&str], stdout: &str) -> &Self {
+        self.respond(
+            args,
+            Response::Success {
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            },
+        )
+    }
+
+    /// Answer commands starting with `args` with `stderr`, as commands that
+    /// change the repo like `jj new` do
+    pub fn on_stderr(&self, args: &[&str], stderr: &str) -> &Self {
+        self.respond(
+            args,
+            Response::Success {
+                stdout: String::new(),
+                stderr: stderr.to_string(),
+            },
+        )
+    }
+
+    /// Answer successive commands starting with `args` with each `stdouts` in
+    /// turn, then the last one from then on, as for the latest operation
+    pub fn on_each(&self, args: &[&str], stdouts: &[&str]) -> &Self {
+        self.respond(
+            args,
+            Response::Sequence {
+                stdouts: stdouts.iter().map(|stdout| stdout.to_string()).collect(),
+            },
+        )
+    }
+
+    /// Fail commands starting with `args` with `stderr`
+    pub fn fail(&self, args: &[&str], stderr: &str) -> &Self {
+        self.respond(
+            args,
+            Response::Failure {
+                stderr: stderr.to_string(),
+            },
+        )
+    }
+
+    fn respond(&self, args: &[&str], response: Response) -> &Self {
+        let args = args.iter().map(|arg| arg.to_string()).collect();
+        self.0.borrow_mut().responses.push((args, response));
+        self
+    }
+
+    /// Answer the jj commands run on this thread from now on
+    pub fn install(&self) {
+        shell_out::set_runner(Some(Rc::new(self.clone())));
+    }
+
+    /// Answer the jj commands run on this thread from now on, and start jjdag
+    /// on all revisions with `log` as the log
+    pub fn start(&self, log: &str, config: &Config, width: u16, height: u16) -> Result<Headless> {
+        self.on(&["log", "--template", "builtin_log_compact"], log);
+        self.install();
+        Headless::new(".".to_string(), "::".to_string(), config, width, height)
+    }
+
+    /// Arguments of every command run so far, oldest first, as a
+    /// [`JjRunner`] is given them
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.0.borrow().calls.clone()
+    }
+
+    /// Whether a command starting with `args` was run
+    pub fn ran(&self, args: &[&str]) -> bool {
+        self.0.borrow().calls.iter().any(|call| {
+            call.iter()
+                .map(String::as_str)
+                .take(args.len())
+                .eq(args.iter().copied())
+        })
+    }
+}
+
+impl JjRunner for FakeJj {
+    fn run(&self, args: &[String]) -> Result<JjCommandOutput, JjCommandError> {
+        let args = args.to_vec();
+        let mut state = self.0.borrow_mut();
+        state.calls.push(args.clone());
+        let response = state
+            .responses
+            .iter_mut()
+            .rev()
+            .find(|(prefix, _)| args.starts_with(prefix))
+            .map(|(_, response)| response);
+        match response {
+            None => Ok(JjCommandOutput {
+                stdout: String::new(),
+                stderr: String::new(),
+            }),
+            Some(Response::Success { stdout, stderr }) => Ok(JjCommandOutput {
+                stdout: stdout.clone(),
+                stderr: stderr.clone(),
+            }),
+            Some(Response::Failure { stderr }) => Err(JjCommandError::new_failed(stderr.clone())),
+            Some(Response::Sequence { stdouts }) => {
+                let stdout = if stdouts.len() > 1 {
+                    stdouts.remove(0)
+                } else {
+                    stdouts.first().cloned().unwrap_or_default()
+                };
+                Ok(JjCommandOutput {
+                    stdout,
+                    stderr: String::new(),
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use clap::ValueEnum;
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// Built-in color themes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Subtle background highlights and jj's own diff colors
+    #[default]
+    Default,
+    /// Bright, reversed highlights and +/- diffs that don't rely on color
+    HighContrast,
+    /// Blue/yellow diffs and highlights that avoid red/green distinctions
+    Deuteranopia,
+}
+
+/// Styles and jj settings that make up a theme
+#[derive(Debug)]
+pub struct Palette {
+    /// Selected log line and popup item
+    pub selection: Style,
+    /// Saved selections of two-step commands
+    pub saved_selection: Style,
+    /// Status bar along the bottom
+    pub status_bar: Style,
+    /// Commit and file headers pinned above a diff scrolled past them
+    pub sticky_header: Style,
+    /// Drawn in front of the selected log line
+    pub selection_symbol: &'static str,
+    /// Mark removed and added lines of diffs with -/+
+    pub diff_symbols: bool,
+    /// `--config` overrides passed to every jj command
+    pub jj_config: &'static [&'static str],
+}
+
+const DEFAULT_PALETTE: Palette = Palette {
+    selection: Style::new()
+        .bg(Color::Rgb(40, 42, 54))
+        .add_modifier(Modifier::BOLD),
+    saved_selection: Style::new().bg(Color::Rgb(33, 35, 45)),
+    status_bar: Style::new().bg(Color::Rgb(33, 35, 45)),
+    sticky_header: Style::new()
+        .bg(Color::Rgb(33, 35, 45))
+        .add_modifier(Modifier::UNDERLINED),
+    selection_symbol: "",
+    diff_symbols: false,
+    jj_config: &[],
+};
+
+const HIGH_CONTRAST_PALETTE: Palette = Palette {
+    selection: Style::new()
+        .fg(Color::Black)
+        .bg(Color::White)
+        .add_modifier(Modifier::BOLD),
+    saved_selection: Style::new()
+        .fg(Color::White)
+        .bg(Color::DarkGray)
+        .add_modifier(Modifier::UNDERLINED),
+    status_bar: Style::new().fg(Color::White).bg(Color::Black),
+    sticky_header: Style::new()
+        .bg(Color::Black)
+        .add_modifier(Modifier::UNDERLINED),
+    selection_symbol: "▶ ",
+    diff_symbols: true,
+    jj_config: &[
+        r#"colors."diff added"={fg="bright white", bold=true}"#,
+        r#"colors."diff removed"={fg="bright black"}"#,
+        r#"colors."diff added token"={fg="bright white", bold=true, underline=true}"#,
+        r#"colors."diff removed token"={fg="bright black", underline=true}"#,
+    ],
+};
+
+const DEUTERANOPIA_PALETTE: Palette = Palette {
+    selection: Style::new()
+        .bg(Color::Rgb(58, 62, 90))
+        .add_modifier(Modifier::BOLD),
+    saved_selection: Style::new()
+        .bg(Color::Rgb(30, 32, 40))
+        .add_modifier(Modifier::UNDERLINED),
+    status_bar: Style::new().bg(Color::Rgb(30, 32, 40)),
+    sticky_header: Style::new()
+        .bg(Color::Rgb(30, 32, 40))
+        .add_modifier(Modifier::UNDERLINED),
+    selection_symbol: "▶ ",
+    diff_symbols: true,
+    jj_config: &[
+        r#"colors."diff added"={fg="bright blue"}"#,
+        r#"colors."diff removed"={fg="bright yellow"}"#,
+        r#"colors."diff added token"={fg="bright blue", bold=true, underline=true}"#,
+        r#"colors."diff removed token"={fg="bright yellow", bold=true, underline=true}"#,
+    ],
+};
+
+static CURRENT: OnceLock<Theme> = OnceLock::new();
+
+impl Theme {
+    pub fn palette(self) -> &'static Palette {
+        match self {
+            Theme::Default => &DEFAULT_PALETTE,
+            Theme::HighContrast => &HIGH_CONTRAST_PALETTE,
+            Theme::Deuteranopia => &DEUTERANOPIA_PALETTE,
+        }
+    }
+
+    /// Make this the theme for the rest of the run
+    pub fn install(self) {
+        let _ = CURRENT.set(self);
+    }
+}
+
+/// The palette of the installed theme
+pub fn palette() -> &'static Palette {
+    CURRENT.get().copied().unwrap_or_default().palette()
+}
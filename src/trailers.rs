@@ -0,0 +1,70 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Used when the config defines no trailers
+pub const DEFAULT_TRAILERS: [&str; 4] = [
+    "Signed-off-by: {name} <{email}>",
+    "Reviewed-by: {name} <{email}>",
+    "Tested-by: {name} <{email}>",
+    "Acked-by: {name} <{email}>",
+];
+
+static TRAILER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9-]*: \S").unwrap());
+
+/// Fill in a trailer template with the user's name and email
+pub fn fill_template(template: &str, name: &str, email: &str) -> String {
+    template.replace("{name}", name).replace("{email}", email)
+}
+
+/// The lines of the description's closing trailer paragraph
+pub fn trailers(description: &str) -> Vec<String> {
+    let paragraph = last_paragraph(description.trim_end());
+    if !paragraph.is_empty() && paragraph.lines().all(|line| TRAILER_REGEX.is_match(line)) {
+        paragraph.lines().map(String::from).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Append a trailer, starting a trailer paragraph if there isn't one
+pub fn add_trailer(description: &str, trailer: &str) -> String {
+    let body = description.trim_end();
+    let separator = if body.is_empty() {
+        ""
+    } else if trailers(description).is_empty() || last_paragraph(body) == first_line(body) {
+        // A subject like `fix: typo` looks like a trailer but isn't one
+        "\n\n"
+    } else {
+        "\n"
+    };
+    format!("{body}{separator}{trailer}\n")
+}
+
+/// Drop a trailer line, along with the paragraph break if it was the last one
+pub fn remove_trailer(description: &str, trailer: &str) -> String {
+    let kept: Vec<&str> = description
+        .trim_end()
+        .lines()
+        .filter(|line| *line != trailer)
+        .collect();
+    let body = kept.join("\n");
+    let body = body.trim_end();
+    if body.is_empty() {
+        String::new()
+    } else {
+        format!("{body}\n")
+    }
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
+}
+
+fn last_paragraph(text: &str) -> &str {
+    match text.rfind("\n\n") {
+        Some(idx) => text[idx + 2..].trim_start_matches('\n'),
+        None => text,
+    }
+}
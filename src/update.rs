@@ -1,9 +1,20 @@
-use crate::{model::Model, terminal::Term};
+use crate::{
+    model::Model,
+    session::{self, ReplayStep},
+    shell_out::OversizedFiles,
+    terminal::{self, Term},
+};
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyModifiers, MouseEventKind};
-use std::time::Duration;
+use nucleo_matcher::{
+    Matcher, Utf32Str,
+    pattern::{CaseMatching, Normalization, Pattern},
+};
+use ratatui::layout::{Position, Rect};
+use std::{cell::Cell, ops::Range, time::Duration};
 
-const EVENT_POLL_DURATION: Duration = Duration::from_millis(200);
+/// Pause before each event played back from a recorded session
+const REPLAY_STEP_DURATION: Duration = Duration::from_millis(100);
 
 /// A fuzzy searchable popup for selecting from a list of options
 #[derive(Debug, Clone)]
@@ -21,7 +32,8 @@ pub enum Popup {
     BookmarkSet {
         bookmarks: Vec<String>,
     },
-    BookmarkTrack {
+    /// Offer to track the remote bookmarks a fetch brought in
+    TrackFetched {
         remote_bookmarks: Vec<String>,
     },
     BookmarkUntrack {
@@ -30,19 +42,105 @@ pub enum Popup {
     FileTrack {
         untracked_files: Vec<String>,
     },
+    FileUntrack {
+        ignored_files: Vec<String>,
+    },
+    GrepResults {
+        change_id: String,
+        is_working_copy: bool,
+        matches: Vec<String>,
+        /// File and line of each match
+        locations: Vec<(String, u32)>,
+    },
     GitFetchRemote {
         remotes: Vec<String>,
-        select_for_branches: bool,
     },
-    GitFetchRemoteBranches {
-        remote: String,
-        branches: Vec<String>,
+    GitPushRemote {
+        remotes: Vec<String>,
+    },
+    /// Bookmarks deleted locally that a push would delete from the remote
+    GitPushDeleted {
+        bookmarks: Vec<String>,
+        remote: Option<String>,
     },
     GitPushBookmark {
         bookmarks: Vec<String>,
         change_id: String,
         is_named_mode: bool,
     },
+    TugBookmarks {
+        bookmarks: Vec<String>,
+        to_change_id: String,
+        /// Push the moved bookmarks afterwards
+        push: bool,
+    },
+    AuthorFilter {
+        authors: Vec<String>,
+    },
+    /// Pick what a merge's diffs are against
+    DiffParent {
+        choices: Vec<String>,
+        /// The parent of each choice, `None` for all of them
+        parents: Vec<Option<String>>,
+    },
+    SavedRevsets {
+        presets: Vec<String>,
+        revsets: Vec<String>,
+    },
+    Help {
+        commands: Vec<String>,
+    },
+    ActionMenu {
+        actions: Vec<String>,
+        /// What each action sends, in the same order
+        messages: Vec<Message>,
+    },
+    OpenRepository {
+        repositories: Vec<String>,
+    },
+    InitColocated {
+        choices: Vec<String>,
+    },
+    /// Offer to retry a command that failed on an immutable commit
+    ImmutableRetry {
+        choices: Vec<String>,
+    },
+    /// Ask before exporting the log over an existing file
+    ExportOverwrite {
+        path: String,
+        choices: Vec<String>,
+    },
+    /// Offer ways around new files too large for jj to snapshot
+    SnapshotTooLarge {
+        files: OversizedFiles,
+        choices: Vec<String>,
+    },
+    SignKey {
+        revset: String,
+        keys: Vec<String>,
+    },
+    MergeTool {
+        change_id: String,
+        tools: Vec<String>,
+    },
+    DescriptionTemplate {
+        mode: DescribeMode,
+        names: Vec<String>,
+    },
+    TrailerAdd {
+        change_id: String,
+        trailers: Vec<String>,
+    },
+    TrailerRemove {
+        change_id: String,
+        trailers: Vec<String>,
+    },
+    SplitFiles {
+        change_id: String,
+        first_description: String,
+        second_description: String,
+        files: Vec<String>,
+    },
     WorkspaceForget {
         workspaces: Vec<String>,
     },
@@ -69,6 +167,16 @@ pub enum Popup {
 /// Action to take when text prompt is submitted
 #[derive(Debug, Clone, PartialEq)]
 pub enum TextPromptAction {
+    BookmarkCreateName {
+        change_id: String,
+    },
+    BookmarkCreateRevision {
+        name: String,
+        change_id: String,
+    },
+    BookmarkCreateFromRemote {
+        commit_id: String,
+    },
     BookmarkRenameSubmit {
         old_name: String,
     },
@@ -79,6 +187,19 @@ pub enum TextPromptAction {
         change_id: String,
     },
     ParallelizeRevset,
+    FileUntrackGlob,
+    Grep {
+        change_id: String,
+        is_working_copy: bool,
+    },
+    OpAbandon,
+    SplitFirstDescription {
+        change_id: String,
+    },
+    SplitSecondDescription {
+        change_id: String,
+        first_description: String,
+    },
     NextPrev {
         direction: NextPrevDirection,
         mode: NextPrevMode,
@@ -87,6 +208,15 @@ pub enum TextPromptAction {
     WorkspaceRenameSubmit,
     PowerWorkspaceAdd,
     PowerWorkspaceRename,
+    ShellCommand,
+    RunOnRevisions {
+        revset: String,
+    },
+    SaveRevsetName,
+    ExportLogPath,
+    EditNote {
+        change_id: String,
+    },
 }
 
 /// Location where text input is currently active
@@ -111,6 +241,36 @@ pub enum TextInputLocation {
     },
 }
 
+/// What Tab completes in a text input
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CompletionKind {
+    Revisions,
+    Paths,
+}
+
+impl TextInputLocation {
+    /// What the input holds that Tab can complete, if anything
+    pub fn completion_kind(&self) -> Option<CompletionKind> {
+        match self {
+            TextInputLocation::Revset { .. }
+            | TextInputLocation::Popup {
+                action:
+                    TextPromptAction::ParallelizeRevset
+                    | TextPromptAction::BookmarkCreateRevision { .. },
+                ..
+            } => Some(CompletionKind::Revisions),
+            TextInputLocation::Popup {
+                action:
+                    TextPromptAction::WorkspaceAdd
+                    | TextPromptAction::FileUntrackGlob
+                    | TextPromptAction::ExportLogPath,
+                ..
+            } => Some(CompletionKind::Paths),
+            _ => None,
+        }
+    }
+}
+
 impl Popup {
     /// Get the title to display in the popup
     pub fn title(&self) -> &'static str {
@@ -119,12 +279,32 @@ impl Popup {
             Popup::BookmarkForget { .. } => "Forget Bookmark",
             Popup::BookmarkRenameSelect { .. } => "Select Bookmark to Rename",
             Popup::BookmarkSet { .. } => "Set Bookmark",
-            Popup::BookmarkTrack { .. } => "Track Remote Bookmark",
+            Popup::TrackFetched { .. } => "Track Fetched Bookmarks",
             Popup::BookmarkUntrack { .. } => "Untrack Remote Bookmark",
             Popup::FileTrack { .. } => "Track File",
+            Popup::FileUntrack { .. } => "Untrack Ignored Files",
+            Popup::GrepResults { .. } => "Matches",
             Popup::GitFetchRemote { .. } => "Select Remote",
-            Popup::GitFetchRemoteBranches { .. } => "Select Branch to Fetch",
+            Popup::GitPushRemote { .. } => "Select Remote to Push To",
             Popup::GitPushBookmark { .. } => "Select Bookmark to Push",
+            Popup::GitPushDeleted { .. } => "Select Bookmarks to Delete From Remote",
+            Popup::TugBookmarks { .. } => "Bookmarks to Tug",
+            Popup::AuthorFilter { .. } => "Show Commits by Author",
+            Popup::DiffParent { .. } => "Diff Merge Against",
+            Popup::SavedRevsets { .. } => "Saved Revsets",
+            Popup::Help { .. } => "Help",
+            Popup::ActionMenu { .. } => "Actions for Selection",
+            Popup::OpenRepository { .. } => "Open Repository",
+            Popup::InitColocated { .. } => "Git Repository Without jj",
+            Popup::ImmutableRetry { .. } => "Commit Is Immutable",
+            Popup::ExportOverwrite { .. } => "File Already Exists",
+            Popup::SnapshotTooLarge { .. } => "Files Too Large to Snapshot",
+            Popup::SplitFiles { .. } => "Files for the First Part",
+            Popup::SignKey { .. } => "Signing Key",
+            Popup::MergeTool { .. } => "Merge Tool",
+            Popup::DescriptionTemplate { .. } => "Description Template",
+            Popup::TrailerAdd { .. } => "Add Trailer",
+            Popup::TrailerRemove { .. } => "Remove Trailer",
             Popup::WorkspaceForget { .. } => "Forget Workspace",
             Popup::WorkspaceUpdateStale { .. } => "Update Stale Workspace",
             Popup::PowerWorkspaceForget { .. } => "Forget Workspace (Power)",
@@ -142,12 +322,32 @@ impl Popup {
             Popup::BookmarkForget { bookmarks, .. } => bookmarks,
             Popup::BookmarkRenameSelect { bookmarks } => bookmarks,
             Popup::BookmarkSet { bookmarks } => bookmarks,
-            Popup::BookmarkTrack { remote_bookmarks } => remote_bookmarks,
+            Popup::TrackFetched { remote_bookmarks } => remote_bookmarks,
             Popup::BookmarkUntrack { tracked_bookmarks } => tracked_bookmarks,
             Popup::FileTrack { untracked_files } => untracked_files,
+            Popup::FileUntrack { ignored_files } => ignored_files,
+            Popup::GrepResults { matches, .. } => matches,
             Popup::GitFetchRemote { remotes, .. } => remotes,
-            Popup::GitFetchRemoteBranches { branches, .. } => branches,
+            Popup::GitPushRemote { remotes } => remotes,
             Popup::GitPushBookmark { bookmarks, .. } => bookmarks,
+            Popup::GitPushDeleted { bookmarks, .. } => bookmarks,
+            Popup::TugBookmarks { bookmarks, .. } => bookmarks,
+            Popup::AuthorFilter { authors } => authors,
+            Popup::DiffParent { choices, .. } => choices,
+            Popup::SavedRevsets { presets, .. } => presets,
+            Popup::Help { commands } => commands,
+            Popup::ActionMenu { actions, .. } => actions,
+            Popup::OpenRepository { repositories } => repositories,
+            Popup::InitColocated { choices } => choices,
+            Popup::ImmutableRetry { choices } => choices,
+            Popup::ExportOverwrite { choices, .. } => choices,
+            Popup::SnapshotTooLarge { choices, .. } => choices,
+            Popup::SplitFiles { files, .. } => files,
+            Popup::SignKey { keys, .. } => keys,
+            Popup::MergeTool { tools, .. } => tools,
+            Popup::DescriptionTemplate { names, .. } => names,
+            Popup::TrailerAdd { trailers, .. } => trailers,
+            Popup::TrailerRemove { trailers, .. } => trailers,
             Popup::WorkspaceForget { workspaces } => workspaces,
             Popup::WorkspaceUpdateStale { workspaces } => workspaces,
             Popup::PowerWorkspaceForget { workspaces } => workspaces,
@@ -157,12 +357,183 @@ impl Popup {
             Popup::PowerWorkspaceMoveTo { workspaces } => workspaces,
         }
     }
+
+    /// Whether several items can be marked with Tab and selected at once
+    pub fn allows_marking(&self) -> bool {
+        matches!(
+            self,
+            Popup::FileUntrack { .. }
+                | Popup::TrackFetched { .. }
+                | Popup::TugBookmarks { .. }
+                | Popup::GitPushDeleted { .. }
+                | Popup::SplitFiles { .. }
+        )
+    }
+
+    /// Fuzzy match the items against the filter, best matches first. Each
+    /// whitespace-separated word of the filter must match on its own.
+    pub fn matched_items(&self, filter: &str) -> Vec<PopupMatch<'_>> {
+        if filter.trim().is_empty() {
+            return self
+                .items()
+                .iter()
+                .map(|item| PopupMatch {
+                    item,
+                    indices: Vec::new(),
+                })
+                .collect();
+        }
+
+        let pattern = Pattern::parse(filter, CaseMatching::Ignore, Normalization::Smart);
+        let mut matcher = Matcher::new(nucleo_matcher::Config::DEFAULT);
+        let mut buf = Vec::new();
+        let mut matches: Vec<(u32, PopupMatch)> = self
+            .items()
+            .iter()
+            .filter_map(|item| {
+                let mut indices = Vec::new();
+                let score =
+                    pattern.indices(Utf32Str::new(item, &mut buf), &mut matcher, &mut indices)?;
+                indices.sort_unstable();
+                indices.dedup();
+                let indices = indices.into_iter().map(|idx| idx as usize).collect();
+                Some((score, PopupMatch { item, indices }))
+            })
+            .collect();
+        // Stable sort keeps the original order between equally good matches
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches
+            .into_iter()
+            .map(|(_, popup_match)| popup_match)
+            .collect()
+    }
+}
+
+/// Selection and scroll position within a popup's (filtered) item list
+#[derive(Debug, Default)]
+pub struct PopupListState {
+    selected: usize,
+    /// Items marked for popups that select several at once
+    marked: Vec<String>,
+    /// First visible item; adjusted while rendering to keep the selection visible
+    offset: Cell<usize>,
+    /// Where the whole popup and its item rows were last rendered, for mouse clicks
+    popup_area: Cell<Rect>,
+    list_area: Cell<Rect>,
+}
+
+impl PopupListState {
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn reset(&mut self) {
+        self.selected = 0;
+        self.offset.set(0);
+    }
+
+    pub fn marked(&self) -> &[String] {
+        &self.marked
+    }
+
+    pub fn is_marked(&self, item: &str) -> bool {
+        self.marked.iter().any(|marked| marked == item)
+    }
+
+    pub fn toggle_mark(&mut self, item: &str) {
+        if self.is_marked(item) {
+            self.marked.retain(|marked| marked != item);
+        } else {
+            self.marked.push(item.to_string());
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    pub fn select_next(&mut self, len: usize) {
+        self.select(self.selected + 1, len);
+    }
+
+    pub fn select_prev(&mut self, len: usize) {
+        self.select(self.selected.saturating_sub(1), len);
+    }
+
+    pub fn page_down(&mut self, len: usize) {
+        self.select(self.selected + self.page_size(), len);
+    }
+
+    pub fn page_up(&mut self, len: usize) {
+        self.select(self.selected.saturating_sub(self.page_size()), len);
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn select_last(&mut self, len: usize) {
+        self.selected = len.saturating_sub(1);
+    }
+
+    pub fn select(&mut self, idx: usize, len: usize) {
+        self.selected = idx.min(len.saturating_sub(1));
+    }
+
+    fn page_size(&self) -> usize {
+        (self.list_area.get().height as usize)
+            .saturating_sub(1)
+            .max(1)
+    }
+
+    pub fn popup_area(&self) -> Rect {
+        self.popup_area.get()
+    }
+
+    pub fn set_popup_area(&self, area: Rect) {
+        self.popup_area.set(area);
+    }
+
+    /// Index of the item rendered at the given screen position, if any
+    pub fn item_at(&self, position: Position, len: usize) -> Option<usize> {
+        let list_area = self.list_area.get();
+        if !list_area.contains(position) {
+            return None;
+        }
+        let idx = self.offset.get() + (position.y - list_area.y) as usize;
+        (idx < len).then_some(idx)
+    }
+
+    /// Range of items to show in the rows of `list_area`, scrolling only as far as
+    /// needed to keep the selection visible
+    pub fn visible_range(&self, len: usize, list_area: Rect) -> Range<usize> {
+        self.list_area.set(list_area);
+        let height = list_area.height as usize;
+        let selected = self.selected.min(len.saturating_sub(1));
+        let mut offset = self.offset.get().min(len.saturating_sub(height));
+        if selected < offset {
+            offset = selected;
+        } else if height > 0 && selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+        self.offset.set(offset);
+        offset..(offset + height).min(len)
+    }
+}
+
+/// A popup item that matched the filter
+#[derive(Debug)]
+pub struct PopupMatch<'a> {
+    pub item: &'a String,
+    /// Char indices of the item that matched the filter, for highlighting
+    pub indices: Vec<usize>,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Message {
     Abandon {
         mode: AbandonMode,
+        range: bool,
     },
     Absorb {
         mode: AbsorbMode,
@@ -176,13 +547,62 @@ pub enum Message {
     },
     BookmarkRename,
     BookmarkSet,
-    BookmarkTrack,
     BookmarkUntrack,
     /// Start editing a bookmark name inline for the selected commit
     BookmarkEditStart,
+    /// Prompt for a bookmark name and the revision to create it at
+    BookmarkCreateAtStart,
     /// Start editing description inline for the selected commit
     DescriptionEditStart {
         mode: DescribeMode,
+        range: bool,
+    },
+    /// Start recording keys into a register, or stop recording
+    RecordMacro,
+    /// Replay the keys recorded into a register
+    PlayMacro,
+    /// A key typed while a macro register is being picked
+    MacroRegister {
+        key_code: KeyCode,
+    },
+    /// Summarize the commits of the loaded revset
+    ShowStats,
+    /// Show the selected commit's whole description in the info list
+    ShowDescription,
+    /// Prompt for a file to write the log to, with its colors
+    ExportLog,
+    /// Narrow the revset to the selection's descendants or ancestors
+    Zoom {
+        mode: ZoomMode,
+    },
+    /// Go back to the revset from before the last zoom
+    ZoomOut,
+    /// Pick an author to narrow the revset to
+    AuthorFilter,
+    /// Go back to the revset from before the author filter
+    ClearAuthorFilter,
+    /// Prompt for a name to save the revset under
+    SaveRevset,
+    /// Pick a saved revset to show
+    SavedRevsets,
+    /// Dim the commits that are neither ancestors nor descendants of the
+    /// selection, or stop dimming them
+    ToggleLineage,
+    ToggleInfoPanePosition,
+    ToggleFocusMode,
+    ToggleReviewMode,
+    /// Write or change the selection's note
+    EditNote,
+    /// Mark the selected file viewed, or unmark it
+    ToggleFileViewed,
+    ShowOlderOutput,
+    DiffParent,
+    ShowNewerOutput,
+    /// Show how the selection and trunk() have diverged
+    CompareWithTrunk,
+    /// Pick a template to start editing a description from
+    DescriptionTemplate {
+        mode: DescribeMode,
     },
     /// Add a character to the popup filter
     PopupFilterChar {
@@ -190,14 +610,35 @@ pub enum Message {
     },
     /// Remove last character from popup filter
     PopupFilterBackspace,
+    /// Paste clipboard contents into the popup filter
+    PopupFilterPaste,
     /// Select the currently highlighted popup item
     PopupSelect,
+    /// Mark or unmark the selected item in popups that select several at once
+    PopupToggleMark,
     /// Cancel the popup without selecting
     PopupCancel,
     /// Move selection down in popup
     PopupNext,
     /// Move selection up in popup
     PopupPrev,
+    /// Move selection down one page in popup
+    PopupPageDown,
+    /// Move selection up one page in popup
+    PopupPageUp,
+    /// Scroll the log viewer back (positive) or forward (negative) by lines
+    LogViewerScroll {
+        lines: i32,
+    },
+    /// Click in a popup
+    PopupClick {
+        row: u16,
+        column: u16,
+    },
+    /// Select the first popup item
+    PopupFirst,
+    /// Select the last popup item
+    PopupLast,
     /// Add a character to the text input at cursor position
     TextInputChar {
         ch: char,
@@ -224,6 +665,12 @@ pub enum Message {
     TextInputCancel,
     /// Insert a newline in text input (for multi-line)
     TextInputNewline,
+    /// Jump to the next `{placeholder}` of a description template
+    TextInputNextPlaceholder,
+    /// Complete the revision or path being typed, or fill in the next match
+    TextInputComplete {
+        reverse: bool,
+    },
     /// Cut from cursor to end of current line
     TextInputCutToEnd,
     /// Copy from cursor to end of current line
@@ -235,6 +682,8 @@ pub enum Message {
     /// Move cursor to end of current line
     TextInputMoveLineEnd,
     Clear,
+    /// Drop the last key of the pending key chord
+    CommandKeyBack,
     Commit,
 
     Duplicate {
@@ -248,14 +697,60 @@ pub enum Message {
     Evolog {
         patch: bool,
     },
+    /// Load a file diff that was cut off for being too long
+    LoadFullFileDiff,
+    /// Show or hide the selected commit's earlier versions as child nodes
+    ToggleInlineEvolog,
+    /// Restore the commit to the earlier version selected in its inline evolog
+    EvologRestore,
     FileTrack,
     FileUntrack,
+    /// Pick ignored files that are still tracked and untrack them
+    FileUntrackIgnored,
+    /// Untrack the files matching a glob typed into a prompt
+    FileUntrackGlobStart,
+    /// Search the files of the selected revision for a pattern
+    GrepStart,
+    /// Browse the files of the selected revision
+    FileBrowserStart,
+    FileBrowserClose,
+    FileBrowserMove {
+        delta: i32,
+    },
+    FileBrowserExpand,
+    FileBrowserCollapse,
+    /// Toggle the selected directory or open the selected file
+    FileBrowserEnter,
+    /// Restore the selected file into the working copy
+    FileBrowserRestore,
+    /// Browse the bookmarks of every remote
+    RemoteBookmarksStart,
+    RemoteBookmarksClose,
+    RemoteBookmarksMove {
+        delta: i32,
+    },
+    /// Track the selected remote bookmark, or untrack it
+    RemoteBookmarksTrack,
+    /// Fetch the selected remote bookmark, or all of the selected remote
+    RemoteBookmarksFetch,
+    /// Create a local bookmark where the selected remote bookmark is
+    RemoteBookmarksCreateLocal,
+    /// Set or clear the executable bit of the selected file in its revision
+    FileChmod {
+        executable: bool,
+    },
+    /// Show the selected image file before and after its revision
+    FileImagePreview,
+    /// Show the conflict markers in the selected file
+    FileConflictView,
     GitFetch {
         mode: GitFetchMode,
     },
     GitPush {
         mode: GitPushMode,
     },
+    /// Choose the remote for the next push
+    GitPushRemote,
     Interdiff {
         mode: InterdiffMode,
     },
@@ -270,7 +765,10 @@ pub enum Message {
         mode: NewMode,
     },
     NewAfterTrunkSync,
-    NewOnBranch,
+    NewOnBranch {
+        /// Describe the new change once it exists
+        describe: bool,
+    },
     RebaseSelectedBranchOntoTrunk,
     RebaseSelectedBranchOntoTrunkSync,
     NextPrev {
@@ -287,6 +785,14 @@ pub enum Message {
         destination_type: RebaseDestinationType,
         destination: RebaseDestination,
     },
+    /// Abandon old operations and garbage collect the repo
+    OpAbandon,
+    /// Restore to before the last command jjdag ran
+    UndoAction,
+    WatchmanEnable,
+    WatchmanStatus,
+    /// Collect the selection as one of several rebase destinations
+    AddRebaseDestination,
     Redo,
     Refresh,
     Restore {
@@ -298,31 +804,82 @@ pub enum Message {
         destination: RevertDestination,
     },
     Resolve,
+    /// Resolve the selected file's conflicts by taking one side
+    ResolveTakeSide {
+        side: ResolveSide,
+    },
+    /// Drag with the left button held, selecting a range
+    LeftMouseDrag {
+        row: u16,
+        column: u16,
+    },
+    /// Shift-click, selecting a range from the current selection
+    ShiftLeftMouseClick {
+        row: u16,
+        column: u16,
+    },
+    /// Select the clicked node and open its actions menu
     RightMouseClick {
         row: u16,
         column: u16,
     },
+    /// Open a menu of the actions that apply to the selected commit, file or
+    /// hunk
+    ActionMenu,
     SaveSelection,
     ScrollDown,
     ScrollDownPage,
     ScrollUp,
     ScrollUpPage,
     SelectCurrentWorkingCopy,
+    JumpTo {
+        target: JumpTarget,
+    },
     SelectNextNode,
     SelectNextSiblingNode,
     SelectParentNode,
+    SelectNextCommit,
+    SelectPrevCommit,
+    SelectNextFile,
+    SelectPrevFile,
+    SelectNextHunk,
+    SelectPrevHunk,
     SelectPrevNode,
     SelectPrevSiblingNode,
     SetRevset,
+    /// Prompt for a shell command to run with the selection filled in
+    ShellCommand,
+    /// Prompt for a shell command to run on each revision of a range
+    RunOnRevisions,
+    /// Pick a trailer to add to the selected description
+    TrailerAdd,
+    /// Pick a trailer to remove from the selected description
+    TrailerRemove,
+    /// Ask the CI provider about the loaded commits
+    CiRefresh,
+    /// Show what the CI provider said about the selected commit
+    CiDetails,
+    /// Run a shell command from the config, by its index
+    ExternalCommand {
+        idx: usize,
+    },
+    /// Call a function bound with `bind()` in the hooks script, by its index
+    ScriptBinding {
+        idx: usize,
+    },
     ShowHelp,
     Sign {
         action: SignAction,
         range: bool,
     },
+    /// Sign the selection with a key picked from a popup
+    SignWithKey,
     SimplifyParents {
         mode: SimplifyParentsMode,
     },
     Split,
+    /// Split after prompting for both descriptions and which file goes first
+    SplitDescribed,
     Squash {
         mode: SquashMode,
     },
@@ -331,8 +888,15 @@ pub enum Message {
     Tug,
     /// Tug bookmark and push it to origin
     TugAndGitPush,
+    /// Stop jjdag and return to the shell until it is resumed
+    Suspend,
+    /// Run the commands shown in the info list awaiting confirmation
+    ConfirmCommands,
     ToggleIgnoreImmutable,
+    ToggleIgnoreWorkingCopy,
     ToggleLogListFold,
+    /// Show or hide jjdag's own log
+    ToggleLogViewer,
     Undo,
     View {
         mode: ViewMode,
@@ -363,10 +927,13 @@ pub enum AbandonMode {
 pub enum AbsorbMode {
     Default,
     Into,
+    /// Show where each hunk would go before absorbing
+    Preview,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum BookmarkMoveMode {
+    AllOnSaved,
     AllowBackwards,
     Default,
     Tug,
@@ -390,7 +957,6 @@ pub enum DuplicateDestinationType {
 pub enum GitFetchMode {
     Default,
     AllRemotes,
-    Branch,
     Remote,
     Tracked,
 }
@@ -402,6 +968,8 @@ pub enum GitPushMode {
     Bookmark,
     Change,
     Deleted,
+    /// Pick which of the deletions `Deleted` would push
+    DeletedSelect,
     Named,
     Revision,
     Tracked,
@@ -428,6 +996,8 @@ pub enum MetaeditAction {
 pub enum DescribeMode {
     Default,
     IgnoreImmutable,
+    /// Commit the working copy with the description
+    Commit,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -441,9 +1011,20 @@ pub enum NewMode {
     AfterTrunk,
     Before,
     Default,
+    /// After the selection, then describe the new change
+    Describe,
     InsertAfter,
 }
 
+/// A commit to jump the selection to
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JumpTarget {
+    Trunk,
+    Root,
+    /// The closest ancestor of the selection with a bookmark
+    BookmarkedAncestor,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum NextPrevDirection {
     Next,
@@ -514,6 +1095,14 @@ pub enum RevertRevision {
     Selection,
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ResolveSide {
+    /// Side #1
+    Ours,
+    /// Side #2
+    Theirs,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum SignAction {
     Sign,
@@ -530,6 +1119,16 @@ pub enum SimplifyParentsMode {
 pub enum SquashMode {
     Default,
     Into,
+    /// The selected hunk into the commit that last changed its lines
+    HunkIntoBlamed,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ZoomMode {
+    /// The selection and everything on top of it
+    Descendants,
+    /// The selection and everything it's built on
+    Ancestors,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -543,6 +1142,8 @@ pub enum ViewMode {
 
 pub fn update(terminal: Term, model: &mut Model) -> Result<()> {
     log::debug!("Processing update cycle");
+    model.expire_toasts();
+    model.receive_ci_statuses(false);
     model.process_jj_command_queue()?;
 
     let mut current_msg = handle_event(model)?;
@@ -554,30 +1155,167 @@ pub fn update(terminal: Term, model: &mut Model) -> Result<()> {
     Ok(())
 }
 
+/// Handle a key as if it had been typed, along with every message it leads to
+pub fn handle_key_press(terminal: Term, model: &mut Model, key: event::KeyEvent) -> Result<()> {
+    let mut current_msg = handle_key(model, key);
+    while let Some(msg) = current_msg {
+        log::debug!("Handling message: {:?}", msg);
+        current_msg = handle_msg(terminal.clone(), model, msg)?;
+    }
+    Ok(())
+}
+
 fn handle_event(model: &mut Model) -> Result<Option<Message>> {
-    if event::poll(EVENT_POLL_DURATION)? {
-        match event::read()? {
-            Event::Key(key) => {
-                if key.kind == event::KeyEventKind::Press {
-                    log::debug!(
-                        "Key pressed: {:?}, modifiers: {:?}",
-                        key.code,
-                        key.modifiers
-                    );
-                    return Ok(handle_key(model, key));
-                }
-            }
-            Event::Mouse(mouse) => {
-                log::debug!("Mouse event: {:?}", mouse.kind);
-                return Ok(handle_mouse(mouse));
+    // Keys typed while a macro plays come first, so Esc or Ctrl-C can stop it
+    if model.is_playing_macro() && !session::is_replaying() && event::poll(Duration::ZERO)? {
+        let event = event::read()?;
+        session::record_event(&event);
+        if let Event::Key(key) = event
+            && key.kind == event::KeyEventKind::Press
+            && (key.code == KeyCode::Esc
+                || (key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL)))
+        {
+            model.stop_macro();
+            return Ok(None);
+        }
+        return Ok(event_message(model, event));
+    }
+    if let Some(key) = model.next_macro_key() {
+        log::debug!(
+            "Replaying key: {:?}, modifiers: {:?}",
+            key.code,
+            key.modifiers
+        );
+        return Ok(handle_key(model, key));
+    }
+    let event = match session::next_replay_step() {
+        Some(ReplayStep::Event(event)) => {
+            // Slow enough to follow along
+            std::thread::sleep(REPLAY_STEP_DURATION);
+            Some(event)
+        }
+        Some(ReplayStep::Finished) => {
+            model.show_toast("Replay finished");
+            None
+        }
+        // Whether the terminal lost focus while a long command ran is only
+        // known once the events read meanwhile are handled
+        None if model.has_pending_notification() && !event::poll(Duration::ZERO)? => {
+            model.resolve_notification();
+            None
+        }
+        // Sleep until there's input or something else to redraw, rather than
+        // waking up on a fixed tick
+        None if model.input_timeout().map_or(Ok(true), event::poll)? => {
+            let event = event::read()?;
+            session::record_event(&event);
+            Some(event)
+        }
+        None => {
+            // The log viewer follows the log file between inputs
+            if model.log_viewer_scroll.is_some() {
+                model.dirty = true;
             }
-            _ => {}
+            None
         }
+    };
+    Ok(event.and_then(|event| event_message(model, event)))
+}
+
+/// Handle an input event as if it had been read from the terminal, along with
+/// every message it leads to
+pub fn handle_input_event(terminal: Term, model: &mut Model, event: Event) -> Result<()> {
+    let mut current_msg = event_message(model, event);
+    while let Some(msg) = current_msg {
+        log::debug!("Handling message: {:?}", msg);
+        current_msg = handle_msg(terminal.clone(), model, msg)?;
+    }
+    Ok(())
+}
+
+fn event_message(model: &mut Model, event: Event) -> Option<Message> {
+    // Mouse movement and focus changes only matter if they lead to a message
+    if matches!(event, Event::Paste(_) | Event::Resize(..)) {
+        model.dirty = true;
+    }
+    match event {
+        Event::Key(key) if key.kind == event::KeyEventKind::Press => {
+            log::debug!(
+                "Key pressed: {:?}, modifiers: {:?}",
+                key.code,
+                key.modifiers
+            );
+            model.macros.record(key);
+            handle_key(model, key)
+        }
+        Event::Mouse(mouse) => {
+            log::debug!("Mouse event: {:?}", mouse.kind);
+            handle_mouse(model, mouse)
+        }
+        Event::Paste(text) => {
+            log::debug!("Bracketed paste of {} bytes", text.len());
+            model.paste_text(&text);
+            None
+        }
+        Event::FocusGained | Event::FocusLost => {
+            model.terminal_focused = event == Event::FocusGained;
+            None
+        }
+        _ => None,
     }
-    Ok(None)
 }
 
 fn handle_key(model: &mut Model, key: event::KeyEvent) -> Option<Message> {
+    // Every key press gets a fresh frame, whether or not it leads to a message
+    model.dirty = true;
+    // Only an Enter right away confirms, so a later one can't run the
+    // commands unnoticed
+    if key.code != KeyCode::Enter && model.has_commands_awaiting_confirmation() {
+        model.cancel_confirmation();
+    }
+    if key.code == KeyCode::F(12) {
+        return Some(Message::ToggleLogViewer);
+    }
+    if model.macros.awaiting_register().is_some() {
+        return Some(Message::MacroRegister { key_code: key.code });
+    }
+    if model.log_viewer_scroll.is_some() {
+        let page = model.log_list_layout.height.max(1) as i32;
+        return match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => Some(Message::ToggleLogViewer),
+            KeyCode::Up | KeyCode::Char('k') => Some(Message::LogViewerScroll { lines: 1 }),
+            KeyCode::Down | KeyCode::Char('j') => Some(Message::LogViewerScroll { lines: -1 }),
+            KeyCode::PageUp => Some(Message::LogViewerScroll { lines: page }),
+            KeyCode::PageDown => Some(Message::LogViewerScroll { lines: -page }),
+            KeyCode::Home | KeyCode::Char('g') => {
+                Some(Message::LogViewerScroll { lines: i32::MAX })
+            }
+            KeyCode::End | KeyCode::Char('G') => Some(Message::LogViewerScroll { lines: i32::MIN }),
+            _ => None,
+        };
+    }
+
+    if model.file_browser.is_some() {
+        let page = model.log_list_layout.height.max(1) as i32;
+        return match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => Some(Message::FileBrowserClose),
+            KeyCode::Down | KeyCode::Char('j') => Some(Message::FileBrowserMove { delta: 1 }),
+            KeyCode::Up | KeyCode::Char('k') => Some(Message::FileBrowserMove { delta: -1 }),
+            KeyCode::PageDown => Some(Message::FileBrowserMove { delta: page }),
+            KeyCode::PageUp => Some(Message::FileBrowserMove { delta: -page }),
+            KeyCode::Home | KeyCode::Char('g') => {
+                Some(Message::FileBrowserMove { delta: i32::MIN })
+            }
+            KeyCode::End | KeyCode::Char('G') => Some(Message::FileBrowserMove { delta: i32::MAX }),
+            KeyCode::Right | KeyCode::Char('l') => Some(Message::FileBrowserExpand),
+            KeyCode::Left | KeyCode::Char('h') => Some(Message::FileBrowserCollapse),
+            KeyCode::Enter => Some(Message::FileBrowserEnter),
+            KeyCode::Char('r') => Some(Message::FileBrowserRestore),
+            _ => None,
+        };
+    }
+
     // When text input is active (single source of truth)
     // When text input is active, dispatch to unified TextInput messages
     if model.text_input_location != crate::update::TextInputLocation::None {
@@ -589,6 +1327,21 @@ fn handle_key(model: &mut Model, key: event::KeyEvent) -> Option<Message> {
             }
             KeyCode::Enter => Some(Message::TextInputSubmit),
             KeyCode::Esc => Some(Message::TextInputCancel),
+            KeyCode::Tab
+                if model.description_from_template
+                    && matches!(
+                        model.text_input_location,
+                        crate::update::TextInputLocation::Description { .. }
+                    ) =>
+            {
+                Some(Message::TextInputNextPlaceholder)
+            }
+            KeyCode::Tab if model.text_input_location.completion_kind().is_some() => {
+                Some(Message::TextInputComplete { reverse: false })
+            }
+            KeyCode::BackTab if model.text_input_location.completion_kind().is_some() => {
+                Some(Message::TextInputComplete { reverse: true })
+            }
             KeyCode::Backspace => Some(Message::TextInputBackspace),
             KeyCode::Delete => Some(Message::TextInputDelete),
             KeyCode::Left => Some(Message::TextInputMoveLeft),
@@ -613,7 +1366,7 @@ fn handle_key(model: &mut Model, key: event::KeyEvent) -> Option<Message> {
             KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 Some(Message::TextInputCutToEnd)
             }
-            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            KeyCode::Char('y' | 'v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 Some(Message::TextInputPaste)
             }
             KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
@@ -644,51 +1397,71 @@ fn handle_key(model: &mut Model, key: event::KeyEvent) -> Option<Message> {
     // When a selection popup is active (not text input) AND we're not in a command sequence
     if model.current_popup.is_some() && !model.has_pending_command_keys() {
         log::debug!("Popup active, handling popup navigation");
+        let is_help_popup = matches!(model.current_popup, Some(Popup::Help { .. }));
         return match key.code {
             KeyCode::Enter => Some(Message::PopupSelect),
+            KeyCode::Tab => Some(Message::PopupToggleMark),
             KeyCode::Esc => Some(Message::PopupCancel),
             KeyCode::Backspace => Some(Message::PopupFilterBackspace),
-            KeyCode::Down | KeyCode::Char('j') => Some(Message::PopupNext),
-            KeyCode::Up | KeyCode::Char('k') => Some(Message::PopupPrev),
+            KeyCode::Char('y' | 'v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Message::PopupFilterPaste)
+            }
+            KeyCode::Down => Some(Message::PopupNext),
+            KeyCode::Up => Some(Message::PopupPrev),
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Message::PopupNext)
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Message::PopupPrev)
+            }
+            KeyCode::PageDown => Some(Message::PopupPageDown),
+            KeyCode::PageUp => Some(Message::PopupPageUp),
+            KeyCode::Home => Some(Message::PopupFirst),
+            KeyCode::End => Some(Message::PopupLast),
+            // The help overlay is searched by free text, so j/k must reach the filter
+            KeyCode::Char('j') if !is_help_popup => Some(Message::PopupNext),
+            KeyCode::Char('k') if !is_help_popup => Some(Message::PopupPrev),
             KeyCode::Char(c) => Some(Message::PopupFilterChar { ch: c }),
             _ => None,
         };
     }
 
+    if model.remote_bookmarks.is_some() && !model.has_pending_command_keys() {
+        let page = model.log_list_layout.height.max(1) as i32;
+        return match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => Some(Message::RemoteBookmarksClose),
+            KeyCode::Down | KeyCode::Char('j') => Some(Message::RemoteBookmarksMove { delta: 1 }),
+            KeyCode::Up | KeyCode::Char('k') => Some(Message::RemoteBookmarksMove { delta: -1 }),
+            KeyCode::PageDown => Some(Message::RemoteBookmarksMove { delta: page }),
+            KeyCode::PageUp => Some(Message::RemoteBookmarksMove { delta: -page }),
+            KeyCode::Home | KeyCode::Char('g') => {
+                Some(Message::RemoteBookmarksMove { delta: i32::MIN })
+            }
+            KeyCode::End | KeyCode::Char('G') => {
+                Some(Message::RemoteBookmarksMove { delta: i32::MAX })
+            }
+            KeyCode::Char('t') => Some(Message::RemoteBookmarksTrack),
+            KeyCode::Char('f') => Some(Message::RemoteBookmarksFetch),
+            KeyCode::Char('c') => Some(Message::RemoteBookmarksCreateLocal),
+            _ => None,
+        };
+    }
+
+    // Plain keys continuing a key chord take priority over global bindings
+    let continues_chord = key.modifiers.difference(KeyModifiers::SHIFT).is_empty()
+        && model.is_pending_command_key(&key.code);
+    if !continues_chord && let Some(message) = model.keymap.get(&key) {
+        return match message {
+            Message::Clear if model.has_pending_command_keys() => Some(Message::CommandKeyBack),
+            message => Some(message),
+        };
+    }
+
     match key.code {
-        KeyCode::Char('q') => Some(Message::Quit),
         KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Message::Quit),
-        KeyCode::Down => Some(Message::SelectNextNode),
-        KeyCode::Char('j') if !model.has_pending_command_keys() => Some(Message::SelectNextNode),
-        KeyCode::Up => Some(Message::SelectPrevNode),
-        KeyCode::Char('k') if !model.has_pending_command_keys() => Some(Message::SelectPrevNode),
-        KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            Some(Message::SelectNextNode)
-        }
-        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            Some(Message::SelectPrevNode)
-        }
-        KeyCode::PageDown => Some(Message::ScrollDownPage),
-        KeyCode::PageUp => Some(Message::ScrollUpPage),
-        KeyCode::Left => Some(Message::SelectPrevSiblingNode),
-        KeyCode::Char('h') if !model.has_pending_command_keys() => {
-            Some(Message::SelectPrevSiblingNode)
-        }
-        KeyCode::Right => Some(Message::SelectNextSiblingNode),
-        KeyCode::Char('l') if !model.has_pending_command_keys() => {
-            Some(Message::SelectNextSiblingNode)
-        }
-        KeyCode::Char('K') => Some(Message::SelectParentNode),
-        KeyCode::Char(' ') => Some(Message::Refresh),
-        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            Some(Message::Refresh)
-        }
-        KeyCode::Tab => Some(Message::ToggleLogListFold),
-        KeyCode::Esc => Some(Message::Clear),
-        KeyCode::Char('@') => Some(Message::SelectCurrentWorkingCopy),
-        KeyCode::Char('L') => Some(Message::SetRevset),
-        KeyCode::Char('I') => Some(Message::ToggleIgnoreImmutable),
-        KeyCode::Char('?') => Some(Message::ShowHelp),
+        KeyCode::Enter if model.has_commands_awaiting_confirmation() => {
+            Some(Message::ConfirmCommands)
+        }
         KeyCode::Enter => {
             if model.has_pending_command_keys() {
                 model.handle_command_key(key.code)
@@ -700,14 +1473,62 @@ fn handle_key(model: &mut Model, key: event::KeyEvent) -> Option<Message> {
     }
 }
 
-fn handle_mouse(mouse: event::MouseEvent) -> Option<Message> {
+fn handle_mouse(model: &Model, mouse: event::MouseEvent) -> Option<Message> {
+    if model.file_browser.is_some() {
+        return match mouse.kind {
+            MouseEventKind::ScrollDown => Some(Message::FileBrowserMove { delta: 3 }),
+            MouseEventKind::ScrollUp => Some(Message::FileBrowserMove { delta: -3 }),
+            _ => None,
+        };
+    }
+
+    if model.remote_bookmarks.is_some() && model.current_popup.is_none() {
+        return match mouse.kind {
+            MouseEventKind::ScrollDown => Some(Message::RemoteBookmarksMove { delta: 3 }),
+            MouseEventKind::ScrollUp => Some(Message::RemoteBookmarksMove { delta: -3 }),
+            _ => None,
+        };
+    }
+
+    if model.log_viewer_scroll.is_some() {
+        return match mouse.kind {
+            MouseEventKind::ScrollDown => Some(Message::LogViewerScroll { lines: -3 }),
+            MouseEventKind::ScrollUp => Some(Message::LogViewerScroll { lines: 3 }),
+            _ => None,
+        };
+    }
+
+    if model.current_popup.is_some() {
+        return match mouse.kind {
+            MouseEventKind::ScrollDown => Some(Message::PopupNext),
+            MouseEventKind::ScrollUp => Some(Message::PopupPrev),
+            MouseEventKind::Down(event::MouseButton::Left) => Some(Message::PopupClick {
+                row: mouse.row,
+                column: mouse.column,
+            }),
+            _ => None,
+        };
+    }
+
     match mouse.kind {
         MouseEventKind::ScrollDown => Some(Message::ScrollDown),
         MouseEventKind::ScrollUp => Some(Message::ScrollUp),
+        MouseEventKind::Down(event::MouseButton::Left)
+            if mouse.modifiers.contains(KeyModifiers::SHIFT) =>
+        {
+            Some(Message::ShiftLeftMouseClick {
+                row: mouse.row,
+                column: mouse.column,
+            })
+        }
         MouseEventKind::Down(event::MouseButton::Left) => Some(Message::LeftMouseClick {
             row: mouse.row,
             column: mouse.column,
         }),
+        MouseEventKind::Drag(event::MouseButton::Left) => Some(Message::LeftMouseDrag {
+            row: mouse.row,
+            column: mouse.column,
+        }),
         MouseEventKind::Down(event::MouseButton::Right) => Some(Message::RightMouseClick {
             row: mouse.row,
             column: mouse.column,
@@ -718,43 +1539,107 @@ fn handle_mouse(mouse: event::MouseEvent) -> Option<Message> {
 
 fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Message>> {
     log::debug!("Handling message: {:?}", msg);
+    model.dirty = true;
+    // Moving the selection from the keyboard drops a range picked with the mouse
+    if matches!(
+        msg,
+        Message::ScrollDownPage
+            | Message::ScrollUpPage
+            | Message::SelectCurrentWorkingCopy
+            | Message::JumpTo { .. }
+            | Message::SelectNextNode
+            | Message::SelectNextSiblingNode
+            | Message::SelectNextCommit
+            | Message::SelectParentNode
+            | Message::SelectPrevNode
+            | Message::SelectPrevSiblingNode
+            | Message::SelectPrevCommit
+            | Message::SelectNextFile
+            | Message::SelectPrevFile
+            | Message::SelectNextHunk
+            | Message::SelectPrevHunk
+    ) {
+        model.drop_mouse_range();
+    }
     match msg {
         // General
         Message::Clear => model.clear(),
+        Message::CommandKeyBack => model.command_key_back(),
         Message::Quit => {
             log::info!("Quit message received");
             model.quit()
         }
         Message::Refresh => model.refresh()?,
+        Message::ConfirmCommands => model.confirm_commands()?,
+        Message::Suspend => {
+            log::info!("Suspending");
+            terminal::suspend(&term)?;
+            log::info!("Resumed");
+        }
         Message::SetRevset => model.set_revset(term)?,
+        Message::Zoom { mode } => model.zoom(mode)?,
+        Message::ZoomOut => model.zoom_out()?,
+        Message::ToggleLineage => model.toggle_lineage()?,
+        Message::ToggleInfoPanePosition => model.toggle_info_pane_position(),
+        Message::ToggleFocusMode => model.toggle_focus_mode(),
+        Message::ToggleReviewMode => model.toggle_review_mode(),
+        Message::EditNote => model.edit_note_start(),
+        Message::ToggleFileViewed => model.toggle_file_viewed(),
+        Message::ShowOlderOutput => model.show_older_output(),
+        Message::DiffParent => model.diff_parent_start()?,
+        Message::ShowNewerOutput => model.show_newer_output(),
+        Message::AuthorFilter => model.author_filter_start()?,
+        Message::ClearAuthorFilter => model.clear_author_filter()?,
+        Message::SaveRevset => model.save_revset_start()?,
+        Message::SavedRevsets => model.saved_revsets_start()?,
+        Message::ShellCommand => model.shell_command_start()?,
+        Message::CiRefresh => model.ci_refresh()?,
+        Message::TrailerAdd => model.trailer_add_start()?,
+        Message::TrailerRemove => model.trailer_remove_start()?,
+        Message::CiDetails => model.ci_details()?,
+        Message::RunOnRevisions => model.run_on_revisions_start()?,
+        Message::ExternalCommand { idx } => model.run_external_command(idx)?,
+        Message::ScriptBinding { idx } => model.run_script_binding(idx)?,
 
-        Message::ShowHelp => model.show_help(),
+        Message::ShowHelp => model.show_help()?,
         Message::ToggleIgnoreImmutable => model.toggle_ignore_immutable(),
+        Message::ToggleIgnoreWorkingCopy => model.toggle_ignore_working_copy()?,
+        Message::ToggleLogViewer => model.toggle_log_viewer(),
+        Message::LogViewerScroll { lines } => model.log_viewer_scroll(lines),
 
         // Navigation
         Message::ScrollDownPage => model.scroll_down_page(),
         Message::ScrollUpPage => model.scroll_up_page(),
         Message::SelectCurrentWorkingCopy => model.select_current_working_copy(),
+        Message::JumpTo { target } => model.jump_to(target)?,
         Message::SelectNextNode => model.select_next_node()?,
-        Message::SelectNextSiblingNode => model.select_current_next_sibling_node()?,
+        Message::SelectNextSiblingNode => model.select_current_next_sibling_node(),
+        Message::SelectNextCommit => model.select_next_commit()?,
         Message::SelectParentNode => model.select_parent_node()?,
         Message::SelectPrevNode => model.select_prev_node(),
-        Message::SelectPrevSiblingNode => model.select_current_prev_sibling_node()?,
+        Message::SelectPrevSiblingNode => model.select_current_prev_sibling_node(),
+        Message::SelectPrevCommit => model.select_prev_commit(),
+        Message::SelectNextFile => model.select_next_file(),
+        Message::SelectPrevFile => model.select_prev_file(),
+        Message::SelectNextHunk => model.select_next_hunk(),
+        Message::SelectPrevHunk => model.select_prev_hunk(),
         Message::ToggleLogListFold => model.toggle_current_fold()?,
 
         // Mouse
-        Message::LeftMouseClick { row, column } => model.handle_mouse_click(row, column),
-        Message::RightMouseClick { row, column } => {
-            model.handle_mouse_click(row, column);
-            model.toggle_current_fold()?;
+        Message::LeftMouseClick { row, column } => {
+            model.handle_mouse_click(row, column, term.clone())
         }
+        Message::LeftMouseDrag { row, column } => model.handle_mouse_drag(row, column),
+        Message::ShiftLeftMouseClick { row, column } => model.handle_shift_mouse_click(row, column),
+        Message::RightMouseClick { row, column } => model.handle_right_mouse_click(row, column)?,
+        Message::ActionMenu => model.open_action_menu()?,
         Message::ScrollDown => model.scroll_down_once(),
         Message::ScrollUp => model.scroll_up_once(),
 
         // Commands
-        Message::Abandon { mode } => {
-            log::info!("Abandon command, mode: {:?}", mode);
-            model.jj_abandon(mode)?
+        Message::Abandon { mode, range } => {
+            log::info!("Abandon command, mode: {:?}, range: {}", mode, range);
+            model.jj_abandon(mode, range)?
         }
         Message::Absorb { mode } => {
             log::info!("Absorb command, mode: {:?}", mode);
@@ -767,18 +1652,44 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
         Message::BookmarkMove { mode } => model.jj_bookmark_move(mode)?,
         Message::BookmarkRename => model.jj_bookmark_rename(term)?,
         Message::BookmarkSet => model.jj_bookmark_set(term)?,
-        Message::BookmarkTrack => model.jj_bookmark_track(term)?,
         Message::BookmarkUntrack => model.jj_bookmark_untrack(term)?,
         // Bookmark editing
         Message::BookmarkEditStart => model.bookmark_edit_start()?,
+        Message::BookmarkCreateAtStart => model.bookmark_create_at_start()?,
         // Description editing
-        Message::DescriptionEditStart { mode } => model.description_edit_start(mode)?,
+        Message::DescriptionEditStart { mode, range: false } => {
+            model.description_edit_start(mode)?
+        }
+        Message::DescriptionEditStart { mode, range: true } => {
+            model.description_range_edit_start(mode)?
+        }
+        Message::DescriptionTemplate { mode } => model.description_template_start(mode)?,
+        Message::CompareWithTrunk => model.compare_with_trunk()?,
+        Message::ShowStats => model.show_stats()?,
+        Message::ExportLog => model.export_log_start()?,
+        Message::ShowDescription => model.show_description()?,
+        Message::RecordMacro => model.record_macro(),
+        Message::PlayMacro => model.play_macro(),
+        Message::MacroRegister { key_code } => model.macro_register(key_code),
         // Popup messages
         Message::PopupFilterChar { ch } => model.popup_filter_char(ch),
         Message::PopupFilterBackspace => model.popup_filter_backspace(),
+        Message::PopupFilterPaste => model.popup_filter_paste(),
         Message::PopupNext => model.popup_next(),
         Message::PopupPrev => model.popup_prev(),
-        Message::PopupSelect => model.popup_select(term)?,
+        Message::PopupPageDown => model.popup_page_down(),
+        Message::PopupPageUp => model.popup_page_up(),
+        Message::PopupClick { row, column } => return Ok(model.popup_click(row, column)),
+        Message::PopupFirst => model.popup_first(),
+        Message::PopupLast => model.popup_last(),
+        Message::PopupSelect => {
+            // An action picked from the actions menu runs as if its keys were typed
+            if let Some(action) = model.take_action_menu_selection() {
+                return Ok(Some(action));
+            }
+            model.popup_select(term)?
+        }
+        Message::PopupToggleMark => model.popup_toggle_mark(),
         Message::PopupCancel => model.popup_cancel(),
         // Text input messages
         Message::TextInputChar { ch } => model.text_input_char(ch),
@@ -793,6 +1704,10 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
         Message::TextInputSubmit => model.text_input_submit(term)?,
         Message::TextInputCancel => model.text_input_cancel(),
         Message::TextInputNewline => model.text_input_newline(),
+        Message::TextInputNextPlaceholder => {
+            model.text_input_next_placeholder();
+        }
+        Message::TextInputComplete { reverse } => model.text_input_complete(reverse),
         Message::TextInputCutToEnd => model.text_input_cut_to_end(),
         Message::TextInputCopyToEnd => model.text_input_copy_to_end(),
         Message::TextInputPaste => model.text_input_paste(),
@@ -811,10 +1726,32 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
             log::info!("Edit command, mode: {:?}", mode);
             model.jj_edit(mode)?
         }
-        Message::EnterPressed => model.enter_pressed()?,
+        Message::EnterPressed => model.enter_pressed(term)?,
         Message::Evolog { patch } => model.jj_evolog(patch, term)?,
+        Message::LoadFullFileDiff => model.load_full_file_diff()?,
+        Message::ToggleInlineEvolog => model.toggle_inline_evolog()?,
+        Message::EvologRestore => model.jj_evolog_restore()?,
         Message::FileTrack => model.jj_file_track(term)?,
         Message::FileUntrack => model.jj_file_untrack()?,
+        Message::FileUntrackIgnored => model.jj_file_untrack_ignored()?,
+        Message::FileUntrackGlobStart => model.file_untrack_glob_start(),
+        Message::FileChmod { executable } => model.jj_file_chmod(executable)?,
+        Message::FileImagePreview => model.file_image_preview(term)?,
+        Message::FileConflictView => model.file_conflict_view()?,
+        Message::GrepStart => model.grep_start()?,
+        Message::FileBrowserStart => model.file_browser_start()?,
+        Message::FileBrowserClose => model.file_browser_close(),
+        Message::FileBrowserMove { delta } => model.file_browser_move(delta),
+        Message::FileBrowserExpand => model.file_browser_expand(),
+        Message::FileBrowserCollapse => model.file_browser_collapse(),
+        Message::FileBrowserEnter => model.file_browser_enter(term)?,
+        Message::FileBrowserRestore => model.file_browser_restore()?,
+        Message::RemoteBookmarksStart => model.remote_bookmarks_start()?,
+        Message::RemoteBookmarksClose => model.remote_bookmarks_close(),
+        Message::RemoteBookmarksMove { delta } => model.remote_bookmarks_move(delta),
+        Message::RemoteBookmarksTrack => model.remote_bookmarks_track()?,
+        Message::RemoteBookmarksFetch => model.remote_bookmarks_fetch()?,
+        Message::RemoteBookmarksCreateLocal => model.remote_bookmarks_create_local()?,
         Message::GitFetch { mode } => {
             log::info!("Git fetch command, mode: {:?}", mode);
             model.jj_git_fetch(mode, term)?
@@ -823,6 +1760,7 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
             log::info!("Git push command, mode: {:?}", mode);
             model.jj_git_push(mode, term)?
         }
+        Message::GitPushRemote => model.git_push_remote_select()?,
         Message::Interdiff { mode } => model.jj_interdiff(mode, term)?,
         Message::Metaedit { action } => model.jj_metaedit(action, term)?,
         Message::New { mode } => {
@@ -830,7 +1768,7 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
             model.jj_new(mode)?
         }
         Message::NewAfterTrunkSync => model.jj_new_after_trunk_sync()?,
-        Message::NewOnBranch => model.jj_new_on_branch()?,
+        Message::NewOnBranch { describe } => model.jj_new_on_branch(describe)?,
         Message::RebaseSelectedBranchOntoTrunk => model.jj_rebase_selected_branch_onto_trunk()?,
         Message::RebaseSelectedBranchOntoTrunkSync => {
             model.jj_rebase_selected_branch_onto_trunk_sync()?
@@ -856,6 +1794,9 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
             );
             model.jj_rebase(source_type, destination_type, destination)?
         }
+        Message::OpAbandon => model.op_abandon_start(),
+        Message::UndoAction => model.jj_undo_action()?,
+        Message::AddRebaseDestination => model.add_rebase_destination()?,
         Message::Redo => model.jj_redo()?,
         Message::Restore { mode } => model.jj_restore(mode)?,
         Message::Revert {
@@ -864,10 +1805,13 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
             destination,
         } => model.jj_revert(revision, destination_type, destination)?,
         Message::Resolve => model.jj_resolve(term)?,
+        Message::ResolveTakeSide { side } => model.jj_resolve_take_side(side)?,
         Message::SaveSelection => model.save_selection()?,
         Message::Sign { action, range } => model.jj_sign(action, range)?,
+        Message::SignWithKey => model.sign_key_select_start()?,
         Message::SimplifyParents { mode } => model.jj_simplify_parents(mode)?,
         Message::Split => model.jj_split(term)?,
+        Message::SplitDescribed => model.split_described_start()?,
         Message::Squash { mode } => {
             log::info!("Squash command, mode: {:?}", mode);
             model.jj_squash(mode, term)?
@@ -880,6 +1824,8 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
             model.jj_undo()?
         }
         Message::View { mode } => model.jj_view(mode, term)?,
+        Message::WatchmanEnable => model.jj_watchman_enable()?,
+        Message::WatchmanStatus => model.jj_watchman_status()?,
         Message::WorkspaceAdd => model.workspace_add_start()?,
         Message::WorkspaceForget => model.jj_workspace_forget()?,
         Message::WorkspaceList => model.jj_workspace_list()?,
@@ -895,6 +1841,7 @@ fn handle_msg(term: Term, model: &mut Model, msg: Message) -> Result<Option<Mess
         Message::PowerWorkspaceUpdateStale => model.power_workspace_update_stale_start()?,
         Message::PowerWorkspaceMoveTo => model.power_workspace_move_to_start()?,
     };
+    model.sync_lineage()?;
 
     Ok(None)
 }
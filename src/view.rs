@@ -1,4 +1,10 @@
-use crate::{log_tree::strip_ansi, model::Model};
+use crate::{
+    ansi::strip_ansi,
+    log_tree::{CommitOrText, LogTreeNode},
+    macros::MacroAction,
+    model::Model,
+    profile, theme,
+};
 
 use ratatui::{
     Frame,
@@ -7,25 +13,59 @@ use ratatui::{
     text::{Line, Span, Text},
     widgets::{Block, Borders, List, Paragraph},
 };
-
-pub const SELECTION_COLOR: Color = Color::Rgb(40, 42, 54);
-pub const SAVED_SELECTION_COLOR: Color = Color::Rgb(33, 35, 45);
+use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 /// Standard style for normal text in input fields
 pub const INPUT_STYLE: Style = Style::new().fg(Color::Yellow);
 /// Style for text beyond column limits (grayed out)
 pub const GRAYED_OUT_STYLE: Style = Style::new().fg(Color::DarkGray);
+const HINT_STYLE: Style = Style::new()
+    .fg(Color::DarkGray)
+    .add_modifier(Modifier::ITALIC);
+/// Completion matches shown at once, scrolling with the selection
+const COMPLETION_MENU_ROWS: usize = 6;
+/// The smallest terminal the layout fits in: popups are at least 40 columns
+/// by 10 rows
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 10;
+/// Share of the width the info pane takes on the right
+const INFO_PANE_RIGHT_PERCENT: u16 = 40;
+
+/// Where command output and other info is shown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InfoPanePosition {
+    /// Below the log, as tall as its contents
+    #[default]
+    Bottom,
+    /// Beside the log, for wide terminals
+    Right,
+}
 
 pub fn view(model: &mut Model, frame: &mut Frame) {
+    let _timer = profile::timer("render");
+    if frame.area().width < MIN_WIDTH || frame.area().height < MIN_HEIGHT {
+        render_too_small(frame, frame.area());
+        return;
+    }
     let header = render_header(model);
-    let log_list = render_log_list(model);
     let layout = render_layout(model, frame.area());
     frame.render_widget(header, layout[0]);
-    frame.render_stateful_widget(log_list, layout[1], &mut model.log_list_state);
-    model.log_list_layout = layout[1];
+    if model.file_browser.is_some() {
+        render_file_browser(model, frame, layout[1]);
+        model.log_list_layout = layout[1];
+    } else if model.remote_bookmarks.is_some() {
+        render_remote_bookmarks(model, frame, layout[1]);
+        model.log_list_layout = layout[1];
+    } else {
+        render_log(model, frame, layout[1]);
+    }
     if let Some(info_list) = render_info_list(model) {
         frame.render_widget(info_list, layout[2]);
     }
+    frame.render_widget(render_status_bar(model), layout[3]);
     if model.current_popup.is_some()
         || matches!(
             model.text_input_location,
@@ -34,6 +74,25 @@ pub fn view(model: &mut Model, frame: &mut Frame) {
     {
         render_popup(model, frame, model.current_popup.as_ref(), frame.area());
     }
+    if let crate::update::TextInputLocation::Revset { .. } = model.text_input_location
+        && let (Some((cursor_x, cursor_y)), Some(completion)) = (
+            model.calculate_cursor_position(),
+            model.text_input_completion(),
+        )
+    {
+        let word_width = model.text_input[completion.word_start()..model.text_cursor].width();
+        render_completion_menu(model, frame, cursor_x - word_width as u16, cursor_y);
+    }
+
+    render_toasts(model, frame, frame.area());
+    if profile::is_enabled() {
+        render_profile_overlay(frame, frame.area());
+    }
+
+    if model.log_viewer_scroll.is_some() {
+        render_log_viewer(model, frame, frame.area());
+        return;
+    }
 
     // Set the terminal cursor position for text input
     if let Some((x, y)) = model.calculate_cursor_position() {
@@ -41,21 +100,64 @@ pub fn view(model: &mut Model, frame: &mut Frame) {
     }
 }
 
+/// The header, log, info pane and status bar areas
 fn render_layout(model: &Model, area: Rect) -> std::rc::Rc<[Rect]> {
+    if model.info_pane == InfoPanePosition::Right && shown_info(model).is_some() {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(header_height(model)),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Percentage(INFO_PANE_RIGHT_PERCENT),
+            ])
+            .split(rows[1]);
+        return [rows[0], columns[0], columns[1], rows[2]].into();
+    }
     Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(2),
+            Constraint::Length(header_height(model)),
             Constraint::Min(0),
-            if let Some(info_list) = &model.info_list {
+            if let Some(info_list) = shown_info(model) {
                 Constraint::Length(info_list.lines.len() as u16 + 2)
             } else {
                 Constraint::Length(0)
             },
+            Constraint::Length(1),
         ])
         .split(area)
 }
 
+/// Focus mode hides the header, except while the revset is edited in it
+fn header_height(model: &Model) -> u16 {
+    if model.focus_mode
+        && !matches!(
+            model.text_input_location,
+            crate::update::TextInputLocation::Revset { .. }
+        )
+    {
+        0
+    } else {
+        2
+    }
+}
+
+/// What the info pane shows, if anything. Focus mode leaves out the key
+/// hints of a pending chord, so it only opens for output and messages.
+fn shown_info(model: &Model) -> Option<&Text<'static>> {
+    if model.focus_mode && model.has_pending_command_keys() {
+        return None;
+    }
+    model.info_list.as_ref()
+}
+
 fn render_header(model: &Model) -> Paragraph<'_> {
     let mut header_spans = vec![
         Span::styled("repository: ", Style::default().fg(Color::Blue)),
@@ -63,6 +165,11 @@ fn render_header(model: &Model) -> Paragraph<'_> {
         Span::raw("  "),
         Span::styled("revset: ", Style::default().fg(Color::Blue)),
     ];
+    // The revsets zoomed in from, leading up to the current one
+    for outer in &model.zoom_stack {
+        header_spans.push(Span::styled(outer, Style::default().fg(Color::DarkGray)));
+        header_spans.push(Span::styled(" › ", Style::default().fg(Color::DarkGray)));
+    }
 
     if matches!(
         model.text_input_location,
@@ -75,24 +182,252 @@ fn render_header(model: &Model) -> Paragraph<'_> {
             &model.revset,
             Style::default().fg(Color::Green),
         ));
+        if let Some(author) = model.active_author_filter() {
+            header_spans.push(Span::raw("  "));
+            header_spans.push(Span::styled("author: ", Style::default().fg(Color::Blue)));
+            header_spans.push(Span::styled(author, Style::default().fg(Color::Yellow)));
+            header_spans.push(Span::styled(
+                " (U to clear)",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+    }
+    Paragraph::new(Line::from(header_spans))
+}
+
+fn render_status_bar(model: &Model) -> Paragraph<'_> {
+    let separator = || Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+    let mut spans = vec![Span::raw(" ")];
+
+    match model.get_selected_commit() {
+        Some(commit) => {
+            spans.push(Span::styled(
+                &commit.change_id,
+                Style::default().fg(Color::Magenta),
+            ));
+            if !commit.bookmarks.is_empty() {
+                spans.push(separator());
+                spans.push(Span::styled(
+                    commit.bookmarks.join(" "),
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+        }
+        None => spans.push(Span::styled(
+            "no commit selected",
+            Style::default().fg(Color::DarkGray),
+        )),
+    }
+
+    if model.has_pending_command_keys() {
+        let keys: Vec<String> = model
+            .pending_command_keys()
+            .iter()
+            .map(|key_code| key_code.to_string())
+            .collect();
+        spans.push(separator());
+        spans.push(Span::styled("keys: ", Style::default().fg(Color::Blue)));
+        spans.push(Span::styled(
+            keys.join(" "),
+            Style::default().fg(Color::Green),
+        ));
     }
+
+    if let Some(register) = model.macros.recording_register() {
+        spans.push(separator());
+        spans.push(Span::styled(
+            format!("recording @{register}"),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if let Some((action, count)) = model.macros.awaiting_register() {
+        let verb = match action {
+            MacroAction::Record => "record into",
+            MacroAction::Play => "play",
+        };
+        let count = if count > 0 {
+            format!("{count}× ")
+        } else {
+            String::new()
+        };
+        spans.push(separator());
+        spans.push(Span::styled(
+            format!("{count}{verb} register?"),
+            Style::default().fg(Color::Blue),
+        ));
+    }
+
     if model.global_args.ignore_immutable {
-        header_spans.push(Span::styled(
-            "  --ignore-immutable",
+        spans.push(separator());
+        spans.push(Span::styled(
+            "--ignore-immutable",
             Style::default().fg(Color::LightRed),
         ));
     }
-    Paragraph::new(Line::from(header_spans))
+
+    if let Some(fsmonitor) = &model.fsmonitor {
+        spans.push(separator());
+        spans.push(Span::styled(
+            fsmonitor.clone(),
+            Style::default().fg(Color::Green),
+        ));
+    }
+
+    if model.global_args.ignore_working_copy {
+        spans.push(separator());
+        spans.push(Span::styled(
+            "working copy not snapshotted, may be stale",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    if let Some(cmd) = model.running_jj_command() {
+        spans.push(separator());
+        spans.push(Span::styled(
+            format!("running: {}", cmd.to_command_string()),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    Paragraph::new(Line::from(spans)).style(theme::palette().status_bar)
+}
+
+fn render_log_list(model: &Model, items: Vec<Text<'static>>) -> List<'static> {
+    List::new(items)
+        .highlight_style(theme::palette().selection)
+        .highlight_symbol(theme::palette().selection_symbol)
+        .scroll_padding(model.log_list_scroll_padding)
+}
+
+/// The log, with the header lines of the commit and file its top is scrolled
+/// into pinned above it
+fn render_log(model: &mut Model, frame: &mut Frame, area: Rect) {
+    use ratatui::widgets::Clear;
+    let items = log_items(model);
+    let heights: Vec<usize> = items.iter().map(Text::height).collect();
+    let mut list_area = area;
+    // Pinning headers shrinks the list, which can scroll it to a new top
+    for _ in 0..3 {
+        scroll_log(model, &heights, list_area);
+        let pinned_height =
+            (model.sticky_header_indices().len() as u16).min(area.height.saturating_sub(1));
+        let shrunk_area = Rect {
+            y: area.y + pinned_height,
+            height: area.height - pinned_height,
+            ..area
+        };
+        if shrunk_area == list_area {
+            break;
+        }
+        list_area = shrunk_area;
+    }
+    scroll_log(model, &heights, list_area);
+    model.log_list_layout = list_area;
+
+    // Line the headers up with the list's, which are indented by the
+    // selection symbol
+    let indent = " ".repeat(theme::palette().selection_symbol.width());
+    let pinned: Vec<Line> = model
+        .sticky_header_indices()
+        .iter()
+        .filter_map(|idx| items.get(*idx)?.lines.first().cloned())
+        .map(|line| {
+            let mut spans = vec![Span::raw(indent.clone())];
+            spans.extend(line.spans);
+            Line::from(spans).patch_style(theme::palette().sticky_header)
+        })
+        .collect();
+    let pinned_area = Rect {
+        height: list_area.y - area.y,
+        ..area
+    };
+    frame.render_widget(Clear, area);
+    let log_list = render_log_list(model, items);
+    frame.render_stateful_widget(log_list, list_area, &mut model.log_list_state);
+    frame.render_widget(Paragraph::new(pinned), pinned_area);
 }
 
-fn render_log_list(model: &Model) -> List<'static> {
+/// Scroll the log to the top the list will pick when drawn in `area`, so the
+/// headers to pin are known before drawing it. Follows ratatui's `List`:
+/// the selection and its scroll padding are kept in view, moving the top as
+/// little as possible.
+fn scroll_log(model: &mut Model, heights: &[usize], area: Rect) {
+    let Some(last) = heights.len().checked_sub(1) else {
+        return;
+    };
+    if area.is_empty() {
+        return;
+    }
+    let height = area.height as usize;
+    let offset = model.log_list_state.offset().min(last);
+    let mut first = offset;
+    let mut end = offset;
+    let mut used = 0;
+    for item_height in &heights[offset..] {
+        if used + item_height > height {
+            break;
+        }
+        used += item_height;
+        end += 1;
+    }
+
+    let target = match model.log_list_state.selected() {
+        None => offset,
+        Some(selected) => {
+            let selected = selected.min(last);
+            // Less padding if the items around the selection don't fit
+            let mut padding = model.log_list_scroll_padding;
+            while padding > 0
+                && heights[selected.saturating_sub(padding)..=(selected + padding).min(last)]
+                    .iter()
+                    .sum::<usize>()
+                    > height
+            {
+                padding -= 1;
+            }
+            if (selected + padding).min(last) >= end {
+                selected + padding
+            } else if selected.saturating_sub(padding) < first {
+                selected.saturating_sub(padding)
+            } else {
+                selected
+            }
+            .min(last)
+        }
+    };
+
+    while target >= end {
+        used += heights[end];
+        end += 1;
+        while used > height {
+            used -= heights[first];
+            first += 1;
+        }
+    }
+    while target < first {
+        first -= 1;
+        used += heights[first];
+        while used > height {
+            end -= 1;
+            used -= heights[end];
+        }
+    }
+    *model.log_list_state.offset_mut() = first;
+}
+
+/// The log's lines as they're drawn, without the selection highlight
+pub fn log_items(model: &Model) -> Vec<ratatui::text::Text<'static>> {
     let mut log_items = model.log_list.clone();
     inject_virtual_bookmark(model, &mut log_items);
     inject_virtual_description(model, &mut log_items);
     apply_saved_selection_highlights(model, &mut log_items);
-    List::new(log_items)
-        .highlight_style(Style::new().bold().bg(SELECTION_COLOR))
-        .scroll_padding(model.log_list_scroll_padding)
+    dim_outside_lineage(model, &mut log_items);
+    apply_run_results(model, &mut log_items);
+    apply_badges(model, &mut log_items);
+    apply_ci_statuses(model, &mut log_items);
+    apply_review_marks(model, &mut log_items);
+    apply_notes(model, &mut log_items);
+    log_items
 }
 
 /// When bookmark editing is active, inject the virtual bookmark into the selected commit's line.
@@ -128,6 +463,26 @@ fn inject_virtual_bookmark(model: &Model, log_items: &mut [ratatui::text::Text<'
             .push(Span::styled(model.text_input.clone(), style));
         first_line.spans.push(Span::styled("]", style));
     }
+
+    // Show why the name can't be used right under the line being edited
+    if let Some(error) = model.text_input_name_error() {
+        // Continue the graph drawn in front of the commit's second line
+        let graph: String = text
+            .lines
+            .get(1)
+            .map(|line| strip_ansi(&line.to_string()))
+            .unwrap_or_default()
+            .chars()
+            .take_while(|c| c.is_whitespace() || ('\u{2500}'..='\u{257F}').contains(c))
+            .collect();
+        text.lines.insert(
+            1,
+            Line::from(vec![
+                Span::raw(graph),
+                Span::styled(error, Style::default().fg(Color::Red)),
+            ]),
+        );
+    }
 }
 
 /// Strip ANSI codes from all spans in a line
@@ -145,17 +500,24 @@ fn strip_ansi_from_line(line: &Line<'_>) -> Line<'static> {
 
 /// Render a single line of description with column limit styling.
 /// The real cursor is rendered via terminal ANSI codes, not inserted text.
-fn render_description_line(line_text: &str, line_idx: usize) -> Vec<Span<'static>> {
-    let col_limit = if line_idx == 0 { 50 } else { 72 };
-
+fn render_description_line(line_text: &str, col_limit: usize) -> Vec<Span<'static>> {
     if line_text.is_empty() {
         return Vec::new();
     }
 
-    if line_text.len() <= col_limit {
+    if line_text.width() <= col_limit {
         vec![Span::styled(line_text.to_string(), INPUT_STYLE)]
     } else {
-        let (within, beyond) = line_text.split_at(col_limit);
+        // Split on a grapheme boundary at the last grapheme that fits in the limit
+        let mut width = 0;
+        let split_idx = line_text
+            .grapheme_indices(true)
+            .find(|(_, grapheme)| {
+                width += grapheme.width();
+                width > col_limit
+            })
+            .map_or(line_text.len(), |(idx, _)| idx);
+        let (within, beyond) = line_text.split_at(split_idx);
         vec![
             Span::styled(within.to_string(), INPUT_STYLE),
             Span::styled(beyond.to_string(), GRAYED_OUT_STYLE),
@@ -205,12 +567,22 @@ fn inject_virtual_description(model: &Model, log_items: &mut [ratatui::text::Tex
 
         // Add description lines (real cursor is rendered via ANSI codes)
         for (line_idx, line_text) in desc_lines.iter().enumerate() {
-            let desc_spans = render_description_line(line_text, line_idx);
+            let col_limit = model.description_linter.line_limit(line_idx);
+            let desc_spans = render_description_line(line_text, col_limit);
             let mut all_spans = vec![prefix_span.clone(), Span::raw(" ")];
             all_spans.extend(desc_spans);
             new_lines.push(Line::from(all_spans));
         }
 
+        // Lint hints below the input
+        for hint in model.description_linter.hints(&model.text_input) {
+            new_lines.push(Line::from(vec![
+                prefix_span.clone(),
+                Span::raw(" "),
+                Span::styled(format!("⚠ {hint}"), HINT_STYLE),
+            ]));
+        }
+
         // Replace the text lines
         text.lines = new_lines;
     }
@@ -232,15 +604,343 @@ fn apply_saved_selection_highlights(model: &Model, log_items: &mut [ratatui::tex
     }
 }
 
+/// Gray out the commits that aren't ancestors or descendants of the selection
+fn dim_outside_lineage(model: &Model, log_items: &mut [ratatui::text::Text<'static>]) {
+    for commit_or_text in &model.jj_log.log_tree {
+        let CommitOrText::Commit(commit) = commit_or_text else {
+            continue;
+        };
+        if !model.is_outside_lineage(&commit.commit_id) {
+            continue;
+        }
+        let Some(item) = log_items.get_mut(commit.flat_log_idx) else {
+            continue;
+        };
+        for span in item.lines.iter_mut().flat_map(|line| line.spans.iter_mut()) {
+            span.style = span.style.patch(GRAYED_OUT_STYLE);
+        }
+    }
+}
+
+/// Mark commits with whether the last command run on a range passed on them
+fn apply_run_results(model: &Model, log_items: &mut [ratatui::text::Text<'static>]) {
+    if model.run_results.is_empty() {
+        return;
+    }
+    for commit_or_text in &model.jj_log.log_tree {
+        let CommitOrText::Commit(commit) = commit_or_text else {
+            continue;
+        };
+        let Some(passed) = model
+            .run_results
+            .iter()
+            .find(|(change_id, _)| change_id.starts_with(&commit.change_id))
+            .map(|(_, passed)| *passed)
+        else {
+            continue;
+        };
+        let Some(line) = log_items
+            .get_mut(commit.flat_log_idx)
+            .and_then(|item| item.lines.first_mut())
+        else {
+            continue;
+        };
+        let (mark, color) = if passed {
+            (" ✓", Color::Green)
+        } else {
+            (" ✗", Color::Red)
+        };
+        line.spans.push(Span::styled(
+            mark,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+    }
+}
+
+fn apply_badges(model: &Model, log_items: &mut [ratatui::text::Text<'static>]) {
+    if model.badges.is_empty() {
+        return;
+    }
+    for commit_or_text in &model.jj_log.log_tree {
+        let CommitOrText::Commit(commit) = commit_or_text else {
+            continue;
+        };
+        let Some(badges) = model.badges.get(&commit.commit_id) else {
+            continue;
+        };
+        if let Some(line) = log_items
+            .get_mut(commit.flat_log_idx)
+            .and_then(|item| item.lines.first_mut())
+        {
+            // Colored text rather than a background, which the selection
+            // highlight would paint over
+            for (text, color) in badges {
+                line.spans.push(Span::styled(
+                    format!(" [{text}]"),
+                    Style::default().fg(*color).add_modifier(Modifier::BOLD),
+                ));
+            }
+        }
+    }
+}
+
+/// Columns of a note shown on its commit's line, `v n` showing all of it
+const NOTE_PREVIEW_WIDTH: usize = 30;
+
+fn apply_notes(model: &Model, log_items: &mut [ratatui::text::Text<'static>]) {
+    if model.notes.is_empty() {
+        return;
+    }
+    for commit_or_text in &model.jj_log.log_tree {
+        let CommitOrText::Commit(commit) = commit_or_text else {
+            continue;
+        };
+        let Some(note) = model.notes.get(&commit.change_id) else {
+            continue;
+        };
+        let Some(line) = log_items
+            .get_mut(commit.flat_log_idx)
+            .and_then(|item| item.lines.first_mut())
+        else {
+            continue;
+        };
+        let mut preview = String::new();
+        for grapheme in note.graphemes(true) {
+            if preview.width() + grapheme.width() > NOTE_PREVIEW_WIDTH {
+                preview.push('…');
+                break;
+            }
+            preview.push_str(grapheme);
+        }
+        line.spans.push(Span::styled(
+            format!(" [✎ {preview}]"),
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+}
+
+/// In review mode, check off the files marked viewed and show how many of
+/// each commit's files are
+fn apply_review_marks(model: &Model, log_items: &mut [ratatui::text::Text<'static>]) {
+    if !model.review_mode {
+        return;
+    }
+    for commit_or_text in &model.jj_log.log_tree {
+        let CommitOrText::Commit(commit) = commit_or_text else {
+            continue;
+        };
+        for file_diff in commit.shown_file_diffs() {
+            let Some(line) = log_items
+                .get_mut(file_diff.flat_log_idx())
+                .and_then(|item| item.lines.first_mut())
+            else {
+                continue;
+            };
+            let mark = if model.is_file_viewed(&commit.change_id, &file_diff.path) {
+                Span::styled("☑ ", Style::default().fg(Color::Green))
+            } else {
+                Span::styled("☐ ", Style::default().fg(Color::DarkGray))
+            };
+            // After the graph and the fold symbol
+            let mark_idx = line.spans.len().min(3);
+            line.spans.insert(mark_idx, mark);
+        }
+
+        // Only counted out of the commit's files once they're loaded
+        let (progress, color) = match commit.file_diffs() {
+            Some([]) => continue,
+            Some(file_diffs) => {
+                let viewed = file_diffs
+                    .iter()
+                    .filter(|file_diff| model.is_file_viewed(&commit.change_id, &file_diff.path))
+                    .count();
+                let color = if viewed == file_diffs.len() {
+                    Color::Green
+                } else {
+                    Color::Yellow
+                };
+                (format!(" [{viewed}/{} viewed]", file_diffs.len()), color)
+            }
+            None => match model.viewed_file_count(&commit.change_id) {
+                0 => continue,
+                viewed => (format!(" [{viewed} viewed]"), Color::Yellow),
+            },
+        };
+        if let Some(line) = log_items
+            .get_mut(commit.flat_log_idx)
+            .and_then(|item| item.lines.first_mut())
+        {
+            line.spans.push(Span::styled(
+                progress,
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ));
+        }
+    }
+}
+
+fn apply_ci_statuses(model: &Model, log_items: &mut [ratatui::text::Text<'static>]) {
+    if model.ci_statuses.is_empty() {
+        return;
+    }
+    for commit_or_text in &model.jj_log.log_tree {
+        let CommitOrText::Commit(commit) = commit_or_text else {
+            continue;
+        };
+        let Some(status) = model.ci_statuses.get(&commit.commit_id) else {
+            continue;
+        };
+        if let Some(line) = log_items
+            .get_mut(commit.flat_log_idx)
+            .and_then(|item| item.lines.first_mut())
+        {
+            let (glyph, style) = status.state.glyph();
+            line.spans.push(Span::styled(format!(" {glyph}"), style));
+        }
+    }
+}
+
 fn apply_saved_selection_highlight(text: &mut ratatui::text::Text<'static>) {
-    text.style = text.style.bg(SAVED_SELECTION_COLOR);
+    let saved_selection = theme::palette().saved_selection;
+    text.style = text.style.patch(saved_selection);
     for line in &mut text.lines {
         for span in &mut line.spans {
-            span.style = span.style.bg(SAVED_SELECTION_COLOR);
+            span.style = span.style.patch(saved_selection);
+        }
+    }
+}
+
+/// Shown instead of everything else until the terminal is at least
+/// `MIN_WIDTH` by `MIN_HEIGHT`
+fn render_too_small(frame: &mut Frame, area: Rect) {
+    let lines = vec![
+        Line::styled(
+            "Terminal too small",
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Line::raw(format!(
+            "Please enlarge it to {MIN_WIDTH}x{MIN_HEIGHT} (it is {}x{})",
+            area.width, area.height
+        )),
+    ];
+    let top = area.height.saturating_sub(lines.len() as u16) / 2;
+    frame.render_widget(
+        Paragraph::new(lines)
+            .alignment(ratatui::layout::Alignment::Center)
+            .wrap(ratatui::widgets::Wrap { trim: true }),
+        Rect::new(area.x, area.y + top, area.width, area.height - top),
+    );
+}
+
+/// The latest timings of the profiled stages, above the status bar's right end
+fn render_profile_overlay(frame: &mut Frame, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    let text = format!(" {} ", profile::overlay_text());
+    let width = (text.width() as u16).min(area.width);
+    if width == 0 || area.height < 2 {
+        return;
+    }
+    let overlay_area = Rect::new(area.right() - width, area.bottom() - 2, width, 1);
+    frame.render_widget(Clear, overlay_area);
+    frame.render_widget(
+        Paragraph::new(text).style(Style::default().fg(Color::Black).bg(Color::Magenta)),
+        overlay_area,
+    );
+}
+
+/// Render transient toasts stacked in the top-right corner
+fn render_toasts(model: &Model, frame: &mut Frame, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    for (idx, toast) in model.toasts.iter().rev().enumerate() {
+        let width = (toast.message.width() as u16 + 4).min(area.width);
+        let y = area.y + 1 + idx as u16;
+        if y >= area.bottom() {
+            break;
         }
+        let toast_area = Rect::new(area.right() - width, y, width, 1);
+        frame.render_widget(Clear, toast_area);
+        frame.render_widget(
+            Paragraph::new(format!("  {}  ", toast.message)).style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            toast_area,
+        );
     }
 }
 
+/// Render the completion matches under the word being completed, which
+/// starts at column `word_x` of row `input_y`
+fn render_completion_menu(model: &Model, frame: &mut Frame, word_x: u16, input_y: u16) {
+    use ratatui::widgets::Clear;
+
+    let Some(completion) = model.text_input_completion() else {
+        return;
+    };
+    let area = frame.area();
+    let first = completion
+        .selected
+        .saturating_sub(COMPLETION_MENU_ROWS - 1)
+        .min(
+            completion
+                .matches
+                .len()
+                .saturating_sub(COMPLETION_MENU_ROWS),
+        );
+    let visible =
+        &completion.matches[first..(first + COMPLETION_MENU_ROWS).min(completion.matches.len())];
+
+    let width = visible.iter().map(|item| item.width()).max().unwrap_or(0) as u16 + 4;
+    // Line the matches up with the word, past the border and padding
+    let x = word_x.saturating_sub(2);
+    let y = input_y + 1;
+    if y >= area.bottom() {
+        return;
+    }
+    let menu_area = Rect::new(
+        x.min(area.right().saturating_sub(width)),
+        y,
+        width.min(area.width),
+        (visible.len() as u16 + 2).min(area.bottom() - y),
+    );
+
+    let lines: Vec<Line> = visible
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| {
+            if first + idx == completion.selected {
+                Line::styled(
+                    format!(" {item} "),
+                    Style::default()
+                        .bg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Line::raw(format!(" {item} "))
+            }
+        })
+        .collect();
+    frame.render_widget(Clear, menu_area);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray))
+                .title(format!(
+                    " {}/{} ",
+                    completion.selected + 1,
+                    completion.matches.len()
+                )),
+        ),
+        menu_area,
+    );
+}
+
 /// Render a centered popup for fuzzy selection
 fn render_popup(
     model: &Model,
@@ -248,8 +948,6 @@ fn render_popup(
     popup: Option<&crate::update::Popup>,
     area: Rect,
 ) {
-    use ratatui::widgets::{Clear, Wrap};
-
     // Handle text input popup separately
     if let crate::update::TextInputLocation::Popup {
         prompt,
@@ -257,7 +955,7 @@ fn render_popup(
         ..
     } = &model.text_input_location
     {
-        render_text_prompt_popup(model, frame, *prompt, *placeholder, area);
+        render_text_prompt_popup(model, frame, prompt, placeholder, area);
         return;
     }
 
@@ -266,9 +964,27 @@ fn render_popup(
         return;
     };
 
+    if let crate::update::Popup::Help { .. } = popup {
+        render_help_popup(model, frame, popup, area);
+        return;
+    }
+
+    render_selection_popup(frame, popup, &model.popup_filter, &model.popup_list, area);
+}
+
+/// Render a centered fuzzy selection popup
+pub fn render_selection_popup(
+    frame: &mut Frame,
+    popup: &crate::update::Popup,
+    filter: &str,
+    list_state: &crate::update::PopupListState,
+    area: Rect,
+) {
+    use ratatui::widgets::{Clear, Wrap};
+
     // Calculate popup size
-    let popup_width = (area.width * 2 / 3).min(60).max(40);
-    let popup_height = (area.height * 2 / 3).min(20).max(10);
+    let popup_width = (area.width * 2 / 3).clamp(40, 60);
+    let popup_height = (area.height * 2 / 3).clamp(10, 20);
     let popup_x = (area.width - popup_width) / 2;
     let popup_y = (area.height - popup_height) / 2;
     let popup_area = Rect::new(popup_x, popup_y, popup_width, popup_height);
@@ -277,20 +993,16 @@ fn render_popup(
     frame.render_widget(Clear, popup_area);
 
     // Get items and filter them
-    let items = popup.items();
-    let filtered_items: Vec<&String> = items
-        .iter()
-        .filter(|item| {
-            let filter_lower = model.popup_filter.to_lowercase();
-            let item_lower = item.to_lowercase();
-            filter_lower.is_empty() || item_lower.contains(&filter_lower)
-        })
-        .collect();
+    let filtered_items = popup.matched_items(filter);
 
     // Build popup content
     let title = format!(" {} ", popup.title());
-    let filter_line = format!("> {}", model.popup_filter);
-    let help_line = "Enter: select | Esc: cancel | ↑↓: navigate";
+    let filter_line = format!("> {}", filter);
+    let help_line = if popup.allows_marking() {
+        "Enter: select | Tab: mark | Esc: cancel | ↑↓: navigate"
+    } else {
+        "Enter: select | Esc: cancel | ↑↓/PgUp/PgDn: navigate"
+    };
 
     let mut lines = vec![
         Line::from(vec![Span::styled(
@@ -307,22 +1019,22 @@ fn render_popup(
 
     // Add filtered items
     let max_visible_items = popup_height.saturating_sub(5) as usize;
-    let selection = model
-        .popup_selection
-        .min(filtered_items.len().saturating_sub(1));
-
-    // Calculate scroll offset to keep selection visible
-    let scroll_offset = if selection >= max_visible_items {
-        selection - max_visible_items + 1
-    } else {
-        0
-    };
+    let selection = list_state.selected();
+    // Items start below the border, title, filter line and two spacers
+    let list_area = Rect::new(
+        popup_x + 1,
+        popup_y + 5,
+        popup_width.saturating_sub(2),
+        max_visible_items as u16,
+    );
+    list_state.set_popup_area(popup_area);
+    let visible_range = list_state.visible_range(filtered_items.len(), list_area);
 
     for (idx, item) in filtered_items
         .iter()
         .enumerate()
-        .skip(scroll_offset)
-        .take(max_visible_items)
+        .skip(visible_range.start)
+        .take(visible_range.len())
     {
         let is_selected = idx == selection;
         let style = if is_selected {
@@ -332,13 +1044,24 @@ fn render_popup(
         } else {
             Style::default()
         };
-        lines.push(Line::from(vec![
-            Span::styled(format!(" {} ", if is_selected { "▸" } else { " " }), style),
-            Span::styled(
-                format!("{:<width$}", item, width = popup_width as usize - 4),
-                style,
+        let mut spans = vec![Span::styled(
+            format!(
+                "{}{} ",
+                if is_selected { "▸" } else { " " },
+                if list_state.is_marked(item.item) {
+                    "●"
+                } else {
+                    " "
+                }
             ),
-        ]));
+            style,
+        )];
+        let padding = (popup_width as usize - 4).saturating_sub(item.item.width());
+        let padded_item = format!("{}{}", item.item, " ".repeat(padding));
+        spans.extend(fuzzy_highlighted_spans(&padded_item, &item.indices, |_| {
+            style
+        }));
+        lines.push(Line::from(spans));
     }
 
     // Fill remaining space
@@ -363,6 +1086,134 @@ fn render_popup(
     frame.render_widget(paragraph, popup_area);
 }
 
+/// Render the searchable help overlay, filling the whole screen
+fn render_help_popup(model: &Model, frame: &mut Frame, popup: &crate::update::Popup, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    frame.render_widget(Clear, area);
+
+    let filtered_items = popup.matched_items(&model.popup_filter);
+    let title = format!(
+        " {} ({}/{}) ",
+        popup.title(),
+        filtered_items.len(),
+        popup.items().len()
+    );
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::raw(format!("> {}", model.popup_filter)),
+            Span::styled("_", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![]), // spacer
+    ];
+
+    // Borders, filter line, spacer, spacer and help line
+    let max_visible_items = area.height.saturating_sub(6) as usize;
+    let selection = model.popup_list.selected();
+    // Items start below the border, filter line and spacer
+    let list_area = Rect::new(
+        area.x + 1,
+        area.y + 3,
+        area.width.saturating_sub(2),
+        max_visible_items as u16,
+    );
+    model.popup_list.set_popup_area(area);
+    let visible_range = model
+        .popup_list
+        .visible_range(filtered_items.len(), list_area);
+
+    for (idx, item) in filtered_items
+        .iter()
+        .enumerate()
+        .skip(visible_range.start)
+        .take(visible_range.len())
+    {
+        let is_selected = idx == selection;
+        let base_style = if is_selected {
+            theme::palette().selection
+        } else {
+            Style::default()
+        };
+
+        // Items are formatted as "<chord>  <description>  (<group>)"
+        let item = item.item;
+        let chord_end = item
+            .find("  ")
+            .map_or(usize::MAX, |idx| item[..idx].chars().count());
+        let group_start = item
+            .rfind("  (")
+            .map_or(usize::MAX, |idx| item[..idx].chars().count());
+        let mut spans = vec![Span::styled(
+            if is_selected { " ▸ " } else { "   " },
+            base_style,
+        )];
+        spans.extend(fuzzy_highlighted_spans(
+            item,
+            &filtered_items[idx].indices,
+            |char_idx| {
+                if char_idx < chord_end {
+                    base_style.fg(Color::Green)
+                } else if char_idx >= group_start {
+                    base_style.fg(Color::Blue)
+                } else {
+                    base_style
+                }
+            },
+        ));
+        lines.push(Line::from(spans));
+    }
+
+    for _ in 0..max_visible_items.saturating_sub(filtered_items.len()) {
+        lines.push(Line::from(vec![Span::raw("")]));
+    }
+
+    lines.push(Line::from(vec![])); // spacer
+    lines.push(Line::from(vec![Span::styled(
+        "Type to search | ↑↓: navigate | Enter/Esc: close",
+        Style::default().fg(Color::DarkGray),
+    )]));
+
+    let paragraph = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .title(Span::styled(
+                title,
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Blue)),
+    );
+
+    frame.render_widget(paragraph, area);
+}
+
+/// Split text into spans styled per char by `style_at`, highlighting the chars at
+/// the fuzzy-matched `indices`
+fn fuzzy_highlighted_spans(
+    text: &str,
+    indices: &[usize],
+    style_at: impl Fn(usize) -> Style,
+) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut current = String::new();
+    let mut current_style = Style::default();
+    for (char_idx, ch) in text.chars().enumerate() {
+        let mut style = style_at(char_idx);
+        if indices.binary_search(&char_idx).is_ok() {
+            style = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        }
+        if style != current_style && !current.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut current), current_style));
+        }
+        current_style = style;
+        current.push(ch);
+    }
+    if !current.is_empty() {
+        spans.push(Span::styled(current, current_style));
+    }
+    spans
+}
+
 /// Render a text prompt popup for single-line input
 fn render_text_prompt_popup(
     model: &Model,
@@ -374,7 +1225,7 @@ fn render_text_prompt_popup(
     use ratatui::widgets::Clear;
 
     // Calculate popup size - fixed height for text prompt
-    let popup_width = (area.width * 2 / 3).min(60).max(40);
+    let popup_width = (area.width * 2 / 3).clamp(40, 60);
     let popup_height = 7u16; // Fixed height: title + spacer + prompt + input + spacer + help
     let popup_x = (area.width - popup_width) / 2;
     let popup_y = (area.height - popup_height) / 2;
@@ -385,7 +1236,11 @@ fn render_text_prompt_popup(
 
     // Build text prompt content
     let title = format!(" {} ", prompt);
-    let help_line = "Enter: confirm | Esc: cancel";
+    let help_line = if model.text_input_location.completion_kind().is_some() {
+        "Enter: confirm | Tab: complete | Esc: cancel"
+    } else {
+        "Enter: confirm | Esc: cancel"
+    };
 
     // Build input line - real cursor is rendered via frame.set_cursor_position()
     let mut input_line = vec![Span::raw("> ")];
@@ -401,6 +1256,10 @@ fn render_text_prompt_popup(
         input_line.push(Span::styled(model.text_input.clone(), Style::default()));
     }
 
+    let error_line = match model.text_input_name_error() {
+        Some(error) => Line::styled(error, Style::default().fg(Color::Red)),
+        None => Line::from(vec![]), // spacer
+    };
     let mut lines = vec![
         Line::from(vec![Span::styled(
             title,
@@ -408,7 +1267,7 @@ fn render_text_prompt_popup(
         )]),
         Line::from(vec![]), // spacer
         Line::from(input_line),
-        Line::from(vec![]), // spacer
+        error_line,
     ];
 
     lines.push(Line::from(vec![Span::styled(
@@ -423,15 +1282,185 @@ fn render_text_prompt_popup(
     );
 
     frame.render_widget(paragraph, popup_area);
+
+    if let Some(completion) = model.text_input_completion() {
+        // Border, title and spacer above the input, border and "> " before it
+        let word_x = popup_x + 3 + model.text_input[..completion.word_start()].width() as u16;
+        render_completion_menu(model, frame, word_x, popup_y + 3);
+    }
+}
+
+fn render_log_viewer(model: &mut Model, frame: &mut Frame, area: Rect) {
+    use ratatui::widgets::Clear;
+
+    let title = match crate::logger::FileLogger::log_file() {
+        Some(path) => format!(" {} ", path.display()),
+        None => " Logging is disabled ".to_string(),
+    };
+    let read_error;
+    let lines = match model.log_tail.lines() {
+        Ok(lines) => lines,
+        Err(err) => {
+            read_error = [format!("Cannot read log file: {err}")];
+            &read_error[..]
+        }
+    };
+
+    let height = area.height.saturating_sub(3) as usize;
+    let max_scroll = lines.len().saturating_sub(height);
+    let scroll = model.log_viewer_scroll.unwrap_or_default().min(max_scroll);
+    // Keep the stored offset in range so scrolling back down responds immediately
+    model.log_viewer_scroll = Some(scroll);
+    let end = lines.len() - scroll;
+    let start = end.saturating_sub(height);
+
+    let level_style = |line: &str| {
+        if line.contains("] [ERROR] ") {
+            Style::default().fg(Color::Red)
+        } else if line.contains("] [WARN] ") {
+            Style::default().fg(Color::Yellow)
+        } else if line.contains("] [DEBUG] ") || line.contains("] [TRACE] ") {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default()
+        }
+    };
+    let mut text: Vec<Line> = lines[start..end]
+        .iter()
+        .map(|line| Line::styled(line.as_str(), level_style(line)))
+        .collect();
+    text.push(Line::styled(
+        format!(
+            "{}/{}  j/k PgUp/PgDn g/G: scroll | F12/Esc: close",
+            end,
+            lines.len()
+        ),
+        Style::default().fg(Color::Blue),
+    ));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Blue)),
+        ),
+        area,
+    );
+}
+
+fn render_file_browser(model: &mut Model, frame: &mut Frame, area: Rect) {
+    let Some(browser) = &mut model.file_browser else {
+        return;
+    };
+    let items: Vec<Line> = browser
+        .rows()
+        .into_iter()
+        .map(|row| {
+            let indent = "  ".repeat(row.depth);
+            if row.is_dir {
+                let marker = if row.expanded { "▾" } else { "▸" };
+                Line::styled(
+                    format!("{indent}{marker} {}/", row.name),
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Line::raw(format!("{indent}  {}", row.name))
+            }
+        })
+        .collect();
+    let title = format!(
+        " Files in {} ",
+        &browser.change_id[..8.min(browser.change_id.len())]
+    );
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .title_bottom(
+                    " Enter: open/expand | h/l: collapse/expand | r: restore into @ | q/Esc: close ",
+                )
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Blue)),
+        )
+        .highlight_style(theme::palette().selection)
+        .highlight_symbol(theme::palette().selection_symbol);
+    frame.render_stateful_widget(list, area, &mut browser.list_state);
+}
+
+fn render_remote_bookmarks(model: &mut Model, frame: &mut Frame, area: Rect) {
+    use crate::remote_bookmarks::RemoteBookmarkRow;
+    let Some(browser) = &mut model.remote_bookmarks else {
+        return;
+    };
+    let items: Vec<Line> = browser
+        .rows()
+        .into_iter()
+        .map(|row| match row {
+            RemoteBookmarkRow::Remote { name, count } => Line::from(vec![
+                Span::styled(
+                    format!("@{name}"),
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(format!("  {count}"), Style::default().fg(Color::DarkGray)),
+            ]),
+            RemoteBookmarkRow::Bookmark(bookmark) => {
+                let (tracking, tracking_style) = if bookmark.tracked {
+                    ("tracked  ", Style::default().fg(Color::Green))
+                } else {
+                    ("untracked", Style::default().fg(Color::DarkGray))
+                };
+                Line::from(vec![
+                    Span::raw("  "),
+                    Span::styled(bookmark.name.clone(), Style::default().fg(Color::Magenta)),
+                    Span::raw("  "),
+                    Span::styled(tracking, tracking_style),
+                    Span::raw("  "),
+                    Span::styled(
+                        bookmark.target.clone(),
+                        Style::default().fg(Color::LightCyan),
+                    ),
+                    Span::raw(" "),
+                    Span::raw(bookmark.description.clone()),
+                ])
+            }
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(" Remote Bookmarks ")
+                .title_bottom(
+                    " t: track/untrack | f: fetch bookmark (remote on its line) | c: create local bookmark | q/Esc: close ",
+                )
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Blue)),
+        )
+        .highlight_style(theme::palette().selection)
+        .highlight_symbol(theme::palette().selection_symbol);
+    frame.render_stateful_widget(list, area, &mut browser.list_state);
 }
 
-fn render_info_list(model: &Model) -> Option<List<'static>> {
-    let info_list = model.info_list.as_ref()?;
-    Some(
-        List::new(info_list.clone()).block(
+fn render_info_list(model: &Model) -> Option<Paragraph<'static>> {
+    let info = Paragraph::new(shown_info(model)?.clone());
+    // The bottom pane is as tall as its lines, the right one is narrow
+    Some(match model.info_pane {
+        InfoPanePosition::Bottom => info.block(
             Block::default()
                 .borders(Borders::TOP)
                 .border_style(Style::default().fg(Color::Blue)),
         ),
-    )
+        InfoPanePosition::Right => info
+            .block(
+                Block::default()
+                    .borders(Borders::LEFT)
+                    .border_style(Style::default().fg(Color::Blue)),
+            )
+            .wrap(ratatui::widgets::Wrap { trim: false }),
+    })
 }
@@ -0,0 +1,1786 @@
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use jjdag_core::{
+    config::{
+        Badge, CiProvider, Config, DescriptionLintConfig, DescriptionTemplate, NotificationConfig,
+    },
+    headless::Headless,
+    model::GlobalArgs,
+    profile, session,
+    shell_out::JjCommand,
+    testing::FakeJj,
+};
+use ratatui::style::Color;
+use std::{
+    path::Path,
+    process::{Command, Output},
+    sync::Once,
+    time::Duration,
+};
+
+const LOG: &str = "\
+@  qpvuntsm alice@example.com 2024-01-02 10:00:00 1a2b3c4d
+│  (empty) (no description set)
+○  rlvkpnrz alice@example.com 2024-01-01 09:00:00 main 5e6f7a8b
+│  Add the parser
+◆  zzzzzzzz root() 00000000
+";
+
+fn start(jj: &FakeJj) -> Headless {
+    start_with(jj, &Config::default())
+}
+
+/// How `LOG`'s commits are asked for their local bookmarks
+const LOCAL_BOOKMARKS: [&str; 4] = ["log", "-r", "1a2b3c4d | 5e6f7a8b | 00000000", "-T"];
+
+fn start_with(jj: &FakeJj, config: &Config) -> Headless {
+    jj.on(&LOCAL_BOOKMARKS, "1a2b3c4d\t\n5e6f7a8b\tmain\n00000000\t\n");
+    jj.start(LOG, config, 100, 30).unwrap()
+}
+
+/// Keep what jjdag saves between runs, like viewed files, out of the real
+/// state directory
+fn isolate_state() {
+    static ISOLATE: Once = Once::new();
+    ISOLATE.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("jjdag-state-{}", std::process::id()));
+        // SAFETY: nothing else sets environment variables while tests run
+        unsafe { std::env::set_var("XDG_STATE_HOME", dir) };
+    });
+}
+
+#[test]
+fn renders_the_log() {
+    let jj = FakeJj::new();
+    let jjdag = start(&jj);
+    let screen = jjdag.screen();
+    assert!(screen.contains("qpvuntsm"), "{screen}");
+    assert!(screen.contains("Add the parser"), "{screen}");
+}
+
+#[test]
+fn abandons_the_selection_and_reloads() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on_stderr(&["abandon"], "Abandoned 1 commits");
+    jjdag.send_keys("aa").unwrap();
+    assert!(jj.ran(&["abandon", "qpvuntsm"]), "{:?}", jj.calls());
+    let log_loads = jj
+        .calls()
+        .iter()
+        .filter(|call| call[..2] == ["log", "--template"])
+        .count();
+    assert_eq!(log_loads, 2);
+    assert!(jjdag.screen().contains("Abandoned 1 commits"));
+}
+
+#[test]
+fn shows_why_a_command_failed() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.fail(&["abandon"], "Error: Revision `qpvuntsm` doesn't exist");
+    jjdag.send_keys("aa").unwrap();
+    let screen = jjdag.screen();
+    assert!(
+        screen.contains("Revision `qpvuntsm` doesn't exist"),
+        "{screen}"
+    );
+    assert!(screen.contains("to refresh it"), "{screen}");
+}
+
+#[test]
+fn retries_a_command_on_an_immutable_commit() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.fail(&["abandon"], "Error: Commit 1a2b3c4d is immutable");
+    jjdag.send_keys("aa").unwrap();
+    let screen = jjdag.screen();
+    assert!(
+        screen.contains("Retry `jj abandon qpvuntsm` with --ignore-immutable"),
+        "{screen}"
+    );
+
+    jj.on_stderr(&["abandon"], "Abandoned 1 commits");
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(
+        jj.ran(&["abandon", "qpvuntsm", "--ignore-immutable"]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn retries_with_a_raised_snapshot_limit() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.fail(
+        &["abandon"],
+        "Error: Failed to snapshot the working copy
+Refused to snapshot some files:
+  big.bin: 15.0MiB (15728640 bytes); the maximum size allowed is 1.0MiB (1048576 bytes)",
+    );
+    jjdag.send_keys("aa").unwrap();
+    let screen = jjdag.screen();
+    assert!(
+        screen.contains("Add big.bin to .gitignore and retry"),
+        "{screen}"
+    );
+
+    jj.on_stderr(&["abandon"], "Abandoned 1 commits");
+    for code in [KeyCode::Down, KeyCode::Enter] {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+    assert!(
+        jj.ran(&[
+            "abandon",
+            "qpvuntsm",
+            "--config",
+            "snapshot.max-new-file-size=15728640"
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn offers_to_track_bookmarks_a_fetch_brought_in() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on_stderr(
+        &["git", "fetch"],
+        "bookmark: feature@origin [new] untracked
+bookmark: main@origin       [updated] tracked
+bookmark: topic@origin      [new] untracked",
+    );
+    jjdag.send_keys("gff").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Track Fetched Bookmarks"), "{screen}");
+
+    for code in [KeyCode::Tab, KeyCode::Tab, KeyCode::Enter] {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+    assert!(
+        jj.ran(&["bookmark", "track", "feature@origin", "topic@origin"]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn asks_which_bookmarks_to_tug() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(&["bookmark", "list", "-r"], "main\nrelease\n");
+    jjdag.send_keys("bT").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Bookmarks to Tug"), "{screen}");
+
+    for code in [KeyCode::Down, KeyCode::Enter] {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+    assert!(
+        jj.ran(&["bookmark", "move", "release", "--to", "@"]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn describes_a_new_change_right_away() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    // The new change is the working copy, not the selection it was made on
+    jj.on(&["log", "-r", "@"], "mzvwutvlkqwt\n");
+    jjdag.send_keys("Jnd").unwrap();
+    assert!(jj.ran(&["new", "rlvkpnrz"]), "{:?}", jj.calls());
+
+    jjdag.send_keys("Start the lexer").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(
+        jj.ran(&["describe", "mzvwutvlkqwt", "--message", "Start the lexer"]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn previews_the_operation_before_undoing_it() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["op", "log"],
+        "@  8f2c1d0e alice@example.com 2 minutes ago\n│  abandon commit 5e6f7a8b\n",
+    );
+    jj.on(
+        &["op", "diff"],
+        "Changed commits:\n- rlvkpnrz 5e6f7a8b Add the parser\n",
+    );
+    jjdag.send_keys("uu").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("abandon commit 5e6f7a8b"), "{screen}");
+    assert!(screen.contains("- rlvkpnrz 5e6f7a8b"), "{screen}");
+    assert!(!jj.ran(&["undo"]), "{:?}", jj.calls());
+
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(jj.ran(&["undo"]), "{:?}", jj.calls());
+}
+
+#[test]
+fn undoes_only_what_jjdag_ran() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on_each(
+        &["op", "log", "-n1", "--no-graph", "-T", "id"],
+        &["before", "after"],
+    );
+    jj.on(
+        &["op", "log", "--at-op", "after"],
+        "after\nsnapshot\nbefore\nolder\n",
+    );
+    jjdag.send_keys("aa").unwrap();
+    jjdag.send_keys("uj").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Undoes `jj abandon qpvuntsm`"), "{screen}");
+
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    let calls = jj.calls();
+    let reverts: Vec<_> = calls
+        .iter()
+        .filter(|call| call[..2] == ["op", "revert"])
+        .collect();
+    assert_eq!(
+        reverts,
+        [&["op", "revert", "after"], &["op", "revert", "snapshot"]],
+        "{calls:?}"
+    );
+    assert!(!jj.ran(&["op", "restore"]), "{calls:?}");
+}
+
+#[test]
+fn browses_without_snapshotting_when_toggled() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("F ").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("may be stale"), "{screen}");
+    let log_call = jj
+        .calls()
+        .into_iter()
+        .rfind(|call| call.first().is_some_and(|arg| arg == "log"))
+        .unwrap();
+    assert!(
+        log_call.contains(&"--ignore-working-copy".to_string()),
+        "{log_call:?}"
+    );
+
+    jjdag.send_keys("aa").unwrap();
+    assert!(
+        jj.calls()
+            .iter()
+            .filter(|call| call.first().is_some_and(|arg| arg == "abandon"))
+            .all(|call| !call.contains(&"--ignore-working-copy".to_string())),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn shows_the_sides_of_a_conflicted_file() {
+    let jj = FakeJj::new();
+    jj.on(&["diff", "--summary"], "M src/parser.rs\n");
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["file", "show"],
+        "fn parse() {
+<<<<<<< Conflict 1 of 1
+%%%%%%% Changes from base to side #1
+-    old();
++    ours();
++++++++ Contents of side #2
+    theirs();
+>>>>>>> Conflict 1 of 1 ends
+}
+",
+    );
+    jjdag.send_keys("jj").unwrap();
+    jjdag.send_keys("fc").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("1 conflict in src/parser.rs"), "{screen}");
+    assert!(screen.contains("+++++++ Contents of side #2"), "{screen}");
+    assert!(
+        jj.ran(&["file", "show", "-r", "qpvuntsm", "--", "src/parser.rs"]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn resolves_a_file_by_taking_a_side() {
+    let jj = FakeJj::new();
+    jj.on(&["diff", "--summary"], "M src/parser.rs\n");
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("jjf2").unwrap();
+    assert!(
+        jj.ran(&[
+            "resolve",
+            "-r",
+            "qpvuntsm",
+            "--tool",
+            ":theirs",
+            "src/parser.rs"
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn picks_a_merge_tool_and_offers_it_first_next_time() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["config", "list"],
+        r#"merge-tools.difft.diff-args = ["--color=always", "$left", "$right"]
+merge-tools.meld.merge-args = ["$left", "$base", "$right", "-o", "$output"]
+merge-tools.meld.program = "meld"
+"#,
+    );
+    jjdag.send_keys("X").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("meld"), "{screen}");
+    assert!(!screen.contains("difft"), "{screen}");
+
+    for code in [KeyCode::Down, KeyCode::Down, KeyCode::Enter] {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+    assert!(
+        jj.ran(&["resolve", "-r", "qpvuntsm", "--tool", "meld"]),
+        "{:?}",
+        jj.calls()
+    );
+
+    jjdag.send_keys("X").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    let resolves: Vec<Vec<String>> = jj
+        .calls()
+        .into_iter()
+        .filter(|call| call.first().is_some_and(|arg| arg == "resolve"))
+        .collect();
+    assert_eq!(resolves.len(), 2, "{resolves:?}");
+    assert_eq!(resolves[0], resolves[1]);
+}
+
+#[test]
+fn keeps_the_selection_after_an_interactive_command() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("jj").unwrap();
+    assert!(jjdag.screen().contains(" rlvkpnrz │ main"));
+
+    jj.on_each(
+        &["op", "log", "-n1", "--no-graph", "-T", "id"],
+        &["before", "after", "after"],
+    );
+    jjdag.send_keys("X").unwrap();
+    assert!(jj.ran(&["resolve", "-r", "rlvkpnrz"]), "{:?}", jj.calls());
+    let screen = jjdag.screen();
+    assert!(screen.contains(" rlvkpnrz │ main"), "{screen}");
+
+    let calls_before = jj.calls().len();
+    jjdag.send_keys("X").unwrap();
+    assert!(
+        jj.calls()[calls_before..]
+            .iter()
+            .all(|call| call.first().is_none_or(|arg| arg != "log")),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn takes_only_local_bookmarks_from_the_log() {
+    let jj = FakeJj::new();
+    jj.on(&LOCAL_BOOKMARKS, "5e6f7a8b\tmain\n");
+    let log = LOG.replace(" main ", " main* v1.0 feature@origin ");
+    let mut jjdag = jj.start(&log, &Config::default(), 100, 30).unwrap();
+    jjdag.send_keys("jj").unwrap();
+    let screen = jjdag.screen();
+    let status = screen
+        .lines()
+        .find(|line| line.contains(" rlvkpnrz │ "))
+        .unwrap_or_else(|| panic!("{screen}"));
+    assert_eq!(status.trim_end(), " rlvkpnrz │ main", "{screen}");
+
+    jjdag.send_keys("prma").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+        .unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(jj.ran(&["parallelize", "main"]), "{:?}", jj.calls());
+}
+
+#[test]
+fn completes_revisions_in_revset_prompts() {
+    let jj = FakeJj::new();
+    jj.on(&["tag", "list"], "release-1\n");
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("pr").unwrap();
+    jjdag.send_keys("main::r").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+        .unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("main::release-1"), "{screen}");
+    assert!(screen.contains(" 1/2 "), "{screen}");
+    assert!(screen.contains("rlvkpnrz"), "{screen}");
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+        .unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(
+        jj.ran(&["parallelize", "main::rlvkpnrz"]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn completes_paths_with_directories_first() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("fgs").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+        .unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("> src/"), "{screen}");
+    assert!(screen.contains("screenshot.png"), "{screen}");
+    jjdag.send_keys("confi").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+        .unwrap();
+    assert!(
+        jjdag.screen().contains("> src/config.rs"),
+        "{}",
+        jjdag.screen()
+    );
+}
+
+#[test]
+fn runs_an_action_picked_from_the_actions_menu() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jjdag.send_keys(".bookmark").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Actions for Selection"), "{screen}");
+    // The working copy has no bookmarks to delete, but can push a new one
+    assert!(screen.contains("New bookmark for selection"), "{screen}");
+    assert!(!screen.contains("Delete"), "{screen}");
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+        .unwrap();
+    jjdag.send_keys(".abandon").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(jj.ran(&["abandon", "qpvuntsm"]), "{:?}", jj.calls());
+}
+
+#[test]
+fn shows_the_whole_description() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["log", "-r", "qpvuntsm", "-T", "description"],
+        "\x1b[1mFix the parser\x1b[0m\n\nIt dropped the last token.\n",
+    );
+    jjdag.send_keys("vd").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Description of qpvuntsm"), "{screen}");
+    assert!(screen.contains("\nFix the parser\n"), "{screen}");
+    assert!(screen.contains("It dropped the last token."), "{screen}");
+}
+
+#[test]
+fn zooms_into_a_subtree_and_back_out() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("zz").unwrap();
+    assert!(
+        jj.ran(&[
+            "log",
+            "--template",
+            "builtin_log_compact",
+            "--revisions",
+            "qpvuntsm::"
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+    assert!(jjdag.screen().contains("revset: :: › qpvuntsm::"));
+    jjdag.send_keys("zo").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("revset: ::"), "{screen}");
+    assert!(!screen.contains("qpvuntsm::"), "{screen}");
+}
+
+#[test]
+fn dims_commits_outside_the_selections_lineage() {
+    let jj = FakeJj::new();
+    // The parser commit is a sibling of the working copy, not its parent
+    jj.on(&["log", "--revisions"], "1a2b3c4d\n00000000\n");
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("vl").unwrap();
+    assert!(
+        jj.ran(&[
+            "log",
+            "--revisions",
+            "(::1a2b3c4d | 1a2b3c4d::) & (1a2b3c4d | 5e6f7a8b | 00000000)"
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+    let color_of = |jjdag: &Headless, text: &str| {
+        let buffer = jjdag.buffer();
+        let (y, line) = jjdag
+            .screen()
+            .lines()
+            .enumerate()
+            .find(|(_, line)| line.contains(text))
+            .map(|(y, line)| (y, line.to_string()))
+            .unwrap();
+        let x = line[..line.find(text).unwrap()].chars().count();
+        buffer[(x as u16, y as u16)].fg
+    };
+    assert_eq!(color_of(&jjdag, "rlvkpnrz"), Color::DarkGray);
+    assert_ne!(color_of(&jjdag, "zzzzzzzz"), Color::DarkGray);
+    // Selecting another commit asks jj about its lineage instead
+    jjdag.send_keys("J").unwrap();
+    assert!(
+        jj.ran(&[
+            "log",
+            "--revisions",
+            "(::5e6f7a8b | 5e6f7a8b::) & (1a2b3c4d | 5e6f7a8b | 00000000)"
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+    jjdag.send_keys("vl").unwrap();
+    assert_ne!(color_of(&jjdag, "rlvkpnrz"), Color::DarkGray);
+}
+
+#[test]
+fn filters_the_log_by_author_and_clears_it() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["log", "-r", "::", "--no-graph"],
+        "Alice <alice@example.com>\nBob <bob@example.com>\nBob <bob@example.com>\n",
+    );
+    jjdag.send_keys("zu").unwrap();
+    let screen = jjdag.screen();
+    // Most commits first
+    assert!(
+        screen.find("Bob <bob@example.com>") < screen.find("Alice <alice@example.com>"),
+        "{screen}"
+    );
+    jjdag.send_keys("alice").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(
+        jj.ran(&[
+            "log",
+            "--template",
+            "builtin_log_compact",
+            "--revisions",
+            r#"author(exact:"alice@example.com") & (::)"#
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+    assert!(jjdag.screen().contains("author: Alice"));
+    jjdag.send_keys("U").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("revset: ::"), "{screen}");
+    assert!(!screen.contains("author: Alice"), "{screen}");
+}
+
+#[test]
+fn shows_badges_from_templates() {
+    let jj = FakeJj::new();
+    let badges_query = ["log", "-r", "1a2b3c4d | 5e6f7a8b | 00000000", "--no-graph"];
+    jj.on(
+        &badges_query,
+        "1a2b3c4d\tempty\t\n5e6f7a8b\t\tv1.0\n00000000\t\t\n",
+    );
+    let config = Config {
+        badges: vec![
+            Badge {
+                template: "empty".to_string(),
+                label: Some("empty".to_string()),
+                color: "yellow".to_string(),
+            },
+            Badge {
+                template: r#"tags.map(|t| t.name()).join(" ")"#.to_string(),
+                label: None,
+                color: "#00ff00".to_string(),
+            },
+        ],
+        ..Config::default()
+    };
+    let jjdag = start_with(&jj, &config);
+    let template = jj
+        .calls()
+        .into_iter()
+        .find(|call| call.starts_with(&badges_query.map(String::from)))
+        .map(|call| call[call.len() - 1].clone())
+        .unwrap();
+    assert!(template.contains(r#"if(empty, "empty")"#), "{template}");
+
+    let screen = jjdag.screen();
+    let lines: Vec<&str> = screen.lines().collect();
+    let row = lines
+        .iter()
+        .position(|line| line.contains("qpvuntsm"))
+        .unwrap();
+    assert!(lines[row].ends_with("1a2b3c4d [empty]"), "{screen}");
+    assert!(screen.contains("main 5e6f7a8b [v1.0]"), "{screen}");
+    let column = lines[row].chars().count() - "[empty]".len();
+    let cell = &jjdag.buffer()[(column as u16, row as u16)];
+    assert_eq!(cell.fg, Color::Yellow);
+}
+
+#[test]
+fn exports_the_log_to_html_and_ansi() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    let dir = std::env::temp_dir().join(format!("jjdag-export-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let html_path = dir.join("log.html");
+    jjdag.send_keys("vx").unwrap();
+    jjdag.send_keys(html_path.to_str().unwrap()).unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    let html = std::fs::read_to_string(&html_path).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"), "{html}");
+    assert!(html.contains("<title>jj log -r '::'</title>"), "{html}");
+    assert!(
+        html.contains("qpvuntsm") && html.contains("Add the parser"),
+        "{html}"
+    );
+    assert!(jjdag.screen().contains("Exported the log to"));
+
+    let ansi_path = dir.join("log.ansi");
+    jjdag.send_keys("vx").unwrap();
+    jjdag.send_keys(ansi_path.to_str().unwrap()).unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    let ansi = std::fs::read_to_string(&ansi_path).unwrap();
+    assert!(!ansi.contains("<span"), "{ansi}");
+    assert!(ansi.lines().any(|line| line.contains("rlvkpnrz")), "{ansi}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn asks_before_exporting_over_an_existing_file() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    let path = std::env::temp_dir().join(format!("jjdag-overwrite-{}.ansi", std::process::id()));
+    std::fs::write(&path, "kept").unwrap();
+
+    jjdag.send_keys("vx").unwrap();
+    jjdag.send_keys(path.to_str().unwrap()).unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("File Already Exists"), "{screen}");
+    for code in [KeyCode::Down, KeyCode::Enter] {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "kept");
+
+    jjdag.send_keys("vx").unwrap();
+    jjdag.send_keys(path.to_str().unwrap()).unwrap();
+    for _ in 0..2 {
+        jjdag
+            .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .unwrap();
+    }
+    let ansi = std::fs::read_to_string(&path).unwrap();
+    assert!(ansi.contains("rlvkpnrz"), "{ansi}");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn adds_workspaces_at_paths_under_the_home_directory() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on_stderr(&["workspace", "add"], "Created workspace in \"../ws\"");
+    jjdag.send_keys("wa~/ws").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    let home = std::env::var("HOME").unwrap();
+    assert!(
+        jj.ran(&["workspace", "add", &format!("{home}/ws")]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn adds_a_power_workspace_beside_the_others() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["workspace", "list"],
+        "default: qpvuntsm 1a2b3c4d (empty) (no description set)\n\
+         other: rlvkpnrz 5e6f7a8b Add the parser\n",
+    );
+    jj.on_stderr(&["workspace", "add"], "Created workspace in \"../feature\"");
+    jjdag.send_keys("Wafeature").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(
+        jj.ran(&["workspace", "add", "../feature/"]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn replays_a_recorded_session_without_jj() {
+    let path = std::env::temp_dir().join(format!("jjdag-session-{}.toml", std::process::id()));
+    let jj = FakeJj::new();
+    session::start_recording(&path, ".", "::").unwrap();
+    let mut jjdag = start(&jj);
+    jj.on_stderr(&["abandon"], "Abandoned 1 commits");
+    jjdag.send_keys("aa").unwrap();
+    session::stop_recording();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("Abandoned 1 commits"), "{contents}");
+
+    // The recording answers jj in place of the fake from here on
+    let fake_calls = jj.calls().len();
+    let (repository, revset) = session::start_replay(&path).unwrap();
+    let mut jjdag = Headless::new(repository, revset, &Config::default(), 100, 30).unwrap();
+    assert!(jjdag.screen().contains("qpvuntsm"));
+    jjdag.replay().unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Abandoned 1 commits"), "{screen}");
+    assert!(screen.contains("Replay finished"), "{screen}");
+    assert_eq!(jj.calls().len(), fake_calls);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn profiles_refreshing_and_rendering() {
+    let jj = FakeJj::new();
+    profile::enable();
+    let mut jjdag = start(&jj);
+    jjdag.send_keys(" ").unwrap();
+    let screen = jjdag.screen();
+    let overlay = screen.lines().rev().nth(1).unwrap();
+    assert!(overlay.contains("refresh"), "{screen}");
+    assert!(overlay.contains("flatten_log"), "{screen}");
+    assert!(overlay.contains("render"), "{screen}");
+
+    let summary = profile::summary().unwrap();
+    let refresh = summary
+        .lines()
+        .find(|line| line.starts_with("refresh"))
+        .unwrap();
+    assert_eq!(refresh.split_whitespace().nth(1), Some("2"), "{summary}");
+}
+
+#[test]
+fn sleeps_until_input_unless_something_changes_on_its_own() {
+    let jj = FakeJj::new();
+    let config = Config {
+        tick_ms: Some(50),
+        ..Config::default()
+    };
+    let mut jjdag = start_with(&jj, &config);
+    assert_eq!(jjdag.model().input_timeout(), None);
+
+    // Until the toast is cleared
+    jjdag.send_keys(" ").unwrap();
+    let timeout = jjdag.model().input_timeout().unwrap();
+    assert!(timeout > Duration::from_secs(1), "{timeout:?}");
+
+    // The log viewer follows the log file
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE))
+        .unwrap();
+    assert_eq!(
+        jjdag.model().input_timeout(),
+        Some(Duration::from_millis(50))
+    );
+}
+
+#[test]
+fn redraws_only_when_something_changed() {
+    let jj = FakeJj::new();
+    profile::enable();
+    let mut jjdag = start(&jj);
+    let renders = || {
+        profile::timings()
+            .iter()
+            .find(|(stage, _)| *stage == "render")
+            .map_or(0, |(_, timing)| timing.count)
+    };
+    assert_eq!(renders(), 1);
+
+    jjdag
+        .send_event(Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Moved,
+            column: 10,
+            row: 5,
+            modifiers: KeyModifiers::NONE,
+        }))
+        .unwrap();
+    assert_eq!(renders(), 1);
+
+    jjdag.send_keys("j").unwrap();
+    assert_eq!(renders(), 2);
+}
+
+#[test]
+fn reuses_diffs_until_the_operation_changes() {
+    let jj = FakeJj::new();
+    jj.on_each(
+        &["op", "log", "-n1", "--no-graph", "-T", "id"],
+        &["first", "first", "second"],
+    );
+    jj.on(&["diff", "--summary"], "M src/parser.rs\n");
+    // Starts with @ unfolded
+    let mut jjdag = start(&jj);
+    let summaries = || {
+        jj.calls()
+            .iter()
+            .filter(|call| call.starts_with(&["diff".to_string(), "--summary".to_string()]))
+            .count()
+    };
+    assert_eq!(summaries(), 1);
+
+    let tab = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+    jjdag.send_key(tab).unwrap();
+    jjdag.send_key(tab).unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("src/parser.rs"), "{screen}");
+    assert_eq!(summaries(), 1);
+
+    // Refreshing unfolds @ again, from the cache while the operation is the
+    // same
+    jjdag.send_keys(" ").unwrap();
+    assert_eq!(summaries(), 1);
+    jjdag.send_keys(" ").unwrap();
+    assert_eq!(summaries(), 2);
+}
+
+#[test]
+fn asks_to_enlarge_a_terminal_too_small_for_the_layout() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jjdag.resize(30, 6).unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Terminal too small"), "{screen}");
+    assert!(!screen.contains("qpvuntsm"), "{screen}");
+
+    // Popups, which don't fit, still open and show once there's room
+    jjdag.send_keys("zs").unwrap();
+    jjdag.resize(80, 24).unwrap();
+    let screen = jjdag.screen();
+    assert!(!screen.contains("Terminal too small"), "{screen}");
+    assert!(screen.contains("Save Revset As"), "{screen}");
+}
+
+#[test]
+fn shows_info_beside_the_log_when_toggled() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.fail(&["abandon"], "Error: Revision `qpvuntsm` doesn't exist");
+    jjdag.send_keys("vpaa").unwrap();
+    let screen = jjdag.screen();
+    let row = screen
+        .lines()
+        .find(|line| line.contains("jj abandon qpvuntsm"))
+        .unwrap_or_else(|| panic!("{screen}"));
+    assert!(row.starts_with("@ ▾ qpvuntsm"), "{screen}");
+    // Wrapped to fit beside the log
+    assert!(screen.contains("to refresh it"), "{screen}");
+
+    jjdag.send_keys("vpaa").unwrap();
+    let screen = jjdag.screen();
+    let row = screen
+        .lines()
+        .find(|line| line.contains("jj abandon qpvuntsm"))
+        .unwrap_or_else(|| panic!("{screen}"));
+    assert!(!row.contains("@ ▾ qpvuntsm"), "{screen}");
+}
+
+#[test]
+fn hides_the_header_and_key_hints_in_focus_mode() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.fail(&["abandon"], "Error: Revision `qpvuntsm` doesn't exist");
+    assert!(jjdag.screen().contains("repository:"));
+
+    jjdag.send_keys("vF").unwrap();
+    let screen = jjdag.screen();
+    assert!(!screen.contains("repository:"), "{screen}");
+    assert!(screen.starts_with("@ ▾ qpvuntsm"), "{screen}");
+    jjdag.send_keys("a").unwrap();
+    assert!(!jjdag.screen().contains("Abandon"), "{}", jjdag.screen());
+    // Output still opens the info pane
+    jjdag.send_keys("a").unwrap();
+    assert!(jjdag.screen().contains("jj abandon qpvuntsm"));
+
+    jjdag.send_keys("vF").unwrap();
+    assert!(jjdag.screen().contains("repository:"));
+}
+
+#[test]
+fn pages_back_through_earlier_command_outputs() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on_stderr(&["git", "fetch"], "bookmark: main@origin [updated] tracked");
+    jj.fail(&["abandon"], "Error: Revision `qpvuntsm` doesn't exist");
+    jjdag.send_keys("gff").unwrap();
+    jjdag.send_keys("aa").unwrap();
+    assert!(!jjdag.screen().contains("main@origin"));
+
+    jjdag.send_keys("[").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Output 1 of 2"), "{screen}");
+    assert!(screen.contains("jj abandon qpvuntsm"), "{screen}");
+    jjdag.send_keys("[").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Output 2 of 2"), "{screen}");
+    assert!(screen.contains("main@origin [updated]"), "{screen}");
+    jjdag.send_keys("]").unwrap();
+    assert!(jjdag.screen().contains("Output 1 of 2"));
+}
+
+#[test]
+fn notifies_about_long_fetches_only_when_unfocused() {
+    let jj = FakeJj::new();
+    let config = Config {
+        notifications: NotificationConfig {
+            enabled: true,
+            min_seconds: 0,
+        },
+        ..Config::default()
+    };
+    let mut jjdag = start_with(&jj, &config);
+    jjdag.send_keys("gff").unwrap();
+    assert!(jjdag.model().has_pending_notification());
+    jjdag.model_mut().resolve_notification();
+    assert!(!jjdag.model().has_pending_notification());
+
+    // Only fetches and pushes are worth it
+    jjdag.send_event(Event::FocusLost).unwrap();
+    assert!(!jjdag.model().terminal_focused);
+    jjdag.send_keys("aa").unwrap();
+    assert!(!jjdag.model().has_pending_notification());
+}
+
+#[test]
+fn diffs_a_merge_against_the_parent_picked() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["log", "-r", "parents(qpvuntsm)"],
+        "rlvkpnrz\tAdd the parser\nzzzzzzzz\t\n",
+    );
+    jj.on(
+        &["diff", "--stat"],
+        "a.txt | 1 +\n1 file changed, 1 insertion(+), 0 deletions(-)\n",
+    );
+    jjdag.send_keys("vo").unwrap();
+    assert!(jjdag.screen().contains("Diff Merge Against"));
+    jjdag.send_keys("rlvk").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(jj.ran(&[
+        "diff",
+        "--summary",
+        "--from",
+        "rlvkpnrz",
+        "--to",
+        "qpvuntsm"
+    ]));
+    let screen = jjdag.screen();
+    assert!(screen.contains("(from parent rlvkpnrz)"), "{screen}");
+
+    // Kept through a refresh
+    jjdag.send_keys(" ").unwrap();
+    assert!(jjdag.screen().contains("(from parent rlvkpnrz)"));
+}
+
+#[test]
+fn jumps_to_trunk_root_and_bookmarked_ancestors() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(&["log", "-r", "(trunk()) & (::)"], "rlvkpnrzwxyz\n");
+    jj.on(&["log", "-r", "(root()) & (::)"], "zzzzzzzzzzzz\n");
+    jj.on(
+        &["log", "-r", "(heads(::qpvuntsm- & bookmarks())) & (::)"],
+        "rlvkpnrzwxyz\n",
+    );
+    // The status bar starts with the selected change
+    let selected = |jjdag: &Headless| {
+        jjdag
+            .screen()
+            .lines()
+            .last()
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    jjdag.send_keys("Gr").unwrap();
+    assert!(selected(&jjdag).contains("zzzzzzzz"), "{}", jjdag.screen());
+    jjdag.send_keys("Gt").unwrap();
+    assert!(selected(&jjdag).contains("rlvkpnrz"), "{}", jjdag.screen());
+    jjdag.send_keys("@Gb").unwrap();
+    assert!(selected(&jjdag).contains("rlvkpnrz"), "{}", jjdag.screen());
+
+    jj.on(&["log", "-r", "(trunk()) & (::)"], "");
+    jjdag.send_keys("Gt").unwrap();
+    assert!(jjdag.screen().contains("trunk() is not in the log"));
+}
+
+#[test]
+fn moves_between_commits_out_of_unfolded_diffs() {
+    let jj = FakeJj::new();
+    jj.on(&["diff", "--summary"], "M a.txt\nM b.txt\n");
+    let mut jjdag = start(&jj);
+    // Nothing more to load after the root
+    jj.on(
+        &[
+            "log",
+            "--template",
+            "builtin_log_compact",
+            "--revisions",
+            "..zzzzzzzz-",
+        ],
+        "",
+    );
+    let press = |jjdag: &mut Headless, code: KeyCode| {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+        jjdag.model().log_list_state.selected().unwrap()
+    };
+    // qpvuntsm, its diffstat, a.txt, b.txt, rlvkpnrz, zzzzzzzz
+    assert_eq!(press(&mut jjdag, KeyCode::Down), 1);
+    assert_eq!(press(&mut jjdag, KeyCode::Down), 2);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('J')), 4);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('K')), 0);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('K')), 0);
+    assert_eq!(press(&mut jjdag, KeyCode::Down), 1);
+    assert_eq!(press(&mut jjdag, KeyCode::Down), 2);
+    assert_eq!(press(&mut jjdag, KeyCode::Right), 3);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('K')), 0);
+
+    // Past the last file of a commit, on to the next commit
+    assert_eq!(press(&mut jjdag, KeyCode::Down), 1);
+    assert_eq!(press(&mut jjdag, KeyCode::Down), 2);
+    assert_eq!(press(&mut jjdag, KeyCode::Down), 3);
+    assert_eq!(press(&mut jjdag, KeyCode::Right), 4);
+    assert_eq!(press(&mut jjdag, KeyCode::Left), 0);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('J')), 4);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('J')), 5);
+    // Nothing after the last commit
+    assert_eq!(press(&mut jjdag, KeyCode::Right), 5);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('J')), 5);
+}
+
+#[test]
+fn moves_between_files_and_hunks_of_a_commit() {
+    let jj = FakeJj::new();
+    jj.on(&["diff", "--summary"], "M a.txt\nM b.txt\n");
+    jj.on(
+        &["diff", "--color-words"],
+        "Modified regular file a.txt:\n   1    1: one\n    ...\n  10   10: ten\n",
+    );
+    let mut jjdag = start(&jj);
+    let press = |jjdag: &mut Headless, code: KeyCode| {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+        jjdag.model().log_list_state.selected().unwrap()
+    };
+    // qpvuntsm, its diffstat, a.txt, b.txt, rlvkpnrz, zzzzzzzz
+    assert_eq!(press(&mut jjdag, KeyCode::Char('}')), 2);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('}')), 3);
+    // Not past the commit's last file
+    assert_eq!(press(&mut jjdag, KeyCode::Char('}')), 3);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('{')), 2);
+
+    // a.txt, its first hunk with its line, its second hunk, then b.txt
+    assert_eq!(press(&mut jjdag, KeyCode::Tab), 2);
+    assert_eq!(press(&mut jjdag, KeyCode::Char(')')), 3);
+    assert_eq!(press(&mut jjdag, KeyCode::Char(')')), 5);
+    assert_eq!(press(&mut jjdag, KeyCode::Char(')')), 5);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('(')), 3);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('}')), 8);
+    assert_eq!(press(&mut jjdag, KeyCode::Char('(')), 5);
+}
+
+#[test]
+fn pins_the_commit_above_its_scrolled_diff() {
+    let jj = FakeJj::new();
+    jj.on(&["diff", "--summary"], "M a.txt\n");
+    let diff: String = (1..=60)
+        .map(|num| format!("{num:>4} {num:>4}: line {num}\n"))
+        .collect();
+    jj.on(
+        &["diff", "--color-words"],
+        &format!("Modified regular file a.txt:\n{diff}"),
+    );
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("vF").unwrap();
+    // qpvuntsm, its diffstat, a.txt unfolded, then down its diff
+    jjdag.send_keys("jj").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+        .unwrap();
+    jjdag.send_keys(&"j".repeat(50)).unwrap();
+    let screen = jjdag.screen();
+    let mut lines = screen.lines();
+    assert!(lines.next().unwrap().contains("qpvuntsm"), "{screen}");
+    assert!(!lines.any(|line| line.contains("a.txt")), "{screen}");
+    assert!(screen.contains("line 48"), "{screen}");
+}
+
+#[test]
+fn checks_off_viewed_files_while_reviewing() {
+    isolate_state();
+    let jj = FakeJj::new();
+    jj.on(&["diff", "--summary"], "M a.txt\nM b.txt\n");
+    let mut jjdag = start(&jj);
+    assert!(!jjdag.screen().contains("viewed]"));
+
+    jjdag.send_keys("vr").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("[0/2 viewed]"), "{screen}");
+    assert!(screen.contains("☐ modified  a.txt"), "{screen}");
+    // Marking a file moves on to the next one
+    jjdag.send_keys("jj'").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("☑ modified  a.txt"), "{screen}");
+    assert!(screen.contains("[1/2 viewed]"), "{screen}");
+    assert_eq!(jjdag.model().log_list_state.selected(), Some(3));
+    jjdag.send_keys("'").unwrap();
+    assert!(jjdag.screen().contains("[2/2 viewed]"));
+
+    // Kept for the next run
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("vr").unwrap();
+    assert!(jjdag.screen().contains("[2/2 viewed]"));
+    jjdag.send_keys("jjj'").unwrap();
+    assert!(jjdag.screen().contains("[1/2 viewed]"));
+}
+
+#[test]
+fn keeps_a_note_on_a_change() {
+    isolate_state();
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("vncheck the error paths").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    let screen = jjdag.screen();
+    assert!(
+        screen.contains(
+            "qpvuntsm alice@example.com 2024-01-02 10:00:00 1a2b3c4d [✎ check the error paths]"
+        ),
+        "{screen}"
+    );
+
+    // Kept for the next run, and edited starting from what it says
+    let mut jjdag = start(&jj);
+    assert!(jjdag.screen().contains("[✎ check the error paths]"));
+    jjdag.send_keys("vn").unwrap();
+    assert!(jjdag.screen().contains("> check the error paths"));
+    for _ in "check the error paths".chars() {
+        jjdag
+            .send_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE))
+            .unwrap();
+    }
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(!jjdag.screen().contains("✎"), "{}", jjdag.screen());
+}
+
+#[test]
+fn pushes_only_the_deletions_picked() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on_stderr(
+        &["git", "push", "--deleted", "--dry-run"],
+        "Changes to push to origin:
+  Delete bookmark old-feature from 1a2b3c4d
+  Delete bookmark shared from 5e6f7a8b
+Dry-run requested, not pushing.",
+    );
+    jj.on_stderr(
+        &["git", "push", "-b", "exact:old-feature"],
+        "Changes to push to origin:
+  Delete bookmark old-feature from 1a2b3c4d",
+    );
+    jjdag.send_keys("gpD").unwrap();
+    let screen = jjdag.screen();
+    assert!(
+        screen.contains("Select Bookmarks to Delete From Remote"),
+        "{screen}"
+    );
+    assert!(screen.contains("shared"), "{screen}");
+
+    for code in [KeyCode::Tab, KeyCode::Enter, KeyCode::Enter] {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+    let calls = jj.calls();
+    let pushes: Vec<_> = calls
+        .iter()
+        .filter(|call| call.starts_with(&["git".to_string(), "push".to_string()]))
+        .collect();
+    assert_eq!(
+        pushes.last().unwrap(),
+        &&["git", "push", "-b", "exact:old-feature"],
+        "{calls:?}"
+    );
+}
+
+#[test]
+fn browses_remote_bookmarks_by_remote() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["bookmark", "list", "--all-remotes", "-T"],
+        "feature\torigin\t\tmzvwutvl\t0123456789abcdef\tAdd the feature
+main\torigin\ttracked\trlvkpnrz\t5e6f7a8b9c0d1e2f\tAdd the parser
+main\tgit\ttracked\trlvkpnrz\t5e6f7a8b9c0d1e2f\tAdd the parser
+old\tupstream\t\tdeleted
+",
+    );
+    jjdag.send_keys("bR").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("Remote Bookmarks"), "{screen}");
+    assert!(screen.contains("@origin  2"), "{screen}");
+    assert!(screen.contains("@upstream  1"), "{screen}");
+    assert!(!screen.contains("@git"), "{screen}");
+    assert!(
+        screen.contains("main  tracked    rlvkpnrz Add the parser"),
+        "{screen}"
+    );
+
+    // The first bookmark is selected, then main@origin, which is tracked
+    jjdag.send_keys("t").unwrap();
+    assert!(jj.ran(&["bookmark", "track", "feature@origin"]));
+    jjdag.send_keys("jt").unwrap();
+    assert!(jj.ran(&["bookmark", "untrack", "main@origin"]));
+    jjdag.send_keys("f").unwrap();
+    assert!(jj.ran(&["git", "fetch", "--remote", "origin", "-b", "main"]));
+    // A remote's line fetches all of it
+    jjdag.send_keys("kkf").unwrap();
+    assert!(
+        jj.calls().contains(&vec![
+            "git".to_string(),
+            "fetch".to_string(),
+            "--remote".to_string(),
+            "origin".to_string()
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+
+    jjdag.send_keys("jc").unwrap();
+    assert!(jjdag.screen().contains("> feature"), "{}", jjdag.screen());
+    jjdag.send_keys("-copy").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(
+        jj.ran(&[
+            "bookmark",
+            "create",
+            "--revision",
+            "0123456789abcdef",
+            "feature-copy"
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+    // Deleted bookmarks have no commit to create one at
+    jjdag.send_keys("jjjc").unwrap();
+    assert!(
+        jjdag.screen().contains("old@upstream is deleted"),
+        "{}",
+        jjdag.screen()
+    );
+
+    jjdag.send_keys("q").unwrap();
+    assert!(!jjdag.screen().contains("Remote Bookmarks"));
+}
+
+/// Run a command's `--config`ured merge tool the way jj runs a diff editor,
+/// with `$left` and `$right` standing for the given directories
+fn run_merge_tool(args: &[String], tool: &str, left: &Path, right: &Path) -> Output {
+    let setting = |key: &str| {
+        let prefix = format!("merge-tools.{tool}.{key}=");
+        let value = args
+            .iter()
+            .find_map(|arg| arg.strip_prefix(&prefix))
+            .unwrap_or_else(|| panic!("no {prefix} in {args:?}"));
+        toml::from_str::<toml::Table>(&format!("value = {value}")).unwrap()["value"].clone()
+    };
+    let program = setting("program");
+    let edit_args: Vec<String> = setting("edit-args")
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|arg| {
+            arg.as_str()
+                .unwrap()
+                .replace("$left", &left.to_string_lossy())
+                .replace("$right", &right.to_string_lossy())
+        })
+        .collect();
+    Command::new(program.as_str().unwrap())
+        .args(edit_args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn applies_edited_contents_with_the_diff_editor() {
+    let contents = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(contents.path(), "edited\n").unwrap();
+    let cmd = JjCommand::diffedit_file(
+        "qpvuntsm",
+        "src/new dir/parser.rs",
+        contents.path(),
+        GlobalArgs {
+            repository: ".".to_string(),
+            ignore_immutable: false,
+            ignore_working_copy: false,
+        },
+    );
+    let left = tempfile::tempdir().unwrap();
+    let right = tempfile::tempdir().unwrap();
+    let output = run_merge_tool(cmd.args(), "jjdag-apply", left.path(), right.path());
+    assert!(output.status.success(), "{output:?}");
+    let applied = std::fs::read_to_string(right.path().join("src/new dir/parser.rs")).unwrap();
+    assert_eq!(applied, "edited\n");
+}
+
+#[test]
+fn selects_exactly_the_given_contents_to_squash() {
+    let contents = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(contents.path(), "with the hunk\n").unwrap();
+    let cmd = JjCommand::squash_file_contents(
+        "qpvuntsm",
+        "rlvkpnrz",
+        "src/parser.rs",
+        contents.path(),
+        GlobalArgs {
+            repository: ".".to_string(),
+            ignore_immutable: false,
+            ignore_working_copy: false,
+        },
+    );
+    let left = tempfile::tempdir().unwrap();
+    std::fs::write(left.path().join("README.md"), "parent\n").unwrap();
+    let right = tempfile::tempdir().unwrap();
+    std::fs::write(right.path().join("other.rs"), "not selected\n").unwrap();
+    let output = run_merge_tool(cmd.args(), "jjdag-select", left.path(), right.path());
+    assert!(output.status.success(), "{output:?}");
+    let read = |path: &str| std::fs::read_to_string(right.path().join(path)).ok();
+    assert_eq!(read("src/parser.rs").as_deref(), Some("with the hunk\n"));
+    assert_eq!(read("README.md").as_deref(), Some("parent\n"));
+    assert_eq!(read("other.rs"), None);
+}
+
+#[test]
+fn drops_a_pending_push_on_any_other_key() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(&["bookmark", "list", "-r"], "main\n");
+    jj.on_stderr(
+        &["git", "push", "-b", "exact:main", "--dry-run"],
+        "Changes to push to origin:
+  Move forward bookmark main from 5e6f7a8b to 1a2b3c4d
+Dry-run requested, not pushing.",
+    );
+    let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+    let pushed = |jj: &FakeJj| {
+        jj.calls().iter().any(|call| {
+            call.starts_with(&["git".to_string(), "push".to_string()])
+                && !call.contains(&"--dry-run".to_string())
+        })
+    };
+
+    // Tugging previews the push once the bookmark has moved
+    jjdag.send_keys("gpT").unwrap();
+    assert!(
+        jj.ran(&["bookmark", "move", "main", "--to", "@"]),
+        "{:?}",
+        jj.calls()
+    );
+    assert!(
+        jj.ran(&["git", "push", "-b", "exact:main", "--dry-run"]),
+        "{:?}",
+        jj.calls()
+    );
+    assert!(
+        jjdag.screen().contains("Press Enter to push"),
+        "{}",
+        jjdag.screen()
+    );
+
+    jjdag.send_keys("j").unwrap();
+    assert!(
+        !jjdag.screen().contains("Press Enter to push"),
+        "{}",
+        jjdag.screen()
+    );
+    jjdag.send_key(enter).unwrap();
+    assert!(!pushed(&jj), "{:?}", jj.calls());
+}
+
+#[test]
+fn replays_without_running_shell_commands() {
+    let dir = std::env::temp_dir().join(format!("jjdag-replay-shell-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("session.toml");
+    let marker = dir.join("ran");
+    let jj = FakeJj::new();
+    jj.on(&["workspace", "root"], &dir.to_string_lossy());
+    session::start_recording(&path, ".", "::").unwrap();
+    let mut jjdag = start(&jj);
+    jjdag
+        .send_keys(&format!("!!touch {}", marker.display()))
+        .unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    session::stop_recording();
+    assert!(marker.exists());
+    std::fs::remove_file(&marker).unwrap();
+
+    let (repository, revset) = session::start_replay(&path).unwrap();
+    let mut jjdag = Headless::new(repository, revset, &Config::default(), 100, 30).unwrap();
+    jjdag.replay().unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("while replaying a session"), "{screen}");
+    assert!(!marker.exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn drops_a_mouse_range_on_keyboard_moves() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    let row = jjdag
+        .screen()
+        .lines()
+        .position(|line| line.contains("zzzzzzzz"))
+        .unwrap() as u16;
+    jjdag
+        .send_event(Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 10,
+            row,
+            modifiers: KeyModifiers::SHIFT,
+        }))
+        .unwrap();
+    assert!(
+        jjdag.screen().contains("Range selected"),
+        "{}",
+        jjdag.screen()
+    );
+
+    // The range is dropped, so the abandon starts from the keyboard selection
+    jjdag.send_keys("JaAK").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    assert!(
+        jj.ran(&["abandon", "rlvkpnrz::qpvuntsm"]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn previews_the_operation_a_repeated_undo_reverts() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["op", "log", "-n1", "--no-graph", "-T", "description"],
+        "undo: restore to operation 3c4d5e6f7a8b",
+    );
+    jj.on(
+        &["op", "log", "-n1", "--at-op", "3c4d5e6f7a8b"],
+        "@  3c4d5e6f alice@example.com 5 minutes ago\n│  describe commit 1a2b3c4d\n",
+    );
+    jj.on(
+        &["op", "diff", "--operation", "3c4d5e6f7a8b"],
+        "Changed commits:\n+ qpvuntsm 1a2b3c4d Start the lexer\n",
+    );
+    jjdag.send_keys("uu").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("describe commit 1a2b3c4d"), "{screen}");
+    assert!(screen.contains("Start the lexer"), "{screen}");
+}
+
+#[test]
+fn checks_ci_in_the_background_with_full_commit_ids() {
+    let jj = FakeJj::new();
+    jj.on(&["workspace", "root"], ".");
+    jj.on(
+        &["log", "-r", "1a2b3c4d | 5e6f7a8b | 00000000"],
+        "1a2b3c4d\t1a2b3c4d00000000000000000000000000000000\n\
+         5e6f7a8b\t5e6f7a8b00000000000000000000000000000000\n\
+         00000000\t0000000000000000000000000000000000000000\n",
+    );
+    let config = Config {
+        ci: Some(CiProvider {
+            command: "test {commit_id} = 1a2b3c4d00000000000000000000000000000000 \
+                      && echo success || echo failure"
+                .to_string(),
+            on_refresh: false,
+        }),
+        ..Config::default()
+    };
+    let mut jjdag = start_with(&jj, &config);
+    jjdag.send_keys("CC").unwrap();
+    jjdag.wait_for_ci().unwrap();
+    let screen = jjdag.screen();
+    let line_of = |id: &str| screen.lines().find(|line| line.contains(id)).unwrap();
+    assert!(line_of("qpvuntsm").contains('✓'), "{screen}");
+    assert!(line_of("rlvkpnrz").contains('✗'), "{screen}");
+}
+
+#[test]
+fn stops_a_macro_that_plays_itself() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    // Playing the register while recording into it finds it empty
+    jjdag.send_keys("QajMaQ").unwrap();
+    jjdag.send_keys("Ma").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("more than 10 deep"), "{screen}");
+    assert!(!jjdag.model().is_playing_macro());
+}
+
+#[test]
+fn splits_off_several_marked_files() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(&["diff", "--name-only"], "a.rs\nb.rs\nc.rs\n");
+    jjdag.send_keys("\\First").unwrap();
+    for code in [KeyCode::Enter, KeyCode::Enter] {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+    let screen = jjdag.screen();
+    assert!(screen.contains("Files for the First Part"), "{screen}");
+
+    for code in [
+        KeyCode::Down,
+        KeyCode::Tab,
+        KeyCode::Down,
+        KeyCode::Tab,
+        KeyCode::Enter,
+    ] {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+    let calls = jj.calls();
+    let split = calls
+        .iter()
+        .find(|call| call.first().is_some_and(|arg| arg == "split"))
+        .unwrap();
+    assert_eq!(split[3..], ["-m", "First", "a.rs", "c.rs"], "{calls:?}");
+}
+
+#[test]
+fn wraps_description_bodies_at_the_configured_length() {
+    let jj = FakeJj::new();
+    let config = Config {
+        description_lint: DescriptionLintConfig {
+            body_length: 10,
+            ..DescriptionLintConfig::default()
+        },
+        ..Config::default()
+    };
+    let mut jjdag = start_with(&jj, &config);
+    let newline = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::CONTROL);
+    jjdag.send_keys("ddSubject").unwrap();
+    jjdag.send_key(newline).unwrap();
+    jjdag.send_key(newline).unwrap();
+    jjdag.send_keys("one two three four").unwrap();
+    jjdag
+        .send_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        .unwrap();
+    let calls = jj.calls();
+    let describe = calls
+        .iter()
+        .find(|call| call.first().is_some_and(|arg| arg == "describe"))
+        .unwrap();
+    assert!(
+        describe.contains(&"Subject\n\none two three\nfour".to_string()),
+        "{calls:?}"
+    );
+}
+
+#[test]
+fn fills_template_placeholders_with_tab_only_in_templates() {
+    let jj = FakeJj::new();
+    jj.on(&["log", "-r"], "Keep {braces}");
+    let config = Config {
+        templates: vec![DescriptionTemplate {
+            name: "fix".to_string(),
+            text: "fix({scope}): {summary}".to_string(),
+        }],
+        ..Config::default()
+    };
+    let mut jjdag = start_with(&jj, &config);
+    let press = |jjdag: &mut Headless, code: KeyCode| {
+        jjdag
+            .send_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    };
+    let described = |jj: &FakeJj| {
+        jj.calls()
+            .into_iter()
+            .rfind(|call| call.first().is_some_and(|arg| arg == "describe"))
+            .unwrap()
+    };
+
+    jjdag.send_keys("dp").unwrap();
+    press(&mut jjdag, KeyCode::Enter);
+    jjdag.send_keys("ui").unwrap();
+    press(&mut jjdag, KeyCode::Tab);
+    jjdag.send_keys("crash").unwrap();
+    press(&mut jjdag, KeyCode::Enter);
+    assert!(described(&jj).contains(&"fix(ui): crash".to_string()));
+
+    // Braces in a description being edited are just text
+    jjdag.send_keys("dd").unwrap();
+    press(&mut jjdag, KeyCode::Tab);
+    press(&mut jjdag, KeyCode::Enter);
+    assert!(described(&jj).contains(&"Keep {braces}".to_string()));
+}
+
+#[test]
+fn reads_the_fsmonitor_once_and_shows_it_only_when_set() {
+    let jj = FakeJj::new();
+    let jjdag = start(&jj);
+    assert!(!jjdag.screen().contains("fsmonitor"), "{}", jjdag.screen());
+
+    let jj = FakeJj::new();
+    jj.on(&["config", "get", "fsmonitor.backend"], "watchman\n");
+    jj.on_stderr(&["abandon"], "Abandoned 1 commits");
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("aa").unwrap();
+    assert!(jjdag.screen().contains("watchman"), "{}", jjdag.screen());
+    let config_gets = jj
+        .calls()
+        .iter()
+        .filter(|call| call[..2] == ["config", "get"])
+        .count();
+    assert_eq!(config_gets, 1, "{:?}", jj.calls());
+}
+
+#[test]
+fn rebases_onto_added_destinations_and_trunk() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jjdag.send_keys("rboJ+m").unwrap();
+    assert!(
+        jj.ran(&[
+            "rebase", "--branch", "qpvuntsm", "--onto", "rlvkpnrz", "--onto", "trunk()"
+        ]),
+        "{:?}",
+        jj.calls()
+    );
+}
+
+#[test]
+fn compares_with_trunk_from_a_single_fork_point() {
+    let jj = FakeJj::new();
+    let mut jjdag = start(&jj);
+    jj.on(
+        &["log", "-r", "heads(::trunk() & ::(qpvuntsm))"],
+        "5e6f7a8b\n",
+    );
+    jjdag.send_keys("vc").unwrap();
+    assert!(
+        jj.ran(&["diff", "--stat", "--from", "5e6f7a8b", "--to", "qpvuntsm"]),
+        "{:?}",
+        jj.calls()
+    );
+
+    // A criss-cross merge leaves no single one to diff from
+    jj.on(
+        &["log", "-r", "heads(::trunk() & ::(qpvuntsm))"],
+        "5e6f7a8b\n1a2b3c4d\n",
+    );
+    jjdag.send_keys("vc").unwrap();
+    let screen = jjdag.screen();
+    assert!(screen.contains("No single fork point"), "{screen}");
+    let diffs = jj
+        .calls()
+        .iter()
+        .filter(|call| {
+            call.starts_with(&[
+                "diff".to_string(),
+                "--stat".to_string(),
+                "--from".to_string(),
+            ])
+        })
+        .count();
+    assert_eq!(diffs, 1, "{:?}", jj.calls());
+}